@@ -0,0 +1,262 @@
+//! Simple Bitbucket snippets API wrapper, for syncing with `sync_provider = "bitbucket"`
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use color_eyre::Help;
+
+use crate::errors::LostTheWay;
+use crate::gist::{CreateGistPayload, Gist, GistFile, UpdateGistPayload};
+use crate::http::HttpClient;
+use crate::sync::{self, SyncClient};
+use crate::utils;
+
+pub const BITBUCKET_API_URL: &str = "https://api.bitbucket.org";
+const BITBUCKET_BASE_PATH: &str = "/2.0/snippets";
+
+#[derive(Deserialize, Debug)]
+struct Link {
+    href: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct SnippetLinks {
+    html: Link,
+}
+
+#[derive(Deserialize, Debug)]
+struct FileLinks {
+    /// Raw file contents
+    #[serde(rename = "self")]
+    raw: Link,
+}
+
+#[derive(Deserialize, Debug)]
+struct BitbucketSnippetFile {
+    links: FileLinks,
+}
+
+#[derive(Deserialize, Debug)]
+struct BitbucketSnippet {
+    id: String,
+    title: String,
+    updated_on: DateTime<Utc>,
+    links: SnippetLinks,
+    #[serde(default)]
+    files: HashMap<String, BitbucketSnippetFile>,
+}
+
+impl BitbucketSnippet {
+    /// Same shape as a Gist, so syncing works the same way (file contents are left empty)
+    fn into_gist(self) -> Gist {
+        let files = self
+            .files
+            .into_keys()
+            .map(|name| {
+                (
+                    name.to_owned(),
+                    GistFile {
+                        filename: name,
+                        content: String::new(),
+                    },
+                )
+            })
+            .collect();
+        Gist {
+            html_url: self.links.html.href,
+            id: self.id,
+            updated_at: self.updated_on,
+            description: self.title,
+            files,
+        }
+    }
+}
+
+/// One part of a multipart/form-data body: a plain field, or a file (name, content)
+enum Part<'a> {
+    Field(&'a str, &'a str),
+    File(&'a str, &'a str),
+}
+
+/// multipart/form-data body made of `parts`, and its content type
+fn multipart(parts: &[Part<'_>]) -> (String, Vec<u8>) {
+    // Can't be in any of the parts, since it's made from all of them
+    let contents: Vec<_> = parts
+        .iter()
+        .map(|part| match part {
+            Part::Field(name, value) => format!("{}={}", name, value),
+            Part::File(name, content) => format!("{}={}", name, content),
+        })
+        .collect();
+    let boundary = format!("the-way-{}", utils::content_hash(&contents.join("\n")));
+    let mut body = String::new();
+    for part in parts {
+        body.push_str(&format!("--{}\r\n", boundary));
+        match part {
+            Part::Field(name, value) => body.push_str(&format!(
+                "Content-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                name, value
+            )),
+            Part::File(name, content) => body.push_str(&format!(
+                "Content-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\
+                Content-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+                name, content
+            )),
+        }
+    }
+    body.push_str(&format!("--{}--\r\n", boundary));
+    (
+        format!("multipart/form-data; boundary={}", boundary),
+        body.into_bytes(),
+    )
+}
+
+pub struct BitbucketClient {
+    client: HttpClient,
+    /// e.g. https://api.bitbucket.org/2.0/snippets/<workspace>
+    snippets_url: String,
+}
+
+impl BitbucketClient {
+    /// Create a new Bitbucket client for snippets in `workspace`, sending requests through `client`
+    /// with `username` and an app password
+    pub fn new(
+        mut client: HttpClient,
+        url: &str,
+        workspace: &str,
+        username: &str,
+        app_password: &str,
+        user_agent: &str,
+    ) -> color_eyre::Result<Self> {
+        client
+            .set(
+                "authorization",
+                &format!(
+                    "Basic {}",
+                    base64::encode(format!("{}:{}", username, app_password))
+                ),
+            )
+            .set("user-agent", user_agent);
+        Ok(Self {
+            client,
+            snippets_url: format!(
+                "{}{}/{}",
+                url.trim_end_matches('/'),
+                BITBUCKET_BASE_PATH,
+                workspace
+            ),
+        })
+    }
+
+    fn check_response(response: ureq::Response) -> color_eyre::Result<ureq::Response> {
+        if response.ok() {
+            Ok(response)
+        } else {
+            Err(LostTheWay::SyncError {
+                message: format!("{} {}", response.status(), response.into_string()?),
+            })
+            .suggestion(
+                "Make sure your Bitbucket username and app password are valid and the app password has the \"snippet:write\" permission.\n\
+        Make one from https://bitbucket.org/account/settings/app-passwords/.\n\
+        Set it to the environment variable $THE_WAY_BITBUCKET_APP_PASSWORD",
+            )
+        }
+    }
+
+    fn parse_snippet(json: &str) -> color_eyre::Result<BitbucketSnippet> {
+        Ok(
+            serde_json::from_str::<BitbucketSnippet>(json).map_err(|e| LostTheWay::SyncError {
+                message: format!("{}", e),
+            })?,
+        )
+    }
+
+    fn get_response(response: ureq::Response) -> color_eyre::Result<BitbucketSnippet> {
+        Self::parse_snippet(&Self::check_response(response)?.into_string()?)
+    }
+
+    fn snippet_url(&self, snippet_id: &str) -> String {
+        format!("{}/{}", self.snippets_url, snippet_id)
+    }
+
+    /// Sends `parts` as a multipart/form-data `method` request to `url`
+    fn send_parts(
+        &self,
+        method: &str,
+        url: &str,
+        parts: &[Part<'_>],
+    ) -> color_eyre::Result<BitbucketSnippet> {
+        let (content_type, body) = multipart(parts);
+        let mut request = self.client.request(method, url)?;
+        request.set("content-type", &content_type);
+        Self::get_response(self.client.send(request, Some(&body)))
+    }
+
+    /// Snippet metadata, without file contents
+    fn get_snippet(&self, snippet_id: &str) -> color_eyre::Result<BitbucketSnippet> {
+        Self::parse_snippet(&self.client.get_cached(
+            self.client.request("GET", &self.snippet_url(snippet_id))?,
+            |response| sync::check_found(snippet_id, response, Self::check_response),
+        )?)
+    }
+}
+
+impl SyncClient for BitbucketClient {
+    fn create_gist(&self, payload: &CreateGistPayload<'_>) -> color_eyre::Result<Gist> {
+        let is_private = if payload.public { "false" } else { "true" };
+        let mut parts = vec![
+            Part::Field("title", payload.description),
+            Part::Field("is_private", is_private),
+        ];
+        parts.extend(
+            payload
+                .files
+                .iter()
+                .map(|(name, file)| Part::File(name, file.content)),
+        );
+        Ok(self
+            .send_parts("POST", &self.snippets_url, &parts)?
+            .into_gist())
+    }
+
+    fn update_gist(
+        &self,
+        gist_id: &str,
+        payload: &UpdateGistPayload<'_>,
+    ) -> color_eyre::Result<Gist> {
+        let mut parts = vec![Part::Field("title", payload.description)];
+        parts.extend(payload.files.iter().map(|(name, file)| match file {
+            Some(file) => Part::File(name, file.content),
+            // A file named without content is deleted
+            None => Part::Field("file", name),
+        }));
+        Ok(self
+            .send_parts("PUT", &self.snippet_url(gist_id), &parts)?
+            .into_gist())
+    }
+
+    fn get_gist(&self, gist_id: &str) -> color_eyre::Result<Gist> {
+        let snippet = self.get_snippet(gist_id)?;
+        let raw_urls: Vec<_> = snippet
+            .files
+            .iter()
+            .map(|(name, file)| (name.to_owned(), file.links.raw.href.to_owned()))
+            .collect();
+        let mut gist = snippet.into_gist();
+        for (name, raw_url) in raw_urls {
+            if let Some(file) = gist.files.get_mut(&name) {
+                file.content = self
+                    .client
+                    .get_cached(self.client.request("GET", &raw_url)?, Self::check_response)?;
+            }
+        }
+        Ok(gist)
+    }
+
+    fn file_url(&self, html_url: &str, filename: &str) -> String {
+        format!("{}#file-{}", html_url, filename)
+    }
+
+    fn name(&self) -> &'static str {
+        "Bitbucket snippet"
+    }
+}
@@ -1,8 +1,13 @@
+//! Errors, and how they're reported: as text, or as JSON for scripts (`--error-format json`),
+//! with an exit code per kind of failure
+use std::str::FromStr;
+
 use thiserror::Error;
 
 /// Errors which can be caused by normal the-way operation.
 /// Those caused by external libraries throw their own errors when possible
-#[derive(Debug, Error)]
+#[derive(Debug, Error, Serialize)]
+#[serde(tag = "kind")]
 pub enum LostTheWay {
     /// Thrown when trying to access an unrecorded language
     #[error("I don't know what {language:?} is.")]
@@ -10,9 +15,30 @@ pub enum LostTheWay {
     /// Thrown when trying to access a nonexistent snippet index
     #[error("You haven't written that snippet: {index:?}.")]
     SnippetNotFound { index: usize },
+    /// Thrown when no snippet has the index or short ID given
+    #[error("You haven't written a snippet with ID {id:?}.")]
+    SnippetIdNotFound { id: String },
+    /// Thrown when more than one snippet's short ID starts with the one given
+    #[error("More than one snippet ID starts with {id:?}.")]
+    AmbiguousId { id: String },
+    /// Thrown when the command line can't be parsed (unknown command, bad filter, ...)
+    #[error("UsageError: {message:?}")]
+    UsageError { message: String },
+    /// Thrown when another the-way process has the database open
+    #[error("DatabaseLocked: {path:?} is in use")]
+    DatabaseLocked { path: String },
     /// Thrown when trying to access an unrecorded tag
     #[error("You haven't tagged anything as {tag:?} yet.")]
     TagNotFound { tag: String },
+    /// Thrown by `the-way cp --last` when nothing has been copied
+    #[error("You haven't copied any snippets yet.")]
+    NoCopyHistory,
+    /// Thrown by `the-way undo` when there's nothing left to undo
+    #[error("Nothing to undo.")]
+    NothingToUndo,
+    /// Thrown by `the-way run` when the snippet's code fails
+    #[error("RunError: snippet #{index:?} exited with {status:?}")]
+    RunError { index: usize, status: String },
     /// Thrown when no text is returned from an external editor
     #[error("EditorError")]
     EditorError,
@@ -28,17 +54,204 @@ pub enum LostTheWay {
     /// Thrown when trying to load a syntax which hasn't been added / doesn't exist
     #[error("SyntaxError: {syntax:?}")]
     SyntaxError { syntax: String },
-    #[error("ClipboardError: Couldn't copy to clipboard")]
+    #[error("ClipboardError: Couldn't access the clipboard")]
     ClipboardError,
     #[error("SearchError: Search failed")]
     SearchError,
     /// Errors related to changing the configuration file
     #[error("ConfigError: {message:?}")]
     ConfigError { message: String },
+    /// Thrown when a snippet breaks one of the rules in the `[validation]` config section
+    #[error("ValidationError: {message:?}")]
+    ValidationError { message: String },
+    /// Thrown by `the-way doctor` when some checks fail
+    #[error("DoctorError: {failed:?} checks failed")]
+    DoctorError { failed: usize },
+    /// Thrown by `the-way selftest` when some fixtures don't survive a round trip
+    #[error("SelftestError: {failed:?} fixtures changed on the way")]
+    SelftestError { failed: usize },
     /// Sync Error
     #[error("SyncError: {message:?}")]
     SyncError { message: String },
+    /// Thrown when there's no remote collection to sync with (yet), the only time `sync` makes a new one
+    #[error("CollectionNotFound: {location:?}")]
+    CollectionNotFound { location: String },
+    /// Thrown when synced files are encrypted with another passphrase (or encryption is off)
+    #[error("DecryptionError: {message:?}")]
+    DecryptionError { message: String },
+    /// Thrown when `the-way serve` can't start
+    #[error("ServeError: {message:?}")]
+    ServeError { message: String },
+    /// Thrown when a file given to `the-way restore` isn't a backup
+    #[error("RestoreError: {message:?}")]
+    RestoreError { message: String },
+    /// Thrown when a file given to `the-way import` can't be read in the format it's imported from
+    #[error("ImportError: {message:?}")]
+    ImportError { message: String },
+    /// Thrown when writing snippets to the git repository fails
+    #[error("GitError: {message:?}")]
+    GitError { message: String },
+    /// Thrown when downloading a file fails
+    #[error("DownloadError: {message:?}")]
+    DownloadError { message: String },
     /// Catch-all for stuff that should never happen
     #[error("OutOfCheeseError: {message:?}\nRedo from start.")]
     OutOfCheeseError { message: String },
 }
+
+/// Kinds of failure, each with its own exit code. These stay the same between versions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorClass {
+    Other = 1,
+    /// Bad arguments, filters, or IDs
+    Usage = 2,
+    /// No such snippet, tag, language, theme, ...
+    NotFound = 3,
+    /// Another the-way process has the database open
+    Locked = 4,
+    Clipboard = 5,
+    /// Bad config file, or a snippet breaking the `[validation]` rules
+    Config = 6,
+    /// Sync or download failures
+    Sync = 7,
+    /// Nothing done, because it wasn't confirmed
+    Cancelled = 8,
+    /// A snippet run, `doctor`, or `selftest` failed
+    Failed = 9,
+}
+
+impl ErrorClass {
+    /// Class of `error` (`Other` for errors that don't come from the-way)
+    pub fn of(error: &color_eyre::Report) -> Self {
+        match error.downcast_ref::<LostTheWay>() {
+            Some(
+                LostTheWay::LanguageNotFound { .. }
+                | LostTheWay::SnippetNotFound { .. }
+                | LostTheWay::SnippetIdNotFound { .. }
+                | LostTheWay::TagNotFound { .. }
+                | LostTheWay::NoCopyHistory
+                | LostTheWay::NothingToUndo
+                | LostTheWay::ThemeError { .. }
+                | LostTheWay::SyntaxError { .. },
+            ) => Self::NotFound,
+            Some(LostTheWay::UsageError { .. } | LostTheWay::AmbiguousId { .. }) => Self::Usage,
+            Some(LostTheWay::DatabaseLocked { .. }) => Self::Locked,
+            Some(LostTheWay::ClipboardError) => Self::Clipboard,
+            Some(
+                LostTheWay::ConfigError { .. }
+                | LostTheWay::ValidationError { .. }
+                | LostTheWay::Homeless,
+            ) => Self::Config,
+            Some(
+                LostTheWay::SyncError { .. }
+                | LostTheWay::CollectionNotFound { .. }
+                | LostTheWay::DecryptionError { .. }
+                | LostTheWay::DownloadError { .. },
+            ) => Self::Sync,
+            Some(LostTheWay::DoingNothing) => Self::Cancelled,
+            Some(
+                LostTheWay::RunError { .. }
+                | LostTheWay::DoctorError { .. }
+                | LostTheWay::SelftestError { .. },
+            ) => Self::Failed,
+            _ => Self::Other,
+        }
+    }
+
+    /// Exit code for this class
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    /// Name used in JSON errors
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Other => "other",
+            Self::Usage => "usage",
+            Self::NotFound => "not_found",
+            Self::Locked => "locked",
+            Self::Clipboard => "clipboard",
+            Self::Config => "config",
+            Self::Sync => "sync",
+            Self::Cancelled => "cancelled",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// How errors are printed to stderr
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorFormat {
+    /// For people, with suggestions
+    Text,
+    /// One JSON object per error, for scripts and editor plugins
+    Json,
+}
+
+impl ErrorFormat {
+    /// Possible format names
+    pub const VARIANTS: [&'static str; 2] = ["text", "json"];
+
+    /// `--error-format` in `args`, for errors from before (or while) the command line is parsed
+    pub fn from_args<T: AsRef<std::ffi::OsStr>>(args: &[T]) -> Self {
+        let args: Vec<_> = args
+            .iter()
+            .map(|arg| arg.as_ref().to_string_lossy())
+            .collect();
+        args.iter()
+            .enumerate()
+            .find_map(|(i, arg)| match arg.strip_prefix("--error-format") {
+                Some("") => args.get(i + 1).map(|value| value.to_string()),
+                Some(value) => value.strip_prefix('=').map(String::from),
+                None => None,
+            })
+            .and_then(|format| format.parse().ok())
+            .unwrap_or(Self::Text)
+    }
+}
+
+impl FromStr for ErrorFormat {
+    type Err = LostTheWay;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(LostTheWay::UsageError {
+                message: format!("Unknown error format {:?}", other),
+            }),
+        }
+    }
+}
+
+/// Prints `error` to stderr in `format`, returns the exit code for its class.
+///
+/// JSON errors look like `{"kind": "SnippetNotFound", "class": "not_found", "code": 3,
+/// "message": "You haven't written that snippet: 12.", "details": {"index": 12}, "causes": []}`,
+/// with the kind "Other" (and no details) for errors that don't come from the-way
+pub fn report(error: &color_eyre::Report, format: ErrorFormat) -> i32 {
+    let class = ErrorClass::of(error);
+    match format {
+        ErrorFormat::Text => eprintln!("Error: {:?}", error),
+        ErrorFormat::Json => {
+            let mut details = error
+                .downcast_ref::<LostTheWay>()
+                .and_then(|error| serde_json::to_value(error).ok())
+                .unwrap_or_else(|| serde_json::json!({ "kind": "Other" }));
+            let kind = details
+                .as_object_mut()
+                .and_then(|details| details.remove("kind"))
+                .unwrap_or_else(|| serde_json::json!("Other"));
+            let json = serde_json::json!({
+                "kind": kind,
+                "class": class.name(),
+                "code": class.code(),
+                "message": error.to_string(),
+                "details": details,
+                "causes": error.chain().skip(1).map(ToString::to_string).collect::<Vec<_>>(),
+            });
+            eprintln!("{}", json);
+        }
+    }
+    class.code()
+}
@@ -5,6 +5,8 @@ use chrono::{DateTime, Utc};
 use color_eyre::Help;
 
 use crate::errors::LostTheWay;
+use crate::http::HttpClient;
+use crate::sync::{self, SyncClient};
 
 const GITHUB_API_URL: &str = "https://api.github.com";
 const GITHUB_BASE_PATH: &str = "";
@@ -39,32 +41,72 @@ pub struct Gist {
 
 #[derive(Deserialize, Debug)]
 pub struct GistFile {
-    filename: String,
+    pub filename: String,
     pub content: String,
 }
 
+/// One Gist in a list of a user's Gists: file contents aren't included, fetch them from `raw_url`
+#[derive(Deserialize, Debug)]
+pub struct GistListing {
+    pub html_url: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub files: HashMap<String, GistListingFile>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GistListingFile {
+    pub filename: String,
+    /// As GitHub names it, e.g. "Rust"
+    pub language: Option<String>,
+    pub raw_url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct GithubUser {
+    login: String,
+}
+
+/// Gists asked for per page when listing
+const PER_PAGE: usize = 100;
+
 pub struct GistClient {
-    client: ureq::Agent,
+    client: HttpClient,
 }
 
 impl GistClient {
-    /// Create a new Gist client
-    pub fn new(access_token: &str, user_agent: &str) -> color_eyre::Result<Self> {
-        let mut client = ureq::agent();
-        client
-            .set("Authorization", &format!("token {}", access_token))
-            .set("user-agent", user_agent)
-            .set("content-type", ACCEPT);
+    /// Create a new Gist client, sending requests through `client`
+    pub fn new(
+        mut client: HttpClient,
+        access_token: &str,
+        user_agent: &str,
+    ) -> color_eyre::Result<Self> {
+        client.set("Authorization", &format!("token {}", access_token));
+        Self::anonymous(client, user_agent)
+    }
+
+    /// Create a Gist client without an access token, it only sees public Gists
+    pub fn anonymous(mut client: HttpClient, user_agent: &str) -> color_eyre::Result<Self> {
+        client.set("user-agent", user_agent).set("accept", ACCEPT);
         Ok(Self { client })
     }
 
+    fn parse_gist(json: &str) -> color_eyre::Result<Gist> {
+        Ok(
+            serde_json::from_str::<Gist>(json).map_err(|e| LostTheWay::SyncError {
+                message: format!("{}", e),
+            })?,
+        )
+    }
+
     fn get_response(response: ureq::Response) -> color_eyre::Result<Gist> {
+        Self::parse_gist(&Self::check_response(response)?.into_string()?)
+    }
+
+    fn check_response(response: ureq::Response) -> color_eyre::Result<ureq::Response> {
         if response.ok() {
-            Ok(response
-                .into_json_deserialize::<Gist>()
-                .map_err(|e| LostTheWay::SyncError {
-                    message: format!("{}", e),
-                })?)
+            Ok(response)
         } else {
             Err(LostTheWay::SyncError {
                 message: format!("{} {}", response.status(), response.into_string()?),
@@ -82,8 +124,7 @@ impl GistClient {
         let url = format!("{}{}/gists", GITHUB_API_URL, GITHUB_BASE_PATH);
         let response = self
             .client
-            .post(&url)
-            .send_json(ureq::serde_to_value(payload)?);
+            .send_json(self.client.request("POST", &url)?, payload)?;
         Self::get_response(response)
     }
 
@@ -94,25 +135,116 @@ impl GistClient {
         payload: &UpdateGistPayload<'_>,
     ) -> color_eyre::Result<Gist> {
         let url = format!("{}{}/gists", GITHUB_API_URL, GITHUB_BASE_PATH);
-        let response = self
-            .client
-            .patch(&format!("{}/{}", url, gist_id))
-            .send_json(ureq::serde_to_value(payload)?);
+        let response = self.client.send_json(
+            self.client
+                .request("PATCH", &format!("{}/{}", url, gist_id))?,
+            payload,
+        )?;
         Self::get_response(response)
     }
 
     /// Retrieve a Gist by ID
     pub fn get_gist(&self, gist_id: &str) -> color_eyre::Result<Gist> {
         let url = format!("{}{}/gists", GITHUB_API_URL, GITHUB_BASE_PATH);
-        let response = self.client.get(&format!("{}/{}", url, gist_id)).call();
-        Self::get_response(response)
+        // Asking again for an unchanged Gist doesn't count against GitHub's rate limit
+        let json = self.client.get_cached(
+            self.client
+                .request("GET", &format!("{}/{}", url, gist_id))?,
+            |response| sync::check_found(gist_id, response, Self::check_response),
+        )?;
+        Self::parse_gist(&json)
+    }
+
+    /// Login of the user the access token belongs to
+    pub fn login(&self) -> color_eyre::Result<String> {
+        let url = format!("{}{}/user", GITHUB_API_URL, GITHUB_BASE_PATH);
+        let json = self
+            .client
+            .get_cached(self.client.request("GET", &url)?, Self::check_response)?;
+        let user: GithubUser = serde_json::from_str(&json).map_err(|e| LostTheWay::SyncError {
+            message: format!("{}", e),
+        })?;
+        Ok(user.login)
+    }
+
+    /// Lists every Gist `username` has, or with `None`, every Gist (secret ones too) of the token's user
+    pub fn list_gists(&self, username: Option<&str>) -> color_eyre::Result<Vec<GistListing>> {
+        let url = match username {
+            Some(username) => format!(
+                "{}{}/users/{}/gists",
+                GITHUB_API_URL, GITHUB_BASE_PATH, username
+            ),
+            None => format!("{}{}/gists", GITHUB_API_URL, GITHUB_BASE_PATH),
+        };
+        let mut gists = Vec::new();
+        for page in 1.. {
+            let json = self.client.get_cached(
+                self.client.request(
+                    "GET",
+                    &format!("{}?per_page={}&page={}", url, PER_PAGE, page),
+                )?,
+                |response| match response.status() {
+                    404 => Err(LostTheWay::DownloadError {
+                        message: format!("No GitHub user {}", username.unwrap_or_default()),
+                    })
+                    .suggestion("Check the username"),
+                    _ => Self::check_response(response),
+                },
+            )?;
+            let listings: Vec<GistListing> =
+                serde_json::from_str(&json).map_err(|e| LostTheWay::DownloadError {
+                    message: format!("Couldn't read the list of Gists: {}", e),
+                })?;
+            let last = listings.len() < PER_PAGE;
+            gists.extend(listings);
+            if last {
+                break;
+            }
+        }
+        Ok(gists)
+    }
+
+    /// Contents of a Gist file, from its `raw_url`
+    pub fn get_raw(&self, raw_url: &str) -> color_eyre::Result<String> {
+        self.client
+            .get_cached(self.client.request("GET", raw_url)?, Self::check_response)
     }
 
     /// Delete Gist by ID
     pub fn delete_gist(&self, gist_id: &str) -> color_eyre::Result<()> {
         let url = format!("{}{}/gists", GITHUB_API_URL, GITHUB_BASE_PATH);
-        let status = self.client.delete(&format!("{}/{}", url, gist_id)).call();
+        let status = self.client.send(
+            self.client
+                .request("DELETE", &format!("{}/{}", url, gist_id))?,
+            None,
+        );
         assert!(status.ok());
         Ok(())
     }
 }
+
+impl SyncClient for GistClient {
+    fn create_gist(&self, payload: &CreateGistPayload<'_>) -> color_eyre::Result<Gist> {
+        GistClient::create_gist(self, payload)
+    }
+
+    fn update_gist(
+        &self,
+        gist_id: &str,
+        payload: &UpdateGistPayload<'_>,
+    ) -> color_eyre::Result<Gist> {
+        GistClient::update_gist(self, gist_id, payload)
+    }
+
+    fn get_gist(&self, gist_id: &str) -> color_eyre::Result<Gist> {
+        GistClient::get_gist(self, gist_id)
+    }
+
+    fn file_url(&self, html_url: &str, filename: &str) -> String {
+        format!("{}#file-{}", html_url, filename.replace('.', "-"))
+    }
+
+    fn name(&self) -> &'static str {
+        "Gist"
+    }
+}
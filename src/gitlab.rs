@@ -0,0 +1,236 @@
+//! Simple GitLab snippets API wrapper, for syncing with `sync_provider = "gitlab"`
+use chrono::{DateTime, Utc};
+use color_eyre::Help;
+
+use crate::errors::LostTheWay;
+use crate::gist::{CreateGistPayload, Gist, GistFile, UpdateGistPayload};
+use crate::http::HttpClient;
+use crate::sync::{self, SyncClient};
+
+pub const GITLAB_URL: &str = "https://gitlab.com";
+const GITLAB_BASE_PATH: &str = "/api/v4";
+
+#[derive(Serialize, Debug)]
+struct SnippetFile<'a> {
+    file_path: &'a str,
+    content: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+struct CreateSnippetPayload<'a> {
+    title: &'a str,
+    visibility: &'a str,
+    files: Vec<SnippetFile<'a>>,
+}
+
+/// Change to one file of a snippet ("create", "update", or "delete")
+#[derive(Serialize, Debug)]
+struct SnippetFileAction<'a> {
+    action: &'a str,
+    file_path: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<&'a str>,
+}
+
+#[derive(Serialize, Debug)]
+struct UpdateSnippetPayload<'a> {
+    title: &'a str,
+    files: Vec<SnippetFileAction<'a>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitlabSnippet {
+    id: u64,
+    title: String,
+    web_url: String,
+    updated_at: DateTime<Utc>,
+    #[serde(default)]
+    files: Vec<GitlabSnippetFile>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GitlabSnippetFile {
+    path: String,
+    raw_url: String,
+}
+
+impl GitlabSnippet {
+    /// Same shape as a Gist, so syncing works the same way (file contents are left empty)
+    fn into_gist(self) -> Gist {
+        let files = self
+            .files
+            .into_iter()
+            .map(|file| {
+                (
+                    file.path.to_owned(),
+                    GistFile {
+                        filename: file.path,
+                        content: String::new(),
+                    },
+                )
+            })
+            .collect();
+        Gist {
+            html_url: self.web_url,
+            id: self.id.to_string(),
+            updated_at: self.updated_at,
+            description: self.title,
+            files,
+        }
+    }
+}
+
+pub struct GitlabClient {
+    client: HttpClient,
+    /// e.g. https://gitlab.com/api/v4, or a self-hosted instance
+    api_url: String,
+}
+
+impl GitlabClient {
+    /// Create a new GitLab client for the instance at `url`, sending requests through `client`
+    pub fn new(
+        mut client: HttpClient,
+        url: &str,
+        access_token: &str,
+        user_agent: &str,
+    ) -> color_eyre::Result<Self> {
+        client
+            .set("PRIVATE-TOKEN", access_token)
+            .set("user-agent", user_agent);
+        Ok(Self {
+            client,
+            api_url: format!("{}{}", url.trim_end_matches('/'), GITLAB_BASE_PATH),
+        })
+    }
+
+    fn check_response(response: ureq::Response) -> color_eyre::Result<ureq::Response> {
+        if response.ok() {
+            Ok(response)
+        } else {
+            Err(LostTheWay::SyncError {
+                message: format!("{} {}", response.status(), response.into_string()?),
+            })
+            .suggestion(
+                "Make sure your GitLab access token is valid and has the \"api\" scope.\n\
+        Get one from <your GitLab URL>/-/profile/personal_access_tokens.\n\
+        Set it to the environment variable $THE_WAY_GITLAB_TOKEN",
+            )
+        }
+    }
+
+    fn parse_snippet(json: &str) -> color_eyre::Result<GitlabSnippet> {
+        Ok(
+            serde_json::from_str::<GitlabSnippet>(json).map_err(|e| LostTheWay::SyncError {
+                message: format!("{}", e),
+            })?,
+        )
+    }
+
+    fn get_response(response: ureq::Response) -> color_eyre::Result<GitlabSnippet> {
+        Self::parse_snippet(&Self::check_response(response)?.into_string()?)
+    }
+
+    fn snippet_url(&self, snippet_id: &str) -> String {
+        format!("{}/snippets/{}", self.api_url, snippet_id)
+    }
+
+    /// Snippet metadata, without file contents
+    fn get_snippet(&self, snippet_id: &str) -> color_eyre::Result<GitlabSnippet> {
+        Self::parse_snippet(&self.client.get_cached(
+            self.client.request("GET", &self.snippet_url(snippet_id))?,
+            |response| sync::check_found(snippet_id, response, Self::check_response),
+        )?)
+    }
+}
+
+impl SyncClient for GitlabClient {
+    fn create_gist(&self, payload: &CreateGistPayload<'_>) -> color_eyre::Result<Gist> {
+        let files = payload
+            .files
+            .iter()
+            .map(|(file_path, file)| SnippetFile {
+                file_path,
+                content: file.content,
+            })
+            .collect();
+        let payload = CreateSnippetPayload {
+            title: payload.description,
+            visibility: if payload.public { "public" } else { "private" },
+            files,
+        };
+        let response = self.client.send_json(
+            self.client
+                .request("POST", &format!("{}/snippets", self.api_url))?,
+            &payload,
+        )?;
+        Ok(Self::get_response(response)?.into_gist())
+    }
+
+    fn update_gist(
+        &self,
+        gist_id: &str,
+        payload: &UpdateGistPayload<'_>,
+    ) -> color_eyre::Result<Gist> {
+        // GitLab needs to be told whether a file is new
+        let existing: Vec<_> = self
+            .get_snippet(gist_id)?
+            .files
+            .into_iter()
+            .map(|file| file.path)
+            .collect();
+        let files = payload
+            .files
+            .iter()
+            .filter_map(|(file_path, file)| {
+                let exists = existing.contains(file_path);
+                let action = match (file, exists) {
+                    (Some(_), true) => "update",
+                    (Some(_), false) => "create",
+                    (None, true) => "delete",
+                    (None, false) => return None,
+                };
+                Some(SnippetFileAction {
+                    action,
+                    file_path,
+                    content: file.as_ref().map(|file| file.content),
+                })
+            })
+            .collect();
+        let payload = UpdateSnippetPayload {
+            title: payload.description,
+            files,
+        };
+        let response = self.client.send_json(
+            self.client.request("PUT", &self.snippet_url(gist_id))?,
+            &payload,
+        )?;
+        Ok(Self::get_response(response)?.into_gist())
+    }
+
+    fn get_gist(&self, gist_id: &str) -> color_eyre::Result<Gist> {
+        let snippet = self.get_snippet(gist_id)?;
+        let raw_urls: Vec<_> = snippet
+            .files
+            .iter()
+            .map(|file| (file.path.to_owned(), file.raw_url.to_owned()))
+            .collect();
+        let mut gist = snippet.into_gist();
+        for (path, raw_url) in raw_urls {
+            if let Some(file) = gist.files.get_mut(&path) {
+                file.content = self
+                    .client
+                    .get_cached(self.client.request("GET", &raw_url)?, Self::check_response)?;
+            }
+        }
+        Ok(gist)
+    }
+
+    fn file_url(&self, html_url: &str, _filename: &str) -> String {
+        // GitLab shows every file of a snippet on one page
+        html_url.to_owned()
+    }
+
+    fn name(&self) -> &'static str {
+        "GitLab snippet"
+    }
+}
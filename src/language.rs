@@ -1,6 +1,7 @@
 //! Language specific code like highlighting and extensions
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
 use color_eyre::Help;
@@ -8,12 +9,76 @@ use hex::FromHex;
 use serde_yaml::Value;
 use syntect::easy::HighlightLines;
 use syntect::highlighting::{Color, FontStyle, Style, StyleModifier, ThemeSet};
-use syntect::parsing::{SyntaxDefinition, SyntaxSet};
+use syntect::html::highlighted_html_for_string;
+use syntect::parsing::{SyntaxDefinition, SyntaxReference, SyntaxSet};
 use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
 
+use crate::configuration::{self, LanguageConfig};
 use crate::errors::LostTheWay;
+use crate::http::HttpClient;
 use crate::utils;
 
+/// Name of the file storing the selected theme in a theme bundle
+const SELECTED_THEME_FILE: &str = "selected_theme";
+
+/// Latest languages.yml from GitHub Linguist
+pub const LINGUIST_URL: &str =
+    "https://raw.githubusercontent.com/github/linguist/master/lib/linguist/languages.yml";
+
+/// Extensions of files stored in the themes folder
+const THEME_EXTENSIONS: [&str; 2] = ["tmTheme", "sublime-syntax"];
+
+/// Okabe-Ito colors, distinguishable with any kind of color vision deficiency
+const COLORBLIND_ORANGE: Color = Color {
+    r: 0xE6,
+    g: 0x9F,
+    b: 0x00,
+    a: 0xFF,
+};
+const COLORBLIND_SKY_BLUE: Color = Color {
+    r: 0x56,
+    g: 0xB4,
+    b: 0xE9,
+    a: 0xFF,
+};
+const COLORBLIND_BLUE: Color = Color {
+    r: 0x00,
+    g: 0x72,
+    b: 0xB2,
+    a: 0xFF,
+};
+
+/// Shifts the red/green information a deuteranope can't see into brightness and blue,
+/// i.e. daltonization using the LMS color space
+fn daltonize(color: Color) -> Color {
+    let (r, g, b) = (color.r as f32, color.g as f32, color.b as f32);
+    // RGB -> LMS
+    let l = 17.8824 * r + 43.5161 * g + 4.11935 * b;
+    let s = 0.0299566 * r + 0.184309 * g + 1.46709 * b;
+    // Simulate deuteranopia (M cone values are guessed from L and S)
+    let m = 0.494207 * l + 1.24827 * s;
+    // LMS -> RGB
+    let sim_r = 0.080_944_45 * l - 0.130_504_41 * m + 0.116_721_07 * s;
+    let sim_g = -0.010_248_534 * l + 0.054_019_33 * m - 0.113_614_71 * s;
+    let sim_b = -0.000_365_296_94 * l - 0.004_121_614_7 * m + 0.693_511_4 * s;
+    // Move the lost information to channels that can be seen
+    let (error_r, error_g, error_b) = (r - sim_r, g - sim_g, b - sim_b);
+    let clamp = |x: f32| x.clamp(0., 255.) as u8;
+    Color {
+        r: clamp(r),
+        g: clamp(g + 0.7 * error_r + error_g),
+        b: clamp(b + 0.7 * error_r + error_b),
+        a: color.a,
+    }
+}
+
+/// Checks if a file is a theme or syntax file
+fn is_theme_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| THEME_EXTENSIONS.contains(&x))
+}
+
 /// Relevant information from languages.yml file
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 struct LanguageYML {
@@ -28,7 +93,7 @@ struct LanguageYML {
 #[derive(Debug, Clone)]
 pub struct Language {
     name: String,
-    extension: String,
+    pub(crate) extension: String,
     pub(crate) color: Color,
 }
 
@@ -50,7 +115,7 @@ impl Language {
     fn get_color(color_string: Option<String>) -> color_eyre::Result<Color> {
         let mut language_color = [0; 3];
         if let Some(color) = color_string {
-            language_color = <[u8; 3]>::from_hex(&color.get(1..).unwrap_or("FFFFFF"))?;
+            language_color = <[u8; 3]>::from_hex(color.get(1..).unwrap_or("FFFFFF"))?;
         }
         Ok(Color {
             r: language_color[0],
@@ -73,10 +138,36 @@ impl Language {
             default.extension
         }
     }
+
+    /// Finds a language for a file extension: `guess` if it uses that extension,
+    /// otherwise the shortest language name (not alias) using it, or "text"
+    pub(crate) fn from_extension(
+        extension: &str,
+        guess: &str,
+        languages: &HashMap<String, Self>,
+    ) -> String {
+        if languages
+            .get(guess)
+            .is_some_and(|language| language.extension == extension)
+        {
+            return guess.to_owned();
+        }
+        languages
+            .iter()
+            .filter(|(key, language)| {
+                // Lowercase copies of language names, aliases keep their own name
+                language.extension == extension
+                    && key.as_str() != language.name
+                    && **key == language.name.to_ascii_lowercase()
+            })
+            .map(|(key, _)| key)
+            .min_by_key(|key| (key.len(), key.as_str()))
+            .cloned()
+            .unwrap_or_else(|| String::from("text"))
+    }
 }
 
 /// Loads language information from GitHub's languages.yml file
-// TODO: find a way to keep this up to date without downloading it every time
 fn read_languages_from_yml(yml_string: &str) -> color_eyre::Result<HashMap<String, LanguageYML>> {
     let language_strings: HashMap<String, Value> = serde_yaml::from_str(yml_string)?;
     let mut languages = HashMap::with_capacity(language_strings.len());
@@ -105,6 +196,127 @@ pub fn get_languages(yml_string: &str) -> color_eyre::Result<HashMap<String, Lan
     Ok(name_to_language)
 }
 
+/// Loads languages from the copy downloaded by `the-way languages update` if there is one,
+/// otherwise (or if it's broken) from `bundled_yml_string`
+pub fn load_languages(bundled_yml_string: &str) -> color_eyre::Result<HashMap<String, Language>> {
+    if let Ok(languages_file) = configuration::languages_file() {
+        if languages_file.exists() {
+            match fs::read_to_string(&languages_file)
+                .map_err(color_eyre::Report::from)
+                .and_then(|yml_string| get_languages(&yml_string))
+            {
+                Ok(languages) => return Ok(languages),
+                Err(e) => eprintln!(
+                    "Couldn't read {} ({}), using the bundled languages instead. \
+                    Run `the-way languages update` to download it again",
+                    languages_file.display(),
+                    e
+                ),
+            }
+        }
+    }
+    get_languages(bundled_yml_string)
+}
+
+/// Downloads languages.yml from `url` to the data directory, it's used instead of the bundled one from then on
+pub(crate) fn update_languages(client: &HttpClient, url: &str) -> color_eyre::Result<()> {
+    let spinner = utils::get_spinner("Downloading languages.yml...");
+    let yml_string = client.get_cached(client.request("GET", url)?, |response| {
+        if response.ok() {
+            return Ok(response);
+        }
+        spinner.finish_and_clear();
+        let error: color_eyre::Result<ureq::Response> = Err(LostTheWay::DownloadError {
+            message: format!("{} {}", response.status(), response.status_text()),
+        }
+        .into());
+        error.suggestion(format!(
+            "Check your internet connection and that {} exists",
+            url
+        ))
+    })?;
+    spinner.finish_and_clear();
+    let num_languages = read_languages_from_yml(&yml_string)
+        .map_err(|e| LostTheWay::OutOfCheeseError {
+            message: format!("{} isn't a valid languages.yml: {}", url, e),
+        })?
+        .len();
+    let languages_file = configuration::languages_file()?;
+    if let Some(dir) = languages_file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Write to a temporary file first so a failed write doesn't leave a broken languages.yml
+    let temporary_file = languages_file.with_extension("yml.part");
+    fs::write(&temporary_file, yml_string)?;
+    fs::rename(&temporary_file, &languages_file)?;
+    println!(
+        "Saved {} languages to {}",
+        num_languages,
+        languages_file.display()
+    );
+    Ok(())
+}
+
+/// Removes the downloaded languages.yml, going back to the bundled one
+pub(crate) fn reset_languages() -> color_eyre::Result<()> {
+    let languages_file = configuration::languages_file()?;
+    if languages_file.exists() {
+        fs::remove_file(&languages_file)?;
+        println!(
+            "Removed {}, using the bundled languages",
+            languages_file.display()
+        );
+    } else {
+        println!("Already using the bundled languages");
+    }
+    Ok(())
+}
+
+/// Adds languages from the `[languages]` config section, or changes the languages.yml information of existing ones
+pub(crate) fn apply_language_config(
+    languages: &mut HashMap<String, Language>,
+    language_configs: &BTreeMap<String, LanguageConfig>,
+) -> color_eyre::Result<()> {
+    for (name, language_config) in language_configs {
+        let existing = languages
+            .get(name)
+            .or_else(|| languages.get(&name.to_ascii_lowercase()))
+            .cloned();
+        let mut language = match (existing, &language_config.extension) {
+            (Some(language), _) => language,
+            (None, Some(extension)) => Language::new(name.to_owned(), extension.to_owned(), None)?,
+            (None, None) => {
+                let error: color_eyre::Result<()> = Err(LostTheWay::LanguageNotFound {
+                    language: name.to_owned(),
+                }
+                .into());
+                return error.suggestion(format!(
+                    "Set an extension for new languages, e.g. `extension = \".{}\"` in [languages.{}]",
+                    name.to_ascii_lowercase(),
+                    name
+                ));
+            }
+        };
+        if let Some(extension) = &language_config.extension {
+            language.extension = extension.to_owned();
+        }
+        if let Some(color) = &language_config.color {
+            language.color = Language::get_color(Some(color.to_owned())).suggestion(format!(
+                "Colors look like \"#FF8800\", check [languages.{}]",
+                name
+            ))?;
+        }
+        language.name = name.to_owned();
+        languages.insert(name.to_ascii_lowercase(), language.clone());
+        languages.insert(name.to_owned(), language.clone());
+        for alias in &language_config.aliases {
+            language.name = alias.to_owned();
+            languages.insert(alias.to_owned(), language.clone());
+        }
+    }
+    Ok(())
+}
+
 pub(crate) struct CodeHighlight {
     syntax_set: SyntaxSet,
     theme_set: ThemeSet,
@@ -118,17 +330,25 @@ pub(crate) struct CodeHighlight {
     pub(crate) tag_style: Style,
     /// Style in `skim` when selecting during search
     pub(crate) highlight_style: Style,
+    /// Avoid red/green-only distinctions
+    colorblind: bool,
+    /// Syntax names to use for extensions, from the `[languages]` config section
+    syntax_overrides: HashMap<String, String>,
 }
 
 impl CodeHighlight {
     /// Loads themes from `theme_dir` and default syntax set.
     /// Sets highlighting styles
-    pub(crate) fn new(theme: &str, syntect_dir: PathBuf) -> color_eyre::Result<Self> {
+    pub(crate) fn new(
+        theme: &str,
+        syntect_dir: PathBuf,
+        colorblind: bool,
+    ) -> color_eyre::Result<Self> {
         let mut theme_set = ThemeSet::load_defaults();
         theme_set
             .add_from_folder(&syntect_dir)
             .map_err(|_| LostTheWay::ThemeError {
-                theme: String::from((&syntect_dir).to_str().unwrap()),
+                theme: String::from(syntect_dir.to_str().unwrap()),
             })
             .suggestion(format!(
                 "Make sure {:#?} is a valid directory that has .tmTheme files",
@@ -138,7 +358,7 @@ impl CodeHighlight {
         syntax_set
             .add_from_folder(&syntect_dir, true)
             .map_err(|_| LostTheWay::ThemeError {
-                theme: String::from((&syntect_dir).to_str().unwrap()),
+                theme: String::from(syntect_dir.to_str().unwrap()),
             })
             .suggestion(format!(
                 "Make sure {:#?} is a valid directory that has .sublime-syntax files",
@@ -154,6 +374,8 @@ impl CodeHighlight {
             accent_style: Style::default(),
             tag_style: Style::default(),
             highlight_style: Style::default(),
+            colorblind,
+            syntax_overrides: HashMap::new(),
         };
         highlighter.set_styles();
         Ok(highlighter)
@@ -165,6 +387,25 @@ impl CodeHighlight {
         self.set_accent_style();
         self.set_tag_style();
         self.set_highlight_style();
+        if self.colorblind {
+            self.set_colorblind_styles();
+        }
+    }
+
+    /// Swaps theme colors for metadata with colorblind-friendly ones
+    fn set_colorblind_styles(&mut self) {
+        self.accent_style.foreground = COLORBLIND_ORANGE;
+        self.tag_style.foreground = COLORBLIND_SKY_BLUE;
+        self.highlight_style.foreground = COLORBLIND_BLUE;
+    }
+
+    /// Foreground color adjusted for colorblind mode
+    fn adjust_color(&self, color: Color) -> Color {
+        if self.colorblind {
+            daltonize(color)
+        } else {
+            color
+        }
     }
 
     /// Style used to print description
@@ -224,6 +465,32 @@ impl CodeHighlight {
         });
     }
 
+    /// Highlights code with these extensions using the syntax set in the `[languages]` config section
+    pub(crate) fn set_syntax_overrides(
+        &mut self,
+        language_configs: &BTreeMap<String, LanguageConfig>,
+        languages: &HashMap<String, Language>,
+    ) -> color_eyre::Result<()> {
+        for (name, language_config) in language_configs {
+            if let Some(syntax) = &language_config.syntax {
+                if self.syntax_set.find_syntax_by_name(syntax).is_none() {
+                    let error: color_eyre::Result<()> = Err(LostTheWay::SyntaxError {
+                        syntax: syntax.to_owned(),
+                    }
+                    .into());
+                    return error.suggestion(format!(
+                        "Use the name of a syntax syntect knows (like \"Rust\") in [languages.{}], \
+                        or add one with `the-way themes language <file.sublime-syntax>`",
+                        name
+                    ));
+                }
+                let extension = Language::get_extension(name, languages);
+                self.syntax_overrides.insert(extension, syntax.to_owned());
+            }
+        }
+        Ok(())
+    }
+
     /// Sets the current theme
     pub(crate) fn set_theme(&mut self, theme_name: String) -> color_eyre::Result<()> {
         if self.theme_set.themes.contains_key(&theme_name) {
@@ -255,7 +522,7 @@ impl CodeHighlight {
     /// The file is copied to the themes folder
     // TODO: should it automatically be set?
     pub(crate) fn add_theme(&mut self, theme_file: &Path) -> color_eyre::Result<()> {
-        let theme = ThemeSet::get_theme(&theme_file)
+        let theme = ThemeSet::get_theme(theme_file)
             .map_err(|_| LostTheWay::ThemeError {
                 theme: theme_file.to_str().unwrap().into(),
             })
@@ -277,11 +544,27 @@ impl CodeHighlight {
         Ok(())
     }
 
+    /// Adds a new theme from the contents of a .tmTheme file.
+    /// The theme is saved to the themes folder
+    pub(crate) fn add_theme_from_string(
+        &mut self,
+        theme_name: &str,
+        contents: &str,
+    ) -> color_eyre::Result<()> {
+        let new_theme_file = self.syntect_dir.join(format!("{}.tmTheme", theme_name));
+        fs::write(&new_theme_file, contents)?;
+        let theme = ThemeSet::get_theme(&new_theme_file).map_err(|_| LostTheWay::ThemeError {
+            theme: theme_name.into(),
+        })?;
+        self.theme_set.themes.insert(theme_name.to_owned(), theme);
+        Ok(())
+    }
+
     /// Adds a new language syntax from a .sublime-syntax file.
     /// The file is copied to the themes folder
     pub(crate) fn add_syntax(&mut self, syntax_file: &Path) -> color_eyre::Result<()> {
         SyntaxDefinition::load_from_str(
-            &fs::read_to_string(&syntax_file)?,
+            &fs::read_to_string(syntax_file)?,
             true,
             None,
         )
@@ -305,12 +588,87 @@ impl CodeHighlight {
         Ok(())
     }
 
+    /// Writes a gzipped tar file with every theme and syntax in the themes folder
+    /// and the name of the current theme
+    pub(crate) fn export_themes(&self, bundle_file: &Path) -> color_eyre::Result<()> {
+        let encoder = flate2::write::GzEncoder::new(
+            fs::File::create(bundle_file)?,
+            flate2::Compression::default(),
+        );
+        let mut archive = tar::Builder::new(encoder);
+        let mut num_files = 0;
+        for path in fs::read_dir(&self.syntect_dir)? {
+            let path = path?.path();
+            if let (true, Some(filename)) = (is_theme_file(&path), path.file_name()) {
+                archive.append_path_with_name(&path, filename)?;
+                num_files += 1;
+            }
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_size(self.theme_name.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        archive.append_data(&mut header, SELECTED_THEME_FILE, self.theme_name.as_bytes())?;
+        archive.into_inner()?.finish()?;
+        println!(
+            "Exported {} theme file(s) and the current theme ({}) to {}",
+            num_files,
+            self.theme_name,
+            bundle_file.display()
+        );
+        Ok(())
+    }
+
+    /// Copies themes and syntaxes from a bundle made by `export_themes` to the themes folder
+    /// and sets the bundled theme as the current theme.
+    /// Returns the new theme name (if the bundle had one)
+    pub(crate) fn import_themes(
+        &mut self,
+        bundle_file: &Path,
+    ) -> color_eyre::Result<Option<String>> {
+        let mut archive =
+            tar::Archive::new(flate2::read::GzDecoder::new(fs::File::open(bundle_file)?));
+        let mut theme_name = None;
+        let mut num_files = 0;
+        for entry in archive
+            .entries()
+            .map_err(|_| LostTheWay::ThemeError {
+                theme: bundle_file.to_string_lossy().into(),
+            })
+            .suggestion("Make sure this is a bundle made with `the-way themes export`")?
+        {
+            let mut entry = entry?;
+            let filename = match entry.path()?.file_name() {
+                Some(filename) => filename.to_owned(),
+                None => continue,
+            };
+            if filename == SELECTED_THEME_FILE {
+                let mut name = String::new();
+                entry.read_to_string(&mut name)?;
+                theme_name = Some(name.trim().to_owned());
+            } else if is_theme_file(Path::new(&filename)) {
+                entry.unpack(self.syntect_dir.join(&filename))?;
+                num_files += 1;
+            }
+        }
+        // Reload to pick up the new themes and syntaxes
+        let syntax_overrides = std::mem::take(&mut self.syntax_overrides);
+        *self = Self::new(&self.theme_name, self.syntect_dir.clone(), self.colorblind)?;
+        self.syntax_overrides = syntax_overrides;
+        println!("Imported {} theme file(s)", num_files);
+        if let Some(theme_name) = &theme_name {
+            self.set_theme(theme_name.to_owned())?;
+            println!("Theme set to {}", theme_name);
+        }
+        Ok(theme_name)
+    }
+
     /// Makes a box colored according to GitHub language colors
-    pub(crate) fn highlight_block(language_color: Color) -> color_eyre::Result<String> {
+    pub(crate) fn highlight_block(&self, language_color: Color) -> color_eyre::Result<String> {
         Ok(Self::highlight_string(
             &format!("{} ", utils::BOX),
             Style::default().apply(StyleModifier {
-                foreground: Some(language_color),
+                foreground: Some(self.adjust_color(language_color)),
                 background: None,
                 font_style: None,
             }),
@@ -322,6 +680,20 @@ impl CodeHighlight {
         as_24_bit_terminal_escaped(&[(style, line)], false)
     }
 
+    /// Syntax for code in files with `extension` (or plain text)
+    fn syntax(&self, extension: &str) -> &SyntaxReference {
+        let syntax = match self.syntax_overrides.get(extension) {
+            Some(syntax) => self.syntax_set.find_syntax_by_name(syntax),
+            None => self
+                .syntax_set
+                .find_syntax_by_extension(extension.split('.').nth(1).unwrap_or("txt")),
+        };
+        match syntax {
+            Some(syntax) => syntax,
+            None => self.syntax_set.find_syntax_by_extension("txt").unwrap(),
+        }
+    }
+
     /// Syntax highlight code block
     pub(crate) fn highlight_code(
         &self,
@@ -329,18 +701,52 @@ impl CodeHighlight {
         extension: &str,
     ) -> color_eyre::Result<Vec<String>> {
         let mut colorized = Vec::new();
-        let extension = extension.split('.').nth(1).unwrap_or("txt");
-        let syntax = self.syntax_set.find_syntax_by_extension(extension);
-        let syntax = match syntax {
-            Some(syntax) => syntax,
-            None => self.syntax_set.find_syntax_by_extension("txt").unwrap(),
-        };
+        let syntax = self.syntax(extension);
         let mut h = HighlightLines::new(syntax, &self.theme_set.themes[&self.theme_name]);
         for line in LinesWithEndings::from(code) {
-            let ranges: Vec<(Style, &str)> = h.highlight(line, &self.syntax_set);
+            let mut ranges: Vec<(Style, &str)> = h.highlight(line, &self.syntax_set);
+            for (style, _) in &mut ranges {
+                style.foreground = self.adjust_color(style.foreground);
+            }
             let escaped = as_24_bit_terminal_escaped(&ranges[..], false);
             colorized.push(escaped);
         }
         Ok(colorized)
     }
+
+    /// Syntax highlighted lines of `code`, as pieces of text and their colors, for printing on white paper:
+    /// in a light theme whatever the current one is
+    pub(crate) fn highlight_for_print(
+        &self,
+        code: &str,
+        extension: &str,
+    ) -> Vec<Vec<(Color, String)>> {
+        let theme = self
+            .theme_set
+            .themes
+            .get("InspiredGitHub")
+            .unwrap_or(&self.theme_set.themes[&self.theme_name]);
+        let mut h = HighlightLines::new(self.syntax(extension), theme);
+        LinesWithEndings::from(code)
+            .map(|line| {
+                h.highlight(line, &self.syntax_set)
+                    .into_iter()
+                    .map(|(style, text)| {
+                        let text = text.trim_end_matches(['\n', '\r']);
+                        (style.foreground, text.to_owned())
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Syntax highlighted code block as HTML (a `<pre>` with inline styles, in the current theme's colors)
+    pub(crate) fn highlight_html(&self, code: &str, extension: &str) -> String {
+        highlighted_html_for_string(
+            code,
+            &self.syntax_set,
+            self.syntax(extension),
+            &self.theme_set.themes[&self.theme_name],
+        )
+    }
 }
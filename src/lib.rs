@@ -1,9 +1,15 @@
 #[macro_use]
 extern crate serde_derive;
 
+pub mod bitbucket;
 pub mod configuration;
-mod errors;
+pub mod errors;
 pub mod gist;
+pub mod gitlab;
+pub mod http;
 pub mod language;
+pub mod sync;
+mod terminal_palette;
 pub mod the_way;
 mod utils;
+mod zip;
@@ -1,13 +1,21 @@
-use structopt::StructOpt;
-
-use the_way::language::get_languages;
+use the_way::errors::{self, ErrorFormat};
+use the_way::language::load_languages;
 use the_way::the_way::{cli::TheWayCLI, TheWay};
 
-fn main() -> color_eyre::Result<()> {
-    color_eyre::install()?;
+fn main() {
+    let args: Vec<_> = std::env::args_os().collect();
+    let error_format = ErrorFormat::from_args(&args);
+    if let Err(error) = color_eyre::install() {
+        std::process::exit(errors::report(&error, error_format));
+    }
+    let cli = match TheWayCLI::parse(args, error_format) {
+        Ok(cli) => cli,
+        Err(code) => std::process::exit(code),
+    };
     let languages_yml = include_str!("languages.yml");
-    let languages = get_languages(languages_yml)?;
-    let cli = TheWayCLI::from_args();
-    TheWay::start(cli, languages)?;
-    Ok(())
+    if let Err(error) =
+        load_languages(languages_yml).and_then(|languages| TheWay::start(cli, languages))
+    {
+        std::process::exit(errors::report(&error, error_format));
+    }
 }
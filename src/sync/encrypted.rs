@@ -0,0 +1,269 @@
+//! End-to-end encrypted sync: `EncryptedClient` wraps another `SyncClient`,
+//! encrypting file contents before they're uploaded and decrypting them after they're downloaded,
+//! so the remote only ever sees ciphertext (file names, and so snippet indices and extensions, aren't encrypted).
+//!
+//! Files are sealed with ChaCha20-Poly1305, using a key derived from a passphrase with PBKDF2-HMAC-SHA256.
+//! Each encrypted file is a header line followed by base64 of salt, nonce, and ciphertext
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+use color_eyre::Help;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::errors::LostTheWay;
+use crate::gist::{CreateGistPayload, Gist, GistContent, UpdateGistPayload};
+use crate::sync::SyncClient;
+
+/// First line of every encrypted file
+const HEADER: &str = "the-way-encrypted:v1\n";
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// Encrypts and decrypts everything going through `inner` with a passphrase.
+/// Without one, files are passed through as they are, but encrypted files are refused
+pub struct EncryptedClient<'a> {
+    inner: &'a dyn SyncClient,
+    passphrase: Option<String>,
+    rng: SystemRandom,
+    /// Salt for files encrypted in this sync, so the key is only derived once
+    salt: [u8; SALT_LEN],
+    /// Keys derived so far, by salt
+    keys: RefCell<HashMap<[u8; SALT_LEN], [u8; KEY_LEN]>>,
+    /// Whether files uploaded before encryption was turned on are encrypted when they're seen
+    encrypt_plain: bool,
+}
+
+impl<'a> EncryptedClient<'a> {
+    pub fn new(inner: &'a dyn SyncClient, passphrase: Option<String>) -> color_eyre::Result<Self> {
+        let rng = SystemRandom::new();
+        let mut salt = [0; SALT_LEN];
+        rng.fill(&mut salt).map_err(|_| LostTheWay::SyncError {
+            message: String::from("Couldn't get random bytes for encryption"),
+        })?;
+        Ok(Self {
+            inner,
+            passphrase,
+            rng,
+            salt,
+            keys: RefCell::new(HashMap::new()),
+            encrypt_plain: true,
+        })
+    }
+
+    /// Leaves files uploaded before encryption was turned on as they are (for `sync --dry-run`)
+    pub fn read_only(self) -> Self {
+        Self {
+            encrypt_plain: false,
+            ..self
+        }
+    }
+
+    fn key(&self, passphrase: &str, salt: [u8; SALT_LEN]) -> color_eyre::Result<LessSafeKey> {
+        let mut keys = self.keys.borrow_mut();
+        let key = keys.entry(salt).or_insert_with(|| {
+            let mut key = [0; KEY_LEN];
+            pbkdf2::derive(
+                pbkdf2::PBKDF2_HMAC_SHA256,
+                NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+                &salt,
+                passphrase.as_bytes(),
+                &mut key,
+            );
+            key
+        });
+        let key = UnboundKey::new(&CHACHA20_POLY1305, key).map_err(|_| LostTheWay::SyncError {
+            message: String::from("Couldn't make an encryption key"),
+        })?;
+        Ok(LessSafeKey::new(key))
+    }
+
+    /// `content` as it's uploaded
+    fn encrypt(&self, content: &str) -> color_eyre::Result<String> {
+        let passphrase = match &self.passphrase {
+            Some(passphrase) => passphrase,
+            None => return Ok(content.to_owned()),
+        };
+        let mut nonce = [0; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| LostTheWay::SyncError {
+                message: String::from("Couldn't get random bytes for encryption"),
+            })?;
+        let mut sealed = content.as_bytes().to_vec();
+        self.key(passphrase, self.salt)?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut sealed,
+            )
+            .map_err(|_| LostTheWay::SyncError {
+                message: String::from("Couldn't encrypt a snippet"),
+            })?;
+        let mut payload = self.salt.to_vec();
+        payload.extend_from_slice(&nonce);
+        payload.extend_from_slice(&sealed);
+        Ok(format!("{}{}\n", HEADER, base64::encode(payload)))
+    }
+
+    /// `content` of the file `name` as it was before it was uploaded
+    fn decrypt(&self, name: &str, content: &str) -> color_eyre::Result<String> {
+        let encoded = match content.strip_prefix(HEADER) {
+            Some(encoded) => encoded.trim(),
+            None => return Ok(content.to_owned()),
+        };
+        let passphrase = match &self.passphrase {
+            Some(passphrase) => passphrase,
+            None => {
+                let error: color_eyre::Result<String> = Err(LostTheWay::DecryptionError {
+                    message: format!("{} is encrypted", name),
+                }
+                .into());
+                return error.suggestion(
+                    "Set `enabled = true` in the [encryption] section of your config file \
+                    and use the same passphrase as the machine that encrypted it",
+                );
+            }
+        };
+        let undecryptable = || LostTheWay::DecryptionError {
+            message: format!("Couldn't decrypt {}", name),
+        };
+        let mut payload = base64::decode(encoded).map_err(|_| undecryptable())?;
+        if payload.len() < SALT_LEN + NONCE_LEN {
+            return Err(undecryptable().into());
+        }
+        let mut salt = [0; SALT_LEN];
+        salt.copy_from_slice(&payload[..SALT_LEN]);
+        let mut nonce = [0; NONCE_LEN];
+        nonce.copy_from_slice(&payload[SALT_LEN..SALT_LEN + NONCE_LEN]);
+        let opened = self
+            .key(passphrase, salt)?
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut payload[SALT_LEN + NONCE_LEN..],
+            )
+            .map_err(|_| undecryptable())
+            .suggestion("Check that the sync passphrase is the same on every machine")?;
+        Ok(String::from_utf8(opened.to_vec()).map_err(|_| undecryptable())?)
+    }
+
+    /// Decrypts every file of `gist`
+    fn decrypt_gist(&self, mut gist: Gist) -> color_eyre::Result<Gist> {
+        for (name, file) in gist.files.iter_mut() {
+            file.content = self.decrypt(name, &file.content)?;
+        }
+        Ok(gist)
+    }
+
+    fn encrypt_files<'b>(
+        &self,
+        files: impl Iterator<Item = (&'b String, Option<&'b str>)>,
+    ) -> color_eyre::Result<HashMap<String, Option<String>>> {
+        files
+            .map(|(name, content)| {
+                Ok((
+                    name.to_owned(),
+                    content.map(|content| self.encrypt(content)).transpose()?,
+                ))
+            })
+            .collect()
+    }
+}
+
+impl SyncClient for EncryptedClient<'_> {
+    fn create_gist(&self, payload: &CreateGistPayload<'_>) -> color_eyre::Result<Gist> {
+        let files = self.encrypt_files(
+            payload
+                .files
+                .iter()
+                .map(|(name, file)| (name, Some(file.content))),
+        )?;
+        let gist = self.inner.create_gist(&CreateGistPayload {
+            description: payload.description,
+            public: payload.public,
+            files: files
+                .iter()
+                .filter_map(|(name, content)| {
+                    content
+                        .as_deref()
+                        .map(|content| (name.to_owned(), GistContent { content }))
+                })
+                .collect(),
+        })?;
+        self.decrypt_gist(gist)
+    }
+
+    fn update_gist(
+        &self,
+        gist_id: &str,
+        payload: &UpdateGistPayload<'_>,
+    ) -> color_eyre::Result<Gist> {
+        let files = self.encrypt_files(
+            payload
+                .files
+                .iter()
+                .map(|(name, file)| (name, file.as_ref().map(|file| file.content))),
+        )?;
+        let gist = self.inner.update_gist(
+            gist_id,
+            &UpdateGistPayload {
+                description: payload.description,
+                files: files
+                    .iter()
+                    .map(|(name, content)| {
+                        (
+                            name.to_owned(),
+                            content.as_deref().map(|content| GistContent { content }),
+                        )
+                    })
+                    .collect(),
+            },
+        )?;
+        self.decrypt_gist(gist)
+    }
+
+    /// Files uploaded before encryption was turned on are encrypted here, the first time they're seen
+    fn get_gist(&self, gist_id: &str) -> color_eyre::Result<Gist> {
+        let gist = self.inner.get_gist(gist_id)?;
+        if self.passphrase.is_some() && self.encrypt_plain {
+            let plain = self.encrypt_files(
+                gist.files
+                    .iter()
+                    .filter(|(_, file)| {
+                        !file.content.is_empty() && !file.content.starts_with(HEADER)
+                    })
+                    .map(|(name, file)| (name, Some(file.content.as_str()))),
+            )?;
+            if !plain.is_empty() {
+                self.inner.update_gist(
+                    &gist.id,
+                    &UpdateGistPayload {
+                        description: &gist.description,
+                        files: plain
+                            .iter()
+                            .map(|(name, content)| {
+                                (
+                                    name.to_owned(),
+                                    content.as_deref().map(|content| GistContent { content }),
+                                )
+                            })
+                            .collect(),
+                    },
+                )?;
+            }
+        }
+        self.decrypt_gist(gist)
+    }
+
+    fn file_url(&self, html_url: &str, filename: &str) -> String {
+        self.inner.file_url(html_url, filename)
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
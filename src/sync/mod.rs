@@ -0,0 +1,185 @@
+//! Sync backends: remotes that keep snippets as the files of a single collection.
+//! Gists and GitLab snippets have their own APIs, anything that can store files (S3, WebDAV, `the-way serve --sync`)
+//! implements `FileStore` and is wrapped in a `FileStoreClient`
+use chrono::{DateTime, Utc};
+
+use crate::errors::LostTheWay;
+use crate::gist::{CreateGistPayload, Gist, GistFile, UpdateGistPayload};
+
+pub mod encrypted;
+pub mod s3;
+pub mod server;
+pub mod webdav;
+
+/// A remote that keeps snippets as the files of a single Gist-like collection
+pub trait SyncClient {
+    /// Create a new collection with the given payload
+    fn create_gist(&self, payload: &CreateGistPayload<'_>) -> color_eyre::Result<Gist>;
+    /// Add, change (or with `None`, delete) files in an existing collection
+    fn update_gist(
+        &self,
+        gist_id: &str,
+        payload: &UpdateGistPayload<'_>,
+    ) -> color_eyre::Result<Gist>;
+    /// Retrieve a collection (with file contents) by ID
+    fn get_gist(&self, gist_id: &str) -> color_eyre::Result<Gist>;
+    /// Web link to one file of a collection, used in the index file
+    fn file_url(&self, html_url: &str, filename: &str) -> String;
+    /// What a collection is called, for messages
+    fn name(&self) -> &'static str;
+}
+
+/// Somewhere files can be listed, read, written, and deleted (a bucket prefix, a WebDAV folder, ...)
+pub trait FileStore {
+    /// Get ready to store files, e.g. by making the folder
+    fn create(&self) -> color_eyre::Result<()>;
+    /// Names and last modified times of the stored files
+    fn list(&self) -> color_eyre::Result<Vec<(String, DateTime<Utc>)>>;
+    fn get(&self, name: &str) -> color_eyre::Result<String>;
+    fn put(&self, name: &str, content: &str) -> color_eyre::Result<()>;
+    fn delete(&self, name: &str) -> color_eyre::Result<()>;
+    /// Where the files are kept, e.g. "s3://bucket/the-way/"
+    fn location(&self) -> String;
+    /// What the store is called, for messages
+    fn name(&self) -> &'static str;
+}
+
+/// Syncs to a `FileStore`, the whole store is one collection (so collection IDs are ignored)
+pub struct FileStoreClient<S: FileStore> {
+    store: S,
+}
+
+impl<S: FileStore> FileStoreClient<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Where the files are kept, used as the collection ID
+    pub fn location(&self) -> String {
+        self.store.location()
+    }
+
+    /// The collection as it is now, without file contents
+    fn listing(&self, description: &str) -> color_eyre::Result<Gist> {
+        let files = self.store.list()?;
+        Ok(Gist {
+            html_url: self.store.location(),
+            id: self.store.location(),
+            updated_at: files
+                .iter()
+                .map(|(_, modified)| *modified)
+                .max()
+                .unwrap_or_else(Utc::now),
+            description: description.to_owned(),
+            files: files
+                .into_iter()
+                .map(|(name, _)| {
+                    (
+                        name.to_owned(),
+                        GistFile {
+                            filename: name,
+                            content: String::new(),
+                        },
+                    )
+                })
+                .collect(),
+        })
+    }
+}
+
+impl<S: FileStore> SyncClient for FileStoreClient<S> {
+    fn create_gist(&self, payload: &CreateGistPayload<'_>) -> color_eyre::Result<Gist> {
+        self.store.create()?;
+        for (name, file) in &payload.files {
+            self.store.put(name, file.content)?;
+        }
+        self.listing(payload.description)
+    }
+
+    fn update_gist(
+        &self,
+        _gist_id: &str,
+        payload: &UpdateGistPayload<'_>,
+    ) -> color_eyre::Result<Gist> {
+        for (name, file) in &payload.files {
+            match file {
+                Some(file) => self.store.put(name, file.content)?,
+                None => self.store.delete(name)?,
+            }
+        }
+        self.listing(payload.description)
+    }
+
+    fn get_gist(&self, _gist_id: &str) -> color_eyre::Result<Gist> {
+        let mut gist = self.listing("")?;
+        if gist.files.is_empty() {
+            return Err(LostTheWay::CollectionNotFound {
+                location: self.store.location(),
+            }
+            .into());
+        }
+        for (name, file) in gist.files.iter_mut() {
+            file.content = self.store.get(name)?;
+        }
+        Ok(gist)
+    }
+
+    fn file_url(&self, html_url: &str, filename: &str) -> String {
+        format!("{}/{}", html_url.trim_end_matches('/'), filename)
+    }
+
+    fn name(&self) -> &'static str {
+        self.store.name()
+    }
+}
+
+/// Fails with `CollectionNotFound` if the response to fetching collection `id` is a 404,
+/// otherwise checks it with `check`
+pub(crate) fn check_found(
+    id: &str,
+    response: ureq::Response,
+    check: fn(ureq::Response) -> color_eyre::Result<ureq::Response>,
+) -> color_eyre::Result<ureq::Response> {
+    if response.status() == 404 {
+        return Err(LostTheWay::CollectionNotFound {
+            location: id.to_owned(),
+        }
+        .into());
+    }
+    check(response)
+}
+
+/// Percent-encodes everything except unreserved characters (and `/` if `keep_slash` is set)
+pub(crate) fn uri_encode(text: &str, keep_slash: bool) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            b'/' if keep_slash => String::from("/"),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Undoes `uri_encode`
+pub(crate) fn uri_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(byte) = std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
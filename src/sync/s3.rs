@@ -0,0 +1,260 @@
+//! S3-compatible object storage (AWS, MinIO, ...) as a sync backend, requests are signed with AWS Signature V4
+use chrono::{DateTime, Utc};
+use color_eyre::Help;
+use ring::{digest, hmac};
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::errors::LostTheWay;
+use crate::http::HttpClient;
+use crate::sync::{uri_encode, FileStore};
+
+pub const DEFAULT_REGION: &str = "us-east-1";
+pub const DEFAULT_PREFIX: &str = "the-way/";
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+/// Keys and last modified times
+type Objects = Vec<(String, DateTime<Utc>)>;
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(digest::digest(&digest::SHA256, data))
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data.as_bytes())
+        .as_ref()
+        .to_vec()
+}
+
+pub struct S3Store {
+    client: HttpClient,
+    /// e.g. https://s3.eu-west-1.amazonaws.com or http://localhost:9000
+    endpoint: url::Url,
+    bucket: String,
+    region: String,
+    /// Key prefix all snippet files are stored under
+    prefix: String,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+impl S3Store {
+    /// Make a store for `bucket`, `endpoint` defaults to AWS in `region`
+    pub fn new(
+        client: HttpClient,
+        endpoint: Option<&str>,
+        bucket: &str,
+        region: &str,
+        prefix: &str,
+        access_key_id: &str,
+        secret_access_key: &str,
+    ) -> color_eyre::Result<Self> {
+        let endpoint = endpoint
+            .map(String::from)
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region));
+        let endpoint = url::Url::parse(&endpoint)
+            .map_err(|e| LostTheWay::ConfigError {
+                message: format!("Invalid S3 endpoint {:?}: {}", endpoint, e),
+            })
+            .suggestion("Set `endpoint` in the [s3] section of your config file to a URL")?;
+        Ok(Self {
+            client,
+            endpoint,
+            bucket: bucket.to_owned(),
+            region: region.to_owned(),
+            prefix: prefix.to_owned(),
+            access_key_id: access_key_id.to_owned(),
+            secret_access_key: secret_access_key.to_owned(),
+        })
+    }
+
+    /// Host header value, with the port if it isn't the default one
+    fn host(&self) -> String {
+        let host = self.endpoint.host_str().unwrap_or_default();
+        match self.endpoint.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_owned(),
+        }
+    }
+
+    /// Canonical (already encoded) path to an object in the bucket, path-style
+    fn object_path(&self, name: &str) -> String {
+        format!(
+            "/{}/{}",
+            uri_encode(&self.bucket, false),
+            uri_encode(&format!("{}{}", self.prefix, name), true)
+        )
+    }
+
+    /// Makes a signed request, `query` has to be sorted by key
+    fn signed_request(
+        &self,
+        method: &str,
+        path: &str,
+        query: &[(&str, &str)],
+        body: &[u8],
+    ) -> color_eyre::Result<ureq::Request> {
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = sha256_hex(body);
+        let host = self.host();
+        let query = query
+            .iter()
+            .map(|(key, value)| format!("{}={}", uri_encode(key, false), uri_encode(value, false)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, query, host, payload_hash, amz_date, SIGNED_HEADERS, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            sha256_hex(canonical_request.as_bytes())
+        );
+        let mut key = format!("AWS4{}", self.secret_access_key).into_bytes();
+        for part in &[date.as_str(), self.region.as_str(), "s3", "aws4_request"] {
+            key = hmac_sha256(&key, part);
+        }
+        let signature = hex::encode(hmac_sha256(&key, &string_to_sign));
+
+        let mut url = format!("{}://{}{}", self.endpoint.scheme(), host, path);
+        if !query.is_empty() {
+            url = format!("{}?{}", url, query);
+        }
+        let mut request = self.client.request(method, &url)?;
+        request
+            .set("host", &host)
+            .set("x-amz-content-sha256", &payload_hash)
+            .set("x-amz-date", &amz_date)
+            .set(
+                "authorization",
+                &format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key_id, scope, SIGNED_HEADERS, signature
+                ),
+            );
+        Ok(request)
+    }
+
+    fn check_response(response: ureq::Response) -> color_eyre::Result<ureq::Response> {
+        if response.ok() {
+            Ok(response)
+        } else {
+            Err(LostTheWay::SyncError {
+                message: format!("{} {}", response.status(), response.into_string()?),
+            })
+            .suggestion(
+                "Check the endpoint, bucket, and region in the [s3] section of your config file.\n\
+        Set your keys in the [s3] section or the environment variables $AWS_ACCESS_KEY_ID and $AWS_SECRET_ACCESS_KEY",
+            )
+        }
+    }
+
+    /// Sends a signed request
+    fn send(
+        &self,
+        method: &str,
+        path: &str,
+        query: &[(&str, &str)],
+        body: &[u8],
+    ) -> color_eyre::Result<ureq::Response> {
+        let request = self.signed_request(method, path, query, body)?;
+        Self::check_response(self.client.send(request, Some(body)))
+    }
+}
+
+/// Reads (key, last modified) pairs and the continuation token (if the listing was cut short)
+/// from a ListObjectsV2 response
+fn parse_listing(xml: &str) -> color_eyre::Result<(Objects, Option<String>)> {
+    let mut objects = Vec::new();
+    let (mut key, mut modified, mut token) = (None, None, None);
+    let mut element = String::new();
+    for event in EventReader::new(xml.as_bytes()) {
+        match event.map_err(|e| LostTheWay::SyncError {
+            message: format!("Couldn't read S3 listing: {}", e),
+        })? {
+            XmlEvent::StartElement { name, .. } => element = name.local_name,
+            XmlEvent::Characters(text) => match element.as_str() {
+                "Key" => key = Some(text),
+                "LastModified" => modified = Some(DateTime::parse_from_rfc3339(&text)?),
+                "NextContinuationToken" => token = Some(text),
+                _ => {}
+            },
+            XmlEvent::EndElement { name } => {
+                if name.local_name == "Contents" {
+                    if let (Some(key), Some(modified)) = (key.take(), modified.take()) {
+                        objects.push((key, modified.with_timezone(&Utc)));
+                    }
+                }
+                element.clear();
+            }
+            _ => {}
+        }
+    }
+    Ok((objects, token))
+}
+
+impl FileStore for S3Store {
+    fn create(&self) -> color_eyre::Result<()> {
+        // Keys are made as they're written
+        Ok(())
+    }
+
+    fn list(&self) -> color_eyre::Result<Vec<(String, DateTime<Utc>)>> {
+        let path = format!("/{}", uri_encode(&self.bucket, false));
+        let mut files = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = vec![];
+            if let Some(token) = &token {
+                query.push(("continuation-token", token.as_str()));
+            }
+            query.push(("list-type", "2"));
+            query.push(("prefix", self.prefix.as_str()));
+            let listing = self.send("GET", &path, &query, &[])?.into_string()?;
+            let (objects, next_token) = parse_listing(&listing)?;
+            files.extend(
+                objects
+                    .into_iter()
+                    .filter_map(|(key, modified)| {
+                        Some((key.strip_prefix(&self.prefix)?.to_owned(), modified))
+                    })
+                    // Only files directly under the prefix
+                    .filter(|(name, _)| !name.is_empty() && !name.contains('/')),
+            );
+            match next_token {
+                Some(next_token) => token = Some(next_token),
+                None => break,
+            }
+        }
+        Ok(files)
+    }
+
+    fn get(&self, name: &str) -> color_eyre::Result<String> {
+        self.client.get_cached(
+            self.signed_request("GET", &self.object_path(name), &[], &[])?,
+            Self::check_response,
+        )
+    }
+
+    fn put(&self, name: &str, content: &str) -> color_eyre::Result<()> {
+        self.send("PUT", &self.object_path(name), &[], content.as_bytes())?;
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> color_eyre::Result<()> {
+        self.send("DELETE", &self.object_path(name), &[], &[])?;
+        Ok(())
+    }
+
+    fn location(&self) -> String {
+        format!("s3://{}/{}", self.bucket, self.prefix)
+    }
+
+    fn name(&self) -> &'static str {
+        "S3 bucket"
+    }
+}
@@ -0,0 +1,118 @@
+//! Another machine running `the-way serve --sync` as a sync backend.
+//! The server keeps the files in its database and exposes them under /sync/:
+//! `GET /sync/` lists them (as JSON), and `GET`, `PUT`, and `DELETE /sync/<name>` read, write, and delete one
+use chrono::{DateTime, Utc};
+use color_eyre::Help;
+
+use crate::errors::LostTheWay;
+use crate::http::HttpClient;
+use crate::sync::{uri_encode, FileStore};
+
+/// Path the sync endpoint is served at
+pub const SYNC_PATH: &str = "/sync/";
+
+/// One entry of the `GET /sync/` listing
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ServerFile {
+    pub name: String,
+    pub modified: DateTime<Utc>,
+}
+
+pub struct ServerStore {
+    client: HttpClient,
+    /// URL of the sync endpoint, always ending in /sync/
+    url: url::Url,
+}
+
+impl ServerStore {
+    /// Make a store for the server at `url`, sending `token` if there is one
+    pub fn new(mut client: HttpClient, url: &str, token: Option<&str>) -> color_eyre::Result<Self> {
+        let endpoint = format!("{}{}", url.trim_end_matches('/'), SYNC_PATH);
+        let url = url::Url::parse(&endpoint)
+            .map_err(|e| LostTheWay::ConfigError {
+                message: format!("Invalid server URL {:?}: {}", url, e),
+            })
+            .suggestion(
+                "Set `url` in the [server] section of your config file, e.g. http://host:8000",
+            )?;
+        if let Some(token) = token {
+            client.set("authorization", &format!("Bearer {}", token));
+        }
+        Ok(Self { client, url })
+    }
+
+    fn file_url(&self, name: &str) -> color_eyre::Result<url::Url> {
+        Ok(self.url.join(&uri_encode(name, false))?)
+    }
+
+    fn check_response(response: ureq::Response) -> color_eyre::Result<ureq::Response> {
+        if response.ok() {
+            Ok(response)
+        } else {
+            Err(LostTheWay::SyncError {
+                message: format!("{} {}", response.status(), response.into_string()?),
+            })
+            .suggestion(
+                "Check that `the-way serve --sync` is running at the url in the [server] section of your config file.\n\
+        Set the server's token there or in the environment variable $THE_WAY_SERVER_TOKEN",
+            )
+        }
+    }
+}
+
+impl FileStore for ServerStore {
+    /// The server keeps files in its database, there's nothing to make
+    fn create(&self) -> color_eyre::Result<()> {
+        Ok(())
+    }
+
+    fn list(&self) -> color_eyre::Result<Vec<(String, DateTime<Utc>)>> {
+        let response = self
+            .client
+            .send(self.client.request("GET", self.url.as_str())?, None);
+        let listing: Vec<ServerFile> =
+            serde_json::from_str(&Self::check_response(response)?.into_string()?).map_err(|e| {
+                LostTheWay::SyncError {
+                    message: format!("Couldn't read the server's listing: {}", e),
+                }
+            })?;
+        Ok(listing
+            .into_iter()
+            .map(|file| (file.name, file.modified))
+            .collect())
+    }
+
+    fn get(&self, name: &str) -> color_eyre::Result<String> {
+        self.client.get_cached(
+            self.client.request("GET", self.file_url(name)?.as_str())?,
+            Self::check_response,
+        )
+    }
+
+    fn put(&self, name: &str, content: &str) -> color_eyre::Result<()> {
+        Self::check_response(self.client.send(
+            self.client.request("PUT", self.file_url(name)?.as_str())?,
+            Some(content.as_bytes()),
+        ))?;
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> color_eyre::Result<()> {
+        Self::check_response(
+            self.client.send(
+                self.client
+                    .request("DELETE", self.file_url(name)?.as_str())?,
+                None,
+            ),
+        )?;
+        Ok(())
+    }
+
+    fn location(&self) -> String {
+        self.url.to_string()
+    }
+
+    fn name(&self) -> &'static str {
+        "sync server"
+    }
+}
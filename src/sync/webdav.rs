@@ -0,0 +1,164 @@
+//! WebDAV folder (Nextcloud, ownCloud, Apache mod_dav, ...) as a sync backend
+use chrono::{DateTime, Utc};
+use color_eyre::Help;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::errors::LostTheWay;
+use crate::http::HttpClient;
+use crate::sync::{uri_decode, FileStore};
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:getlastmodified/><d:resourcetype/></d:prop></d:propfind>"#;
+
+pub struct WebdavStore {
+    client: HttpClient,
+    /// Folder URL, always ending in a /
+    url: url::Url,
+}
+
+impl WebdavStore {
+    /// Make a store for the folder at `url`, logging in if a username is given
+    pub fn new(
+        mut client: HttpClient,
+        url: &str,
+        username: Option<&str>,
+        password: Option<&str>,
+    ) -> color_eyre::Result<Self> {
+        let folder = format!("{}/", url.trim_end_matches('/'));
+        let url = url::Url::parse(&folder)
+            .map_err(|e| LostTheWay::ConfigError {
+                message: format!("Invalid WebDAV URL {:?}: {}", folder, e),
+            })
+            .suggestion("Set `url` in the [webdav] section of your config file to a folder URL")?;
+        if let Some(username) = username {
+            client.set(
+                "authorization",
+                &format!(
+                    "Basic {}",
+                    base64::encode(format!("{}:{}", username, password.unwrap_or_default()))
+                ),
+            );
+        }
+        Ok(Self { client, url })
+    }
+
+    fn file_url(&self, name: &str) -> color_eyre::Result<url::Url> {
+        Ok(self.url.join(name)?)
+    }
+
+    fn check_response(response: ureq::Response) -> color_eyre::Result<ureq::Response> {
+        if response.ok() {
+            Ok(response)
+        } else {
+            Err(LostTheWay::SyncError {
+                message: format!("{} {}", response.status(), response.into_string()?),
+            })
+            .suggestion(
+                "Check the url and username in the [webdav] section of your config file.\n\
+        Set your password there or in the environment variable $THE_WAY_WEBDAV_PASSWORD",
+            )
+        }
+    }
+}
+
+/// Reads (href, last modified) pairs of the files (not folders) in a PROPFIND response
+fn parse_multistatus(xml: &str) -> color_eyre::Result<Vec<(String, DateTime<Utc>)>> {
+    let mut files = Vec::new();
+    let (mut href, mut modified, mut collection) = (None, None, false);
+    let mut element = String::new();
+    for event in EventReader::new(xml.as_bytes()) {
+        match event.map_err(|e| LostTheWay::SyncError {
+            message: format!("Couldn't read WebDAV listing: {}", e),
+        })? {
+            XmlEvent::StartElement { name, .. } => {
+                if name.local_name == "collection" {
+                    collection = true;
+                }
+                element = name.local_name;
+            }
+            XmlEvent::Characters(text) => match element.as_str() {
+                "href" => href = Some(text),
+                "getlastmodified" => modified = Some(DateTime::parse_from_rfc2822(&text)?),
+                _ => {}
+            },
+            XmlEvent::EndElement { name } => {
+                if name.local_name == "response" {
+                    if let (Some(href), Some(modified), false) =
+                        (href.take(), modified.take(), collection)
+                    {
+                        files.push((href, modified.with_timezone(&Utc)));
+                    }
+                    collection = false;
+                }
+                element.clear();
+            }
+            _ => {}
+        }
+    }
+    Ok(files)
+}
+
+impl FileStore for WebdavStore {
+    fn create(&self) -> color_eyre::Result<()> {
+        let response = self
+            .client
+            .send(self.client.request("MKCOL", self.url.as_str())?, None);
+        // 405 means the folder is already there
+        if response.status() == 405 {
+            Ok(())
+        } else {
+            Self::check_response(response).map(|_| ())
+        }
+    }
+
+    fn list(&self) -> color_eyre::Result<Vec<(String, DateTime<Utc>)>> {
+        let mut request = self.client.request("PROPFIND", self.url.as_str())?;
+        request
+            .set("depth", "1")
+            .set("content-type", "application/xml");
+        let response = self.client.send(request, Some(PROPFIND_BODY.as_bytes()));
+        let listing = Self::check_response(response)?.into_string()?;
+        Ok(parse_multistatus(&listing)?
+            .into_iter()
+            .filter_map(|(href, modified)| {
+                // hrefs can be absolute URLs or paths
+                let name = uri_decode(href.trim_end_matches('/').rsplit('/').next()?);
+                Some((name, modified)).filter(|(name, _)| !name.is_empty())
+            })
+            .collect())
+    }
+
+    fn get(&self, name: &str) -> color_eyre::Result<String> {
+        self.client.get_cached(
+            self.client.request("GET", self.file_url(name)?.as_str())?,
+            Self::check_response,
+        )
+    }
+
+    fn put(&self, name: &str, content: &str) -> color_eyre::Result<()> {
+        Self::check_response(self.client.send(
+            self.client.request("PUT", self.file_url(name)?.as_str())?,
+            Some(content.as_bytes()),
+        ))?;
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> color_eyre::Result<()> {
+        Self::check_response(
+            self.client.send(
+                self.client
+                    .request("DELETE", self.file_url(name)?.as_str())?,
+                None,
+            ),
+        )?;
+        Ok(())
+    }
+
+    fn location(&self) -> String {
+        self.url.to_string()
+    }
+
+    fn name(&self) -> &'static str {
+        "WebDAV folder"
+    }
+}
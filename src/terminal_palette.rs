@@ -0,0 +1,191 @@
+//! Builds a syntax highlighting theme from the terminal's own colors
+//! by asking the terminal for its palette with OSC 4 (16 ANSI colors), OSC 10 (foreground), and OSC 11 (background).
+use std::fs;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
+use color_eyre::Help;
+
+use crate::errors::LostTheWay;
+use crate::utils;
+
+/// Terminal device to query
+const TTY: &str = "/dev/tty";
+
+/// Number of ANSI colors asked for
+const NUM_COLORS: usize = 16;
+
+/// An RGB color reported by the terminal
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rgb(u8, u8, u8);
+
+impl Rgb {
+    /// Parses "rgb:RRRR/GGGG/BBBB" (1 to 4 hex digits per channel)
+    fn parse(spec: &str) -> Option<Self> {
+        let mut channels = spec.strip_prefix("rgb:")?.split('/').map(|channel| {
+            if !(1..=4).contains(&channel.len()) {
+                return None;
+            }
+            let value = u32::from_str_radix(channel, 16).ok()?;
+            let max = (1u32 << (4 * channel.len() as u32)) - 1;
+            Some((value * 255 / max) as u8)
+        });
+        Some(Self(
+            channels.next()??,
+            channels.next()??,
+            channels.next()??,
+        ))
+    }
+
+    fn to_hex(self) -> String {
+        format!("#{:02X}{:02X}{:02X}", self.0, self.1, self.2)
+    }
+}
+
+/// Colors reported by the terminal
+#[derive(Debug)]
+pub(crate) struct Palette {
+    foreground: Rgb,
+    background: Rgb,
+    colors: [Option<Rgb>; NUM_COLORS],
+}
+
+/// Runs `stty` on the terminal
+fn stty(args: &[&str]) -> color_eyre::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(fs::File::open(TTY)?)
+        .stderr(Stdio::inherit())
+        .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Sends OSC color queries and collects the raw answers
+fn query_terminal() -> color_eyre::Result<String> {
+    let mut tty = fs::OpenOptions::new().read(true).write(true).open(TTY)?;
+    let saved = stty(&["-g"])?;
+    // Raw mode, reads give up after half a second of silence
+    stty(&["raw", "-echo", "min", "0", "time", "5"])?;
+    let mut queries = String::from("\x1b]10;?\x07\x1b]11;?\x07");
+    for i in 0..NUM_COLORS {
+        queries.push_str(&format!("\x1b]4;{};?\x07", i));
+    }
+    let result = tty.write_all(queries.as_bytes()).and_then(|_| {
+        let mut answers = Vec::new();
+        let mut buffer = [0; 1024];
+        loop {
+            let num_read = tty.read(&mut buffer)?;
+            if num_read == 0 {
+                break;
+            }
+            answers.extend_from_slice(&buffer[..num_read]);
+        }
+        Ok(answers)
+    });
+    stty(&[&saved])?;
+    Ok(String::from_utf8_lossy(&result?).into_owned())
+}
+
+impl Palette {
+    /// Asks the terminal for its colors
+    pub(crate) fn from_terminal() -> color_eyre::Result<Self> {
+        let answers = query_terminal()?;
+        let mut foreground = None;
+        let mut background = None;
+        let mut colors = [None; NUM_COLORS];
+        // Answers look like ESC ] 4 ; 1 ; rgb:cdcd/0000/0000 BEL (or ESC \ instead of BEL)
+        for answer in answers.split(['\x07', '\x1b']) {
+            let mut parts = answer.trim_start_matches(']').split(';');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some("10"), Some(spec), None) => foreground = Rgb::parse(spec),
+                (Some("11"), Some(spec), None) => background = Rgb::parse(spec),
+                (Some("4"), Some(index), Some(spec)) => {
+                    if let Ok(index) = index.parse::<usize>() {
+                        if index < NUM_COLORS {
+                            colors[index] = Rgb::parse(spec);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        match (foreground, background) {
+            (Some(foreground), Some(background)) => Ok(Self {
+                foreground,
+                background,
+                colors,
+            }),
+            _ => {
+                let error: color_eyre::Result<Self> = Err(LostTheWay::ThemeError {
+                    theme: String::from("terminal palette"),
+                }
+                .into());
+                error.suggestion(
+                    "Your terminal didn't answer color queries (OSC 4/10/11). \
+                    Try a different terminal emulator, or run outside of tmux/screen.",
+                )
+            }
+        }
+    }
+
+    /// ANSI color `index`, the foreground color if the terminal didn't report it
+    fn color(&self, index: usize) -> String {
+        self.colors[index].unwrap_or(self.foreground).to_hex()
+    }
+
+    /// Makes a Sublime Text ".tmTheme" file using the terminal's colors
+    pub(crate) fn to_tmtheme(&self, name: &str) -> String {
+        // (scope, ANSI color index, font style)
+        let scopes = [
+            ("comment", 8, "italic"),
+            ("string", 2, ""),
+            (
+                "constant.numeric, constant.language, constant.character",
+                3,
+                "",
+            ),
+            ("keyword, storage.modifier", 5, ""),
+            ("storage.type, support.type", 6, ""),
+            ("entity.name.function, support.function", 4, ""),
+            ("entity.name.type, entity.name.class", 3, "bold"),
+            ("variable.parameter", 11, ""),
+            ("punctuation, keyword.operator", 7, ""),
+            ("markup.heading", 4, "bold"),
+            ("invalid", 1, "underline"),
+        ];
+        let mut settings = format!(
+            "<dict><key>settings</key><dict>\
+            <key>background</key><string>{}</string>\
+            <key>foreground</key><string>{}</string>\
+            <key>caret</key><string>{}</string>\
+            <key>selection</key><string>{}</string>\
+            <key>lineHighlight</key><string>{}</string>\
+            </dict></dict>\n",
+            self.background.to_hex(),
+            self.foreground.to_hex(),
+            self.color(4),
+            self.color(8),
+            self.color(8),
+        );
+        for (scope, color, font_style) in scopes.iter() {
+            settings.push_str(&format!(
+                "<dict><key>scope</key><string>{}</string><key>settings</key><dict>\
+                <key>foreground</key><string>{}</string>\
+                <key>fontStyle</key><string>{}</string></dict></dict>\n",
+                scope,
+                self.color(*color),
+                font_style
+            ));
+        }
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+            <plist version=\"1.0\"><dict>\n\
+            <key>name</key><string>{}</string>\n\
+            <key>settings</key><array>\n{}</array>\n\
+            </dict></plist>\n",
+            utils::escape_html(name),
+            settings
+        )
+    }
+}
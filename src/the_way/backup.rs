@@ -0,0 +1,184 @@
+//! Backups of the database, config file, and themes: `the-way backup` / `the-way restore`,
+//! and automatic ones (`backup_dir` in the config) made before destructive commands and every `backup_interval_hours`
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chrono::Utc;
+use color_eyre::Help;
+use dialoguer::Confirm;
+
+use crate::configuration::TheWayConfig;
+use crate::errors::LostTheWay;
+use crate::the_way::{snapshot::snapshot_file, TheWay};
+
+const BACKUP_PREFIX: &str = "the-way_";
+const BACKUP_EXTENSION: &str = ".tar.gz";
+/// Number of backups kept when `backup_keep` isn't set
+const DEFAULT_BACKUP_KEEP: usize = 5;
+
+/// Backups in `dir`, oldest first (their names start with the time they were made)
+fn list_backups(dir: &Path) -> color_eyre::Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_EXTENSION) {
+            backups.push(path);
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// Replaces `old` with `new`, or with an empty folder if `new` doesn't exist
+fn replace_dir(new: &Path, old: &Path) -> color_eyre::Result<()> {
+    if old.exists() {
+        fs::remove_dir_all(old)?;
+    }
+    if new.exists() {
+        fs::rename(new, old)?;
+    } else {
+        fs::create_dir_all(old)?;
+    }
+    Ok(())
+}
+
+impl TheWay {
+    /// Writes everything to `file`, for `restore` on this or another machine
+    pub(crate) fn backup(&self, file: &Path) -> color_eyre::Result<()> {
+        self.write_archive(file)?;
+        println!("Backed up to {}", file.display());
+        Ok(())
+    }
+
+    /// Replaces the database, themes, and config file with the ones in `file` (made by `write_archive`).
+    /// `db_dir` and `themes_dir` stay as they are, so backups from other machines work too
+    pub(crate) fn restore(self, file: &Path, force: bool) -> color_eyre::Result<()> {
+        // Unpacked next to the database first, so a broken file doesn't leave anything half-replaced
+        let staging = self.config.db_dir.with_extension("restore");
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        let unpacked = fs::File::open(file).and_then(|archive| {
+            tar::Archive::new(flate2::read::GzDecoder::new(archive)).unpack(&staging)
+        });
+        if unpacked.is_err() || !staging.join("db").is_dir() {
+            let _ = fs::remove_dir_all(&staging);
+            let error: color_eyre::Result<()> = Err(LostTheWay::RestoreError {
+                message: format!("{} isn't a backup of the-way", file.display()),
+            }
+            .into());
+            return error.suggestion(
+                "Use a file made with `the-way backup <file.tar.gz>`, or one from your backup_dir",
+            );
+        }
+        if !force
+            && !Confirm::new()
+                .with_prompt("Replace all snippets, themes, and configuration with the backup?")
+                .default(false)
+                .interact()?
+        {
+            fs::remove_dir_all(&staging)?;
+            let error: color_eyre::Result<()> = Err(LostTheWay::DoingNothing.into());
+            return error.suggestion("Press Y next time!");
+        }
+        self.backup_before("restore")?;
+
+        // The database has to be closed before its files are replaced
+        let TheWay { config, db, .. } = self;
+        drop(db);
+        let config_file = staging.join("the-way.toml");
+        if config_file.exists() {
+            let mut restored: TheWayConfig = confy::load_path(&config_file)?;
+            restored.db_dir = config.db_dir.clone();
+            restored.themes_dir = config.themes_dir.clone();
+            restored.store()?;
+        }
+        replace_dir(&staging.join("db"), &config.db_dir)?;
+        // Rewritten from the restored database by the next command
+        let snapshot = snapshot_file(&config);
+        if snapshot.exists() {
+            fs::remove_file(snapshot)?;
+        }
+        replace_dir(&staging.join("themes"), &config.themes_dir)?;
+        fs::remove_dir_all(&staging)?;
+        println!("Restored from {}", file.display());
+        Ok(())
+    }
+
+    /// Backs up everything to `backup_dir` (if set) before running a destructive `command`
+    pub(crate) fn backup_before(&self, command: &str) -> color_eyre::Result<()> {
+        if let Some(dir) = &self.config.backup_dir {
+            self.backup_to(dir, command)?;
+        }
+        Ok(())
+    }
+
+    /// Backs up everything if the newest backup is older than `backup_interval_hours`
+    pub(crate) fn periodic_backup(&self) -> color_eyre::Result<()> {
+        if let (Some(dir), Some(hours)) =
+            (&self.config.backup_dir, self.config.backup_interval_hours)
+        {
+            let newest = match list_backups(dir)?.last() {
+                Some(backup) => Some(fs::metadata(backup)?.modified()?),
+                None => None,
+            };
+            let due = newest.map_or(true, |newest| {
+                SystemTime::now().duration_since(newest).unwrap_or_default()
+                    >= Duration::from_secs(hours * 60 * 60)
+            });
+            if due {
+                self.backup_to(dir, "periodic")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a timestamped backup to `dir` and removes all but the newest `backup_keep` backups
+    fn backup_to(&self, dir: &Path, reason: &str) -> color_eyre::Result<PathBuf> {
+        fs::create_dir_all(dir).map_err(|e| LostTheWay::ConfigError {
+            message: format!("Couldn't create backup dir {}: {}", dir.display(), e),
+        })?;
+        let file = dir.join(format!(
+            "{}{}_{}{}",
+            BACKUP_PREFIX,
+            Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+            reason,
+            BACKUP_EXTENSION
+        ));
+        self.write_archive(&file)?;
+        let backups = list_backups(dir)?;
+        let keep = self
+            .config
+            .backup_keep
+            .unwrap_or(DEFAULT_BACKUP_KEEP)
+            .max(1);
+        for old in &backups[..backups.len().saturating_sub(keep)] {
+            fs::remove_file(old)?;
+        }
+        Ok(file)
+    }
+
+    /// Writes a gzipped tar file with the database (under db/), the config file, and the themes (under themes/)
+    pub(crate) fn write_archive(&self, file: &Path) -> color_eyre::Result<()> {
+        // Everything written so far has to be on disk before it's copied
+        self.db.flush()?;
+        let encoder =
+            flate2::write::GzEncoder::new(fs::File::create(file)?, flate2::Compression::default());
+        let mut archive = tar::Builder::new(encoder);
+        archive.append_dir_all("db", &self.config.db_dir)?;
+        if self.config.themes_dir.exists() {
+            archive.append_dir_all("themes", &self.config.themes_dir)?;
+        }
+        let config_file = TheWayConfig::get()?;
+        if config_file.exists() {
+            archive.append_path_with_name(&config_file, "the-way.toml")?;
+        }
+        archive.into_inner()?.finish()?;
+        Ok(())
+    }
+}
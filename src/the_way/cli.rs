@@ -1,12 +1,20 @@
 //! `StructOpt` data
+use std::io::Write;
+use std::num::NonZeroU64;
 use std::path::PathBuf;
 
 use structopt::clap::AppSettings;
-use structopt::clap::Shell;
 use structopt::StructOpt;
 
 use crate::configuration::ConfigCommand;
-use crate::the_way::filter::Filters;
+use crate::errors::{self, ErrorFormat, LostTheWay};
+use crate::language::LINGUIST_URL;
+use crate::the_way::completions::CompletionShell;
+use crate::the_way::export::ExportArgs;
+use crate::the_way::filter::{self, Filters};
+use crate::the_way::ids::SnippetId;
+use crate::the_way::import::ImportArgs;
+use crate::the_way::sync::ConflictStrategy;
 
 #[derive(Debug, StructOpt)]
 #[structopt(
@@ -15,29 +23,100 @@ rename_all = "kebab-case",
 global_settings = & [AppSettings::DeriveDisplayOrder]
 )]
 /// Record, retrieve, search, and categorize code snippets
-pub enum TheWayCLI {
+pub struct TheWayCLI {
+    /// Screen reader friendly output: no colors or symbols, and every field is labelled
+    #[structopt(long, global = true)]
+    pub accessible: bool,
+    /// How errors are printed: "text", or "json" (one object per line, for scripts and editor plugins).
+    /// Exit codes tell kinds of failure apart either way: 2 usage, 3 not found, 4 database locked, 5 clipboard,
+    /// 6 config or validation, 7 sync, 8 cancelled, 9 run/doctor/selftest failed, 1 anything else
+    #[structopt(long, global = true, default_value = "text", possible_values = &ErrorFormat::VARIANTS)]
+    pub error_format: ErrorFormat,
+    #[structopt(subcommand)]
+    pub cmd: TheWayCommand,
+}
+
+impl TheWayCLI {
+    /// Parses `args` (with saved filters in place of `@name`s), printing help, the version, or usage errors
+    /// (in `error_format`).
+    /// On `Err`, the-way should exit with that code
+    pub fn parse<I>(args: I, error_format: ErrorFormat) -> Result<Self, i32>
+    where
+        I: IntoIterator,
+        I::Item: Into<std::ffi::OsString> + Clone,
+    {
+        let args = match filter::expand_saved_filters(args.into_iter().map(Into::into).collect()) {
+            Ok(args) => args,
+            Err(error) => return Err(errors::report(&error, error_format)),
+        };
+        match Self::from_iter_safe(args) {
+            Ok(cli) => Ok(cli),
+            Err(e) if !e.use_stderr() => {
+                // e.g. piped to `head`
+                let _ = writeln!(std::io::stdout(), "{}", e.message);
+                Err(0)
+            }
+            Err(e) if error_format == ErrorFormat::Text => {
+                eprintln!("{}", e.message);
+                Err(errors::ErrorClass::Usage.code())
+            }
+            Err(e) => {
+                let message = console::strip_ansi_codes(&e.message);
+                let message = message.lines().next().unwrap_or_default();
+                Err(errors::report(
+                    &LostTheWay::UsageError {
+                        message: message.trim_start_matches("error: ").to_owned(),
+                    }
+                    .into(),
+                    error_format,
+                ))
+            }
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub enum TheWayCommand {
     /// Add a new snippet
     New,
     /// Fuzzy search and copy selected to clipboard
     Search {
         #[structopt(flatten)]
         filters: Filters,
+        /// Also search every profile in the [profiles] config section
+        #[structopt(long)]
+        all_profiles: bool,
     },
     /// Sync snippets to a Gist
     ///
-    /// Controlled by $THE_WAY_GITHUB_TOKEN env variable.
-    /// Set this to an access token with the "gist" scope obtained from https://github.com/settings/tokens/new
-    Sync,
+    /// Controlled by $THE_WAY_GITHUB_TOKEN env variable (or `github_access_token` in the config file).
+    /// Set this to an access token with the "gist" scope obtained from https://github.com/settings/tokens/new.
+    /// Set `sync_provider` in the config file to sync to GitLab, Bitbucket, S3, WebDAV, or a `the-way serve --sync` server instead
+    Sync {
+        /// How to settle snippets changed both here and remotely since the last sync
+        /// (default `sync_conflicts` in the config file, or "ask")
+        #[structopt(long, possible_values = &ConflictStrategy::VARIANTS)]
+        conflicts: Option<ConflictStrategy>,
+        /// Show what would be uploaded, downloaded, or deleted (and what's in conflict) without changing anything
+        #[structopt(long)]
+        dry_run: bool,
+        #[structopt(subcommand)]
+        cmd: Option<SyncCommand>,
+    },
     /// Change snippet
     Edit {
-        /// Index of snippet to change
-        index: usize,
+        /// Index (or short ID) of snippet to change
+        index: SnippetId,
+        /// Edit the whole snippet as one document in $EDITOR instead of answering prompts
+        #[structopt(long, short)]
+        editor: bool,
     },
     /// Delete snippet
     #[structopt(alias = "delete")]
     Del {
-        /// Index of snippet to delete
-        index: usize,
+        /// Index (or short ID) of snippet to delete
+        index: SnippetId,
         /// Don't ask for confirmation
         #[structopt(long, short)]
         force: bool,
@@ -45,34 +124,89 @@ pub enum TheWayCLI {
     /// Copy snippet to clipboard
     #[structopt(alias = "copy")]
     Cp {
-        /// Index of snippet to copy
-        index: usize,
+        /// Index (or short ID) of snippet to copy
+        #[structopt(required_unless = "last")]
+        index: Option<SnippetId>,
+        /// Copy the most recently copied snippet again
+        #[structopt(long, conflicts_with = "index")]
+        last: bool,
+    },
+    /// Run a snippet's code in your shell, warning about missing requirements first
+    Run {
+        /// Index (or short ID) of snippet to run
+        index: SnippetId,
+    },
+    /// Keep the database open in the background and run commands sent by the-way
+    ///
+    /// While the daemon runs, every the-way command (in any terminal) is sent to it over a unix socket,
+    /// skipping the database startup and waiting for its lock
+    Daemon {
+        /// Stop the running daemon
+        #[structopt(long)]
+        stop: bool,
+    },
+    /// Serve snippets over HTTP: GET /raw/<index> returns a snippet's code as is
+    ///
+    /// e.g. `curl http://<host>:8000/raw/12 | bash` on another machine
+    Serve {
+        /// Address to listen on (0.0.0.0 for every interface)
+        #[structopt(long, default_value = "127.0.0.1")]
+        address: String,
+        /// Port to listen on (0 picks a free one)
+        #[structopt(long, short, default_value = "8000")]
+        port: u16,
+        /// Also let other the-way instances sync against this one (with `sync_provider = "server"`),
+        /// keeping their snippets in this database. Set `token` in the [server] section to require it from them
+        #[structopt(long)]
+        sync: bool,
+    },
+    /// List recently copied snippets
+    History {
+        /// Number of copies to show
+        #[structopt(long, short, default_value = "10")]
+        number: usize,
     },
     /// View snippet
     View {
-        /// Index of snippet to show
-        index: usize,
+        /// Index (or short ID) of snippet to show
+        index: SnippetId,
     },
+    /// Reverse the last add, edit, delete, or import
+    Undo,
     /// Lists (optionally filtered) snippets
     List {
         #[structopt(flatten)]
         filters: Filters,
     },
-    /// Imports code snippets from JSON.
+    /// Shows snippets added per month and per-tag activity
+    Stats {
+        #[structopt(flatten)]
+        filters: Filters,
+    },
+    /// Add the tags of matching [[tag_rules]] (from the config file) to existing snippets
+    Retag {
+        /// Apply the tag rules (new and imported snippets get them automatically)
+        #[structopt(long)]
+        rules: bool,
+    },
+    /// Step through (optionally filtered) snippets, keeping, editing, retagging, archiving, or deleting each with one key
+    ///
+    /// Archived snippets are tagged "archived". `the-way undo` reverses the whole triage
+    Triage {
+        #[structopt(flatten)]
+        filters: Filters,
+    },
+    /// Imports code snippets from JSON, other snippet managers' files, or a GitHub user's Gists.
     ///
     /// Looks for description, language, and code fields
     Import {
-        /// filename, reads from stdin if not given
-        #[structopt(parse(from_os_str))]
-        file: Option<PathBuf>,
+        #[structopt(flatten)]
+        args: ImportArgs,
     },
-    /// Saves (optionally filtered) snippets to JSON.
+    /// Saves (optionally filtered) snippets to JSON, or a document, site, archive, or other tools' snippets (see --format).
     Export {
-        /// filename, writes to stdout if not given
-        #[structopt(parse(from_os_str))]
-        file: Option<PathBuf>,
         #[structopt(flatten)]
-        filters: Filters,
+        args: ExportArgs,
     },
     /// Clears all data
     Clear {
@@ -80,10 +214,81 @@ pub enum TheWayCLI {
         #[structopt(long, short)]
         force: bool,
     },
+    /// Bundle the database, config file, and themes into a ".tar.gz" file
+    Backup {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+    /// Replace the database, config file, and themes with the ones in a file made by `backup`
+    ///
+    /// The db_dir and themes_dir settings of this machine are kept.
+    Restore {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+        /// Don't ask for confirmation
+        #[structopt(long, short)]
+        force: bool,
+    },
     /// Generate shell completions
+    ///
+    /// Also makes completion specs for fig, carapace, and nushell.
     Complete {
-        #[structopt(possible_values = & Shell::variants())]
-        shell: Shell,
+        #[structopt(possible_values = & CompletionShell::VARIANTS)]
+        shell: CompletionShell,
+    },
+    /// Generate man pages (or Markdown docs) for every command
+    ///
+    /// Writes one page per command and subcommand (the-way.1, the-way-new.1, the-way-themes-set.1, ...) into DIR,
+    /// or prints the main page if no directory is given.
+    Manpage {
+        /// Directory to write pages to
+        #[structopt(parse(from_os_str))]
+        dir: Option<PathBuf>,
+        /// Write Markdown instead of troff
+        #[structopt(long)]
+        markdown: bool,
+    },
+    /// Check the config, database, themes, clipboard, editor, pager, and locale for problems
+    ///
+    /// Prints what passed and what failed (with fixes), include the output in bug reports.
+    Doctor,
+    /// Round-trip every import/export format against the fixture files in a directory
+    #[structopt(setting = AppSettings::Hidden)]
+    Selftest {
+        /// Directory of fixture files, named by format (e.g. snippets.json, with an optional snippets.map)
+        #[structopt(long, parse(from_os_str))]
+        fixtures: PathBuf,
+    },
+    /// Manage the list of known languages (extensions and colors)
+    Languages {
+        #[structopt(subcommand)]
+        cmd: LanguagesCommand,
+    },
+    /// Watch the clipboard and offer to save copied code as a snippet
+    ///
+    /// Code is detected by how many symbols and keywords it has.
+    WatchClipboard {
+        /// Save copied code without asking (tagged "inbox", with a guessed language)
+        #[structopt(long, short)]
+        queue: bool,
+        /// Seconds between clipboard checks (at least 1)
+        #[structopt(long, default_value = "1")]
+        interval: NonZeroU64,
+    },
+    /// Save code without any questions, and describe and tag it later
+    Inbox {
+        #[structopt(subcommand)]
+        cmd: InboxCommand,
+    },
+    /// Save filters under a name, to use as @<name> in place of them (e.g. `the-way list @rust-cli`)
+    Filter {
+        #[structopt(subcommand)]
+        cmd: FilterCommand,
+    },
+    /// Manage individual snippets
+    Snippet {
+        #[structopt(subcommand)]
+        cmd: SnippetCommand,
     },
     /// Manage syntax highlighting themes
     Themes {
@@ -112,6 +317,11 @@ pub enum ThemeCommand {
         #[structopt(parse(from_os_str))]
         file: PathBuf,
     },
+    /// Make a theme matching the terminal's color palette
+    FromTerminal {
+        /// Name of the new theme
+        name: String,
+    },
     /// Add highlight support for a language using a ".sublime-syntax" file.
     Language {
         #[structopt(parse(from_os_str))]
@@ -119,4 +329,118 @@ pub enum ThemeCommand {
     },
     /// Prints the current theme name
     Get,
+    /// Bundle user-added themes and syntaxes with the current theme choice into a ".tar.gz" file
+    Export {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+    /// Add the themes and syntaxes from a bundle made with `themes export` and use its theme
+    Import {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub enum SyncCommand {
+    /// Show what `sync` would upload, download, or delete (and what's in conflict), same as `sync --dry-run`
+    Status,
+    /// List past syncs: when, where to, and how many snippets each uploaded, downloaded, or deleted
+    Log {
+        /// Number of syncs to show
+        #[structopt(long, short, default_value = "10")]
+        number: usize,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub enum InboxCommand {
+    /// Save code tagged "inbox", with its first line as the description and a guessed language
+    Add {
+        /// Code to save, read from stdin if not given
+        code: Option<String>,
+        /// Save the clipboard contents
+        #[structopt(long, short, conflicts_with = "code")]
+        clipboard: bool,
+    },
+    /// Go through inbox snippets, giving each a description, language, and tags (or deleting it)
+    Triage,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum SnippetCommand {
+    /// Link a snippet to a file, its code is replaced by the file contents on `refresh`
+    Track {
+        /// Index (or short ID) of snippet to link
+        index: SnippetId,
+        /// File that the snippet mirrors
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+    /// Remove the link between a snippet and its file
+    Untrack {
+        /// Index (or short ID) of snippet to unlink
+        index: SnippetId,
+    },
+    /// Note what a snippet needs to run (e.g. "jq >= 1.6" docker), shows them if none are given
+    Requires {
+        /// Index (or short ID) of snippet
+        index: SnippetId,
+        /// Requirements, each starting with the program `the-way run` looks for in $PATH
+        requirements: Vec<String>,
+        /// Remove all requirements
+        #[structopt(long, conflicts_with = "requirements")]
+        clear: bool,
+    },
+    /// Share a snippet as a Gist of its own, prints the link and copies it to clipboard.
+    /// Uses $THE_WAY_GITHUB_TOKEN (or `github_access_token` in the config file), like `sync`
+    Share {
+        /// Index (or short ID) of snippet to share
+        index: SnippetId,
+        /// Make a public Gist, it's secret (only seen by people with the link) otherwise
+        #[structopt(long)]
+        public: bool,
+    },
+    /// Re-read linked files into their snippets
+    Refresh {
+        /// Keep running and refresh whenever a linked file changes
+        #[structopt(long, short)]
+        watch: bool,
+        /// Seconds between checks in watch mode
+        #[structopt(long, default_value = "2")]
+        interval: u64,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub enum FilterCommand {
+    /// Save filters under a name, e.g. `the-way filter save rust-cli --languages rust --tags cli`
+    #[structopt(setting = AppSettings::TrailingVarArg, setting = AppSettings::AllowLeadingHyphen)]
+    Save {
+        /// Name to use the filters by, as @<name>
+        name: String,
+        /// Filters, as given to `list`
+        #[structopt(required = true, allow_hyphen_values = true)]
+        filters: Vec<String>,
+    },
+    /// List saved filters
+    List,
+    /// Delete a saved filter
+    #[structopt(alias = "delete")]
+    Del {
+        /// Name of the filter to delete
+        name: String,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub enum LanguagesCommand {
+    /// Download the latest languages.yml from GitHub Linguist, used instead of the bundled one from then on
+    Update {
+        /// Where to download languages.yml from
+        #[structopt(long, default_value = LINGUIST_URL)]
+        url: String,
+    },
+    /// Go back to the bundled languages.yml
+    Reset,
 }
@@ -0,0 +1,198 @@
+//! Watches the clipboard for copied code and offers to save it as a snippet
+use std::collections::HashSet;
+use std::num::NonZeroU64;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use color_eyre::Help;
+use dialoguer::Confirm;
+
+use crate::language::Language;
+use crate::the_way::tag_rules::{self, Rule};
+use crate::the_way::{snippet::Snippet, undo::UndoEntry, TheWay};
+use crate::utils;
+
+/// Tag given to snippets queued without asking
+pub(crate) const INBOX_TAG: &str = "inbox";
+
+/// Characters that are much more common in code than in prose
+const CODE_SYMBOLS: &str = "{}[]();=<>&|$#\\/*_:!";
+
+/// Words that show up at the start of code but rarely in prose
+const CODE_KEYWORDS: [&str; 28] = [
+    "fn", "let", "const", "var", "def", "class", "import", "from", "return", "function", "if",
+    "else", "for", "while", "pub", "use", "#include", "package", "func", "select", "echo", "sudo",
+    "export", "cd", "git", "docker", "kubectl", "curl",
+];
+
+/// Commands that usually start a shell one-liner
+const SHELL_COMMANDS: [&str; 16] = [
+    "echo", "sudo", "export", "cd", "git", "docker", "kubectl", "curl", "ls", "grep", "find",
+    "apt", "brew", "npm", "cargo", "ssh",
+];
+
+/// Guesses if text is code from how many symbols and keywords it has
+pub(crate) fn looks_like_code(text: &str) -> bool {
+    let text = text.trim();
+    let num_chars = text.chars().filter(|c| !c.is_whitespace()).count();
+    if num_chars < 8 || text.starts_with("http://") || text.starts_with("https://") {
+        return false;
+    }
+    let num_symbols = text.chars().filter(|c| CODE_SYMBOLS.contains(*c)).count();
+    let num_keyword_lines = text
+        .lines()
+        .filter(|line| {
+            line.split_whitespace()
+                .next()
+                .is_some_and(|word| CODE_KEYWORDS.contains(&word.to_ascii_lowercase().as_str()))
+        })
+        .count();
+    let symbol_density = num_symbols as f64 / num_chars as f64;
+    symbol_density >= 0.08 || num_keyword_lines * 2 >= text.lines().count().max(2)
+}
+
+/// Guesses the language of a piece of code from a shebang or telltale words
+pub(crate) fn guess_language(code: &str) -> String {
+    let first_line = code.trim_start().lines().next().unwrap_or_default();
+    if let Some(shebang) = first_line.strip_prefix("#!") {
+        for (name, language) in &[
+            ("python", "python"),
+            ("node", "javascript"),
+            ("ruby", "ruby"),
+            ("perl", "perl"),
+            ("zsh", "shell"),
+            ("bash", "shell"),
+            ("sh", "shell"),
+        ] {
+            if shebang.contains(name) {
+                return (*language).to_owned();
+            }
+        }
+    }
+    let has = |needle: &str| code.contains(needle);
+    let language = if has("fn ") && (has("let ") || has("->") || has("::")) {
+        "rust"
+    } else if has("package ") && has("func ") {
+        "go"
+    } else if has("#include") {
+        if has("std::") || has("class ") {
+            "c++"
+        } else {
+            "c"
+        }
+    } else if has("def ") || (has("import ") && has(":\n")) {
+        "python"
+    } else if has("function") || has("=>") || has("const ") || has("console.") {
+        "javascript"
+    } else if has("public class") || has("System.out") {
+        "java"
+    } else if code.to_ascii_uppercase().contains("SELECT ")
+        && code.to_ascii_uppercase().contains(" FROM ")
+    {
+        "sql"
+    } else if first_line.trim_start().starts_with('$')
+        || first_line
+            .split_whitespace()
+            .next()
+            .is_some_and(|word| SHELL_COMMANDS.contains(&word))
+    {
+        "shell"
+    } else {
+        "text"
+    };
+    language.to_owned()
+}
+
+impl TheWay {
+    /// Checks the clipboard every `interval` seconds, when code is copied
+    /// asks if it should be saved (or saves it with the inbox tag if `queue` is set)
+    pub(crate) fn watch_clipboard(
+        &mut self,
+        interval: NonZeroU64,
+        queue: bool,
+    ) -> color_eyre::Result<()> {
+        // Don't offer to save what's already saved (e.g. copied with `the-way cp`)
+        let mut seen: HashSet<String> = self
+            .list_snippets()?
+            .into_iter()
+            .map(|snippet| snippet.code.trim().to_owned())
+            .collect();
+        let rules = self.tag_rules()?;
+        let mut last = utils::read_clipboard()
+            .suggestion("Make sure xclip (Linux) or pbpaste (macOS) is installed")?;
+        println!("Watching the clipboard for code, press Ctrl-C to stop");
+        loop {
+            thread::sleep(Duration::from_secs(interval.get()));
+            let contents = match utils::read_clipboard() {
+                Ok(contents) => contents,
+                Err(_) => continue,
+            };
+            if contents == last {
+                continue;
+            }
+            last = contents.to_owned();
+            let code = contents.trim().to_owned();
+            if seen.contains(&code) || !looks_like_code(&code) {
+                continue;
+            }
+            seen.insert(code.to_owned());
+            if queue {
+                match self.save_to_inbox(&code, "watch-clipboard", &rules) {
+                    Ok(index) => println!("Queued snippet #{} (tagged {})", index, INBOX_TAG),
+                    // Keep watching, e.g. if the code breaks a validation rule
+                    Err(e) => eprintln!("Couldn't save copied code: {}", e),
+                }
+            } else {
+                println!();
+                for line in code.lines().take(5) {
+                    println!("  {}", line);
+                }
+                if code.lines().count() > 5 {
+                    println!("  ...");
+                }
+                if Confirm::new()
+                    .with_prompt("Save this as a snippet?")
+                    .default(false)
+                    .interact()?
+                {
+                    let description = utils::user_input("Description", None, false, false)?;
+                    match self.save_clipboard_snippet(&code, description, &rules) {
+                        Ok(index) => println!("Added snippet #{}", index),
+                        Err(e) => eprintln!("Couldn't save copied code: {}", e),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Saves copied code, asking for language (guessed by default) and tags (added to by `rules`)
+    fn save_clipboard_snippet(
+        &mut self,
+        code: &str,
+        description: String,
+        rules: &[Rule],
+    ) -> color_eyre::Result<usize> {
+        let language = utils::user_input("Language", Some(&guess_language(code)), true, false)?
+            .to_ascii_lowercase();
+        let tags = utils::user_input("Tags (space separated)", None, false, true)?;
+        let extension = Language::get_extension(&language, &self.languages);
+        let mut snippet = Snippet::new(
+            self.get_current_snippet_index()? + 1,
+            description,
+            language,
+            extension,
+            &tags,
+            Utc::now(),
+            Utc::now(),
+            format!("{}\n", code),
+        );
+        tag_rules::apply_tag_rules(rules, &mut snippet);
+        snippet.validate(&self.config.validation)?;
+        let index = self.add_snippet(&snippet)?;
+        self.increment_snippet_index()?;
+        self.record_change(&UndoEntry::added("watch-clipboard", &[index]))?;
+        self.db.flush()?;
+        Ok(index)
+    }
+}
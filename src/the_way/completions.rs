@@ -0,0 +1,479 @@
+//! Shell completion specs for shells `clap` doesn't support (fig, carapace, nushell), read from `clap`'s help
+use std::collections::HashMap;
+use std::io;
+use std::iter;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::json;
+use structopt::clap::{AppSettings, ErrorKind, Shell};
+use structopt::StructOpt;
+
+use crate::errors::LostTheWay;
+use crate::the_way::cli::TheWayCLI;
+use crate::utils;
+
+/// Shells that completions can be generated for
+#[derive(Debug, Clone, Copy)]
+pub enum CompletionShell {
+    /// Shells supported by `clap`
+    Clap(Shell),
+    /// [fig](https://fig.io) completion spec
+    Fig,
+    /// [carapace](https://github.com/rsteube/carapace-spec) YAML spec
+    Carapace,
+    /// nushell `extern` definitions
+    Nushell,
+}
+
+impl CompletionShell {
+    /// Possible shell names
+    pub const VARIANTS: [&'static str; 8] = [
+        "bash",
+        "fish",
+        "zsh",
+        "powershell",
+        "elvish",
+        "fig",
+        "carapace",
+        "nushell",
+    ];
+}
+
+impl FromStr for CompletionShell {
+    type Err = LostTheWay;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fig" => Ok(Self::Fig),
+            "carapace" => Ok(Self::Carapace),
+            "nushell" | "nu" => Ok(Self::Nushell),
+            other => {
+                Shell::from_str(other)
+                    .map(Self::Clap)
+                    .map_err(|_| LostTheWay::OutOfCheeseError {
+                        message: format!("Unknown shell {}", other),
+                    })
+            }
+        }
+    }
+}
+
+/// A flag or option of a command
+#[derive(Clone)]
+pub(crate) struct FlagSpec {
+    pub(crate) short: Option<char>,
+    pub(crate) long: Option<String>,
+    pub(crate) help: String,
+    pub(crate) takes_value: bool,
+    pub(crate) possible_values: Vec<String>,
+}
+
+/// A positional argument of a command
+pub(crate) struct PositionalSpec {
+    pub(crate) name: String,
+    pub(crate) help: String,
+    pub(crate) required: bool,
+    pub(crate) possible_values: Vec<String>,
+}
+
+/// Everything needed to describe a (sub)command, read from its `clap` help
+pub(crate) struct CommandSpec {
+    pub(crate) name: String,
+    pub(crate) about: String,
+    pub(crate) long_about: String,
+    pub(crate) aliases: Vec<String>,
+    pub(crate) flags: Vec<FlagSpec>,
+    pub(crate) positionals: Vec<PositionalSpec>,
+    pub(crate) subcommands: Vec<CommandSpec>,
+}
+
+/// An argument or subcommand in a section of `clap` help: the line with its names, and the help under it
+struct HelpEntry {
+    names: String,
+    help: String,
+}
+
+/// Subcommands' aliases, by the subcommand's path. `clap`'s help leaves out (hidden) aliases
+const ALIASES: [(&[&str], &str); 4] = [
+    (&["del"], "delete"),
+    (&["cp"], "copy"),
+    (&["config"], "configure"),
+    (&["filter", "del"], "delete"),
+];
+
+/// `clap`'s `--help` for the command at `path` (subcommand names),
+/// with each help text on lines of its own, not wrapped
+fn help_text(path: &[String]) -> String {
+    let args = iter::once(utils::NAME)
+        .chain(path.iter().map(String::as_str))
+        .chain(iter::once("--help"));
+    match TheWayCLI::clap()
+        .global_setting(AppSettings::NextLineHelp)
+        .set_term_width(0)
+        .get_matches_from_safe(args)
+    {
+        Err(error) if error.kind == ErrorKind::HelpDisplayed => error.message,
+        _ => String::new(),
+    }
+}
+
+/// Splits `help_text` output into the description (above the usage), the usage line,
+/// and the entries of each section (FLAGS, OPTIONS, ARGS, SUBCOMMANDS)
+fn parse_help(help: &str) -> (String, String, HashMap<String, Vec<HelpEntry>>) {
+    // The first line is the command name and version
+    let mut lines = help.lines().skip(1);
+    let description = lines
+        .by_ref()
+        .take_while(|line| *line != "USAGE:")
+        .collect::<Vec<_>>()
+        .join("\n");
+    let usage = lines.next().unwrap_or_default().trim().to_owned();
+    let mut sections: HashMap<String, Vec<HelpEntry>> = HashMap::new();
+    let mut section = String::new();
+    for line in lines {
+        let indent = line.len() - line.trim_start().len();
+        if line.ends_with(':') && indent == 0 {
+            section = line.trim_end_matches(':').to_owned();
+        } else if !line.trim().is_empty() && indent < 12 {
+            sections
+                .entry(section.clone())
+                .or_default()
+                .push(HelpEntry {
+                    names: line.trim().to_owned(),
+                    help: String::new(),
+                });
+        } else if let Some(entry) = sections
+            .get_mut(&section)
+            .and_then(|entries| entries.last_mut())
+        {
+            entry.help.push_str(line.trim());
+            entry.help.push('\n');
+        }
+    }
+    for entry in sections.values_mut().flatten() {
+        entry.help = entry.help.trim().to_owned();
+    }
+    (description.trim().to_owned(), usage, sections)
+}
+
+/// Takes the "[<label>: a, b]" `clap` adds to the end of some `help` off it, returning the values
+fn take_values(help: &mut String, label: &str) -> Vec<String> {
+    let marker = format!("[{}: ", label);
+    match help.rfind(&marker) {
+        Some(start) if help.ends_with(']') => {
+            let values = help[start + marker.len()..help.len() - 1]
+                .split(", ")
+                .map(String::from)
+                .collect();
+            help.truncate(start);
+            help.truncate(help.trim_end().len());
+            values
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The first paragraph of `help`
+fn summary(help: &str) -> String {
+    help.split("\n\n").next().unwrap_or_default().to_owned()
+}
+
+impl CommandSpec {
+    /// Describes the whole the-way CLI
+    pub(crate) fn the_way() -> Self {
+        let mut command = Self::from_help(&[], String::new());
+        command.about = summary(&command.long_about);
+        command
+    }
+
+    /// Reads the help of the command at `path` (subcommand names), `about` comes from its parent's help
+    fn from_help(path: &[String], about: String) -> Self {
+        let (long_about, usage, mut sections) = parse_help(&help_text(path));
+        let mut flags = Vec::new();
+        for (section, takes_value) in [("FLAGS", false), ("OPTIONS", true)] {
+            for mut entry in sections.remove(section).unwrap_or_default() {
+                let possible_values = take_values(&mut entry.help, "possible values");
+                take_values(&mut entry.help, "aliases");
+                take_values(&mut entry.help, "default");
+                let mut flag = FlagSpec {
+                    short: None,
+                    long: None,
+                    help: summary(&entry.help),
+                    takes_value,
+                    possible_values,
+                };
+                for name in entry.names.split_whitespace() {
+                    let name = name.trim_end_matches(',');
+                    if let Some(long) = name.strip_prefix("--") {
+                        flag.long = Some(long.to_owned());
+                    } else if let Some(short) = name.strip_prefix('-') {
+                        flag.short = short.chars().next();
+                    }
+                }
+                // clap adds these to every command
+                if !matches!(flag.long.as_deref(), Some("help") | Some("version")) {
+                    flags.push(flag);
+                }
+            }
+        }
+        let positionals = sections
+            .remove("ARGS")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|mut entry| {
+                let possible_values = take_values(&mut entry.help, "possible values");
+                take_values(&mut entry.help, "default");
+                let name = entry.names.trim_end_matches("...");
+                PositionalSpec {
+                    name: name.trim_matches(|c| c == '<' || c == '>').to_owned(),
+                    help: summary(&entry.help),
+                    required: usage.contains(name),
+                    possible_values,
+                }
+            })
+            .collect();
+        let subcommands = sections
+            .remove("SUBCOMMANDS")
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|entry| entry.names != "help")
+            .map(|entry| {
+                let mut subcommand_path = path.to_vec();
+                subcommand_path.push(entry.names);
+                Self::from_help(&subcommand_path, summary(&entry.help))
+            })
+            .collect();
+        Self {
+            name: path
+                .last()
+                .cloned()
+                .unwrap_or_else(|| String::from(utils::NAME)),
+            about,
+            long_about,
+            aliases: ALIASES
+                .iter()
+                .filter(|(alias_path, _)| alias_path.iter().eq(path))
+                .map(|(_, alias)| String::from(*alias))
+                .collect(),
+            flags,
+            positionals,
+            subcommands,
+        }
+    }
+}
+
+/// fig spec (as a JSON object) for a command
+fn fig_spec(command: &CommandSpec) -> serde_json::Value {
+    let mut spec = json!({
+        "name": command.name,
+        "description": command.about,
+    });
+    if !command.aliases.is_empty() {
+        let mut names = vec![command.name.to_owned()];
+        names.extend(command.aliases.iter().cloned());
+        spec["name"] = json!(names);
+    }
+    if !command.flags.is_empty() {
+        spec["options"] = command
+            .flags
+            .iter()
+            .map(|flag| {
+                let mut names = Vec::new();
+                if let Some(short) = flag.short {
+                    names.push(format!("-{}", short));
+                }
+                if let Some(long) = &flag.long {
+                    names.push(format!("--{}", long));
+                }
+                let mut option = json!({"name": names, "description": flag.help});
+                if flag.takes_value {
+                    option["args"] = json!({
+                        "name": flag.long.as_deref().unwrap_or("value"),
+                        "suggestions": flag.possible_values,
+                    });
+                }
+                option
+            })
+            .collect();
+    }
+    if !command.positionals.is_empty() {
+        spec["args"] = command
+            .positionals
+            .iter()
+            .map(|positional| {
+                json!({
+                    "name": positional.name,
+                    "description": positional.help,
+                    "isOptional": !positional.required,
+                    "suggestions": positional.possible_values,
+                })
+            })
+            .collect();
+    }
+    if !command.subcommands.is_empty() {
+        spec["subcommands"] = command.subcommands.iter().map(fig_spec).collect();
+    }
+    spec
+}
+
+fn yaml_list(values: &[String]) -> serde_yaml::Value {
+    serde_yaml::Value::Sequence(values.iter().map(|value| value.to_owned().into()).collect())
+}
+
+/// carapace spec (as a YAML mapping) for a command
+fn carapace_spec(command: &CommandSpec) -> serde_yaml::Value {
+    let mut spec = serde_yaml::Mapping::new();
+    spec.insert("name".into(), command.name.to_owned().into());
+    if !command.aliases.is_empty() {
+        spec.insert("aliases".into(), yaml_list(&command.aliases));
+    }
+    spec.insert("description".into(), command.about.to_owned().into());
+    if !command.flags.is_empty() {
+        let mut flags = serde_yaml::Mapping::new();
+        let mut completion_flags = serde_yaml::Mapping::new();
+        for flag in &command.flags {
+            let mut key = match (flag.short, &flag.long) {
+                (Some(short), Some(long)) => format!("-{}, --{}", short, long),
+                (Some(short), None) => format!("-{}", short),
+                (None, Some(long)) => format!("--{}", long),
+                (None, None) => continue,
+            };
+            if flag.takes_value {
+                key.push('=');
+            }
+            flags.insert(key.into(), flag.help.to_owned().into());
+            if !flag.possible_values.is_empty() {
+                if let Some(long) = &flag.long {
+                    completion_flags
+                        .insert(long.to_owned().into(), yaml_list(&flag.possible_values));
+                }
+            }
+        }
+        spec.insert("flags".into(), flags.into());
+        let mut completion = serde_yaml::Mapping::new();
+        if !completion_flags.is_empty() {
+            completion.insert("flag".into(), completion_flags.into());
+        }
+        if command
+            .positionals
+            .iter()
+            .any(|positional| !positional.possible_values.is_empty())
+        {
+            completion.insert(
+                "positional".into(),
+                command
+                    .positionals
+                    .iter()
+                    .map(|positional| yaml_list(&positional.possible_values))
+                    .collect::<Vec<_>>()
+                    .into(),
+            );
+        }
+        if !completion.is_empty() {
+            spec.insert("completion".into(), completion.into());
+        }
+    }
+    if !command.subcommands.is_empty() {
+        spec.insert(
+            "commands".into(),
+            serde_yaml::Value::Sequence(command.subcommands.iter().map(carapace_spec).collect()),
+        );
+    }
+    spec.into()
+}
+
+/// Help text as a trailing nushell comment
+fn nushell_comment(help: &str) -> String {
+    if help.is_empty() {
+        String::new()
+    } else {
+        format!("  # {}", help)
+    }
+}
+
+/// Completer for snippet indices, reading the snippet snapshot
+const NUSHELL_SNIPPETS: &str = "nu-complete the-way snippets";
+
+/// nushell `extern` definitions for a command and its subcommands
+fn nushell_spec(command: &CommandSpec, prefix: &str, output: &mut String) {
+    let name = if prefix.is_empty() {
+        command.name.to_owned()
+    } else {
+        format!("{} {}", prefix, command.name)
+    };
+    // Custom completers have to be defined before the extern using them
+    let mut completers = String::new();
+    let mut definition = format!("# {}\nexport extern \"{}\" [\n", command.about, name);
+    for positional in &command.positionals {
+        let completer = if positional.name == "index" {
+            format!("@\"{}\"", NUSHELL_SNIPPETS)
+        } else if positional.possible_values.is_empty() {
+            String::new()
+        } else {
+            let completer_name = format!("nu-complete {} {}", name, positional.name);
+            completers.push_str(&format!(
+                "def \"{}\" [] {{\n  [{}]\n}}\n\n",
+                completer_name,
+                positional
+                    .possible_values
+                    .iter()
+                    .map(|v| format!("\"{}\"", v))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+            format!("@\"{}\"", completer_name)
+        };
+        definition.push_str(&format!(
+            "  {}{}: string{}{}\n",
+            positional.name,
+            if positional.required { "" } else { "?" },
+            completer,
+            nushell_comment(&positional.help)
+        ));
+    }
+    for flag in &command.flags {
+        let long = match &flag.long {
+            Some(long) => long,
+            None => continue,
+        };
+        definition.push_str(&format!(
+            "  --{}{}{}{}\n",
+            long,
+            flag.short.map(|s| format!("({})", s)).unwrap_or_default(),
+            if flag.takes_value { ": string" } else { "" },
+            nushell_comment(&flag.help)
+        ));
+    }
+    definition.push_str("]\n\n");
+    output.push_str(&completers);
+    output.push_str(&definition);
+    for subcommand in &command.subcommands {
+        nushell_spec(subcommand, &name, output);
+    }
+}
+
+/// Writes completions for `shell` to stdout, completing snippet indices from the snapshot at `snapshot` where the shell can
+pub(crate) fn generate(shell: CompletionShell, snapshot: &Path) -> color_eyre::Result<()> {
+    let command = CommandSpec::the_way();
+    match shell {
+        CompletionShell::Clap(shell) => {
+            TheWayCLI::clap().gen_completions_to(utils::NAME, shell, &mut io::stdout());
+        }
+        CompletionShell::Fig => println!(
+            "const completionSpec: Fig.Spec = {};\n\nexport default completionSpec;",
+            serde_json::to_string_pretty(&fig_spec(&command))?
+        ),
+        CompletionShell::Carapace => print!("{}", serde_yaml::to_string(&carapace_spec(&command))?),
+        CompletionShell::Nushell => {
+            let mut output = format!(
+                "def \"{}\" [] {{\n  open {:?} | get snippets | each {{|snippet| {{value: $snippet.id, description: $snippet.description}}}}\n}}\n\n",
+                NUSHELL_SNIPPETS,
+                snapshot.display().to_string()
+            );
+            nushell_spec(&command, "", &mut output);
+            print!("{}", output);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,477 @@
+//! `the-way daemon`: keeps the database open and runs the commands other the-way processes send it
+//! over a unix socket, so they skip opening the database and don't fight over its lock.
+//!
+//! A client sends its arguments with its stdin, stdout, and stderr attached (as file descriptors),
+//! the daemon runs the command with those as its own and answers with the exit code,
+//! so output, prompts, and $EDITOR work as if the command ran in the client.
+//! Commands that need the terminal to themselves (search) or run until stopped
+//! borrow the database instead: the daemon closes it until the client disconnects.
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use color_eyre::Help;
+use structopt::StructOpt;
+
+use crate::configuration::TheWayConfig;
+use crate::errors::{self, ErrorFormat, LostTheWay};
+use crate::language::Language;
+use crate::the_way::{
+    cli::{SnippetCommand, TheWayCLI, TheWayCommand},
+    TheWay,
+};
+
+/// Environment variables commands depend on, set to the client's while its command runs
+/// (along with every THE_WAY_* variable)
+const FORWARDED_ENV: [&str; 12] = [
+    "PATH",
+    "EDITOR",
+    "VISUAL",
+    "SHELL",
+    "TERM",
+    "COLUMNS",
+    "LINES",
+    "NO_COLOR",
+    "CLICOLOR",
+    "CLICOLOR_FORCE",
+    "DISPLAY",
+    "WAYLAND_DISPLAY",
+];
+/// Most bytes a request can be read in one go with its file descriptors
+const BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Serialize, Deserialize, Debug)]
+enum Request {
+    /// Run a command (arguments include the program name), sent with stdin, stdout, and stderr
+    Run {
+        args: Vec<String>,
+        cwd: PathBuf,
+        /// `None` for unset variables
+        env: Vec<(String, Option<String>)>,
+    },
+    /// Close the database until the connection closes
+    Lend,
+    Stop,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Response {
+    /// Exit code for `Run`, 0 once the database is closed for `Lend`
+    code: i32,
+}
+
+/// What happened to a command sent to the daemon
+pub(crate) enum Forwarded {
+    /// The daemon ran it, exit with this code
+    Ran(i32),
+    /// Run it here, the database is free until the connection is dropped
+    Lent(UnixStream),
+    NoDaemon,
+}
+
+/// Socket next to the database folder (not in it, `clear` empties that)
+fn socket_path(config: &TheWayConfig) -> PathBuf {
+    config.db_dir.with_extension("sock")
+}
+
+/// Connects to the daemon for `config`'s database, if it's running
+fn connect(config: &TheWayConfig) -> Option<UnixStream> {
+    UnixStream::connect(socket_path(config)).ok()
+}
+
+/// Commands the daemon can't run for a client: they read keys from /dev/tty, never finish, or replace the database
+fn needs_database(cmd: &TheWayCommand) -> bool {
+    matches!(
+        cmd,
+        TheWayCommand::Search { .. }
+            | TheWayCommand::Sync { .. }
+            | TheWayCommand::Triage { .. }
+            | TheWayCommand::WatchClipboard { .. }
+            | TheWayCommand::Serve { .. }
+            | TheWayCommand::Restore { .. }
+            | TheWayCommand::Snippet {
+                cmd: SnippetCommand::Refresh { watch: true, .. }
+            }
+    )
+}
+
+fn send(stream: &mut UnixStream, message: &impl serde::Serialize) -> color_eyre::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    stream.write_all(&line)?;
+    Ok(())
+}
+
+fn receive<T: serde::de::DeserializeOwned>(
+    reader: &mut impl BufRead,
+) -> color_eyre::Result<Option<T>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+/// Sends `data` with `fds` attached
+fn send_with_fds(stream: &UnixStream, data: &[u8], fds: &[RawFd]) -> io::Result<()> {
+    let fds_size = mem::size_of_val(fds) as u32;
+    // Safe as long as the control buffer has room for the fds, which CMSG_SPACE makes sure of
+    let sent = unsafe {
+        let mut iov = libc::iovec {
+            iov_base: data.as_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+        let mut control = vec![0u8; libc::CMSG_SPACE(fds_size) as usize];
+        let mut message: libc::msghdr = mem::zeroed();
+        message.msg_iov = &mut iov;
+        message.msg_iovlen = 1;
+        message.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        message.msg_controllen = control.len() as _;
+        let header = libc::CMSG_FIRSTHDR(&message);
+        (*header).cmsg_level = libc::SOL_SOCKET;
+        (*header).cmsg_type = libc::SCM_RIGHTS;
+        (*header).cmsg_len = libc::CMSG_LEN(fds_size) as _;
+        ptr::copy_nonoverlapping(
+            fds.as_ptr(),
+            libc::CMSG_DATA(header) as *mut RawFd,
+            fds.len(),
+        );
+        libc::sendmsg(stream.as_raw_fd(), &message, 0)
+    };
+    if sent < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // The fds went with the first byte, the rest can follow normally
+    (&*stream).write_all(&data[sent as usize..])
+}
+
+/// Reads (at most `BUFFER_SIZE`) bytes and any fds attached to them (closed when dropped)
+fn receive_with_fds(stream: &UnixStream) -> io::Result<(Vec<u8>, Vec<OwnedFd>)> {
+    let mut data = vec![0u8; BUFFER_SIZE];
+    let mut fds = Vec::new();
+    // Safe as the kernel writes no more than the lengths given
+    let received = unsafe {
+        let mut iov = libc::iovec {
+            iov_base: data.as_mut_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+        let mut control = vec![0u8; libc::CMSG_SPACE(3 * mem::size_of::<RawFd>() as u32) as usize];
+        let mut message: libc::msghdr = mem::zeroed();
+        message.msg_iov = &mut iov;
+        message.msg_iovlen = 1;
+        message.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        message.msg_controllen = control.len() as _;
+        let received = libc::recvmsg(stream.as_raw_fd(), &mut message, libc::MSG_CMSG_CLOEXEC);
+        if received >= 0 {
+            let mut header = libc::CMSG_FIRSTHDR(&message);
+            while !header.is_null() {
+                if (*header).cmsg_level == libc::SOL_SOCKET
+                    && (*header).cmsg_type == libc::SCM_RIGHTS
+                {
+                    let count = ((*header).cmsg_len as usize - libc::CMSG_LEN(0) as usize)
+                        / mem::size_of::<RawFd>();
+                    let first = libc::CMSG_DATA(header) as *const RawFd;
+                    for i in 0..count {
+                        fds.push(OwnedFd::from_raw_fd(*first.add(i)));
+                    }
+                }
+                header = libc::CMSG_NXTHDR(&message, header);
+            }
+        }
+        received
+    };
+    if received < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    data.truncate(received as usize);
+    Ok((data, fds))
+}
+
+/// Sends the command to the daemon if one is running for this database:
+/// it either runs the command or closes the database for the command to run here
+pub(crate) fn forward(cli: &TheWayCLI, config: &TheWayConfig) -> color_eyre::Result<Forwarded> {
+    let mut stream = match connect(config) {
+        Some(stream) => stream,
+        None => {
+            if let TheWayCommand::Daemon { stop: true } = &cli.cmd {
+                println!("No daemon running");
+                return Ok(Forwarded::Ran(0));
+            }
+            return Ok(Forwarded::NoDaemon);
+        }
+    };
+    if let TheWayCommand::Daemon { stop: true } = &cli.cmd {
+        send(&mut stream, &Request::Stop)?;
+        receive::<Response>(&mut BufReader::new(&stream))?;
+        println!("Daemon stopped");
+        return Ok(Forwarded::Ran(0));
+    }
+    if needs_database(&cli.cmd) {
+        send(&mut stream, &Request::Lend)?;
+        receive::<Response>(&mut BufReader::new(&stream))?;
+        return Ok(Forwarded::Lent(stream));
+    }
+
+    let env = FORWARDED_ENV
+        .iter()
+        .map(|name| name.to_string())
+        .chain(
+            env::vars()
+                .map(|(name, _)| name)
+                .filter(|name| name.starts_with("THE_WAY_")),
+        )
+        .map(|name| {
+            let value = env::var(&name).ok();
+            (name, value)
+        })
+        .collect();
+    let request = Request::Run {
+        args: env::args().collect(),
+        cwd: env::current_dir()?,
+        env,
+    };
+    let mut line = serde_json::to_vec(&request)?;
+    line.push(b'\n');
+    send_with_fds(
+        &stream,
+        &line,
+        &[
+            io::stdin().as_raw_fd(),
+            io::stdout().as_raw_fd(),
+            io::stderr().as_raw_fd(),
+        ],
+    )?;
+    match receive::<Response>(&mut BufReader::new(&stream))? {
+        Some(response) => Ok(Forwarded::Ran(response.code)),
+        None => {
+            let error: color_eyre::Result<Forwarded> = Err(LostTheWay::OutOfCheeseError {
+                message: String::from("The daemon stopped while running the command"),
+            }
+            .into());
+            error.suggestion("Start it again with `the-way daemon`")
+        }
+    }
+}
+
+/// Points stdin, stdout, and stderr at `fds` until dropped, then back at the daemon's own
+struct ClientIo {
+    saved: [RawFd; 3],
+    fds: Vec<OwnedFd>,
+}
+
+impl ClientIo {
+    fn new(fds: Vec<OwnedFd>) -> io::Result<Self> {
+        if fds.len() != 3 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected stdin, stdout, and stderr",
+            ));
+        }
+        let mut saved = [0; 3];
+        for (target, saved) in saved.iter_mut().enumerate() {
+            // Safe, only duplicates file descriptors
+            *saved = unsafe { libc::dup(target as RawFd) };
+            if *saved < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        let client_io = Self { saved, fds };
+        for (target, fd) in client_io.fds.iter().enumerate() {
+            if unsafe { libc::dup2(fd.as_raw_fd(), target as RawFd) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(client_io)
+    }
+}
+
+impl Drop for ClientIo {
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+        // Safe, these are ours to close (the client's fds close when dropped)
+        unsafe {
+            for (target, saved) in self.saved.iter().enumerate() {
+                libc::dup2(*saved, target as RawFd);
+                libc::close(*saved);
+            }
+        }
+    }
+}
+
+/// Sets environment variables until dropped, then puts the old values back
+struct ClientEnv {
+    old: Vec<(String, Option<String>)>,
+    old_cwd: PathBuf,
+}
+
+impl ClientEnv {
+    fn new(vars: Vec<(String, Option<String>)>, cwd: &Path) -> io::Result<Self> {
+        let client_env = Self {
+            old: vars
+                .iter()
+                .map(|(name, _)| (name.to_owned(), env::var(name).ok()))
+                .collect(),
+            old_cwd: env::current_dir()?,
+        };
+        set_vars(vars);
+        env::set_current_dir(cwd)?;
+        Ok(client_env)
+    }
+}
+
+fn set_vars(vars: Vec<(String, Option<String>)>) {
+    for (name, value) in vars {
+        match value {
+            Some(value) => env::set_var(name, value),
+            None => env::remove_var(name),
+        }
+    }
+}
+
+impl Drop for ClientEnv {
+    fn drop(&mut self) {
+        set_vars(mem::take(&mut self.old));
+        let _ = env::set_current_dir(&self.old_cwd);
+    }
+}
+
+impl TheWay {
+    /// Runs a client's command with its stdin, stdout, stderr, environment, and working directory.
+    /// Errors are printed to the client's stderr the way the-way would print them, the exit code is returned
+    fn run_for_client(
+        &mut self,
+        args: Vec<String>,
+        cwd: &Path,
+        vars: Vec<(String, Option<String>)>,
+        fds: Vec<OwnedFd>,
+    ) -> color_eyre::Result<i32> {
+        let _io = ClientIo::new(fds)?;
+        let _env = ClientEnv::new(vars, cwd)?;
+        let error_format = ErrorFormat::from_args(&args);
+        let cli = match TheWayCLI::parse(args, error_format) {
+            Ok(cli) => cli,
+            Err(code) => return Ok(code),
+        };
+        // Colors depend on the client's terminal
+        console::set_colors_enabled(
+            !cli.accessible && console::Term::stdout().features().colors_supported(),
+        );
+        self.cli = cli;
+        match self.periodic_backup().and_then(|_| self.run()) {
+            Ok(()) => Ok(0),
+            Err(e) => Ok(errors::report(&e, error_format)),
+        }
+    }
+}
+
+/// Opens the database again after it was lent, `None` (and reported) if it can't be
+fn reopen(languages: &HashMap<String, Language>) -> Option<TheWay> {
+    let cli = TheWayCLI::from_iter(&["the-way", "daemon"]);
+    match TheWayConfig::load().and_then(|config| TheWay::open(cli, languages.clone(), config)) {
+        Ok(the_way) => Some(the_way),
+        Err(e) => {
+            eprintln!("Couldn't open the database: {}", e);
+            None
+        }
+    }
+}
+
+/// Binds the socket readable and writable only by the user (whatever the umask says)
+fn bind(socket: &Path) -> io::Result<UnixListener> {
+    // Safe, umask can't fail
+    let umask = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(socket);
+    unsafe { libc::umask(umask) };
+    listener
+}
+
+/// Runs the daemon until `the-way daemon --stop`
+pub(crate) fn daemon(
+    languages: HashMap<String, Language>,
+    config: TheWayConfig,
+) -> color_eyre::Result<()> {
+    let socket = socket_path(&config);
+    if connect(&config).is_some() {
+        let error: color_eyre::Result<()> = Err(LostTheWay::OutOfCheeseError {
+            message: format!("A daemon is already listening on {}", socket.display()),
+        }
+        .into());
+        return error.suggestion("Stop it with `the-way daemon --stop`");
+    }
+    // Left behind by a daemon that didn't stop cleanly
+    if socket.exists() {
+        fs::remove_file(&socket)?;
+    }
+    let listener = bind(&socket)?;
+    let cli = TheWayCLI::from_iter(&["the-way", "daemon"]);
+    let mut the_way = Some(TheWay::open(cli, languages.clone(), config)?);
+    println!("Listening on {}", socket.display());
+
+    // A bad connection is reported and skipped, fds sent with anything but `Run` are closed when dropped
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Couldn't accept connection: {}", e);
+                continue;
+            }
+        };
+        let (data, fds) = match receive_with_fds(&stream) {
+            Ok(received) => received,
+            Err(e) => {
+                eprintln!("Couldn't read request: {}", e);
+                continue;
+            }
+        };
+        let request = receive::<Request>(&mut BufReader::new(io::Cursor::new(data).chain(&stream)));
+        let request = match request {
+            Ok(Some(request)) => request,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!("Bad request: {}", e);
+                continue;
+            }
+        };
+        match request {
+            Request::Run { args, cwd, env } => {
+                if the_way.is_none() {
+                    the_way = reopen(&languages);
+                }
+                let code =
+                    match &mut the_way {
+                        Some(the_way) => the_way
+                            .run_for_client(args, &cwd, env, fds)
+                            .unwrap_or_else(|e| {
+                                eprintln!("Couldn't run command: {}", e);
+                                1
+                            }),
+                        None => 1,
+                    };
+                // The client may have gone already
+                let _ = send(&mut stream, &Response { code });
+            }
+            Request::Lend => {
+                // Dropping every handle releases the database lock
+                drop(the_way.take());
+                if send(&mut stream, &Response { code: 0 }).is_ok() {
+                    // Returns once the client closes the connection (exits)
+                    let _ = io::copy(&mut stream, &mut io::sink());
+                }
+                the_way = reopen(&languages);
+            }
+            Request::Stop => {
+                fs::remove_file(&socket)?;
+                let _ = send(&mut stream, &Response { code: 0 });
+                break;
+            }
+        }
+    }
+    Ok(())
+}
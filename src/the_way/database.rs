@@ -1,13 +1,19 @@
 //! Sled database related code
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use color_eyre::Help;
 
 use crate::errors::LostTheWay;
-use crate::the_way::{snippet::Snippet, TheWay};
+use crate::the_way::{ids, snippet::Snippet, sync_log::SyncLogEntry, undo::UndoEntry, TheWay};
 use crate::utils;
 
+/// Number of search queries kept for recalling in search windows
+const SEARCH_HISTORY_LENGTH: usize = 100;
+
+/// Number of changes kept for `undo` to go back through
+const UNDO_LENGTH: usize = 100;
+
 /// If key exists, add value to existing values - join with a semicolon
 fn merge_index(_key: &[u8], old_indices: Option<&[u8]>, new_index: &[u8]) -> Option<Vec<u8>> {
     let mut ret = old_indices.map_or_else(Vec::new, |old| old.to_vec());
@@ -22,13 +28,26 @@ impl TheWay {
     /// Gets the `sled` database with all the-way info.
     /// Makes a new one the first time round
     pub(crate) fn get_db(db_dir: &Path) -> color_eyre::Result<sled::Db> {
-        Ok(sled::open(db_dir)?)
+        match sled::open(db_dir) {
+            Err(sled::Error::Io(e)) if e.to_string().starts_with("could not acquire lock") => {
+                let error: color_eyre::Result<sled::Db> = Err(LostTheWay::DatabaseLocked {
+                    path: db_dir.display().to_string(),
+                }
+                .into());
+                error.suggestion(
+                    "Another the-way command is using it. Wait for it to finish, \
+                    or run `the-way daemon` so commands share the database",
+                )
+            }
+            db => Ok(db?),
+        }
     }
 
     /// Merge function for appending items to an existing key, uses semicolons
     pub(crate) fn set_merge(&self) -> color_eyre::Result<()> {
         self.language_tree()?.set_merge_operator(merge_index);
         self.tag_tree()?.set_merge_operator(merge_index);
+        self.word_tree()?.set_merge_operator(merge_index);
         Ok(())
     }
 
@@ -51,6 +70,339 @@ impl TheWay {
         Ok(())
     }
 
+    /// Get the snippet index: tracked file path tree
+    fn tracked_tree(&self) -> color_eyre::Result<sled::Tree> {
+        Ok(self.db.open_tree("snippet_to_file")?)
+    }
+
+    /// Link a snippet index to a file path
+    pub(crate) fn add_tracked_file(&self, index: usize, path: &Path) -> color_eyre::Result<()> {
+        self.tracked_tree()?.insert(
+            index.to_string().as_bytes(),
+            path.to_string_lossy().as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// Unlink a snippet from its file, returns the file path if there was one
+    pub(crate) fn delete_tracked_file(&self, index: usize) -> color_eyre::Result<Option<PathBuf>> {
+        self.tracked_tree()?
+            .remove(index.to_string().as_bytes())?
+            .map(|path| Ok(PathBuf::from(utils::u8_to_str(&path)?)))
+            .transpose()
+    }
+
+    /// List all (snippet index, file path) links
+    pub(crate) fn get_tracked_files(&self) -> color_eyre::Result<Vec<(usize, PathBuf)>> {
+        self.tracked_tree()?
+            .iter()
+            .map(|item| {
+                let (index, path) = item?;
+                Ok((
+                    utils::u8_to_str(&index)?.parse::<usize>()?,
+                    PathBuf::from(utils::u8_to_str(&path)?),
+                ))
+            })
+            .collect()
+    }
+
+    /// Get the snippet index: requirements tree
+    fn requirements_tree(&self) -> color_eyre::Result<sled::Tree> {
+        Ok(self.db.open_tree("snippet_to_requirements")?)
+    }
+
+    /// Set what a snippet needs to run (e.g. "jq >= 1.6"), removes them if `requirements` is empty
+    pub(crate) fn set_requirements(
+        &self,
+        index: usize,
+        requirements: &[String],
+    ) -> color_eyre::Result<()> {
+        let index_key = index.to_string();
+        if requirements.is_empty() {
+            self.requirements_tree()?.remove(index_key.as_bytes())?;
+        } else {
+            self.requirements_tree()?
+                .insert(index_key.as_bytes(), requirements.join("\n").as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Get what a snippet needs to run
+    pub(crate) fn get_requirements(&self, index: usize) -> color_eyre::Result<Vec<String>> {
+        match self
+            .requirements_tree()?
+            .get(index.to_string().as_bytes())?
+        {
+            Some(requirements) => Ok(utils::u8_to_str(&requirements)?
+                .lines()
+                .map(String::from)
+                .collect()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Get the snippet index: code hash at the last sync tree
+    fn synced_tree(&self) -> color_eyre::Result<sled::Tree> {
+        Ok(self.db.open_tree("snippet_to_synced_hash")?)
+    }
+
+    /// Remember a snippet's code as synced, so the next sync can tell which side changed it
+    pub(crate) fn set_synced_code(&self, index: usize, code: &str) -> color_eyre::Result<()> {
+        self.synced_tree()?.insert(
+            index.to_string().as_bytes(),
+            utils::content_hash(code).as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// Hash of a snippet's code when it was last synced
+    pub(crate) fn get_synced_hash(&self, index: usize) -> color_eyre::Result<Option<String>> {
+        self.synced_tree()?
+            .get(index.to_string().as_bytes())?
+            .map(|hash| utils::u8_to_str(&hash))
+            .transpose()
+    }
+
+    /// Forget that a snippet was synced
+    pub(crate) fn delete_synced_hash(&self, index: usize) -> color_eyre::Result<()> {
+        self.synced_tree()?.remove(index.to_string().as_bytes())?;
+        Ok(())
+    }
+
+    /// Get the snippet index: deletion time tree
+    fn tombstone_tree(&self) -> color_eyre::Result<sled::Tree> {
+        Ok(self.db.open_tree("deleted_snippets")?)
+    }
+
+    /// Remember that a snippet was deleted, so the next sync deletes it remotely too
+    pub(crate) fn add_tombstone(&self, index: usize) -> color_eyre::Result<()> {
+        self.tombstone_tree()?.insert(
+            index.to_string().as_bytes(),
+            Utc::now().to_rfc3339().as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// Whether a snippet was deleted here since the last sync
+    pub(crate) fn is_tombstoned(&self, index: usize) -> color_eyre::Result<bool> {
+        Ok(self
+            .tombstone_tree()?
+            .contains_key(index.to_string().as_bytes())?)
+    }
+
+    /// Forget that a snippet was deleted
+    pub(crate) fn delete_tombstone(&self, index: usize) -> color_eyre::Result<()> {
+        self.tombstone_tree()?
+            .remove(index.to_string().as_bytes())?;
+        Ok(())
+    }
+
+    /// Forget every deletion, once they've been synced
+    pub(crate) fn clear_tombstones(&self) -> color_eyre::Result<()> {
+        self.tombstone_tree()?.clear()?;
+        Ok(())
+    }
+
+    /// Get the file name: (content, last modified) tree of files synced to `serve --sync`
+    fn sync_files_tree(&self) -> color_eyre::Result<sled::Tree> {
+        Ok(self.db.open_tree("sync_files")?)
+    }
+
+    /// Names and last modified times of the files synced to this server
+    pub(crate) fn list_sync_files(&self) -> color_eyre::Result<Vec<(String, DateTime<Utc>)>> {
+        self.sync_files_tree()?
+            .iter()
+            .map(|item| {
+                let (name, file) = item?;
+                let (_, modified): (String, DateTime<Utc>) = bincode::deserialize(&file)?;
+                Ok((utils::u8_to_str(&name)?, modified))
+            })
+            .collect()
+    }
+
+    /// Content and last modified time of a file synced to this server
+    pub(crate) fn get_sync_file(
+        &self,
+        name: &str,
+    ) -> color_eyre::Result<Option<(String, DateTime<Utc>)>> {
+        self.sync_files_tree()?
+            .get(name.as_bytes())?
+            .map(|file| Ok(bincode::deserialize(&file)?))
+            .transpose()
+    }
+
+    /// Add or replace a file synced to this server
+    pub(crate) fn put_sync_file(&self, name: &str, content: &str) -> color_eyre::Result<()> {
+        self.sync_files_tree()?
+            .insert(name.as_bytes(), bincode::serialize(&(content, Utc::now()))?)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Delete a file synced to this server, returns whether it was there
+    pub(crate) fn delete_sync_file(&self, name: &str) -> color_eyre::Result<bool> {
+        let removed = self.sync_files_tree()?.remove(name.as_bytes())?.is_some();
+        self.db.flush()?;
+        Ok(removed)
+    }
+
+    /// Get the snippet index: UUID tree
+    fn uuid_tree(&self) -> color_eyre::Result<sled::Tree> {
+        Ok(self.db.open_tree("snippet_to_uuid")?)
+    }
+
+    /// A snippet's UUID, made the first time it's asked for
+    pub(crate) fn get_uuid(&self, index: usize) -> color_eyre::Result<String> {
+        let index_key = index.to_string();
+        if let Some(uuid) = self.uuid_tree()?.get(index_key.as_bytes())? {
+            return utils::u8_to_str(&uuid);
+        }
+        let uuid = ids::new_uuid()?;
+        self.uuid_tree()?
+            .insert(index_key.as_bytes(), uuid.as_bytes())?;
+        Ok(uuid)
+    }
+
+    /// Gives the snippet at `index` the UUID it had somewhere else (e.g. in an export)
+    pub(crate) fn set_uuid(&self, index: usize, uuid: &str) -> color_eyre::Result<()> {
+        self.uuid_tree()?
+            .insert(index.to_string().as_bytes(), uuid.as_bytes())?;
+        Ok(())
+    }
+
+    /// (index, UUID) of every snippet
+    pub(crate) fn get_uuids(&self) -> color_eyre::Result<Vec<(usize, String)>> {
+        self.snippets_tree()?
+            .iter()
+            .keys()
+            .map(|index| {
+                let index = utils::u8_to_str(&index?)?.parse::<usize>()?;
+                Ok((index, self.get_uuid(index)?))
+            })
+            .collect()
+    }
+
+    /// Get the copy time: snippet index tree
+    fn copy_history_tree(&self) -> color_eyre::Result<sled::Tree> {
+        Ok(self.db.open_tree("copy_history")?)
+    }
+
+    /// Record that a snippet was copied just now
+    pub(crate) fn add_to_copy_history(&self, index: usize) -> color_eyre::Result<()> {
+        // Big-endian so keys sort by time
+        let key = Utc::now().timestamp_nanos().to_be_bytes();
+        self.copy_history_tree()?
+            .insert(key, index.to_string().as_bytes())?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// List (copy time, snippet index) pairs, most recent first
+    pub(crate) fn get_copy_history(&self) -> color_eyre::Result<Vec<(DateTime<Utc>, usize)>> {
+        self.copy_history_tree()?
+            .iter()
+            .rev()
+            .map(|item| {
+                let (time, index) = item?;
+                let mut nanos = [0; 8];
+                nanos.copy_from_slice(&time);
+                let nanos = i64::from_be_bytes(nanos);
+                Ok((
+                    Utc.timestamp(
+                        nanos.div_euclid(1_000_000_000),
+                        nanos.rem_euclid(1_000_000_000) as u32,
+                    ),
+                    utils::u8_to_str(&index)?.parse::<usize>()?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Get the time: search query tree
+    fn search_history_tree(&self) -> color_eyre::Result<sled::Tree> {
+        Ok(self.db.open_tree("search_history")?)
+    }
+
+    /// Record a query picked from in a search window, keeping the last `SEARCH_HISTORY_LENGTH`
+    pub(crate) fn add_to_search_history(&self, query: &str) -> color_eyre::Result<()> {
+        let tree = self.search_history_tree()?;
+        if query.is_empty()
+            || tree
+                .iter()
+                .values()
+                .next_back()
+                .transpose()?
+                .is_some_and(|last| last.as_ref() == query.as_bytes())
+        {
+            return Ok(());
+        }
+        // Big-endian so keys sort by time
+        let key = Utc::now().timestamp_nanos().to_be_bytes();
+        tree.insert(key, query.as_bytes())?;
+        while tree.len() > SEARCH_HISTORY_LENGTH {
+            tree.pop_min()?;
+        }
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Past search queries, oldest first
+    pub(crate) fn get_search_history(&self) -> color_eyre::Result<Vec<String>> {
+        self.search_history_tree()?
+            .iter()
+            .values()
+            .map(|query| utils::u8_to_str(&query?))
+            .collect()
+    }
+
+    /// Get the time: undo entry tree
+    fn undo_tree(&self) -> color_eyre::Result<sled::Tree> {
+        Ok(self.db.open_tree("undo")?)
+    }
+
+    /// Record a change so `the-way undo` can reverse it
+    pub(crate) fn add_undo_entry(&self, entry: &UndoEntry) -> color_eyre::Result<()> {
+        // Big-endian so keys sort by time
+        let key = Utc::now().timestamp_nanos().to_be_bytes();
+        let tree = self.undo_tree()?;
+        tree.insert(key, entry.to_bytes()?)?;
+        while tree.len() > UNDO_LENGTH {
+            tree.pop_min()?;
+        }
+        Ok(())
+    }
+
+    /// Remove and return the most recent change
+    pub(crate) fn pop_undo_entry(&self) -> color_eyre::Result<Option<UndoEntry>> {
+        self.undo_tree()?
+            .pop_max()?
+            .map(|(_, entry)| UndoEntry::from_bytes(&entry))
+            .transpose()
+    }
+
+    /// Get the time: sync log entry tree
+    fn sync_log_tree(&self) -> color_eyre::Result<sled::Tree> {
+        Ok(self.db.open_tree("sync_log")?)
+    }
+
+    /// Record what a sync did
+    pub(crate) fn add_sync_log_entry(&self, entry: &SyncLogEntry) -> color_eyre::Result<()> {
+        // Big-endian so keys sort by time
+        let key = entry.time.timestamp_nanos().to_be_bytes();
+        self.sync_log_tree()?.insert(key, entry.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// List the last `number` syncs, most recent first
+    pub(crate) fn get_sync_log(&self, number: usize) -> color_eyre::Result<Vec<SyncLogEntry>> {
+        self.sync_log_tree()?
+            .iter()
+            .rev()
+            .take(number)
+            .map(|item| SyncLogEntry::from_bytes(&item?.1))
+            .collect()
+    }
+
     /// Get the language: snippet_indices tree
     fn language_tree(&self) -> color_eyre::Result<sled::Tree> {
         Ok(self.db.open_tree("language_to_snippet")?)
@@ -67,8 +419,7 @@ impl TheWay {
         language_key: &[u8],
         index_key: &[u8],
     ) -> color_eyre::Result<()> {
-        self.language_tree()?
-            .merge(language_key.to_vec(), index_key.to_vec())?;
+        self.language_tree()?.merge(language_key, index_key)?;
         Ok(())
     }
 
@@ -76,13 +427,15 @@ impl TheWay {
     pub(crate) fn get_snippet(&self, index: usize) -> color_eyre::Result<Snippet> {
         let index_key = index.to_string();
         let index_key = index_key.as_bytes();
-        Ok(Snippet::from_bytes(
+        let mut snippet = Snippet::from_bytes(
             &self
                 .snippets_tree()?
                 .get(index_key)?
                 .ok_or(LostTheWay::SnippetNotFound { index })
                 .suggestion("The index of a snippet is in its title after a #")?,
-        )?)
+        )?;
+        snippet.label = Some(self.index_label(index)?);
+        Ok(snippet)
     }
 
     /// Retrieve snippets at indices
@@ -105,8 +458,7 @@ impl TheWay {
 
     /// List all snippets
     pub(crate) fn list_snippets(&self) -> color_eyre::Result<Vec<Snippet>> {
-        Ok(self
-            .snippets_tree()?
+        self.snippets_tree()?
             .iter()
             .map(|item| {
                 item.map_err(|_| {
@@ -115,9 +467,13 @@ impl TheWay {
                     }
                     .into()
                 })
-                .and_then(|(_, snippet)| Snippet::from_bytes(&snippet))
+                .and_then(|(_, snippet)| {
+                    let mut snippet = Snippet::from_bytes(&snippet)?;
+                    snippet.label = Some(self.index_label(snippet.index)?);
+                    Ok(snippet)
+                })
             })
-            .collect::<color_eyre::Result<Vec<_>>>()?)
+            .collect::<color_eyre::Result<Vec<_>>>()
     }
 
     // TODO: think about how deletions should affect snippet indices
@@ -131,6 +487,15 @@ impl TheWay {
         Ok(())
     }
 
+    /// Makes sure new snippets get indices after `index` (e.g. one synced from elsewhere)
+    pub(crate) fn raise_snippet_index(&mut self, index: usize) -> color_eyre::Result<()> {
+        if index > self.get_current_snippet_index()? {
+            self.db
+                .insert("snippet_index", index.to_string().as_bytes())?;
+        }
+        Ok(())
+    }
+
     /// Add a snippet index to each of the tags it's associated with
     pub(crate) fn add_to_tags(
         &mut self,
@@ -139,19 +504,25 @@ impl TheWay {
     ) -> color_eyre::Result<()> {
         for tag in tags {
             let tag_key = tag.as_bytes();
-            self.tag_tree()?
-                .merge(tag_key.to_vec(), index_key.to_vec())?;
+            self.tag_tree()?.merge(tag_key, index_key)?;
         }
         Ok(())
     }
 
-    /// Add a serialized snippet to the snippets tree
+    /// Add a serialized snippet to the snippets tree (and its code to the search index, replacing what was there)
     pub(crate) fn add_to_snippet(
         &self,
         index_key: &[u8],
         snippet_bytes: &[u8],
     ) -> color_eyre::Result<()> {
-        self.snippets_tree()?.insert(index_key, snippet_bytes)?;
+        let old = self.snippets_tree()?.insert(index_key, snippet_bytes)?;
+        if self.search_index_enabled() {
+            if let Some(old) = old {
+                self.unindex_words(&Snippet::from_bytes(&old)?)?;
+            }
+            self.index_words(&Snippet::from_bytes(snippet_bytes)?)?;
+        }
+        self.snippets_changed();
         Ok(())
     }
 
@@ -161,6 +532,8 @@ impl TheWay {
         let index_key = snippet.index.to_string();
         let index_key = index_key.as_bytes();
         self.add_to_snippet(index_key, &snippet.to_bytes()?)?;
+        // e.g. when a deletion is undone
+        self.delete_tombstone(snippet.index)?;
         self.add_to_language(language_key, index_key)?;
         self.add_to_tags(&snippet.tags, index_key)?;
         Ok(snippet.index)
@@ -191,10 +564,8 @@ impl TheWay {
         if new_indices.is_empty() {
             self.delete_language(language_key)?;
         } else {
-            self.language_tree()?.insert(
-                language_key.to_vec(),
-                utils::make_indices_string(&new_indices)?,
-            )?;
+            self.language_tree()?
+                .insert(language_key, utils::make_indices_string(&new_indices)?)?;
         }
         Ok(())
     }
@@ -247,12 +618,17 @@ impl TheWay {
     fn delete_from_snippets_tree(&mut self, index: usize) -> color_eyre::Result<Snippet> {
         let index_key = index.to_string();
         let index_key = index_key.as_bytes();
-        Ok(Snippet::from_bytes(
+        let snippet = Snippet::from_bytes(
             &self
                 .snippets_tree()?
                 .remove(index_key)?
                 .ok_or(LostTheWay::SnippetNotFound { index })?,
-        )?)
+        )?;
+        if self.search_index_enabled() {
+            self.unindex_words(&snippet)?;
+        }
+        self.snippets_changed();
+        Ok(snippet)
     }
 
     /// Retrieve snippets written in a given language
@@ -260,7 +636,7 @@ impl TheWay {
         utils::split_indices_usize(
             &self
                 .language_tree()?
-                .get(&language.to_ascii_lowercase().as_bytes())?
+                .get(language.to_ascii_lowercase().as_bytes())?
                 .ok_or(LostTheWay::LanguageNotFound {
                     language: language.to_owned(),
                 })?,
@@ -0,0 +1,224 @@
+//! Environment diagnostics for `the-way doctor`
+use std::env;
+
+use crate::configuration::{TheWayConfig, DEFAULT_THEME};
+use crate::errors::LostTheWay;
+use crate::language::CodeHighlight;
+use crate::utils;
+
+/// Outcome of a single check
+enum Outcome {
+    /// Passed, with details
+    Pass(String),
+    /// Failed, with what went wrong and how to fix it
+    Fail(String, String),
+    /// Couldn't run because an earlier check failed
+    Skip(String),
+}
+
+/// Checks that the program in the first word of `command_line` exists
+fn check_command(command_line: &str, source: &str, fix: &str) -> Outcome {
+    let program = command_line.split_whitespace().next().unwrap_or_default();
+    match utils::find_command(program) {
+        Some(path) => Outcome::Pass(format!(
+            "{} ({}, from {})",
+            command_line,
+            path.display(),
+            source
+        )),
+        None => Outcome::Fail(
+            format!(
+                "{:?} (from {}) isn't an executable in $PATH",
+                program, source
+            ),
+            fix.to_owned(),
+        ),
+    }
+}
+
+/// First set environment variable out of `variables`, with its name
+fn first_env_var(variables: &[&'static str]) -> Option<(&'static str, String)> {
+    variables.iter().find_map(|variable| {
+        env::var(variable)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .map(|value| (*variable, value))
+    })
+}
+
+fn check_config() -> (Outcome, Option<TheWayConfig>) {
+    let location = match TheWayConfig::get() {
+        Ok(location) => location,
+        Err(e) => return (
+            Outcome::Fail(
+                e.to_string(),
+                String::from(
+                    "Use `the-way config default <file>` to write out the default configuration, \
+                    then `export THE_WAY_CONFIG=<full/path/to/file>`",
+                ),
+            ),
+            None,
+        ),
+    };
+    match TheWayConfig::load() {
+        Ok(config) => (Outcome::Pass(location.display().to_string()), Some(config)),
+        Err(e) => (
+            Outcome::Fail(
+                format!("Couldn't read {}: {}", location.display(), e),
+                String::from("Fix the file, or regenerate it with `the-way config default <file>`"),
+            ),
+            None,
+        ),
+    }
+}
+
+fn check_database(config: &TheWayConfig) -> Outcome {
+    match sled::open(&config.db_dir) {
+        Ok(db) => {
+            let size = db.size_on_disk().unwrap_or_default();
+            Outcome::Pass(format!(
+                "{} ({:.1} KiB)",
+                config.db_dir.display(),
+                size as f64 / 1024.
+            ))
+        }
+        Err(e) => Outcome::Fail(
+            format!("Couldn't open {}: {}", config.db_dir.display(), e),
+            format!(
+                "Make sure no other the-way process is running and that {} is writable",
+                config.db_dir.display()
+            ),
+        ),
+    }
+}
+
+fn check_theme(config: &TheWayConfig) -> Outcome {
+    let fix = format!(
+        "Use `the-way themes list` to see available themes and `the-way themes set <theme>` to pick one, \
+        or make sure {} only has valid .tmTheme and .sublime-syntax files",
+        config.themes_dir.display()
+    );
+    match CodeHighlight::new(DEFAULT_THEME, config.themes_dir.clone(), false) {
+        Ok(mut highlighter) => match highlighter.set_theme(config.theme.to_owned()) {
+            Ok(()) => Outcome::Pass(format!(
+                "{} ({} themes available)",
+                config.theme,
+                highlighter.get_themes().len()
+            )),
+            Err(_) => Outcome::Fail(format!("Theme {:?} doesn't exist", config.theme), fix),
+        },
+        Err(e) => Outcome::Fail(e.to_string(), fix),
+    }
+}
+
+fn check_clipboard() -> Outcome {
+    let (command, fix) = if cfg!(target_os = "macos") {
+        ("pbcopy", "pbcopy should come with macOS, check your $PATH")
+    } else {
+        ("xclip", "Install xclip with your package manager")
+    };
+    check_command(command, "clipboard backend", fix)
+}
+
+fn check_editor() -> Outcome {
+    let fix = "Set $VISUAL or $EDITOR to an installed editor, e.g. `export EDITOR=nano`";
+    match first_env_var(&["VISUAL", "EDITOR"]) {
+        Some((variable, editor)) => check_command(&editor, &format!("${}", variable), fix),
+        None => check_command("vi", "default", fix),
+    }
+}
+
+fn check_pager() -> Outcome {
+    let fix = "Set $PAGER to an installed pager, e.g. `export PAGER=less`";
+    match first_env_var(&["PAGER"]) {
+        Some((variable, pager)) => check_command(&pager, &format!("${}", variable), fix),
+        None => check_command("less", "default", fix),
+    }
+}
+
+fn check_locale() -> Outcome {
+    match first_env_var(&["LC_ALL", "LC_CTYPE", "LANG"]) {
+        Some((variable, locale)) => {
+            let lowercase = locale.to_ascii_lowercase();
+            if lowercase.contains("utf-8") || lowercase.contains("utf8") {
+                Outcome::Pass(format!("{} (from ${})", locale, variable))
+            } else {
+                Outcome::Fail(
+                    format!("{} (from ${}) isn't a UTF-8 locale", locale, variable),
+                    String::from("Use a UTF-8 locale, e.g. `export LANG=en_US.UTF-8`"),
+                )
+            }
+        }
+        None => Outcome::Fail(
+            String::from("No locale set ($LC_ALL, $LC_CTYPE, and $LANG are empty)"),
+            String::from("Use a UTF-8 locale, e.g. `export LANG=en_US.UTF-8`"),
+        ),
+    }
+}
+
+/// Checks the environment the-way runs in and prints what passed and failed, with fixes.
+/// Runs before the config and database are loaded, so it works even when those are broken.
+pub(crate) fn doctor() -> color_eyre::Result<()> {
+    let (config_outcome, config) = check_config();
+    let mut checks = vec![("config", config_outcome)];
+    match &config {
+        Some(config) => {
+            checks.push(("database", check_database(config)));
+            checks.push(("theme", check_theme(config)));
+        }
+        None => {
+            for name in &["database", "theme"] {
+                checks.push((
+                    name,
+                    Outcome::Skip(String::from("the config couldn't be loaded")),
+                ));
+            }
+        }
+    }
+    checks.push(("clipboard", check_clipboard()));
+    checks.push(("editor", check_editor()));
+    checks.push(("pager", check_pager()));
+    checks.push(("locale", check_locale()));
+
+    let mut failed = 0;
+    for (name, outcome) in &checks {
+        match outcome {
+            Outcome::Pass(details) => {
+                println!(
+                    "{} {:<10} {}",
+                    console::style("[ok]  ").green(),
+                    name,
+                    details
+                )
+            }
+            Outcome::Skip(reason) => {
+                println!(
+                    "{} {:<10} {}",
+                    console::style("[skip]").yellow(),
+                    name,
+                    reason
+                )
+            }
+            Outcome::Fail(problem, fix) => {
+                failed += 1;
+                println!(
+                    "{} {:<10} {}",
+                    console::style("[FAIL]").red(),
+                    name,
+                    problem
+                );
+                println!("{:17} fix: {}", "", fix);
+            }
+        }
+    }
+    println!(
+        "{} version {}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    );
+    if failed > 0 {
+        Err(LostTheWay::DoctorError { failed }.into())
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+//! `the-way export --format alfred`: an `.alfredsnippets` collection for Alfred's text expansion
+//! (what `import --from alfred` reads), with each snippet's description as its name and keyword
+use std::io;
+
+use crate::the_way::export::ultisnips::trigger;
+use crate::the_way::snippet::Snippet;
+use crate::zip;
+
+/// The collection's settings: no prefix or suffix around keywords
+const INFO: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>snippetkeywordprefix</key>
+	<string></string>
+	<key>snippetkeywordsuffix</key>
+	<string></string>
+</dict>
+</plist>
+"#;
+
+#[derive(Serialize, Debug)]
+struct AlfredFile<'a> {
+    alfredsnippet: AlfredSnippet<'a>,
+}
+
+#[derive(Serialize, Debug)]
+struct AlfredSnippet<'a> {
+    snippet: &'a str,
+    uid: String,
+    name: &'a str,
+    keyword: String,
+}
+
+/// Writes `snippets` as an Alfred collection, `uuids` being their UUIDs in the same order
+/// (so exporting again updates the same Alfred snippets)
+pub(super) fn write(
+    snippets: &[Snippet],
+    uuids: &[String],
+    writer: &mut dyn io::Write,
+) -> color_eyre::Result<()> {
+    let mut files = vec![(String::from("info.plist"), INFO.as_bytes().to_vec())];
+    for (snippet, uuid) in snippets.iter().zip(uuids) {
+        let name = snippet.description.lines().next().unwrap_or_default();
+        let uid = uuid.to_ascii_uppercase();
+        let json = serde_json::to_vec_pretty(&AlfredFile {
+            alfredsnippet: AlfredSnippet {
+                // Expanding a snippet shouldn't add a line break after it
+                snippet: snippet.code.trim_end_matches(['\n', '\r']),
+                uid: uid.clone(),
+                name,
+                keyword: trigger(snippet),
+            },
+        })?;
+        // Alfred names the files "<name> [<uid>].json"
+        let file_name: String = name
+            .chars()
+            .map(|c| {
+                if matches!(c, '/' | ':' | '\\') {
+                    '-'
+                } else {
+                    c
+                }
+            })
+            .collect();
+        files.push((format!("{} [{}].json", file_name, uid), json));
+    }
+    writer.write_all(&zip::write(&files)?)?;
+    Ok(())
+}
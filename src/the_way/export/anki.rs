@@ -0,0 +1,55 @@
+//! `the-way export --format anki`: a text file of notes for Anki's File > Import (a tab-separated line for
+//! each snippet, as HTML), the description on the front, the highlighted code on the back, and the snippet's tags
+use std::io;
+
+use crate::language::CodeHighlight;
+use crate::the_way::snippet::Snippet;
+use crate::utils::escape_html;
+
+/// Tells Anki how to read the file, so it doesn't have to be set when importing
+const HEADER: &str = "#separator:tab\n#html:true\n#notetype:Basic\n#tags column:3\n";
+
+/// `html` as one field of a line: tabs and line breaks would end the field or the note, and a field starting
+/// with `#` would be read as a comment
+fn field(html: &str) -> String {
+    let field = html
+        .replace('\t', "&#9;")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>");
+    match field.strip_prefix('#') {
+        Some(rest) => format!("&#35;{}", rest),
+        None => field,
+    }
+}
+
+/// `tag` as an Anki tag, which can't have spaces
+fn anki_tag(tag: &str) -> String {
+    tag.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+/// Writes `snippets` as Anki notes, highlighting code with `highlighter`
+pub(super) fn write(
+    snippets: &[Snippet],
+    writer: &mut dyn io::Write,
+    highlighter: &CodeHighlight,
+) -> color_eyre::Result<()> {
+    writer.write_all(HEADER.as_bytes())?;
+    for snippet in snippets {
+        let front = escape_html(snippet.description.trim());
+        // A line break right after `<pre>` is left out in HTML, but not once it's a `<br>`
+        let back = highlighter
+            .highlight_html(&snippet.code, &snippet.extension)
+            .replacen(">\n", ">", 1);
+        let tags: Vec<_> = snippet.tags.iter().map(|tag| anki_tag(tag)).collect();
+        writer.write_all(
+            format!(
+                "{}\t{}\t{}\n",
+                field(&front),
+                field(back.trim_end()),
+                field(&tags.join(" "))
+            )
+            .as_bytes(),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,53 @@
+//! `the-way export --format csv`: a header row, then a row per snippet (RFC 4180 quoting,
+//! so multi-line code stays in one cell)
+use std::io;
+
+use chrono::SecondsFormat;
+
+use crate::the_way::snippet::Snippet;
+
+/// Columns, in order (`import --format csv` reads them back by name)
+const COLUMNS: [&str; 6] = ["index", "description", "language", "tags", "code", "date"];
+
+/// `value` as a CSV cell, quoted if it has to be
+fn cell(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) || value.trim() != value {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+fn row(cells: &[String]) -> String {
+    let cells: Vec<_> = cells.iter().map(|value| cell(value)).collect();
+    format!("{}\r\n", cells.join(","))
+}
+
+/// Writes `snippets` as CSV with the columns that are in `fields`
+pub(super) fn write(
+    snippets: &[Snippet],
+    writer: &mut dyn io::Write,
+    fields: &[&str],
+) -> color_eyre::Result<()> {
+    let columns: Vec<_> = COLUMNS
+        .iter()
+        .filter(|column| fields.contains(column))
+        .collect();
+    let header: Vec<_> = columns.iter().map(|column| column.to_string()).collect();
+    writer.write_all(row(&header).as_bytes())?;
+    for snippet in snippets {
+        let cells: Vec<_> = columns
+            .iter()
+            .map(|column| match **column {
+                "index" => snippet.index.to_string(),
+                "description" => snippet.description.to_owned(),
+                "language" => snippet.language.to_owned(),
+                "tags" => snippet.tags.join(" "),
+                "code" => snippet.code.to_owned(),
+                _ => snippet.date.to_rfc3339_opts(SecondsFormat::Secs, true),
+            })
+            .collect();
+        writer.write_all(row(&cells).as_bytes())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! `the-way export --format files --out snippets.tar.gz`: each snippet's code as a file of its own,
+//! `<index>-<description>.<extension>`, in a tar.gz, tar, or zip archive (going by the name it's written to),
+//! with the rest of each snippet in `metadata.json`
+use std::io::{self, Write};
+use std::path::Path;
+
+use flate2::write::GzEncoder;
+use serde_json::Value;
+
+use crate::the_way::export::slug;
+use crate::the_way::snippet::Snippet;
+use crate::zip;
+
+/// Kinds of archive the files can be put in
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Archive {
+    TarGz,
+    Tar,
+    Zip,
+}
+
+impl Archive {
+    /// The kind of archive the file called `path` is, a tar.gz if it's neither .tar nor .zip (or on stdout)
+    fn of_path(path: Option<&Path>) -> Self {
+        let name = path
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        if name.ends_with(".zip") {
+            Self::Zip
+        } else if name.ends_with(".tar") {
+            Self::Tar
+        } else {
+            Self::TarGz
+        }
+    }
+}
+
+/// `<index>-<description>.<extension>`, with the description's words in lowercase joined by `-`
+fn file_name(snippet: &Snippet) -> String {
+    let slug = slug(snippet);
+    let extension = if snippet.extension.is_empty() {
+        ".txt"
+    } else {
+        &snippet.extension
+    };
+    if slug.is_empty() {
+        format!("{}{}", snippet.index, extension)
+    } else {
+        format!("{}-{}{}", snippet.index, slug, extension)
+    }
+}
+
+/// The `fields` of `snippet` besides its code, and which file the code is in
+fn metadata(snippet: &Snippet, file: &str, fields: &[&str]) -> color_eyre::Result<Value> {
+    let mut metadata = serde_json::Map::new();
+    metadata.insert(String::from("file"), Value::from(file));
+    if let Value::Object(object) = serde_json::to_value(snippet)? {
+        metadata.extend(
+            object
+                .into_iter()
+                .filter(|(key, _)| key != "code" && fields.contains(&key.as_str())),
+        );
+    }
+    Ok(Value::Object(metadata))
+}
+
+fn tar(files: &[(String, Vec<u8>)], writer: impl Write) -> io::Result<()> {
+    let mut builder = tar::Builder::new(writer);
+    let now = chrono::Utc::now().timestamp().max(0) as u64;
+    for (name, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(now);
+        builder.append_data(&mut header, name, contents.as_slice())?;
+    }
+    builder.into_inner()?.flush()
+}
+
+/// Writes an archive of `snippets`, the kind that goes with `path`, with the `fields` asked for
+pub(super) fn write(
+    snippets: &[Snippet],
+    writer: &mut dyn io::Write,
+    path: Option<&Path>,
+    fields: &[&str],
+) -> color_eyre::Result<()> {
+    let mut files = Vec::with_capacity(snippets.len() + 1);
+    let mut metadatas = Vec::with_capacity(snippets.len());
+    for snippet in snippets {
+        let name = file_name(snippet);
+        metadatas.push(metadata(snippet, &name, fields)?);
+        files.push((name, snippet.code.as_bytes().to_vec()));
+    }
+    files.push((
+        String::from("metadata.json"),
+        serde_json::to_vec_pretty(&metadatas)?,
+    ));
+    match Archive::of_path(path) {
+        Archive::TarGz => {
+            let mut encoder = GzEncoder::new(writer, flate2::Compression::default());
+            tar(&files, &mut encoder)?;
+            encoder.finish()?;
+        }
+        Archive::Tar => tar(&files, writer)?,
+        Archive::Zip => writer.write_all(&zip::write(&files)?)?,
+    }
+    Ok(())
+}
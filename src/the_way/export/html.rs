@@ -0,0 +1,216 @@
+//! `the-way export --format html --out <folder>`: a static site to browse snippets in, with a page for each
+//! snippet (code highlighted in the current theme), pages listing the snippets with each tag and in each
+//! language, and an index page that searches as you type
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::language::CodeHighlight;
+use crate::the_way::snippet::Snippet;
+use crate::utils::escape_html;
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 60em; margin: 2em auto; padding: 0 1em; }
+a { color: #0366d6; text-decoration: none; }
+a:hover { text-decoration: underline; }
+nav { margin-bottom: 1em; }
+nav a { margin-right: 1em; }
+ul.snippets { list-style: none; padding: 0; }
+ul.snippets li { margin: 0.4em 0; }
+.language { color: #6a737d; margin-left: 0.5em; }
+.tag { background: #f1f8ff; border-radius: 3px; font-size: 0.85em; margin-left: 0.4em; padding: 0.1em 0.4em; }
+#search { font-size: 1em; margin-bottom: 1em; padding: 0.4em; width: 100%; }
+pre { border-radius: 4px; overflow-x: auto; padding: 1em; }
+";
+
+/// Shows only the snippets with every word typed in the search box
+/// (in their description, language, tags, or code)
+const SEARCH: &str = r#"<script>
+document.getElementById("search").addEventListener("input", function () {
+  var words = this.value.toLowerCase().split(/\s+/).filter(Boolean);
+  document.querySelectorAll("ul.snippets li").forEach(function (item) {
+    var text = item.getAttribute("data-search");
+    item.hidden = !words.every(function (word) { return text.indexOf(word) !== -1; });
+  });
+});
+</script>
+"#;
+
+/// A file name for the page about `name` (a tag or language), with anything but letters, digits, `-`, and `_`
+/// written as its code point so different names can't end up on the same page
+fn page_name(name: &str) -> String {
+    let mut page = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+            page.push(c);
+        } else {
+            page.push_str(&format!("~{:x}", c as u32));
+        }
+    }
+    format!("{}.html", page)
+}
+
+/// A whole page, with links back to the index pages (`root` is the way back to the site's folder)
+fn page(title: &str, root: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+        <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+        <title>{title}</title>\n<link rel=\"stylesheet\" href=\"{root}style.css\">\n</head>\n<body>\n\
+        <nav><a href=\"{root}index.html\">Snippets</a><a href=\"{root}tags.html\">Tags</a>\
+        <a href=\"{root}languages.html\">Languages</a></nav>\n{body}</body>\n</html>\n",
+        title = escape_html(title),
+        root = root,
+        body = body
+    )
+}
+
+/// A snippet's title, language, and tags, linked to their pages
+fn snippet_item(snippet: &Snippet, root: &str, searchable: bool) -> String {
+    let search = if searchable {
+        let text = format!(
+            "{} {} {} {}",
+            snippet.description,
+            snippet.language,
+            snippet.tags.join(" "),
+            snippet.code
+        );
+        format!(" data-search=\"{}\"", escape_html(&text.to_lowercase()))
+    } else {
+        String::new()
+    };
+    let tags: String = snippet
+        .tags
+        .iter()
+        .map(|tag| {
+            format!(
+                "<a class=\"tag\" href=\"{}tags/{}\">{}</a>",
+                root,
+                page_name(tag),
+                escape_html(tag)
+            )
+        })
+        .collect();
+    format!(
+        "<li{}><a href=\"{}snippets/{}.html\">#{} {}</a>\
+        <a class=\"language\" href=\"{}languages/{}\">{}</a>{}</li>\n",
+        search,
+        root,
+        snippet.index,
+        snippet.index,
+        escape_html(snippet.description.lines().next().unwrap_or_default()),
+        root,
+        page_name(&snippet.language),
+        escape_html(&snippet.language),
+        tags
+    )
+}
+
+/// A list of `snippets` for a page one folder down
+fn snippet_list(snippets: &[&Snippet]) -> String {
+    let items: String = snippets
+        .iter()
+        .map(|snippet| snippet_item(snippet, "../", false))
+        .collect();
+    format!("<ul class=\"snippets\">\n{}</ul>\n", items)
+}
+
+/// A page listing `groups` (tags or languages, with their pages in `folder`) with how many snippets each has
+fn group_index(title: &str, folder: &str, groups: &BTreeMap<String, Vec<&Snippet>>) -> String {
+    let items: String = groups
+        .iter()
+        .map(|(name, snippets)| {
+            format!(
+                "<li><a href=\"{}/{}\">{}</a> ({})</li>\n",
+                folder,
+                page_name(name),
+                escape_html(name),
+                snippets.len()
+            )
+        })
+        .collect();
+    page(
+        title,
+        "",
+        &format!("<h1>{}</h1>\n<ul>\n{}</ul>\n", escape_html(title), items),
+    )
+}
+
+/// Writes the site for `snippets` to `dir`, highlighting code with `highlighter`
+pub(super) fn write(
+    snippets: &[Snippet],
+    dir: &Path,
+    highlighter: &CodeHighlight,
+) -> color_eyre::Result<()> {
+    for folder in ["snippets", "tags", "languages"] {
+        fs::create_dir_all(dir.join(folder))?;
+    }
+    fs::write(dir.join("style.css"), STYLE)?;
+
+    let items: String = snippets
+        .iter()
+        .map(|snippet| snippet_item(snippet, "", true))
+        .collect();
+    fs::write(
+        dir.join("index.html"),
+        page(
+            "Snippets",
+            "",
+            &format!(
+                "<h1>Snippets</h1>\n<input id=\"search\" type=\"search\" placeholder=\"Search\" autofocus>\n\
+                <ul class=\"snippets\">\n{}</ul>\n{}",
+                items, SEARCH
+            ),
+        ),
+    )?;
+
+    let mut tags: BTreeMap<String, Vec<&Snippet>> = BTreeMap::new();
+    let mut languages: BTreeMap<String, Vec<&Snippet>> = BTreeMap::new();
+    for snippet in snippets {
+        for tag in &snippet.tags {
+            tags.entry(tag.to_owned()).or_default().push(snippet);
+        }
+        languages
+            .entry(snippet.language.to_owned())
+            .or_default()
+            .push(snippet);
+
+        let mut lines = snippet.description.lines();
+        let title = lines.next().unwrap_or_default();
+        let rest: Vec<_> = lines.map(escape_html).collect();
+        let details = if rest.join("").trim().is_empty() {
+            String::new()
+        } else {
+            format!("<p>{}</p>\n", rest.join("<br>\n"))
+        };
+        let body = format!(
+            "<h1>{}</h1>\n{}<ul class=\"snippets\">\n{}</ul>\n<p>Added {}</p>\n{}\n",
+            escape_html(title),
+            details,
+            snippet_item(snippet, "../", false),
+            snippet.date.format("%Y-%m-%d"),
+            highlighter.highlight_html(&snippet.code, &snippet.extension)
+        );
+        fs::write(
+            dir.join("snippets").join(format!("{}.html", snippet.index)),
+            page(title, "../", &body),
+        )?;
+    }
+
+    for (folder, title, groups) in [
+        ("tags", "Tags", &tags),
+        ("languages", "Languages", &languages),
+    ] {
+        // At the top, so no tag or language page can be written over them
+        fs::write(
+            dir.join(format!("{}.html", folder)),
+            group_index(title, folder, groups),
+        )?;
+        for (name, snippets) in groups {
+            let body = format!("<h1>{}</h1>\n{}", escape_html(name), snippet_list(snippets));
+            fs::write(
+                dir.join(folder).join(page_name(name)),
+                page(name, "../", &body),
+            )?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,102 @@
+//! `the-way export --format hugo --out <folder>` (or `--format jekyll`): a Markdown post for each snippet,
+//! with its title, date, tags, and language as front matter (TOML for Hugo, YAML for Jekyll), the rest of
+//! its description, and its code in a fenced code block
+use std::fs;
+use std::path::Path;
+
+use chrono::SecondsFormat;
+
+use crate::the_way::export::{markdown, slug};
+use crate::the_way::snippet::Snippet;
+
+/// Static site generators the posts are written for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Site {
+    /// TOML front matter between `+++` lines, `<index>-<description>.md` files (for `content/<section>/`)
+    Hugo,
+    /// YAML front matter between `---` lines, `<date>-<index>-<description>.md` files (for `_posts/`)
+    Jekyll,
+}
+
+#[derive(Serialize, Debug)]
+struct FrontMatter<'a> {
+    title: String,
+    /// RFC 3339 to the second, which both read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<&'a str>,
+}
+
+impl Site {
+    /// The post's file name, Jekyll needing the date first
+    fn file_name(self, snippet: &Snippet) -> String {
+        let slug = slug(snippet);
+        let mut name = snippet.index.to_string();
+        if !slug.is_empty() {
+            name = format!("{}-{}", name, slug);
+        }
+        match self {
+            Self::Hugo => format!("{}.md", name),
+            Self::Jekyll => format!("{}-{}.md", snippet.date.format("%Y-%m-%d"), name),
+        }
+    }
+
+    /// `front_matter` between the fences this site reads
+    fn front_matter(self, front_matter: &FrontMatter<'_>) -> color_eyre::Result<String> {
+        Ok(match self {
+            Self::Hugo => format!("+++\n{}+++\n", toml::to_string(front_matter)?),
+            Self::Jekyll => {
+                let yaml = serde_yaml::to_string(front_matter)?;
+                let yaml = yaml.strip_prefix("---\n").unwrap_or(&yaml);
+                format!("---\n{}\n---\n", yaml.trim_end_matches('\n'))
+            }
+        })
+    }
+}
+
+/// `snippet` as a post with the `fields` asked for
+fn post(site: Site, snippet: &Snippet, fields: &[&str]) -> color_eyre::Result<String> {
+    let mut description = snippet.description.lines();
+    let title = if fields.contains(&"description") {
+        description.next().unwrap_or_default().to_owned()
+    } else {
+        format!("Snippet #{}", snippet.index)
+    };
+    let mut post = site.front_matter(&FrontMatter {
+        title,
+        date: Some(snippet.date)
+            .filter(|_| fields.contains(&"date"))
+            .map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        tags: Some(snippet.tags.as_slice()).filter(|_| fields.contains(&"tags")),
+        language: Some(snippet.language.as_str()).filter(|_| fields.contains(&"language")),
+    })?;
+    if fields.contains(&"description") {
+        let rest: Vec<_> = description.collect();
+        if !rest.join("").trim().is_empty() {
+            post.push_str(&format!("\n{}\n", rest.join("\n").trim()));
+        }
+    }
+    if fields.contains(&"code") {
+        post.push_str(&format!("\n{}", markdown::code_block(snippet, fields)));
+    }
+    Ok(post)
+}
+
+/// Writes a post for each of `snippets` to `dir`
+pub(super) fn write(
+    site: Site,
+    snippets: &[Snippet],
+    dir: &Path,
+    fields: &[&str],
+) -> color_eyre::Result<()> {
+    for snippet in snippets {
+        fs::write(
+            dir.join(site.file_name(snippet)),
+            post(site, snippet, fields)?,
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+//! `the-way export --format markdown`: a heading for each snippet (its description), its tags,
+//! and its code in a fenced code block with the language as the info string
+use std::io;
+
+use crate::the_way::snippet::Snippet;
+
+/// A fence longer than any run of backticks in `code`, so the code can't end the block early
+fn fence(code: &str) -> String {
+    let longest = code
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    "`".repeat(longest.max(2) + 1)
+}
+
+/// `snippet` in Markdown with the `fields` asked for, under a heading of `level`
+pub(super) fn snippet(snippet: &Snippet, fields: &[&str], level: usize) -> String {
+    let mut markdown = String::new();
+    let mut description = snippet.description.lines();
+    let title = if fields.contains(&"description") {
+        description.next().unwrap_or_default().to_owned()
+    } else {
+        format!("Snippet #{}", snippet.index)
+    };
+    markdown.push_str(&format!("{} {}\n\n", "#".repeat(level), title));
+    if fields.contains(&"description") {
+        let rest: Vec<_> = description.collect();
+        if !rest.join("").trim().is_empty() {
+            markdown.push_str(&format!("{}\n\n", rest.join("\n").trim()));
+        }
+    }
+    if fields.contains(&"tags") && !snippet.tags.is_empty() {
+        let tags: Vec<_> = snippet
+            .tags
+            .iter()
+            .map(|tag| format!("`{}`", tag))
+            .collect();
+        markdown.push_str(&format!("Tags: {}\n\n", tags.join(" ")));
+    }
+    if fields.contains(&"code") {
+        markdown.push_str(&code_block(snippet, fields));
+    }
+    markdown
+}
+
+/// `snippet`'s code in a fenced code block, labelled with its language if that's in `fields`
+pub(super) fn code_block(snippet: &Snippet, fields: &[&str]) -> String {
+    let fence = fence(&snippet.code);
+    let language = if fields.contains(&"language") {
+        snippet.language.as_str()
+    } else {
+        ""
+    };
+    let mut block = format!("{}{}\n{}", fence, language, snippet.code);
+    if !snippet.code.ends_with('\n') {
+        block.push('\n');
+    }
+    block.push_str(&format!("{}\n", fence));
+    block
+}
+
+/// Writes `snippets` as one Markdown document, with a section for each
+pub(super) fn write(
+    snippets: &[Snippet],
+    writer: &mut dyn io::Write,
+    fields: &[&str],
+) -> color_eyre::Result<()> {
+    writer.write_all(b"# Snippets\n")?;
+    for snippet in snippets {
+        writer.write_all(format!("\n{}", self::snippet(snippet, fields, 2)).as_bytes())?;
+    }
+    Ok(())
+}
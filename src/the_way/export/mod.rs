@@ -0,0 +1,544 @@
+//! Exporting snippets: as JSON (what `import` reads back), CSV for spreadsheets, Markdown, org-mode, a static site,
+//! or a PDF cheat sheet to read, UltiSnips snippets for vim, Sublime Text snippets, an Alfred collection,
+//! a navi cheatsheet, an archive of code files, Hugo or Jekyll posts, Anki notes, or SQL
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use color_eyre::Help;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use structopt::StructOpt;
+
+use crate::errors::LostTheWay;
+use crate::the_way::{
+    filter::Filters,
+    snippet::{self, Snippet},
+    TheWay,
+};
+
+mod alfred;
+mod anki;
+mod csv;
+mod files;
+mod html;
+mod hugo;
+mod markdown;
+mod navi;
+mod org;
+mod pdf;
+mod sql;
+mod sublime;
+mod ultisnips;
+
+/// What `export --format` writes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// A JSON object per line for each snippet, or a JSON array with `--array` (the default)
+    Json,
+    /// A CSV table with a row per snippet
+    Csv,
+    /// A Markdown document with a section per snippet
+    Markdown,
+    /// A static site with a page per snippet
+    Html,
+    /// A printable cheat sheet
+    Pdf,
+    /// A vim UltiSnips file for each language
+    UltiSnips,
+    /// An Alfred snippet collection
+    Alfred,
+    /// An org-mode file with a headline per snippet
+    Org,
+    /// A navi cheatsheet of the shell snippets
+    Navi,
+    /// An archive with a file for each snippet
+    Files,
+    /// A Hugo post for each snippet
+    Hugo,
+    /// A Jekyll post for each snippet
+    Jekyll,
+    /// Anki notes to import
+    Anki,
+    /// SQL statements making a table of snippets
+    Sql,
+    /// A Sublime Text snippet file for each snippet
+    Sublime,
+}
+
+impl ExportFormat {
+    /// Possible format names
+    pub const VARIANTS: [&'static str; 15] = [
+        "json",
+        "csv",
+        "markdown",
+        "html",
+        "pdf",
+        "ultisnips",
+        "alfred",
+        "org",
+        "navi",
+        "files",
+        "hugo",
+        "jekyll",
+        "anki",
+        "sql",
+        "sublime",
+    ];
+
+    /// Extension of the files the format is written to ("" for the ones written to a folder)
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Json => ".json",
+            Self::Csv => ".csv",
+            Self::Markdown => ".md",
+            Self::Pdf => ".pdf",
+            Self::Alfred => ".alfredsnippets",
+            Self::Org => ".org",
+            Self::Navi => ".cheat",
+            Self::Files => ".tar.gz",
+            Self::Anki => ".txt",
+            Self::Sql => ".sql",
+            Self::Html | Self::UltiSnips | Self::Sublime | Self::Hugo | Self::Jekyll => "",
+        }
+    }
+
+    /// Whether the format is written to a folder (with `--out`) rather than a file
+    fn writes_folder(self) -> bool {
+        matches!(
+            self,
+            Self::Html | Self::UltiSnips | Self::Sublime | Self::Hugo | Self::Jekyll
+        )
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = LostTheWay;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "markdown" | "md" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
+            "pdf" => Ok(Self::Pdf),
+            "ultisnips" => Ok(Self::UltiSnips),
+            "alfred" => Ok(Self::Alfred),
+            "org" => Ok(Self::Org),
+            "navi" => Ok(Self::Navi),
+            "files" => Ok(Self::Files),
+            "hugo" => Ok(Self::Hugo),
+            "jekyll" => Ok(Self::Jekyll),
+            "anki" => Ok(Self::Anki),
+            "sql" => Ok(Self::Sql),
+            "sublime" => Ok(Self::Sublime),
+            other => Err(LostTheWay::UsageError {
+                message: format!("Unknown export format {:?}", other),
+            }),
+        }
+    }
+}
+
+/// What snippets are grouped by, under a heading for each (`export --group-by`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    Language,
+    /// Snippets with several tags are under each of them
+    Tag,
+}
+
+impl GroupBy {
+    /// Possible `--group-by` values
+    pub const VARIANTS: [&'static str; 2] = ["language", "tag"];
+
+    /// `snippets` by the name of each group they're in ("Untagged" for snippets with no tags)
+    fn groups(self, snippets: &[Snippet]) -> BTreeMap<&str, Vec<&Snippet>> {
+        let mut groups: BTreeMap<&str, Vec<&Snippet>> = BTreeMap::new();
+        for snippet in snippets {
+            match self {
+                Self::Language => groups.entry(&snippet.language).or_default().push(snippet),
+                Self::Tag if snippet.tags.is_empty() => {
+                    groups.entry("Untagged").or_default().push(snippet)
+                }
+                Self::Tag => {
+                    for tag in &snippet.tags {
+                        groups.entry(tag).or_default().push(snippet);
+                    }
+                }
+            }
+        }
+        groups
+    }
+}
+
+impl FromStr for GroupBy {
+    type Err = LostTheWay;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "language" => Ok(Self::Language),
+            "tag" => Ok(Self::Tag),
+            other => Err(LostTheWay::UsageError {
+                message: format!("Can't group snippets by {:?}", other),
+            }),
+        }
+    }
+}
+
+/// Which snippets `export` writes, where to, and how
+#[derive(StructOpt, Debug)]
+pub struct ExportArgs {
+    /// filename, writes to stdout if not given (with --split, the folder to write files to), gzipped if it ends in .gz
+    #[structopt(parse(from_os_str))]
+    pub(crate) file: Option<PathBuf>,
+    /// Folder (or file) to write to, same as <file>
+    #[structopt(long, parse(from_os_str), conflicts_with = "file")]
+    pub(crate) out: Option<PathBuf>,
+    #[structopt(flatten)]
+    pub(crate) filters: Filters,
+    /// Only export these fields (index, description, language, code, extension, tags, date, updated, uuid, dates)
+    #[structopt(long, use_delimiter = true)]
+    pub(crate) fields: Option<Vec<String>>,
+    /// Leave these fields out ("dates" for both date and updated)
+    #[structopt(long, use_delimiter = true, conflicts_with = "fields")]
+    pub(crate) omit: Vec<String>,
+    /// Format to write (default "json", a snippet per line): "csv" for a table with index, description, language, tags,
+    /// code, and date columns, "markdown" for a heading, tags, and fenced code block for each snippet,
+    /// "org" for a headline, properties drawer, and source block for each snippet,
+    /// "html" for a static site (in the folder given with --out) with highlighted code, tag and language
+    /// pages, and search, "pdf" for a printable cheat sheet, "ultisnips" for a <filetype>.snippets file
+    /// for each language (in the folder given with --out), "alfred" for an .alfredsnippets collection,
+    /// "navi" for a .cheat file with the shell snippets, "files" for a tar.gz (or .tar or .zip, going by
+    /// the file name) with an <index>-<description>.<extension> file for each snippet and a metadata.json,
+    /// "hugo" or "jekyll" for a Markdown post with front matter for each snippet (in the folder given with --out),
+    /// "anki" for notes (description on the front, highlighted code on the back) to import into Anki,
+    /// "sql" for CREATE TABLE and INSERT statements for SQLite or Postgres, "sublime" for a .sublime-snippet
+    /// file for each snippet (in the folder given with --out)
+    #[structopt(long, possible_values = &ExportFormat::VARIANTS)]
+    pub(crate) format: Option<ExportFormat>,
+    /// Write a file for each snippet (snippet_<index>.md) to the folder <file>, with --format markdown
+    #[structopt(long)]
+    pub(crate) split: bool,
+    /// Write a JSON array of snippets rather than one JSON object per line
+    #[structopt(long)]
+    pub(crate) array: bool,
+    /// Trim trailing whitespace from lines of code, turn CRLF line endings into LF, and end code with one newline
+    #[structopt(long)]
+    pub(crate) normalize: bool,
+    /// Write a file (or folder, for formats written to one) in --format for each tag or language
+    /// (<tag>.md, for instance) to the folder given with --out, "Untagged" for snippets without tags
+    #[structopt(long, possible_values = &GroupBy::VARIANTS, conflicts_with = "split")]
+    pub(crate) split_by: Option<GroupBy>,
+    /// What to put snippets under headings by in a PDF cheat sheet (default "language")
+    #[structopt(long, possible_values = &GroupBy::VARIANTS)]
+    pub(crate) group_by: Option<GroupBy>,
+}
+
+impl ExportArgs {
+    /// Where to write to, stdout if `None`
+    fn path(&self) -> Option<&Path> {
+        self.out.as_deref().or(self.file.as_deref())
+    }
+
+    /// The folder to write to, for exports written to one
+    fn folder(&self) -> color_eyre::Result<&Path> {
+        self.path()
+            .ok_or(LostTheWay::UsageError {
+                message: String::from("This export writes files to a folder"),
+            })
+            .suggestion("Give the folder with --out <folder>")
+    }
+
+    /// Whether to gzip what's written, going by the file's name
+    fn gzip(&self) -> bool {
+        self.path()
+            .and_then(Path::extension)
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("gz"))
+    }
+
+    /// The fields to export: `--fields`, or all but the `--omit` ones
+    fn fields(&self) -> color_eyre::Result<Vec<&'static str>> {
+        match &self.fields {
+            Some(fields) => Snippet::expand_fields(fields),
+            None => {
+                let omit = Snippet::expand_fields(&self.omit)?;
+                Ok(snippet::FIELDS
+                    .iter()
+                    .copied()
+                    .filter(|field| !omit.contains(field))
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Most characters of the description kept in file names
+const NAME_LENGTH: usize = 50;
+
+/// The words of the first line of `snippet`'s description, in lowercase
+fn description_words(snippet: &Snippet) -> Vec<String> {
+    snippet
+        .description
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The description's words joined by `-` for a file name, as many as fit in `NAME_LENGTH` characters
+fn slug(snippet: &Snippet) -> String {
+    let mut slug = String::new();
+    for word in description_words(snippet) {
+        if slug.chars().count() + word.chars().count() + 1 > NAME_LENGTH {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word);
+    }
+    slug
+}
+
+/// `name` (a tag or language) as a file name, with anything but letters, digits, `-`, `_`, and `+` as `-`
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | '+') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    if stem.is_empty() {
+        String::from("snippets")
+    } else {
+        stem
+    }
+}
+
+/// `code` with no trailing whitespace on its lines, LF line endings, and one newline at the end
+fn normalize(code: &str) -> String {
+    let lines: Vec<_> = code.lines().map(str::trim_end).collect();
+    format!("{}\n", lines.join("\n").trim_end_matches('\n'))
+}
+
+/// Writes `snippets` as a JSON object per line, or a JSON array with `array`
+fn write_json(
+    snippets: &[Snippet],
+    fields: &[&str],
+    array: bool,
+    writer: &mut dyn io::Write,
+) -> color_eyre::Result<()> {
+    if array {
+        writer.write_all(b"[\n")?;
+    }
+    for (number, snippet) in snippets.iter().enumerate() {
+        snippet.to_json_with_fields(writer, fields)?;
+        let separator = if array && number + 1 < snippets.len() {
+            ",\n"
+        } else {
+            "\n"
+        };
+        writer.write_all(separator.as_bytes())?;
+    }
+    if array {
+        writer.write_all(b"]\n")?;
+    }
+    Ok(())
+}
+
+impl TheWay {
+    /// Writes `snippets` (with every field, and UUIDs if they're set) to `file` as JSON, a snippet per line
+    pub(crate) fn write_json_file(
+        &self,
+        snippets: &[Snippet],
+        file: &Path,
+    ) -> color_eyre::Result<()> {
+        let mut writer = io::BufWriter::new(fs::File::create(file)?);
+        write_json(snippets, &snippet::FIELDS, false, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// The snippets `args` asks for, normalized with `args.normalize`
+    fn snippets_to_export(&self, args: &ExportArgs) -> color_eyre::Result<Vec<Snippet>> {
+        let mut snippets = self.filter_snippets(&args.filters)?;
+        if args.normalize {
+            for snippet in &mut snippets {
+                snippet.code = normalize(&snippet.code);
+            }
+        }
+        Ok(snippets)
+    }
+
+    /// Saves (optionally filtered) snippets to a file (or a file each, with `args.split`, or for each tag or language,
+    /// with `args.split_by`) in `args.format`, with only the given fields
+    pub(crate) fn export(&self, args: &ExportArgs) -> color_eyre::Result<()> {
+        let fields = args.fields()?;
+        let format = args.format.unwrap_or(ExportFormat::Json);
+        if args.split && format != ExportFormat::Markdown {
+            let error: color_eyre::Result<()> = Err(LostTheWay::UsageError {
+                message: String::from("--split only works with --format markdown"),
+            }
+            .into());
+            return error.suggestion("Add --format markdown, or leave out --split");
+        }
+        if args.array && format != ExportFormat::Json {
+            let error: color_eyre::Result<()> = Err(LostTheWay::UsageError {
+                message: String::from("--array only works with JSON"),
+            }
+            .into());
+            return error.suggestion("Leave out --format, or --array");
+        }
+        if let Some(split_by) = args.split_by {
+            let dir = args.folder()?;
+            let snippets = self.snippets_to_export(args)?;
+            fs::create_dir_all(dir)?;
+            let groups = split_by.groups(&snippets);
+            for (name, group) in &groups {
+                let group: Vec<Snippet> = group.iter().map(|snippet| (*snippet).clone()).collect();
+                let path = dir.join(format!("{}{}", file_stem(name), format.extension()));
+                if format.writes_folder() {
+                    fs::create_dir_all(&path)?;
+                    self.write_folder(&group, format, &fields, &path)?;
+                } else {
+                    let mut writer = io::BufWriter::new(fs::File::create(&path)?);
+                    self.write_snippets(group, format, args, &fields, &mut writer)?;
+                    writer.flush()?;
+                }
+            }
+            println!(
+                "Exported {} snippets to {} files in {}",
+                snippets.len(),
+                groups.len(),
+                dir.display()
+            );
+            return Ok(());
+        }
+        if args.split || format.writes_folder() {
+            let dir = args.folder()?;
+            let snippets = self.snippets_to_export(args)?;
+            fs::create_dir_all(dir)?;
+            if args.split {
+                for snippet in &snippets {
+                    let file = dir.join(format!("snippet_{}.md", snippet.index));
+                    fs::write(file, markdown::snippet(snippet, &fields, 1))?;
+                }
+            } else {
+                self.write_folder(&snippets, format, &fields, dir)?;
+            }
+            println!("Exported {} snippets to {}", snippets.len(), dir.display());
+            return Ok(());
+        }
+        let snippets = self.snippets_to_export(args)?;
+        let writer: Box<dyn io::Write> = match args.path() {
+            Some(file) => Box::new(fs::File::create(file)?),
+            None => Box::new(io::stdout()),
+        };
+        let mut buffered = io::BufWriter::new(writer);
+        // `files` makes its own tar.gz
+        if args.gzip() && format != ExportFormat::Files {
+            let mut encoder = GzEncoder::new(&mut buffered, Compression::default());
+            self.write_snippets(snippets, format, args, &fields, &mut encoder)?;
+            encoder.finish()?;
+        } else {
+            self.write_snippets(snippets, format, args, &fields, &mut buffered)?;
+        }
+        buffered.flush()?;
+        Ok(())
+    }
+
+    /// Writes `snippets` to `dir` in `format`, one of the ones written to a folder
+    fn write_folder(
+        &self,
+        snippets: &[Snippet],
+        format: ExportFormat,
+        fields: &[&str],
+        dir: &Path,
+    ) -> color_eyre::Result<()> {
+        match format {
+            ExportFormat::Html => html::write(snippets, dir, &self.highlighter),
+            ExportFormat::UltiSnips => ultisnips::write(snippets, dir),
+            ExportFormat::Sublime => sublime::write(snippets, dir),
+            ExportFormat::Hugo => hugo::write(hugo::Site::Hugo, snippets, dir, fields),
+            ExportFormat::Jekyll => hugo::write(hugo::Site::Jekyll, snippets, dir, fields),
+            _ => unreachable!("written to a file"),
+        }
+    }
+
+    /// Writes `snippets` to `writer` in `format`, one of the ones written to a file
+    fn write_snippets(
+        &self,
+        snippets: Vec<Snippet>,
+        format: ExportFormat,
+        args: &ExportArgs,
+        fields: &[&str],
+        writer: &mut dyn io::Write,
+    ) -> color_eyre::Result<()> {
+        match format {
+            ExportFormat::Json => {
+                let mut snippets = snippets;
+                // For `import` to update the snippet when it's read back
+                if fields.contains(&"uuid") {
+                    for snippet in &mut snippets {
+                        snippet.uuid = Some(self.get_uuid(snippet.index)?);
+                    }
+                }
+                write_json(&snippets, fields, args.array, writer)?
+            }
+            ExportFormat::Csv => csv::write(&snippets, writer, fields)?,
+            ExportFormat::Markdown => markdown::write(&snippets, writer, fields)?,
+            ExportFormat::Org => org::write(&snippets, writer, fields)?,
+            ExportFormat::Anki => anki::write(&snippets, writer, &self.highlighter)?,
+            ExportFormat::Sql => {
+                let mut snippets = snippets;
+                if fields.contains(&"uuid") {
+                    for snippet in &mut snippets {
+                        snippet.uuid = Some(self.get_uuid(snippet.index)?);
+                    }
+                }
+                sql::write(&snippets, writer, fields)?
+            }
+            ExportFormat::Files => files::write(&snippets, writer, args.path(), fields)?,
+            ExportFormat::Navi => {
+                navi::write(&snippets, writer)?;
+                let left_out = snippets
+                    .iter()
+                    .filter(|snippet| !navi::is_command(snippet))
+                    .count();
+                if left_out > 0 {
+                    eprintln!("Left out {} snippets that aren't shell commands", left_out);
+                }
+            }
+            ExportFormat::Alfred => {
+                let uuids = snippets
+                    .iter()
+                    .map(|snippet| self.get_uuid(snippet.index))
+                    .collect::<color_eyre::Result<Vec<_>>>()?;
+                alfred::write(&snippets, &uuids, writer)?
+            }
+            ExportFormat::Html
+            | ExportFormat::UltiSnips
+            | ExportFormat::Sublime
+            | ExportFormat::Hugo
+            | ExportFormat::Jekyll => {
+                unreachable!("written to a folder")
+            }
+            ExportFormat::Pdf => pdf::write(
+                &snippets,
+                writer,
+                args.group_by.unwrap_or(GroupBy::Language),
+                &self.highlighter,
+            )?,
+        }
+        Ok(())
+    }
+}
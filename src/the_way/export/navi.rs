@@ -0,0 +1,56 @@
+//! `the-way export --format navi`: shell snippets as a navi cheatsheet (`.cheat` file), under a `% tags` line
+//! for each set of tags, each command below a `# description` line
+use std::collections::BTreeMap;
+use std::io;
+
+use crate::the_way::snippet::Snippet;
+
+/// Languages whose snippets are commands navi can run
+const SHELLS: [&str; 6] = ["sh", "bash", "shell", "zsh", "fish", "shell script"];
+
+/// Tags for snippets without any, so navi still has something to show
+const UNTAGGED: &str = "the-way";
+
+/// Whether `snippet` is a shell command
+pub(super) fn is_command(snippet: &Snippet) -> bool {
+    SHELLS.contains(&snippet.language.to_lowercase().as_str())
+}
+
+/// A command for navi: it ends at a blank line, and lines starting with `#` or `;` would be read
+/// as descriptions and comments, so shell comments are left out
+fn command(code: &str) -> String {
+    code.lines()
+        .filter(|line| {
+            let line = line.trim_start();
+            !(line.is_empty() || line.starts_with('#') || line.starts_with(';'))
+        })
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+/// Writes the shell snippets in `snippets` as a navi cheatsheet
+pub(super) fn write(snippets: &[Snippet], writer: &mut dyn io::Write) -> color_eyre::Result<()> {
+    let mut groups: BTreeMap<String, Vec<&Snippet>> = BTreeMap::new();
+    for snippet in snippets.iter().filter(|snippet| is_command(snippet)) {
+        let tags = if snippet.tags.is_empty() {
+            String::from(UNTAGGED)
+        } else {
+            snippet.tags.join(", ")
+        };
+        groups.entry(tags).or_default().push(snippet);
+    }
+    let mut sections = Vec::new();
+    for (tags, snippets) in groups {
+        let mut section = format!("% {}\n", tags);
+        for snippet in snippets {
+            section.push_str(&format!(
+                "\n# {}\n{}",
+                snippet.description.lines().next().unwrap_or_default(),
+                command(&snippet.code)
+            ));
+        }
+        sections.push(section);
+    }
+    writer.write_all(sections.join("\n").as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! `the-way export --format org`: a headline for each snippet (with its tags), a `:PROPERTIES:` drawer
+//! with its language, tags, and dates, and its code in a `#+BEGIN_SRC` block (what `import --from org` reads)
+use std::io;
+
+use chrono::{DateTime, Utc};
+
+use crate::the_way::snippet::Snippet;
+
+/// An inactive org timestamp, e.g. "[2021-03-04 Thu 10:00]"
+fn timestamp(date: &DateTime<Utc>) -> String {
+    date.format("[%Y-%m-%d %a %H:%M]").to_string()
+}
+
+/// `tag` as an org tag, which can only have letters, digits, `_`, `@`, `#`, and `%`
+fn org_tag(tag: &str) -> String {
+    tag.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '_' | '@' | '#' | '%') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// `snippet` in org-mode with the `fields` asked for
+fn snippet(snippet: &Snippet, fields: &[&str]) -> String {
+    let mut org = String::new();
+    let mut description = snippet.description.lines();
+    let title = if fields.contains(&"description") {
+        description.next().unwrap_or_default().to_owned()
+    } else {
+        format!("Snippet #{}", snippet.index)
+    };
+    org.push_str(&format!("* {}", title));
+    if fields.contains(&"tags") && !snippet.tags.is_empty() {
+        let tags: Vec<_> = snippet.tags.iter().map(|tag| org_tag(tag)).collect();
+        org.push_str(&format!(" :{}:", tags.join(":")));
+    }
+    org.push_str("\n:PROPERTIES:\n");
+    if fields.contains(&"index") {
+        org.push_str(&format!(":INDEX: {}\n", snippet.index));
+    }
+    if fields.contains(&"language") {
+        org.push_str(&format!(":LANGUAGE: {}\n", snippet.language));
+    }
+    if fields.contains(&"tags") && !snippet.tags.is_empty() {
+        org.push_str(&format!(":TAGS: {}\n", snippet.tags.join(" ")));
+    }
+    if fields.contains(&"date") {
+        org.push_str(&format!(":DATE: {}\n", timestamp(&snippet.date)));
+    }
+    if fields.contains(&"updated") {
+        org.push_str(&format!(":UPDATED: {}\n", timestamp(&snippet.updated)));
+    }
+    org.push_str(":END:\n");
+    if fields.contains(&"description") {
+        let rest: Vec<_> = description.collect();
+        if !rest.join("").trim().is_empty() {
+            org.push_str(&format!("{}\n", rest.join("\n").trim()));
+        }
+    }
+    if fields.contains(&"code") {
+        org.push_str("#+BEGIN_SRC");
+        if fields.contains(&"language") && !snippet.language.trim().is_empty() {
+            let language: Vec<_> = snippet.language.split_whitespace().collect();
+            org.push_str(&format!(" {}", language.join("-")));
+        }
+        org.push('\n');
+        for line in snippet.code.lines() {
+            // Org would read these as headlines or settings (ending the block), so they get a comma
+            // after their indentation
+            let rest = line.trim_start();
+            let indent = &line[..line.len() - rest.len()];
+            if rest.starts_with('*') || rest.starts_with("#+") {
+                org.push_str(&format!("{},{}\n", indent, rest));
+            } else {
+                org.push_str(&format!("{}\n", line));
+            }
+        }
+        org.push_str("#+END_SRC\n");
+    }
+    org
+}
+
+/// Writes `snippets` as one org file, with a headline for each
+pub(super) fn write(
+    snippets: &[Snippet],
+    writer: &mut dyn io::Write,
+    fields: &[&str],
+) -> color_eyre::Result<()> {
+    writer.write_all(b"#+TITLE: Snippets\n")?;
+    for snippet in snippets {
+        writer.write_all(format!("\n{}", self::snippet(snippet, fields)).as_bytes())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,302 @@
+//! `the-way export --format pdf`: a printable cheat sheet, with snippets grouped under a heading for each
+//! language (or tag, with `--group-by tag`) and code highlighted in a light theme.
+//! Written by hand with PDF's built-in fonts (Helvetica and Courier), which only have Latin-1 characters
+use std::io;
+
+use syntect::highlighting::Color;
+
+use crate::language::CodeHighlight;
+use crate::the_way::export::GroupBy;
+use crate::the_way::snippet::Snippet;
+
+/// A4, in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 40.0;
+const CODE_SIZE: f32 = 8.0;
+const CODE_LINE: f32 = 10.0;
+/// Courier's characters are all 0.6 of the font size wide
+const CODE_CHARS: usize = ((PAGE_WIDTH - 2.0 * MARGIN - 8.0) / (CODE_SIZE * 0.6)) as usize;
+
+const BLACK: Color = Color {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 255,
+};
+const GREY: Color = Color {
+    r: 110,
+    g: 110,
+    b: 110,
+    a: 255,
+};
+
+/// Fonts, by their resource names
+#[derive(Clone, Copy)]
+enum Font {
+    Regular,
+    Bold,
+    Code,
+}
+
+impl Font {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Regular => "F1",
+            Self::Bold => "F2",
+            Self::Code => "F3",
+        }
+    }
+}
+
+/// `text` as a PDF string: (, ), and \ escaped, Latin-1 characters as octal escapes
+/// (the fonts are WinAnsi encoded, which matches Latin-1 for them), and anything else as ?
+fn pdf_string(text: &str) -> String {
+    let mut string = String::from("(");
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                string.push('\\');
+                string.push(c);
+            }
+            '\t' => string.push_str("    "),
+            ' '..='~' => string.push(c),
+            '\u{a0}'..='\u{ff}' => string.push_str(&format!("\\{:03o}", c as u32)),
+            _ => string.push('?'),
+        }
+    }
+    string.push(')');
+    string
+}
+
+fn rgb(color: Color) -> String {
+    format!(
+        "{:.3} {:.3} {:.3}",
+        f32::from(color.r) / 255.0,
+        f32::from(color.g) / 255.0,
+        f32::from(color.b) / 255.0
+    )
+}
+
+/// `text` broken into lines of at most `width` characters, at spaces if there are any
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// A highlighted line of code broken into lines that fit across the page
+fn wrap_code(pieces: &[(Color, String)]) -> Vec<Vec<(Color, String)>> {
+    let mut lines = vec![Vec::new()];
+    let mut length = 0;
+    for (color, text) in pieces {
+        let mut text = text.replace('\t', "    ");
+        while !text.is_empty() {
+            if length == CODE_CHARS {
+                lines.push(Vec::new());
+                length = 0;
+            }
+            let take = text
+                .char_indices()
+                .nth(CODE_CHARS - length)
+                .map_or(text.len(), |(i, _)| i);
+            let rest = text.split_off(take);
+            length += text.chars().count();
+            lines.last_mut().unwrap().push((*color, text));
+            text = rest;
+        }
+    }
+    lines
+}
+
+/// Pages of PDF content (drawing operators), filled from the top down
+struct Layout {
+    pages: Vec<String>,
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self {
+            pages: vec![String::new()],
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    /// Starts a new page if there isn't `height` left on this one (and it's not empty)
+    fn make_room(&mut self, height: f32) {
+        if self.y - height < MARGIN && self.y < PAGE_HEIGHT - MARGIN {
+            self.pages.push(String::new());
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn text(&mut self, x: f32, font: Font, size: f32, pieces: &[(Color, String)]) {
+        let page = self.pages.last_mut().unwrap();
+        page.push_str(&format!(
+            "BT /{} {} Tf {:.1} {:.1} Td ",
+            font.name(),
+            size,
+            x,
+            self.y - size
+        ));
+        for (color, text) in pieces {
+            page.push_str(&format!("{} rg {} Tj ", rgb(*color), pdf_string(text)));
+        }
+        page.push_str("ET\n");
+    }
+
+    /// A line of text in one color, moving down `height`
+    fn line(&mut self, font: Font, size: f32, color: Color, text: &str, height: f32) {
+        self.make_room(height);
+        self.text(MARGIN, font, size, &[(color, text.to_owned())]);
+        self.y -= height;
+    }
+
+    /// Highlighted code on a light grey background
+    fn code(&mut self, lines: &[Vec<(Color, String)>]) {
+        let mut lines = lines.iter().peekable();
+        while lines.peek().is_some() {
+            self.make_room(CODE_LINE + 4.0);
+            // As many lines as fit on this page
+            let fit = (((self.y - MARGIN - 4.0) / CODE_LINE) as usize).max(1);
+            let chunk: Vec<_> = lines.by_ref().take(fit).collect();
+            let height = chunk.len() as f32 * CODE_LINE + 4.0;
+            self.pages.last_mut().unwrap().push_str(&format!(
+                "0.96 0.96 0.96 rg {:.1} {:.1} {:.1} {:.1} re f\n",
+                MARGIN,
+                self.y - height,
+                PAGE_WIDTH - 2.0 * MARGIN,
+                height
+            ));
+            self.y -= 2.0;
+            for line in chunk {
+                self.text(MARGIN + 4.0, Font::Code, CODE_SIZE, line);
+                self.y -= CODE_LINE;
+            }
+            self.y -= 2.0;
+        }
+    }
+}
+
+/// A snippet's title (wrapped), what it's in or tagged with, and its code
+fn snippet(layout: &mut Layout, snippet: &Snippet, highlighter: &CodeHighlight) {
+    let title = format!(
+        "#{} {}",
+        snippet.index,
+        snippet.description.lines().next().unwrap_or_default()
+    );
+    // Helvetica characters are about half the font size wide on average
+    let title = wrap(&title, ((PAGE_WIDTH - 2.0 * MARGIN) / 4.5) as usize);
+    let code: Vec<_> = highlighter
+        .highlight_for_print(&snippet.code, &snippet.extension)
+        .iter()
+        .flat_map(|line| wrap_code(line))
+        .collect();
+    // Keep short snippets on one page
+    layout.make_room(title.len() as f32 * 11.0 + 10.0 + code.len().min(20) as f32 * CODE_LINE);
+    for line in &title {
+        layout.line(Font::Bold, 9.0, BLACK, line, 11.0);
+    }
+    let mut about = vec![snippet.language.to_owned()];
+    about.extend(snippet.tags.iter().cloned());
+    layout.line(Font::Regular, 7.0, GREY, &about.join(" \u{b7} "), 10.0);
+    layout.code(&code);
+    layout.y -= 8.0;
+}
+
+/// Writes `snippets` as a PDF cheat sheet, grouped by `group_by`
+pub(super) fn write(
+    snippets: &[Snippet],
+    writer: &mut dyn io::Write,
+    group_by: GroupBy,
+    highlighter: &CodeHighlight,
+) -> color_eyre::Result<()> {
+    let groups = group_by.groups(snippets);
+    let mut layout = Layout::new();
+    layout.line(Font::Bold, 16.0, BLACK, "Snippets", 24.0);
+    for (name, snippets) in groups {
+        // A heading at the bottom of a page goes with the snippet under it
+        layout.make_room(60.0);
+        layout.y -= 6.0;
+        layout.line(Font::Bold, 13.0, BLACK, name, 18.0);
+        for item in snippets {
+            snippet(&mut layout, item, highlighter);
+        }
+    }
+    let total = layout.pages.len();
+    for (number, page) in layout.pages.iter_mut().enumerate() {
+        page.push_str(&format!(
+            "BT /F1 7 Tf {:.1} 20 Td {} rg {} Tj ET\n",
+            PAGE_WIDTH - MARGIN - 30.0,
+            rgb(GREY),
+            pdf_string(&format!("{} / {}", number + 1, total))
+        ));
+    }
+
+    // Catalog, page tree, and fonts, then a page and its contents for each page
+    let mut objects = vec![
+        String::from("<< /Type /Catalog /Pages 2 0 R >>"),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..total)
+                .map(|page| format!("{} 0 R", 6 + 2 * page))
+                .collect::<Vec<_>>()
+                .join(" "),
+            total
+        ),
+    ];
+    for font in ["Helvetica", "Helvetica-Bold", "Courier"] {
+        objects.push(format!(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+            font
+        ));
+    }
+    for (page, content) in layout.pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+            /Resources << /Font << /F1 3 0 R /F2 4 0 R /F3 5 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH,
+            PAGE_HEIGHT,
+            7 + 2 * page
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (number, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", number + 1, object));
+    }
+    let xref = pdf.len();
+    pdf.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    writer.write_all(pdf.as_bytes())?;
+    Ok(())
+}
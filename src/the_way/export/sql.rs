@@ -0,0 +1,104 @@
+//! `the-way export --format sql`: `CREATE TABLE` and `INSERT` statements (that SQLite and Postgres both run)
+//! for a `snippets` table with a row for each snippet, and a `snippet_tags` table with a row for each of their tags
+use std::io;
+
+use chrono::SecondsFormat;
+
+use crate::the_way::snippet::Snippet;
+
+/// Columns of the `snippets` table for each field, besides tags, which are in `snippet_tags`
+/// (`index` being a keyword, the index is the `id` column, always there for tags to point to)
+const COLUMNS: [(&str, &str, &str); 8] = [
+    ("index", "id", "INTEGER PRIMARY KEY"),
+    ("description", "description", "TEXT NOT NULL"),
+    ("language", "language", "TEXT NOT NULL"),
+    ("extension", "extension", "TEXT NOT NULL"),
+    ("code", "code", "TEXT NOT NULL"),
+    ("date", "date", "TIMESTAMP NOT NULL"),
+    ("updated", "updated", "TIMESTAMP NOT NULL"),
+    ("uuid", "uuid", "TEXT"),
+];
+
+/// `text` as an SQL string
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// The value of `snippet`'s `field` as SQL
+fn value(snippet: &Snippet, field: &str) -> String {
+    match field {
+        "index" => snippet.index.to_string(),
+        "description" => quote(&snippet.description),
+        "language" => quote(&snippet.language),
+        "extension" => quote(&snippet.extension),
+        "code" => quote(&snippet.code),
+        "date" => quote(&snippet.date.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        "updated" => quote(&snippet.updated.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        "uuid" => snippet
+            .uuid
+            .as_deref()
+            .map_or_else(|| String::from("NULL"), quote),
+        _ => unreachable!("not a column"),
+    }
+}
+
+/// Writes `snippets` as SQL with the `fields` asked for, replacing the tables if they're already there
+pub(super) fn write(
+    snippets: &[Snippet],
+    writer: &mut dyn io::Write,
+    fields: &[&str],
+) -> color_eyre::Result<()> {
+    let columns: Vec<_> = COLUMNS
+        .iter()
+        .filter(|(field, _, _)| *field == "index" || fields.contains(field))
+        .collect();
+    let tags = fields.contains(&"tags");
+    writer.write_all(b"-- Exported from the-way\nDROP TABLE IF EXISTS snippet_tags;\nDROP TABLE IF EXISTS snippets;\n")?;
+    let definitions: Vec<_> = columns
+        .iter()
+        .map(|(_, column, kind)| format!("    {} {}", column, kind))
+        .collect();
+    writer.write_all(
+        format!("CREATE TABLE snippets (\n{}\n);\n", definitions.join(",\n")).as_bytes(),
+    )?;
+    if tags {
+        writer.write_all(
+            b"CREATE TABLE snippet_tags (\n    snippet_id INTEGER NOT NULL REFERENCES snippets (id),\n    \
+            tag TEXT NOT NULL,\n    PRIMARY KEY (snippet_id, tag)\n);\n",
+        )?;
+    }
+    writer.write_all(b"BEGIN;\n")?;
+    let names: Vec<_> = columns.iter().map(|(_, column, _)| *column).collect();
+    for snippet in snippets {
+        let values: Vec<_> = columns
+            .iter()
+            .map(|(field, _, _)| value(snippet, field))
+            .collect();
+        writer.write_all(
+            format!(
+                "INSERT INTO snippets ({}) VALUES ({});\n",
+                names.join(", "),
+                values.join(", ")
+            )
+            .as_bytes(),
+        )?;
+        if tags {
+            let mut seen = Vec::new();
+            for tag in &snippet.tags {
+                if !seen.contains(&tag) {
+                    seen.push(tag);
+                    writer.write_all(
+                        format!(
+                            "INSERT INTO snippet_tags (snippet_id, tag) VALUES ({}, {});\n",
+                            snippet.index,
+                            quote(tag)
+                        )
+                        .as_bytes(),
+                    )?;
+                }
+            }
+        }
+    }
+    writer.write_all(b"COMMIT;\n")?;
+    Ok(())
+}
@@ -0,0 +1,90 @@
+//! `the-way export --format sublime --out <folder>`: a Sublime Text `.sublime-snippet` file for each snippet
+//! (what `import --from sublime` reads), with the description's words joined by `_` as its tab trigger,
+//! in the scope of its language
+use std::fs;
+use std::path::Path;
+
+use crate::the_way::export::slug;
+use crate::the_way::export::ultisnips::trigger;
+use crate::the_way::snippet::Snippet;
+use crate::utils::escape_html;
+
+/// The scope Sublime Text gives files in `language`, where it's not just `source.<language>`
+/// (`None` for plain text, so those snippets work everywhere)
+fn scope(language: &str) -> Option<String> {
+    let language = language.to_lowercase();
+    let scope = match language.as_str() {
+        "" | "text" | "plain text" | "plaintext" | "txt" => return None,
+        "shell" | "bash" | "sh" | "zsh" | "shell script" => "source.shell.bash",
+        "html" => "text.html.basic",
+        "markdown" | "md" => "text.html.markdown",
+        "xml" => "text.xml",
+        "latex" | "tex" => "text.tex.latex",
+        "javascript" => "source.js",
+        "typescript" => "source.ts",
+        "c#" => "source.cs",
+        "objective-c" => "source.objc",
+        "emacs lisp" => "source.lisp",
+        _ => {
+            let name: String = language
+                .chars()
+                .filter(|c| c.is_alphanumeric() || matches!(c, '+' | '_'))
+                .collect();
+            return if name.is_empty() {
+                None
+            } else {
+                Some(format!("source.{}", name))
+            };
+        }
+    };
+    Some(String::from(scope))
+}
+
+/// `code` with the characters Sublime Text reads as fields and escapes escaped, in CDATA
+/// (split where it has `]]>`, which would end it)
+fn content(code: &str) -> String {
+    let mut escaped = String::with_capacity(code.len());
+    for c in code.chars() {
+        if matches!(c, '\\' | '$') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    format!(
+        "<![CDATA[\n{}\n]]>",
+        escaped
+            .trim_end_matches('\n')
+            .replace("]]>", "]]]]><![CDATA[>")
+    )
+}
+
+/// A `<snippet>` document
+fn snippet(snippet: &Snippet) -> String {
+    let mut xml = format!(
+        "<snippet>\n\t<content>{}</content>\n\t<tabTrigger>{}</tabTrigger>\n",
+        content(&snippet.code),
+        escape_html(&trigger(snippet))
+    );
+    if let Some(scope) = scope(&snippet.language) {
+        xml.push_str(&format!("\t<scope>{}</scope>\n", escape_html(&scope)));
+    }
+    xml.push_str(&format!(
+        "\t<description>{}</description>\n</snippet>\n",
+        escape_html(snippet.description.lines().next().unwrap_or_default())
+    ));
+    xml
+}
+
+/// Writes a `<index>-<description>.sublime-snippet` file for each of `snippets` to `dir`
+pub(super) fn write(snippets: &[Snippet], dir: &Path) -> color_eyre::Result<()> {
+    for item in snippets {
+        let slug = slug(item);
+        let name = if slug.is_empty() {
+            item.index.to_string()
+        } else {
+            format!("{}-{}", item.index, slug)
+        };
+        fs::write(dir.join(format!("{}.sublime-snippet", name)), snippet(item))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,97 @@
+//! `the-way export --format ultisnips --out <folder>`: a `<filetype>.snippets` file for each language,
+//! with a `snippet <trigger> "<description>"` block for each snippet, for vim's UltiSnips.
+//! The trigger is the description's words joined by `_`, and code is inserted as it is (`$` and `` ` `` escaped)
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::the_way::export::description_words;
+use crate::the_way::snippet::Snippet;
+
+/// The vim filetype for `language`, where it's not just the language's name ("all" for plain text,
+/// so those snippets work everywhere)
+fn filetype(language: &str) -> String {
+    let language = language.to_lowercase();
+    match language.as_str() {
+        "" | "text" | "plain text" | "plaintext" | "txt" => String::from("all"),
+        "c++" => String::from("cpp"),
+        "c#" => String::from("cs"),
+        "f#" => String::from("fsharp"),
+        "shell" | "bash" | "sh" | "shell script" => String::from("sh"),
+        "objective-c" => String::from("objc"),
+        "vim script" | "viml" => String::from("vim"),
+        "emacs lisp" => String::from("lisp"),
+        _ => {
+            let filetype: String = language
+                .chars()
+                .filter(|c| c.is_alphanumeric() || *c == '_')
+                .collect();
+            if filetype.is_empty() {
+                String::from("all")
+            } else {
+                filetype
+            }
+        }
+    }
+}
+
+/// The description's first line as a trigger word, e.g. "list_files" for "List files (all)"
+pub(super) fn trigger(snippet: &Snippet) -> String {
+    let words = description_words(snippet);
+    if words.is_empty() {
+        format!("snippet{}", snippet.index)
+    } else {
+        words.join("_")
+    }
+}
+
+/// `code` with the characters UltiSnips reads as tabstops, interpolation, and escapes escaped
+fn escape(code: &str) -> String {
+    let mut escaped = String::with_capacity(code.len());
+    for c in code.chars() {
+        if matches!(c, '\\' | '$' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// A `snippet ... endsnippet` block
+fn snippet(snippet: &Snippet) -> String {
+    let description = snippet
+        .description
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .replace('"', "'");
+    let mut block = format!(
+        "snippet {} \"{}\"\n{}",
+        trigger(snippet),
+        description,
+        escape(&snippet.code)
+    );
+    if !block.ends_with('\n') {
+        block.push('\n');
+    }
+    block.push_str("endsnippet\n");
+    block
+}
+
+/// Writes a `.snippets` file for each language in `snippets` to `dir`
+pub(super) fn write(snippets: &[Snippet], dir: &Path) -> color_eyre::Result<()> {
+    let mut files: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for item in snippets {
+        files
+            .entry(filetype(&item.language))
+            .or_default()
+            .push(snippet(item));
+    }
+    for (filetype, blocks) in files {
+        fs::write(
+            dir.join(format!("{}.snippets", filetype)),
+            format!("# Exported from the-way\n\n{}", blocks.join("\n")),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! Field mappings for importing JSON written by other tools (`import --map`)
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde_json::Value;
+
+use crate::errors::LostTheWay;
+use crate::utils;
+
+/// Snippet fields that can be mapped
+const SNIPPET_FIELDS: [&str; 6] = ["description", "language", "code", "tags", "date", "updated"];
+
+/// Where to find a snippet field in the imported JSON
+#[derive(Debug)]
+struct FieldSource {
+    /// Snippet field to fill in
+    field: String,
+    /// Path to the value, e.g. "labels[].name" is ["labels[]", "name"]
+    path: Vec<String>,
+}
+
+/// Maps snippet fields to fields of the imported JSON objects,
+/// e.g. "description=title,code=body,tags=labels[]"
+#[derive(Debug)]
+pub struct FieldMap {
+    sources: Vec<FieldSource>,
+}
+
+impl FromStr for FieldMap {
+    type Err = LostTheWay;
+
+    /// Reads a mapping from a string, or from a file (one mapping per line or comma-separated, # for comments)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mapping = if Path::new(s).is_file() {
+            fs::read_to_string(s).map_err(|e| LostTheWay::ConfigError {
+                message: format!("Couldn't read mapping file {}: {}", s, e),
+            })?
+        } else {
+            s.to_owned()
+        };
+        let sources = mapping
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .flat_map(|line| line.split(','))
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (field, source) = pair.split_once('=').ok_or(LostTheWay::ConfigError {
+                    message: format!("Expected field=source in mapping, got {:?}", pair),
+                })?;
+                let field = field.trim().to_ascii_lowercase();
+                if !SNIPPET_FIELDS.contains(&field.as_str()) {
+                    return Err(LostTheWay::ConfigError {
+                        message: format!(
+                            "Can't map to {:?}, snippet fields are {}",
+                            field,
+                            SNIPPET_FIELDS.join(", ")
+                        ),
+                    });
+                }
+                Ok(FieldSource {
+                    field,
+                    path: source.trim().split('.').map(String::from).collect(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { sources })
+    }
+}
+
+/// Follows `path` into `value`, "key[]" takes `key` from every element of an array
+fn lookup(value: &Value, path: &[String]) -> Option<Value> {
+    let (key, rest) = match path.split_first() {
+        Some(first) => first,
+        None => return Some(value.to_owned()),
+    };
+    match key.strip_suffix("[]") {
+        Some(key) => {
+            let items = if key.is_empty() {
+                value
+            } else {
+                value.get(key)?
+            };
+            Some(Value::Array(
+                items
+                    .as_array()?
+                    .iter()
+                    .filter_map(|item| lookup(item, rest))
+                    .collect(),
+            ))
+        }
+        None => lookup(value.get(key.as_str())?, rest),
+    }
+}
+
+/// Turns a mapped value into what a snippet field expects
+fn convert(field: &str, value: Value) -> color_eyre::Result<Value> {
+    Ok(match (field, value) {
+        ("tags", Value::Array(tags)) => Value::Array(
+            tags.into_iter()
+                .map(|tag| match tag {
+                    Value::String(tag) => Value::String(tag),
+                    other => Value::String(other.to_string()),
+                })
+                .collect(),
+        ),
+        ("tags", Value::String(tags)) => Value::Array(
+            tags.split([',', ' '])
+                .filter(|tag| !tag.is_empty())
+                .map(|tag| Value::String(tag.to_owned()))
+                .collect(),
+        ),
+        ("date", Value::Number(timestamp)) | ("updated", Value::Number(timestamp)) => {
+            Value::String(
+                Utc.timestamp(timestamp.as_i64().unwrap_or_default(), 0)
+                    .to_rfc3339(),
+            )
+        }
+        ("date", Value::String(date)) | ("updated", Value::String(date)) => {
+            let date = match DateTime::parse_from_rfc3339(&date) {
+                Ok(date) => date.with_timezone(&Utc),
+                Err(_) => utils::date_start(Some(utils::parse_date(&date)?)),
+            };
+            Value::String(date.to_rfc3339())
+        }
+        (_, Value::String(text)) => Value::String(text),
+        (_, other) => Value::String(other.to_string()),
+    })
+}
+
+impl FieldMap {
+    /// Rewrites an imported JSON object so its mapped fields have snippet field names.
+    /// Unmapped fields are left as they are.
+    pub(crate) fn apply(&self, value: Value) -> color_eyre::Result<Value> {
+        let mut snippet = match &value {
+            Value::Object(object) => object.to_owned(),
+            _ => {
+                return Err(LostTheWay::ConfigError {
+                    message: format!("Expected a JSON object to import, got {}", value),
+                }
+                .into())
+            }
+        };
+        for source in &self.sources {
+            if let Some(mapped) = lookup(&value, &source.path) {
+                if !mapped.is_null() {
+                    snippet.insert(source.field.to_owned(), convert(&source.field, mapped)?);
+                }
+            }
+        }
+        Ok(Value::Object(snippet))
+    }
+}
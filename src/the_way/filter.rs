@@ -1,31 +1,338 @@
 //! Code related to filtering search, list, and export results
 use std::collections::HashSet;
+use std::ffi::OsString;
+use std::iter;
+use std::str::FromStr;
 
 use chrono::{Date, Utc};
+use color_eyre::Help;
 use structopt::StructOpt;
 
+use crate::configuration::TheWayConfig;
+use crate::errors::LostTheWay;
 use crate::the_way::{snippet::Snippet, TheWay};
 use crate::utils;
 
+/// Indices picked with `--indices`, as ranges (`None` at the end for ranges with no end, like "90-")
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexRanges(Vec<(usize, Option<usize>)>);
+
+impl IndexRanges {
+    /// Whether `index` is in one of the ranges
+    fn contains(&self, index: usize) -> bool {
+        self.0
+            .iter()
+            .any(|(start, end)| index >= *start && end.map_or(true, |end| index <= end))
+    }
+}
+
+impl FromStr for IndexRanges {
+    type Err = LostTheWay;
+
+    /// Reads "3,7,10-20" as 3, 7, and 10 to 20, and "90-" as 90 on
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || LostTheWay::UsageError {
+            message: format!("Expected indices like 3,7,10-20 or 90-, got {:?}", s),
+        };
+        let number = |text: &str| text.trim().parse::<usize>().map_err(|_| error());
+        s.split(',')
+            .map(|part| match part.split_once('-') {
+                Some((start, end)) if end.trim().is_empty() => Ok((number(start)?, None)),
+                Some((start, end)) => Ok((number(start)?, Some(number(end)?))),
+                None => {
+                    let index = number(part)?;
+                    Ok((index, Some(index)))
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// What to order snippets by (`--sort`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    Index,
+    /// When they were made
+    Date,
+    Language,
+    /// In any case
+    Description,
+    /// When they were last changed
+    Updated,
+    /// How well they match `--code`: with the text in the description or tags first,
+    /// then with it as a whole word in the code (the default with `--code`)
+    Relevance,
+}
+
+impl SortBy {
+    /// Possible `--sort` values
+    pub const VARIANTS: [&'static str; 6] = [
+        "index",
+        "date",
+        "language",
+        "description",
+        "updated",
+        "relevance",
+    ];
+}
+
+impl FromStr for SortBy {
+    type Err = LostTheWay;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "index" => Ok(Self::Index),
+            "date" => Ok(Self::Date),
+            "language" => Ok(Self::Language),
+            "description" => Ok(Self::Description),
+            "updated" => Ok(Self::Updated),
+            "relevance" => Ok(Self::Relevance),
+            other => Err(LostTheWay::UsageError {
+                message: format!("Can't sort snippets by {:?}", other),
+            }),
+        }
+    }
+}
+
 #[derive(StructOpt, Debug)]
 pub struct Filters {
     /// Snippets written in <language> (multiple with 'lang1 lang2')
     #[structopt(short, long)]
     pub(crate) languages: Option<Vec<String>>,
-    /// Snippets with <tag> (multiple with 'tag1 tag2')
+    /// Snippets with <tag> (multiple with 'tag1 tag2' or 'tag1,tag2' for any of them, 'tag1+tag2' for all of them)
     #[structopt(short, long)]
     pub(crate) tags: Option<Vec<String>>,
-    /// Snippets from <date> ("last friday" works too!)
+    /// Snippets from <date> ("last friday", "last week", and "2d" work too!)
     #[structopt(long, parse(try_from_str = utils::parse_date))]
     pub(crate) from: Option<Date<Utc>>,
     /// Snippets before <date>
     #[structopt(long, parse(try_from_str = utils::parse_date))]
     pub(crate) to: Option<Date<Utc>>,
+    /// Snippets whose code has <text> in it (in any case), e.g. --code "PRAGMA journal_mode"
+    #[structopt(long, value_name = "text")]
+    pub(crate) code: Option<String>,
+    /// Snippets without <tag> (multiple with 'tag1 tag2')
+    #[structopt(long = "not-tag", value_name = "tag")]
+    pub(crate) not_tags: Option<Vec<String>>,
+    /// Snippets not written in <language> (multiple with 'lang1 lang2')
+    #[structopt(long = "not-lang", value_name = "language")]
+    pub(crate) not_languages: Option<Vec<String>>,
+    /// Snippets at these indices, e.g. 3,7,10-20 (or 90- for 90 on)
+    #[structopt(long)]
+    pub(crate) indices: Option<IndexRanges>,
+    /// Order snippets by index (the default), date, language, description, updated, or relevance
+    /// (the default with --code: description and tag matches, then whole words in the code, first), then by index
+    #[structopt(long, possible_values = &SortBy::VARIANTS)]
+    pub(crate) sort: Option<SortBy>,
+    /// Show snippets in the opposite order
+    #[structopt(long)]
+    pub(crate) reverse: bool,
+    /// Only the first <n> snippets (the last <n> with --reverse)
+    #[structopt(long, value_name = "n")]
+    pub(crate) limit: Option<usize>,
+}
+
+/// Subcommands that take `Filters`, and so `@name`s
+const FILTERED_COMMANDS: [&str; 5] = ["search", "list", "stats", "triage", "export"];
+
+/// Replaces `@name` arguments of subcommands that take filters (see `FILTERED_COMMANDS`)
+/// with the filters saved as `name` (with `the-way filter save`).
+/// The config file is only read if there are any
+pub(crate) fn expand_saved_filters(args: Vec<OsString>) -> color_eyre::Result<Vec<OsString>> {
+    let saved_name = |arg: &OsString| {
+        arg.to_str()
+            .and_then(|arg| arg.strip_prefix('@'))
+            .map(String::from)
+    };
+    // Global options can come before the subcommand
+    let mut command = None;
+    let mut position = 1;
+    while let Some(arg) = args.get(position) {
+        match arg.to_str() {
+            Some("--error-format") => position += 2,
+            Some(arg) if arg.starts_with('-') => position += 1,
+            arg => {
+                command = arg;
+                break;
+            }
+        }
+    }
+    if !matches!(command, Some(command) if FILTERED_COMMANDS.contains(&command))
+        || args[position..].iter().all(|arg| saved_name(arg).is_none())
+    {
+        return Ok(args);
+    }
+    let config = TheWayConfig::load()?;
+    let mut expanded = args[..=position].to_vec();
+    for arg in &args[position + 1..] {
+        match saved_name(arg).and_then(|name| config.filters.get(&name)) {
+            Some(filters) => expanded.extend(filters.iter().map(OsString::from)),
+            None => expanded.push(arg.clone()),
+        }
+    }
+    Ok(expanded)
+}
+
+/// Whether `word` is in `text` with no letter, digit, or `_` right before or after it
+fn contains_word(text: &str, word: &str) -> bool {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(word).any(|(start, _)| {
+        !text[..start].chars().next_back().is_some_and(is_word_char)
+            && !text[start + word.len()..]
+                .chars()
+                .next()
+                .is_some_and(is_word_char)
+    })
+}
+
+/// How well `snippet` matches the `--code` `text` (lowercase), lower first:
+/// whether it's missing from the description and tags, then whether it's only part of a word in the code
+fn relevance(snippet: &Snippet, text: &str) -> (bool, bool) {
+    let in_metadata = snippet.description.to_lowercase().contains(text)
+        || snippet
+            .tags
+            .iter()
+            .any(|tag| tag.to_lowercase().contains(text));
+    (
+        !in_metadata,
+        !contains_word(&snippet.code.to_lowercase(), text),
+    )
+}
+
+/// `text` split at `separator`, or just `text` if that leaves an empty part (so tags like "c++" are kept whole)
+fn split_terms(text: &str, separator: char) -> Vec<&str> {
+    let parts: Vec<_> = text.split(separator).collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        vec![text]
+    } else {
+        parts
+    }
+}
+
+impl Filters {
+    /// The `--tags` as sets of tags a snippet needs all of, any set being enough:
+    /// "rust+cli python,go" is rust and cli, or python, or go
+    fn tag_sets(&self) -> Option<Vec<Vec<&str>>> {
+        self.tags.as_ref().map(|tags| {
+            tags.iter()
+                .flat_map(|term| split_terms(term, ','))
+                .map(|term| split_terms(term, '+'))
+                .collect()
+        })
+    }
+
+    /// Checks if a snippet has every tag in one of the `--tags` sets
+    fn tags_match(&self, snippet: &Snippet) -> bool {
+        self.tag_sets().map_or(true, |sets| {
+            sets.iter()
+                .any(|set| set.iter().all(|tag| snippet.has_tag(tag)))
+        })
+    }
+
+    /// Puts `snippets` in the `--sort` order, backwards with `--reverse`
+    pub(crate) fn sort(&self, snippets: &mut [Snippet]) {
+        snippets.sort_by_key(|snippet| snippet.index);
+        let default = if self.code.is_some() {
+            SortBy::Relevance
+        } else {
+            SortBy::Index
+        };
+        match self.sort.unwrap_or(default) {
+            SortBy::Index => {}
+            SortBy::Date => snippets.sort_by_key(|snippet| snippet.date),
+            SortBy::Language => {
+                snippets.sort_by_cached_key(|snippet| snippet.language.to_lowercase())
+            }
+            SortBy::Description => {
+                snippets.sort_by_cached_key(|snippet| snippet.description.to_lowercase())
+            }
+            SortBy::Updated => snippets.sort_by_key(|snippet| snippet.updated),
+            SortBy::Relevance => {
+                if let Some(text) = &self.code {
+                    let text = text.to_lowercase();
+                    snippets.sort_by_cached_key(|snippet| relevance(snippet, &text))
+                }
+            }
+        }
+        if self.reverse {
+            snippets.reverse();
+        }
+    }
+
+    /// Drops everything after the first `--limit` snippets
+    pub(crate) fn truncate<T>(&self, snippets: &mut Vec<T>) {
+        if let Some(limit) = self.limit {
+            snippets.truncate(limit);
+        }
+    }
+
+    /// Checks if a snippet passes all the filters
+    /// (for snippets that aren't in this profile's database)
+    pub(crate) fn matches(&self, snippet: &Snippet) -> bool {
+        snippet.in_date_range(utils::date_start(self.from), utils::date_end(self.to))
+            && self.matches_unindexed(snippet)
+            && self
+                .languages
+                .as_ref()
+                .map_or(true, |languages| languages.contains(&snippet.language))
+            && self.tags_match(snippet)
+    }
+
+    /// Checks the filters the language and tag trees can't: whether a snippet is at one of the `--indices`,
+    /// its code has the `--code` text in it, and it has none of the `--not-tag` tags and isn't in a `--not-lang` language
+    fn matches_unindexed(&self, snippet: &Snippet) -> bool {
+        self.indices
+            .as_ref()
+            .map_or(true, |indices| indices.contains(snippet.index))
+            && self.code.as_ref().map_or(true, |text| {
+                snippet.code.to_lowercase().contains(&text.to_lowercase())
+            })
+            && self
+                .not_tags
+                .as_ref()
+                .map_or(true, |tags| !tags.iter().any(|tag| snippet.has_tag(tag)))
+            && self.not_languages.as_ref().map_or(true, |languages| {
+                !languages
+                    .iter()
+                    .any(|language| language.eq_ignore_ascii_case(&snippet.language))
+            })
+    }
 }
 
 impl TheWay {
-    /// Filters a list of snippets by given language/tag/date
+    /// Filters a list of snippets by given language/tag/date/code (and languages and tags to leave out),
+    /// in the `--sort` order and cut off at the `--limit` (looking up the code in the search index, if there is one)
     pub(crate) fn filter_snippets(&self, filters: &Filters) -> color_eyre::Result<Vec<Snippet>> {
+        let indexed = match &filters.code {
+            Some(text) => self.search_index_lookup(text)?,
+            None => None,
+        };
+        let mut snippets = match indexed {
+            Some(indices) if filters.languages.is_none() && filters.tags.is_none() => {
+                let mut indices: Vec<_> = indices.into_iter().collect();
+                indices.sort_unstable();
+                Snippet::filter_in_date_range(
+                    self.get_snippets(&indices)?,
+                    utils::date_start(filters.from),
+                    utils::date_end(filters.to),
+                )?
+            }
+            Some(indices) => {
+                let mut snippets = self.filter_snippets_by_metadata(filters)?;
+                snippets.retain(|snippet| indices.contains(&snippet.index));
+                snippets
+            }
+            None => self.filter_snippets_by_metadata(filters)?,
+        };
+        snippets.retain(|snippet| filters.matches_unindexed(snippet));
+        filters.sort(&mut snippets);
+        filters.truncate(&mut snippets);
+        Ok(snippets)
+    }
+
+    /// Filters a list of snippets by given language/tag/date, using the language and tag trees
+    fn filter_snippets_by_metadata(&self, filters: &Filters) -> color_eyre::Result<Vec<Snippet>> {
         let from_date = utils::date_start(filters.from);
         let to_date = utils::date_end(filters.to);
         let snippets: Option<Vec<_>> = match &filters.languages {
@@ -41,25 +348,87 @@ impl TheWay {
             ),
             None => None,
         };
-        match (filters.tags.clone(), snippets) {
-            (Some(tags), Some(snippets)) => Ok(snippets
+        match (filters.tag_sets(), snippets) {
+            (Some(_), Some(snippets)) => Ok(snippets
                 .into_iter()
                 .filter(|snippet| {
-                    snippet.in_date_range(from_date, to_date)
-                        && tags.iter().any(|tag| snippet.has_tag(tag))
+                    snippet.in_date_range(from_date, to_date) && filters.tags_match(snippet)
                 })
                 .collect()),
-            (Some(tags), None) => {
-                let indices = tags
-                    .iter()
-                    .flat_map(|tag| self.get_tag_snippets(tag).unwrap_or_default())
-                    .collect::<HashSet<_>>()
-                    .into_iter()
-                    .collect::<Vec<_>>();
+            (Some(sets), None) => {
+                let mut indices = HashSet::new();
+                for set in sets {
+                    // Snippets with every tag in the set
+                    let mut in_set: Option<HashSet<usize>> = None;
+                    for tag in set {
+                        let tagged: HashSet<_> = self
+                            .get_tag_snippets(tag)
+                            .unwrap_or_default()
+                            .into_iter()
+                            .collect();
+                        in_set = Some(match in_set {
+                            Some(in_set) => in_set.intersection(&tagged).copied().collect(),
+                            None => tagged,
+                        });
+                    }
+                    indices.extend(in_set.unwrap_or_default());
+                }
+                let indices: Vec<_> = indices.into_iter().collect();
                 Snippet::filter_in_date_range(self.get_snippets(&indices)?, from_date, to_date)
             }
             (None, Some(snippets)) => Snippet::filter_in_date_range(snippets, from_date, to_date),
             (None, None) => self.list_snippets_in_date_range(from_date, to_date),
         }
     }
+
+    /// Saves `filters` (arguments, as given to `list`) in the config file as `name`, for `@name` to stand for them
+    pub(crate) fn save_filter(&mut self, name: &str, filters: &[String]) -> color_eyre::Result<()> {
+        let name = name.trim_start_matches('@');
+        let args = iter::once(String::from("filter")).chain(filters.iter().cloned());
+        if let Err(e) = Filters::from_iter_safe(args) {
+            let message = console::strip_ansi_codes(&e.message);
+            let error: color_eyre::Result<()> = Err(LostTheWay::UsageError {
+                message: message
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .trim_start_matches("error: ")
+                    .to_owned(),
+            }
+            .into());
+            return error.suggestion("Give the filters as you would to `the-way list`");
+        }
+        self.config
+            .filters
+            .insert(name.to_owned(), filters.to_vec());
+        self.config.store()?;
+        println!("Saved filter {}, use it as @{}", name, name);
+        Ok(())
+    }
+
+    /// Prints each saved filter's name and arguments
+    pub(crate) fn list_filters(&self) -> color_eyre::Result<()> {
+        if self.config.filters.is_empty() {
+            println!("No saved filters");
+        }
+        for (name, filters) in &self.config.filters {
+            println!("@{}: {}", name, filters.join(" "));
+        }
+        Ok(())
+    }
+
+    /// Removes the filter saved as `name` from the config file
+    pub(crate) fn delete_filter(&mut self, name: &str) -> color_eyre::Result<()> {
+        let name = name.trim_start_matches('@');
+        if self.config.filters.remove(name).is_none() {
+            let error: color_eyre::Result<()> = Err(LostTheWay::UsageError {
+                message: format!("No saved filter named {}", name),
+            }
+            .into());
+            return error.suggestion("See the saved filters with `the-way filter list`");
+        }
+        self.config.store()?;
+        println!("Deleted filter {}", name);
+        Ok(())
+    }
 }
@@ -0,0 +1,103 @@
+//! Mirrors snippets into a git repository (`git_repository` in the config), committing on every change
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use crate::errors::LostTheWay;
+use crate::the_way::{snippet, undo::UndoEntry, TheWay};
+
+/// File with every snippet's full information, one JSON object per line (importable with `the-way import`)
+const SNIPPETS_FILE: &str = "snippets.json";
+
+/// Runs git in `repository`
+fn git(repository: &Path, args: &[&str]) -> color_eyre::Result<Output> {
+    Ok(Command::new("git")
+        .arg("-C")
+        .arg(repository)
+        .args(args)
+        .output()
+        .map_err(|e| LostTheWay::GitError {
+            message: format!("Couldn't run git: {}", e),
+        })?)
+}
+
+/// Like `git`, but failing is an error
+fn git_checked(repository: &Path, args: &[&str]) -> color_eyre::Result<()> {
+    let output = git(repository, args)?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(LostTheWay::GitError {
+            message: format!(
+                "`git {}` failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        }
+        .into())
+    }
+}
+
+impl TheWay {
+    /// Records a change for `the-way undo` and commits it to the git repository if there is one
+    pub(crate) fn record_change(&self, entry: &UndoEntry) -> color_eyre::Result<()> {
+        self.add_undo_entry(entry)?;
+        self.commit_to_git(&entry.describe());
+        Ok(())
+    }
+
+    /// Writes all snippets to the git repository and commits them with `message`.
+    /// The snippet is already saved to the database by now, so problems are only warned about
+    pub(crate) fn commit_to_git(&self, message: &str) {
+        if let Some(repository) = &self.config.git_repository {
+            if let Err(e) = self.write_to_git(repository, message) {
+                eprintln!(
+                    "Couldn't commit snippets to {}: {}",
+                    repository.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    /// Writes each snippet's code to snippet_<index>.<ext> and everything to snippets.json,
+    /// then commits if anything changed (making the repository the first time)
+    fn write_to_git(&self, repository: &Path, message: &str) -> color_eyre::Result<()> {
+        if !repository.join(".git").exists() {
+            fs::create_dir_all(repository)?;
+            git_checked(repository, &["init", "-q"])?;
+        }
+        // Start from scratch so deleted snippets disappear
+        for entry in fs::read_dir(repository)? {
+            let path = entry?.path();
+            if path.is_file()
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with("snippet_"))
+            {
+                fs::remove_file(path)?;
+            }
+        }
+        let mut snippets = self.list_snippets()?;
+        snippets.sort_by_key(|snippet| snippet.index);
+        let mut json = Vec::new();
+        for snippet in &snippets {
+            fs::write(
+                repository.join(format!("snippet_{}{}", snippet.index, snippet.extension)),
+                &snippet.code,
+            )?;
+            snippet.to_json_with_fields(&mut json, &snippet::FIELDS)?;
+            json.push(b'\n');
+        }
+        fs::write(repository.join(SNIPPETS_FILE), json)?;
+        git_checked(repository, &["add", "-A"])?;
+        // Exits with 1 if there are staged changes
+        if !git(repository, &["diff", "--cached", "--quiet"])?
+            .status
+            .success()
+        {
+            git_checked(repository, &["commit", "-q", "-m", message])?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+//! Copy history for `the-way history` and `the-way cp --last`
+use color_eyre::Help;
+
+use crate::errors::LostTheWay;
+use crate::language::{CodeHighlight, Language};
+use crate::the_way::TheWay;
+
+impl TheWay {
+    /// Copies the most recently copied snippet that still exists
+    pub(crate) fn copy_last(&self) -> color_eyre::Result<()> {
+        let index = self
+            .get_copy_history()?
+            .into_iter()
+            .map(|(_, index)| index)
+            .find(|index| self.get_snippet(*index).is_ok())
+            .ok_or(LostTheWay::NoCopyHistory)
+            .suggestion("Copy a snippet with `the-way cp <index>` or `the-way search` first")?;
+        self.copy(index)
+    }
+
+    /// Lists the last `number` copies, most recent first (skipping deleted snippets)
+    pub(crate) fn history(&self, number: usize) -> color_eyre::Result<()> {
+        let default_language = Language::default();
+        let mut lines = Vec::new();
+        for (time, index) in self.get_copy_history()? {
+            if lines.len() == number {
+                break;
+            }
+            let snippet = match self.get_snippet(index) {
+                Ok(snippet) => snippet,
+                Err(_) => continue,
+            };
+            let time = time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+            lines.push(if self.cli.accessible {
+                format!("Copied {}. {}\n", time, snippet.plain_header())
+            } else {
+                format!(
+                    "{} {}",
+                    CodeHighlight::highlight_string(
+                        &time.to_string(),
+                        self.highlighter.accent_style
+                    ),
+                    snippet
+                        .pretty_print_header(
+                            &self.highlighter,
+                            self.languages
+                                .get(&snippet.language)
+                                .unwrap_or(&default_language),
+                        )?
+                        .join("")
+                )
+            });
+        }
+        if lines.is_empty() {
+            println!("No copied snippets");
+            return Ok(());
+        }
+        for line in lines {
+            print!("{}", line);
+        }
+        Ok(())
+    }
+}
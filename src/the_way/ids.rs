@@ -0,0 +1,162 @@
+//! How snippet indices are shown (the `[display]` config section): zero-padded,
+//! or as short hex / base36 IDs derived from a UUID that each snippet gets the first time it's needed.
+//! Commands taking an index also take these short IDs
+use std::str::FromStr;
+
+use color_eyre::Help;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::errors::LostTheWay;
+use crate::the_way::TheWay;
+
+/// Length of hex short IDs (the first 32 bits of the UUID)
+const HEX_LENGTH: usize = 8;
+/// Length of base36 short IDs (the same 32 bits)
+const BASE36_LENGTH: usize = 7;
+const BASE36_DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// How snippets are labelled in list, search, and view
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum IndexFormat {
+    /// The index itself, zero-padded to `index_width`
+    Decimal,
+    Hex,
+    Base36,
+}
+
+impl IndexFormat {
+    /// Possible format names
+    pub(crate) const VARIANTS: [&'static str; 3] = ["decimal", "hex", "base36"];
+}
+
+impl FromStr for IndexFormat {
+    type Err = LostTheWay;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "decimal" => Ok(Self::Decimal),
+            "hex" => Ok(Self::Hex),
+            "base36" => Ok(Self::Base36),
+            other => Err(LostTheWay::ConfigError {
+                message: format!("Unknown index format {:?}", other),
+            }),
+        }
+    }
+}
+
+/// A snippet given on the command line, by index or by short ID
+#[derive(Debug, Clone)]
+pub struct SnippetId(String);
+
+impl FromStr for SnippetId {
+    type Err = LostTheWay;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let id = s.trim().trim_start_matches('#').to_ascii_lowercase();
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(LostTheWay::OutOfCheeseError {
+                message: format!("{:?} isn't a snippet index or ID", s),
+            });
+        }
+        Ok(Self(id))
+    }
+}
+
+/// A random (version 4) UUID
+pub(crate) fn new_uuid() -> color_eyre::Result<String> {
+    let mut bytes = [0; 16];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| LostTheWay::OutOfCheeseError {
+            message: String::from("Couldn't get random bytes for a snippet UUID"),
+        })?;
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+/// Short ID of a snippet with `uuid`, in `format` (`None` for decimal)
+fn short_id(uuid: &str, format: IndexFormat) -> Option<String> {
+    let hex = &uuid.replace('-', "")[..HEX_LENGTH];
+    match format {
+        IndexFormat::Decimal => None,
+        IndexFormat::Hex => Some(hex.to_owned()),
+        IndexFormat::Base36 => {
+            let mut value = u32::from_str_radix(hex, 16).ok()?;
+            let mut digits = vec![b'0'; BASE36_LENGTH];
+            for digit in digits.iter_mut().rev() {
+                *digit = BASE36_DIGITS[(value % 36) as usize];
+                value /= 36;
+            }
+            String::from_utf8(digits).ok()
+        }
+    }
+}
+
+impl TheWay {
+    fn index_format(&self) -> color_eyre::Result<IndexFormat> {
+        match &self.config.display.index_format {
+            Some(format) => format.parse().suggestion(format!(
+                "Set `index_format` in the [display] section of your config file to one of {}",
+                IndexFormat::VARIANTS.join(", ")
+            )),
+            None => Ok(IndexFormat::Decimal),
+        }
+    }
+
+    /// How the snippet at `index` is labelled, e.g. "0012" or "3f9a2c1b"
+    pub(crate) fn index_label(&self, index: usize) -> color_eyre::Result<String> {
+        match self.index_format()? {
+            IndexFormat::Decimal => Ok(format!(
+                "{:0width$}",
+                index,
+                width = self.config.display.index_width.unwrap_or_default()
+            )),
+            format => Ok(short_id(&self.get_uuid(index)?, format).unwrap_or_default()),
+        }
+    }
+
+    /// Index of the snippet with this index, or with a short ID starting with `id`
+    pub(crate) fn resolve_index(&self, id: &SnippetId) -> color_eyre::Result<usize> {
+        let index = id.0.parse::<usize>().ok();
+        let format = self.index_format()?;
+        if index.is_some_and(|index| self.get_snippet(index).is_ok())
+            || format == IndexFormat::Decimal
+        {
+            return index
+                .ok_or(LostTheWay::SnippetIdNotFound { id: id.0.clone() })
+                .suggestion("The index of a snippet is in its title after a #");
+        }
+        let matches: Vec<_> = self
+            .get_uuids()?
+            .into_iter()
+            .filter(|(_, uuid)| {
+                short_id(uuid, format).is_some_and(|short| short.starts_with(&id.0))
+            })
+            .map(|(index, _)| index)
+            .collect();
+        match (matches.as_slice(), index) {
+            ([index], _) => Ok(*index),
+            // Shows that no snippet has this index
+            ([], Some(index)) => Ok(index),
+            ([], None) => {
+                let error: color_eyre::Result<usize> =
+                    Err(LostTheWay::SnippetIdNotFound { id: id.0.clone() }.into());
+                error.suggestion("The ID of a snippet is in its title after a #")
+            }
+            _ => {
+                let error: color_eyre::Result<usize> =
+                    Err(LostTheWay::AmbiguousId { id: id.0.clone() }.into());
+                error.suggestion("Type more of the ID")
+            }
+        }
+    }
+}
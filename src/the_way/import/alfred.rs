@@ -0,0 +1,67 @@
+//! `the-way import --from alfred`: an Alfred snippet collection exported as an `.alfredsnippets` archive
+//! (or one of its JSON files, or a folder of them): each snippet as a text snippet, tagged with its keyword
+use std::io;
+
+use chrono::Utc;
+
+use crate::the_way::import::{as_tag, import_error};
+use crate::the_way::snippet::Snippet;
+use crate::zip;
+
+#[derive(Deserialize, Debug)]
+struct AlfredFile {
+    alfredsnippet: AlfredSnippet,
+}
+
+#[derive(Deserialize, Debug)]
+struct AlfredSnippet {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    keyword: String,
+    snippet: String,
+}
+
+impl AlfredSnippet {
+    fn into_snippet(self) -> Snippet {
+        let description = [&self.name, &self.keyword]
+            .iter()
+            .find(|description| !description.trim().is_empty())
+            .map_or_else(
+                || self.snippet.lines().next().unwrap_or_default().to_owned(),
+                |description| description.to_string(),
+            );
+        Snippet::new(
+            0,
+            description,
+            String::from("text"),
+            String::new(),
+            &as_tag(&self.keyword),
+            Utc::now(),
+            Utc::now(),
+            format!("{}\n", self.snippet.trim_end()),
+        )
+    }
+}
+
+fn parse(json: &[u8]) -> color_eyre::Result<Snippet> {
+    let file: AlfredFile =
+        serde_json::from_slice(json).map_err(|e| import_error("an Alfred snippet", e))?;
+    Ok(file.alfredsnippet.into_snippet())
+}
+
+/// Snippets in an .alfredsnippets archive, or the one snippet in one of its JSON files
+pub(super) fn read(reader: &mut dyn io::Read) -> color_eyre::Result<Vec<Snippet>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    if !zip::is_zip(&bytes) {
+        return Ok(vec![parse(&bytes)?]);
+    }
+    let files = zip::files(&bytes).map_err(|e| import_error("an Alfred snippets archive", e))?;
+    // Besides a JSON file for each snippet there's an info.plist with the collection's settings
+    files
+        .into_iter()
+        .filter(|(name, _)| name.ends_with(".json"))
+        .map(|(_, json)| parse(&json))
+        .collect()
+}
@@ -0,0 +1,214 @@
+//! `the-way import --from boostnote`: Boostnote notes (`.cson` files, or a folder of them).
+//! Each fenced code block of a Markdown note, and each snippet of a snippet note, is a snippet
+//! described by the note's title and tagged with the note's tags. Notes in the trash are left out
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+
+use crate::language::Language;
+use crate::the_way::import::{
+    as_tag, fragment_snippets, import_error, known_language, markdown, Fragment,
+};
+use crate::the_way::snippet::Snippet;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Note {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Markdown, for "MARKDOWN_NOTE"s
+    #[serde(default)]
+    content: String,
+    /// Code, for "SNIPPET_NOTE"s
+    #[serde(default)]
+    snippets: Vec<NoteSnippet>,
+    #[serde(default)]
+    is_trashed: bool,
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct NoteSnippet {
+    #[serde(default)]
+    name: String,
+    /// Editor mode, e.g. "javascript"
+    #[serde(default)]
+    mode: String,
+    #[serde(default)]
+    content: String,
+}
+
+/// Just enough CSON for Boostnote notes: `key: value` lines, strings (''' for multi-line), numbers,
+/// booleans, and arrays and objects opened at the end of a line and closed on their own line
+struct Cson<'a> {
+    lines: Vec<&'a str>,
+    position: usize,
+}
+
+impl<'a> Cson<'a> {
+    fn parse(text: &'a str) -> Result<Value, String> {
+        let mut cson = Cson {
+            lines: text.lines().collect(),
+            position: 0,
+        };
+        cson.object(None)
+    }
+
+    fn next_line(&mut self) -> Option<&'a str> {
+        let line = self.lines.get(self.position).copied();
+        self.position += 1;
+        line
+    }
+
+    /// `key: value` lines up to the line `closing` (or the end)
+    fn object(&mut self, closing: Option<&str>) -> Result<Value, String> {
+        let mut object = Map::new();
+        while let Some(line) = self.next_line() {
+            let line = line.trim();
+            if Some(line) == closing {
+                return Ok(Value::Object(object));
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| format!("expected `key: value`, found {:?}", line))?;
+            let key = key.trim().trim_matches('"').trim_matches('\'').to_owned();
+            let value = self.value(value.trim())?;
+            object.insert(key, value);
+        }
+        match closing {
+            Some(closing) => Err(format!("missing {:?}", closing)),
+            None => Ok(Value::Object(object)),
+        }
+    }
+
+    /// Items on their own lines up to the line "]"
+    fn array(&mut self) -> Result<Value, String> {
+        let mut array = Vec::new();
+        while let Some(line) = self.next_line() {
+            let line = line.trim().trim_end_matches(',');
+            match line {
+                "]" => return Ok(Value::Array(array)),
+                "" => continue,
+                line => array.push(self.value(line)?),
+            }
+        }
+        Err(String::from("missing \"]\""))
+    }
+
+    /// Lines up to ''', less their shared indentation
+    fn multi_line_string(&mut self) -> Result<Value, String> {
+        let mut lines = Vec::new();
+        while let Some(line) = self.next_line() {
+            if line.trim() == "'''" {
+                let indent = lines
+                    .iter()
+                    .filter(|line: &&&str| !line.trim().is_empty())
+                    .map(|line| line.len() - line.trim_start().len())
+                    .min()
+                    .unwrap_or(0);
+                let text: Vec<_> = lines
+                    .iter()
+                    .map(|line: &&str| line.get(indent..).unwrap_or_default())
+                    .collect();
+                return Ok(Value::String(unescape(&text.join("\n"))));
+            }
+            lines.push(line);
+        }
+        Err(String::from("missing \"'''\""))
+    }
+
+    fn value(&mut self, text: &str) -> Result<Value, String> {
+        Ok(match text {
+            "'''" => self.multi_line_string()?,
+            "[" => self.array()?,
+            "{" => self.object(Some("}"))?,
+            "[]" => Value::Array(Vec::new()),
+            "{}" => Value::Object(Map::new()),
+            "true" | "yes" | "on" => Value::Bool(true),
+            "false" | "no" | "off" => Value::Bool(false),
+            "null" => Value::Null,
+            text if text.len() >= 2 && (text.starts_with('"') || text.starts_with('\'')) => {
+                Value::String(unescape(&text[1..text.len() - 1]))
+            }
+            text if text.starts_with('[') && text.ends_with(']') => Value::Array(
+                text[1..text.len() - 1]
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| self.value(item))
+                    .collect::<Result<_, _>>()?,
+            ),
+            text => serde_json::from_str(text)
+                .map_err(|_| format!("can't read the value {:?}", text))?,
+        })
+    }
+}
+
+/// `text` with CoffeeScript string escapes (\n, \t, \\, \', \") replaced
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Snippets in the Boostnote note `text` (with `join`, all of a note's code is joined into one)
+pub(super) fn read(
+    text: &str,
+    languages: &HashMap<String, Language>,
+    join: bool,
+) -> color_eyre::Result<Vec<Snippet>> {
+    let value = Cson::parse(text).map_err(|e| import_error("a Boostnote note", e))?;
+    let note: Note =
+        serde_json::from_value(value).map_err(|e| import_error("a Boostnote note", e))?;
+    if note.is_trashed {
+        return Ok(Vec::new());
+    }
+    let mut fragments: Vec<_> = note
+        .snippets
+        .into_iter()
+        .map(|snippet| Fragment {
+            label: snippet.name,
+            language: known_language(&snippet.mode, languages),
+            code: snippet.content,
+        })
+        .collect();
+    fragments.extend(
+        markdown::code_blocks(&note.content)
+            .into_iter()
+            .map(|block| Fragment {
+                label: String::new(),
+                language: known_language(&block.language, languages),
+                code: block.code,
+            }),
+    );
+    let tags: Vec<_> = note.tags.iter().map(|tag| as_tag(tag)).collect();
+    let date = note.created_at.unwrap_or_else(Utc::now);
+    Ok(fragment_snippets(
+        &note.title,
+        &tags,
+        fragments,
+        join,
+        (date, note.updated_at.unwrap_or(date)),
+    ))
+}
@@ -0,0 +1,101 @@
+//! `the-way import --from cheat`: cheatsheets for [cheat](https://github.com/cheat/cheat) and cheat.sh,
+//! a plain text file per command (with optional YAML front matter giving its `syntax` and `tags`),
+//! where each `#` comment describes the lines under it
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::language::Language;
+use crate::the_way::import::{as_tag, import_error, known_language};
+use crate::the_way::snippet::Snippet;
+
+/// A cheatsheet's front matter
+#[derive(Deserialize, Debug, Default)]
+struct FrontMatter {
+    #[serde(default)]
+    syntax: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// A cheatsheet's front matter (if it has any) and the rest of it
+fn front_matter(text: &str) -> color_eyre::Result<(FrontMatter, &str)> {
+    let rest = match text.strip_prefix("---\n") {
+        Some(rest) => rest,
+        None => return Ok((FrontMatter::default(), text)),
+    };
+    let (yaml, body) = match rest.find("\n---\n") {
+        Some(end) => (&rest[..end], &rest[end + 5..]),
+        None => match rest.strip_suffix("\n---") {
+            Some(yaml) => (yaml, ""),
+            None => return Err(import_error("cheat", "the front matter doesn't end")),
+        },
+    };
+    if yaml.trim().is_empty() {
+        return Ok((FrontMatter::default(), body));
+    }
+    let front_matter = serde_yaml::from_str(yaml).map_err(|e| import_error("cheat", e))?;
+    Ok((front_matter, body))
+}
+
+/// A snippet for each commented group of lines in the cheatsheet for the command `name`,
+/// tagged with the command and the sheet's tags
+pub(super) fn read(
+    text: &str,
+    name: Option<&str>,
+    languages: &HashMap<String, Language>,
+) -> color_eyre::Result<Vec<Snippet>> {
+    let text = text.replace("\r\n", "\n");
+    let (front_matter, body) = front_matter(&text)?;
+    let language = front_matter.syntax.as_deref().map_or_else(
+        || String::from("sh"),
+        |syntax| known_language(syntax, languages),
+    );
+    let mut tags: Vec<_> = name.map(as_tag).into_iter().collect();
+    for tag in front_matter.tags {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    let mut snippets = Vec::new();
+    let mut comment: Vec<&str> = Vec::new();
+    let mut code: Vec<&str> = Vec::new();
+    let mut flush = |comment: &mut Vec<&str>, code: &mut Vec<&str>| {
+        if code.is_empty() {
+            return;
+        }
+        let description = comment.join(" ");
+        let description = description.trim_end_matches(':').trim();
+        snippets.push(Snippet::new(
+            0,
+            if description.is_empty() {
+                name.unwrap_or("Cheatsheet").to_owned()
+            } else {
+                description.to_owned()
+            },
+            language.to_owned(),
+            String::new(),
+            &tags.join(" "),
+            Utc::now(),
+            Utc::now(),
+            format!("{}\n", code.join("\n")),
+        ));
+        comment.clear();
+        code.clear();
+    };
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            // A comment with nothing under it (like a sheet's title) describes nothing
+            flush(&mut comment, &mut code);
+            comment.clear();
+        } else if let Some(text) = trimmed.strip_prefix('#') {
+            flush(&mut comment, &mut code);
+            comment.push(text.trim());
+        } else {
+            code.push(line.trim_end());
+        }
+    }
+    flush(&mut comment, &mut code);
+    Ok(snippets)
+}
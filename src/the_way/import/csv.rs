@@ -0,0 +1,109 @@
+//! `the-way import --format csv`: a table with a header row naming its columns (description, language, tags,
+//! code, and date, any others are left out), like the ones `export --format csv` writes
+use std::io;
+
+use chrono::Utc;
+
+use crate::the_way::import::{import_error, parse_date, read_to_string};
+use crate::the_way::snippet::Snippet;
+
+/// Rows of cells in CSV `text`, where quoted cells can have commas, quotes (as ""), and line breaks
+fn records(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if cell.is_empty() => quoted = true,
+            c if quoted => cell.push(c),
+            ',' => record.push(std::mem::take(&mut cell)),
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\n' | '\r' => {
+                record.push(std::mem::take(&mut cell));
+                records.push(std::mem::take(&mut record));
+            }
+            c => cell.push(c),
+        }
+    }
+    if quoted {
+        return Err(String::from("a quoted cell doesn't end"));
+    }
+    if !cell.is_empty() || !record.is_empty() {
+        record.push(cell);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Snippets in the rows of a CSV table
+pub(super) fn read(reader: &mut dyn io::Read) -> color_eyre::Result<Vec<Snippet>> {
+    let records = records(&read_to_string(reader)?).map_err(|e| import_error("CSV", e))?;
+    let mut rows = records.into_iter();
+    let header: Vec<_> = rows
+        .next()
+        .unwrap_or_default()
+        .iter()
+        .map(|column| column.trim().to_ascii_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|column| column == name);
+    let code_column = column("code")
+        .ok_or_else(|| import_error("CSV", "there's no code column in the header row"))?;
+    let (description_column, language_column, tags_column, date_column) = (
+        column("description"),
+        column("language"),
+        column("tags"),
+        column("date"),
+    );
+    let mut snippets = Vec::new();
+    // Rows are numbered as in a spreadsheet, after the header row
+    for (number, row) in rows.enumerate().map(|(i, row)| (i + 2, row)) {
+        if row.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+        let get = |column: Option<usize>| {
+            column
+                .and_then(|column| row.get(column))
+                .map_or("", |cell| cell.as_str())
+        };
+        let code = get(Some(code_column));
+        let date = match get(date_column).trim() {
+            "" => Utc::now(),
+            date => parse_date(date).ok_or_else(|| {
+                import_error(
+                    "CSV",
+                    format!("row {} has an unknown date {:?}", number, date),
+                )
+            })?,
+        };
+        let language = match get(language_column).trim() {
+            "" => "text",
+            language => language,
+        };
+        let description = match get(description_column).trim() {
+            "" => code.lines().next().unwrap_or_default(),
+            description => description,
+        };
+        let tags: Vec<_> = get(tags_column)
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        snippets.push(Snippet::new(
+            0,
+            description.to_owned(),
+            language.to_ascii_lowercase(),
+            String::new(),
+            &tags.join(" "),
+            date,
+            date,
+            code.to_owned(),
+        ));
+    }
+    Ok(snippets)
+}
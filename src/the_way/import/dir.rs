@@ -0,0 +1,94 @@
+//! `the-way import --dir <folder>`: every text file in a folder and its subfolders as a snippet,
+//! described by its file name, in the language of its extension
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use crate::language::Language;
+use crate::the_way::import::as_tag;
+use crate::the_way::{clipboard_watch::guess_language, snippet::Snippet};
+
+/// Files in `dir` and its subfolders, sorted, leaving out hidden ones (like .git)
+pub(super) fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> color_eyre::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        // Symlinked folders aren't followed, they could go round in circles
+        if entry.file_type()?.is_dir() {
+            walk(&path, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Language of a file with `extension`, guessed from `code` if no language uses the extension
+fn file_language(extension: &str, code: &str, languages: &HashMap<String, Language>) -> String {
+    let guess = guess_language(code);
+    if languages
+        .values()
+        .any(|language| language.extension == extension)
+    {
+        Language::from_extension(extension, &guess, languages)
+    } else {
+        guess
+    }
+}
+
+/// A snippet for each text file under `dir`, with `dir_tags`, tagged with the subfolders it's in
+pub(super) fn read(
+    dir: &Path,
+    dir_tags: bool,
+    languages: &HashMap<String, Language>,
+) -> color_eyre::Result<Vec<Snippet>> {
+    let mut files = Vec::new();
+    walk(dir, &mut files)?;
+    let mut snippets = Vec::new();
+    let mut skipped = 0;
+    for file in files {
+        let code = match String::from_utf8(fs::read(&file)?) {
+            Ok(code) if !code.trim().is_empty() && !code.contains('\0') => code,
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let extension = name.rfind('.').map_or("", |dot| &name[dot..]);
+        let tags: Vec<_> = if dir_tags {
+            file.strip_prefix(dir)?
+                .parent()
+                .map(|parent| {
+                    parent
+                        .iter()
+                        .map(|folder| as_tag(&folder.to_string_lossy()))
+                        .collect()
+                })
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let modified: DateTime<Utc> = fs::metadata(&file)?.modified()?.into();
+        snippets.push(Snippet::new(
+            0,
+            name.to_string(),
+            file_language(extension, &code, languages),
+            String::new(),
+            &tags.join(" "),
+            modified,
+            modified,
+            code,
+        ));
+    }
+    if skipped > 0 {
+        eprintln!("Skipped {} empty or binary file(s)", skipped);
+    }
+    Ok(snippets)
+}
@@ -0,0 +1,59 @@
+//! `the-way import --gist <username>`: every file of a GitHub user's Gists as a snippet
+use crate::gist::GistClient;
+use crate::http::HttpClient;
+use crate::language::Language;
+use crate::the_way::import::as_tag;
+use crate::the_way::{clipboard_watch::guess_language, snippet::Snippet, sync::USER_AGENT, TheWay};
+use crate::utils;
+
+impl TheWay {
+    /// Each file of `username`'s Gists, taking the Gist's description, the file's language, and its name as a tag.
+    /// Only public Gists, unless there's a GitHub access token belonging to `username`
+    pub(super) fn read_gists(&self, username: &str) -> color_eyre::Result<Vec<Snippet>> {
+        let access_token = std::env::var("THE_WAY_GITHUB_TOKEN")
+            .ok()
+            .or_else(|| self.config.github_access_token.clone());
+        let http = HttpClient::new(&self.config.http);
+        let client = match &access_token {
+            Some(access_token) => GistClient::new(http, access_token, USER_AGENT)?,
+            None => GistClient::anonymous(http, USER_AGENT)?,
+        };
+        let spinner = utils::get_spinner(&format!("Fetching {}'s Gists...", username));
+        // Listing the token's own Gists includes secret ones
+        let own = access_token.is_some() && client.login()?.eq_ignore_ascii_case(username);
+        let gists = client.list_gists(if own { None } else { Some(username) })?;
+        let mut snippets = Vec::new();
+        for gist in gists {
+            let mut files: Vec<_> = gist.files.into_values().collect();
+            files.sort_by(|a, b| a.filename.cmp(&b.filename));
+            for file in files {
+                let code = client.get_raw(&file.raw_url)?;
+                let extension = file
+                    .filename
+                    .rfind('.')
+                    .map_or("", |dot| &file.filename[dot..]);
+                let guess = file
+                    .language
+                    .as_ref()
+                    .map(|language| language.to_ascii_lowercase())
+                    .unwrap_or_else(|| guess_language(&code));
+                let description = match &gist.description {
+                    Some(description) if !description.trim().is_empty() => description.to_owned(),
+                    _ => file.filename.to_owned(),
+                };
+                snippets.push(Snippet::new(
+                    0,
+                    description,
+                    Language::from_extension(extension, &guess, &self.languages),
+                    extension.to_owned(),
+                    &as_tag(&file.filename),
+                    gist.created_at,
+                    gist.updated_at,
+                    code,
+                ));
+            }
+        }
+        spinner.finish_with_message(&format!("Fetched {} files", snippets.len()));
+        Ok(snippets)
+    }
+}
@@ -0,0 +1,188 @@
+//! `the-way import --history`: commands from bash, zsh, or fish history, picked in a search window
+//! and saved as sh snippets, asking for a description of each
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use color_eyre::Help;
+
+use crate::errors::LostTheWay;
+use crate::the_way::{snippet::Snippet, TheWay};
+use crate::utils;
+
+/// Shells whose history can be read
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// The shell that writes history to `file`, going by its name (bash if it's none of the others)
+    fn of_file(file: &Path) -> Self {
+        let name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        if name.contains("fish") {
+            Self::Fish
+        } else if name.contains("zsh") || name.contains("zhistory") {
+            Self::Zsh
+        } else {
+            Self::Bash
+        }
+    }
+}
+
+/// The history file of the current shell: $HISTFILE if it's exported, otherwise the default for $SHELL
+fn history_file() -> color_eyre::Result<PathBuf> {
+    if let Some(file) = env::var_os("HISTFILE").filter(|file| !file.is_empty()) {
+        return Ok(PathBuf::from(file));
+    }
+    let home = directories_next::BaseDirs::new()
+        .map(|dirs| dirs.home_dir().to_path_buf())
+        .ok_or(LostTheWay::Homeless)?;
+    let shell = env::var("SHELL").unwrap_or_default();
+    Ok(if shell.ends_with("fish") {
+        env::var_os("XDG_DATA_HOME")
+            .map_or_else(|| home.join(".local").join("share"), PathBuf::from)
+            .join("fish")
+            .join("fish_history")
+    } else if shell.ends_with("zsh") {
+        env::var_os("ZDOTDIR")
+            .map_or(home, PathBuf::from)
+            .join(".zsh_history")
+    } else {
+        home.join(".bash_history")
+    })
+}
+
+/// zsh's history with "metafied" bytes (0x83, then the byte XOR 32) as they are
+fn unmetafy(bytes: &[u8]) -> Vec<u8> {
+    let mut plain = Vec::with_capacity(bytes.len());
+    let mut bytes = bytes.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte == 0x83 {
+            if let Some(&next) = bytes.next() {
+                plain.push(next ^ 32);
+            }
+        } else {
+            plain.push(byte);
+        }
+    }
+    plain
+}
+
+/// Commands in a history file written by `shell`, oldest first
+fn commands(history: &str, shell: Shell) -> Vec<String> {
+    let mut commands = Vec::new();
+    match shell {
+        // Lines, or with HISTTIMEFORMAT, "#<timestamp>" lines before each command
+        Shell::Bash => commands.extend(
+            history
+                .lines()
+                .filter(|line| {
+                    !(line.starts_with('#') && line[1..].chars().all(|c| c.is_ascii_digit()))
+                })
+                .map(str::to_owned),
+        ),
+        // ": <timestamp>:<duration>;<command>" with extended history, and a \ at the end of all but
+        // the last line of multi-line commands
+        Shell::Zsh => {
+            let mut command: Option<String> = None;
+            for line in history.lines() {
+                let line = match command.take() {
+                    Some(start) => format!("{}\n{}", start, line),
+                    None if line.starts_with(": ") => line
+                        .split_once(';')
+                        .map_or_else(|| line.to_owned(), |(_, command)| command.to_owned()),
+                    None => line.to_owned(),
+                };
+                match line.strip_suffix('\\') {
+                    Some(start) => command = Some(start.to_owned()),
+                    None => commands.push(line),
+                }
+            }
+            commands.extend(command);
+        }
+        // "- cmd: <command>" (with \n and \\ escapes), then "  when:" and "  paths:" lines
+        Shell::Fish => commands.extend(history.lines().filter_map(|line| {
+            line.strip_prefix("- cmd: ").map(|command| {
+                let mut unescaped = String::with_capacity(command.len());
+                let mut chars = command.chars();
+                while let Some(c) = chars.next() {
+                    match (c, chars.clone().next()) {
+                        ('\\', Some('n')) => {
+                            chars.next();
+                            unescaped.push('\n');
+                        }
+                        ('\\', Some('\\')) => {
+                            chars.next();
+                            unescaped.push('\\');
+                        }
+                        (c, _) => unescaped.push(c),
+                    }
+                }
+                unescaped
+            })
+        })),
+    }
+    commands
+}
+
+impl TheWay {
+    /// Snippets for the commands picked from the history in `file` (or the current shell's),
+    /// newest first and each only once
+    pub(super) fn read_history(&self, file: Option<&Path>) -> color_eyre::Result<Vec<Snippet>> {
+        let file = match file {
+            Some(file) => file.to_path_buf(),
+            None => history_file()?,
+        };
+        let bytes = fs::read(&file)
+            .map_err(|e| LostTheWay::ImportError {
+                message: format!("Couldn't read history file {}: {}", file.display(), e),
+            })
+            .suggestion("Give the history file, e.g. `the-way import --history ~/.bash_history`")?;
+        let shell = Shell::of_file(&file);
+        let bytes = if shell == Shell::Zsh {
+            unmetafy(&bytes)
+        } else {
+            bytes
+        };
+        let mut seen = HashSet::new();
+        let commands: Vec<_> = commands(&String::from_utf8_lossy(&bytes), shell)
+            .into_iter()
+            .rev()
+            .map(|command| command.trim().to_owned())
+            .filter(|command| !command.is_empty() && seen.insert(command.to_owned()))
+            .collect();
+        if commands.is_empty() {
+            let error: color_eyre::Result<Vec<Snippet>> = Err(LostTheWay::ImportError {
+                message: format!("There are no commands in {}", file.display()),
+            }
+            .into());
+            return error.suggestion(
+                "Give the history file, e.g. `the-way import --history ~/.zsh_history`",
+            );
+        }
+        let mut snippets = Vec::new();
+        for command in self.pick_lines(commands)? {
+            println!("{}", command);
+            let description = utils::user_input("Description", None, false, false)?;
+            snippets.push(Snippet::new(
+                0,
+                description,
+                String::from("sh"),
+                String::new(),
+                "",
+                Utc::now(),
+                Utc::now(),
+                format!("{}\n", command),
+            ));
+        }
+        Ok(snippets)
+    }
+}
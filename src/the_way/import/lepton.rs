@@ -0,0 +1,116 @@
+//! `the-way import --from lepton`: Gists as Lepton caches them (GitHub's Gist JSON, a list or by ID).
+//! Markdown files give a snippet per fenced code block, other files are a snippet each.
+//! Lepton keeps a title and tags in the Gist description: "[title] description #tags: tag1, tag2"
+use std::collections::HashMap;
+use std::io;
+
+use chrono::{DateTime, Utc};
+
+use crate::language::Language;
+use crate::the_way::import::{as_tag, import_error, known_language, markdown};
+use crate::the_way::snippet::Snippet;
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum Cache {
+    List(Vec<LeptonGist>),
+    ById(HashMap<String, LeptonGist>),
+}
+
+#[derive(Deserialize, Debug)]
+struct LeptonGist {
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    files: HashMap<String, LeptonFile>,
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LeptonFile {
+    filename: String,
+    /// As GitHub names it, e.g. "Markdown"
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    content: Option<String>,
+}
+
+/// (title, tags) from a Lepton Gist description
+fn parse_description(description: &str) -> (String, Vec<String>) {
+    let (description, tags) = match description.split_once("#tags:") {
+        Some((description, tags)) => (
+            description,
+            tags.split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(as_tag)
+                .collect(),
+        ),
+        None => (description, Vec::new()),
+    };
+    let description = description.trim();
+    let title = description
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .map_or(description, |(title, _)| title);
+    (title.trim().to_owned(), tags)
+}
+
+/// Snippets in a Lepton Gist cache
+pub(super) fn read(
+    reader: &mut dyn io::Read,
+    languages: &HashMap<String, Language>,
+) -> color_eyre::Result<Vec<Snippet>> {
+    let cache: Cache =
+        serde_json::from_reader(reader).map_err(|e| import_error("a Lepton Gist cache", e))?;
+    let gists = match cache {
+        Cache::List(gists) => gists,
+        Cache::ById(gists) => gists.into_values().collect(),
+    };
+    let mut snippets = Vec::new();
+    for gist in gists {
+        let (title, tags) = parse_description(gist.description.as_deref().unwrap_or_default());
+        let date = gist.created_at.unwrap_or_else(Utc::now);
+        let updated = gist.updated_at.unwrap_or(date);
+        let mut files: Vec<_> = gist.files.into_values().collect();
+        files.sort_by(|a, b| a.filename.cmp(&b.filename));
+        for file in files {
+            let language = file
+                .language
+                .as_deref()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            let content = file.content.unwrap_or_default();
+            let description = if title.is_empty() {
+                file.filename.to_owned()
+            } else {
+                title.to_owned()
+            };
+            let code: Vec<_> = if language == "markdown" {
+                markdown::code_blocks(&content)
+                    .into_iter()
+                    .map(|block| (block.language, block.code))
+                    .collect()
+            } else {
+                vec![(language, content)]
+            };
+            for (language, code) in code {
+                snippets.push(Snippet::new(
+                    0,
+                    description.to_owned(),
+                    known_language(&language, languages),
+                    String::new(),
+                    &tags.join(" "),
+                    date,
+                    updated,
+                    code,
+                ));
+            }
+        }
+    }
+    Ok(snippets)
+}
@@ -0,0 +1,143 @@
+//! Fenced code blocks in Markdown, for importing notes (Boostnote, Lepton) as snippets,
+//! and `the-way import --from markdown`: each code block in a Markdown file, described by the text before it
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::language::Language;
+use crate::the_way::import::known_language;
+use crate::the_way::snippet::Snippet;
+
+/// A fenced code block
+pub(super) struct CodeBlock {
+    /// First word of the info string, e.g. "rust" for ```rust
+    pub(super) language: String,
+    pub(super) code: String,
+    /// The heading or paragraph closest before the block
+    pub(super) context: Option<String>,
+}
+
+/// `line` without heading, quote, and list markers, e.g. "Usage" for "## Usage ##" or "- Usage"
+fn line_text(line: &str) -> &str {
+    let line = line.trim().trim_start_matches('>').trim_start();
+    let line = if line.starts_with('#') {
+        line.trim_start_matches('#').trim_end_matches('#')
+    } else if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+    {
+        item
+    } else {
+        match line.split_once(". ") {
+            Some((number, item)) if number.chars().all(|c| c.is_ascii_digit()) => item,
+            _ => line,
+        }
+    };
+    line.trim()
+}
+
+/// Lines of a paragraph as one line, without the colon that often leads into a code block
+fn paragraph_text(paragraph: &[&str]) -> Option<String> {
+    let text = paragraph
+        .iter()
+        .map(|line| line_text(line))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let text = text.trim_end_matches(':').trim_end();
+    Some(text.to_owned()).filter(|text| !text.is_empty())
+}
+
+/// Every fenced (``` or ~~~) code block in `text`
+pub(super) fn code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut context = None;
+    let mut paragraph = Vec::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        let fence_char = trimmed.chars().next().filter(|c| *c == '`' || *c == '~');
+        let fence_length = fence_char.map_or(0, |fence_char| {
+            trimmed.chars().take_while(|c| *c == fence_char).count()
+        });
+        let fence_char = match fence_char {
+            Some(fence_char) if fence_length >= 3 => fence_char,
+            _ => {
+                let rule = trimmed.trim_end();
+                let is_rule = !rule.is_empty()
+                    && (rule.chars().all(|c| c == '=') || rule.chars().all(|c| c == '-'));
+                if trimmed.trim().is_empty() || is_rule {
+                    // A rule under a paragraph makes it a heading
+                    context = paragraph_text(&paragraph).or(context);
+                    paragraph.clear();
+                } else if trimmed.starts_with('#') {
+                    paragraph.clear();
+                    context = paragraph_text(&[line]).or(context);
+                } else {
+                    paragraph.push(line);
+                }
+                continue;
+            }
+        };
+        context = paragraph_text(&paragraph).or(context);
+        paragraph.clear();
+        let indent = line.len() - trimmed.len();
+        let language = trimmed[fence_length..]
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches('{')
+            .trim_start_matches('.')
+            .trim_end_matches('}')
+            .to_ascii_lowercase();
+        let mut code = String::new();
+        for line in lines.by_ref() {
+            let closing = line.trim();
+            if closing.len() >= fence_length && closing.chars().all(|c| c == fence_char) {
+                break;
+            }
+            // Lines lose as much indentation as the fence had
+            let dedent = line
+                .chars()
+                .take(indent)
+                .take_while(|c| *c == ' ' || *c == '\t')
+                .count();
+            code.push_str(&line[dedent..]);
+            code.push('\n');
+        }
+        blocks.push(CodeBlock {
+            language,
+            code,
+            context: context.clone(),
+        });
+    }
+    blocks
+}
+
+/// Snippets for the code blocks in a Markdown file called `name`, each described by the heading or paragraph before it
+/// (or if there isn't one, the file's name)
+pub(super) fn read(
+    text: &str,
+    name: Option<&str>,
+    languages: &HashMap<String, Language>,
+) -> Vec<Snippet> {
+    code_blocks(text)
+        .into_iter()
+        .map(|block| {
+            let description = block
+                .context
+                .or_else(|| name.map(str::to_owned))
+                .unwrap_or_else(|| String::from("Markdown code block"));
+            Snippet::new(
+                0,
+                description,
+                known_language(&block.language, languages),
+                String::new(),
+                "",
+                Utc::now(),
+                Utc::now(),
+                block.code,
+            )
+        })
+        .collect()
+}
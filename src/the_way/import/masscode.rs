@@ -0,0 +1,154 @@
+//! `the-way import --from masscode`: massCode's db.json, each fragment as a snippet,
+//! tagged with its folders and massCode tags. Snippets in massCode's trash are left out
+use std::collections::HashMap;
+use std::io;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::language::Language;
+use crate::the_way::import::{as_tag, fragment_snippets, import_error, known_language, Fragment};
+use crate::the_way::snippet::Snippet;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Database {
+    #[serde(default)]
+    folders: Vec<Folder>,
+    #[serde(default)]
+    tags: Vec<Tag>,
+    #[serde(default)]
+    snippets: Vec<MassCodeSnippet>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Folder {
+    id: String,
+    name: String,
+    #[serde(default)]
+    parent_id: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Tag {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct MassCodeSnippet {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    folder_id: Option<String>,
+    #[serde(default)]
+    tags_ids: Vec<String>,
+    #[serde(default)]
+    is_deleted: bool,
+    #[serde(default)]
+    content: Vec<MassCodeFragment>,
+    /// Milliseconds since the epoch
+    #[serde(default)]
+    created_at: Option<i64>,
+    #[serde(default)]
+    updated_at: Option<i64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MassCodeFragment {
+    #[serde(default)]
+    label: String,
+    /// e.g. "javascript", "plain_text"
+    #[serde(default)]
+    language: String,
+    #[serde(default)]
+    value: String,
+}
+
+/// Tags for the folder `id` and every folder it's in, outermost first
+fn folder_tags(id: &str, folders: &HashMap<&str, &Folder>) -> Vec<String> {
+    let mut tags = Vec::new();
+    let mut folder = folders.get(id);
+    // Stops at a cycle, or a folder that's missing
+    while let Some(current) = folder {
+        if tags.len() > folders.len() {
+            break;
+        }
+        tags.insert(0, as_tag(&current.name));
+        folder = current
+            .parent_id
+            .as_deref()
+            .and_then(|parent| folders.get(parent));
+    }
+    tags
+}
+
+/// massCode's language names that aren't the-way's
+fn masscode_language(language: &str) -> &str {
+    match language {
+        "c_cpp" => "c++",
+        "plain_text" => "text",
+        "golang" => "go",
+        language => language,
+    }
+}
+
+fn millis(time: Option<i64>) -> DateTime<Utc> {
+    time.map_or_else(Utc::now, |time| Utc.timestamp_millis(time))
+}
+
+/// Snippets in a massCode db.json (with `join`, fragments of a snippet are joined into one)
+pub(super) fn read(
+    reader: &mut dyn io::Read,
+    languages: &HashMap<String, Language>,
+    join: bool,
+) -> color_eyre::Result<Vec<Snippet>> {
+    let database: Database =
+        serde_json::from_reader(reader).map_err(|e| import_error("a massCode db.json", e))?;
+    let folders: HashMap<_, _> = database
+        .folders
+        .iter()
+        .map(|folder| (folder.id.as_str(), folder))
+        .collect();
+    let tag_names: HashMap<_, _> = database
+        .tags
+        .iter()
+        .map(|tag| (tag.id.as_str(), tag.name.as_str()))
+        .collect();
+    let mut snippets = Vec::new();
+    for snippet in database.snippets {
+        if snippet.is_deleted {
+            continue;
+        }
+        let mut tags = snippet
+            .folder_id
+            .as_deref()
+            .map(|folder| folder_tags(folder, &folders))
+            .unwrap_or_default();
+        tags.extend(
+            snippet
+                .tags_ids
+                .iter()
+                .filter_map(|id| tag_names.get(id.as_str()))
+                .map(|name| as_tag(name)),
+        );
+        let fragments = snippet
+            .content
+            .into_iter()
+            .map(|fragment| Fragment {
+                label: fragment.label,
+                language: known_language(masscode_language(&fragment.language), languages),
+                code: fragment.value,
+            })
+            .collect();
+        snippets.extend(fragment_snippets(
+            snippet.name.as_deref().unwrap_or("Untitled snippet"),
+            &tags,
+            fragments,
+            join,
+            (millis(snippet.created_at), millis(snippet.updated_at)),
+        ));
+    }
+    Ok(snippets)
+}
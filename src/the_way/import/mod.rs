@@ -0,0 +1,619 @@
+//! Importing snippets: from JSON (with fields mapped from other tools' JSON), from other snippet managers' files,
+//! or from a GitHub user's Gists
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{fs, io};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use color_eyre::Help;
+use dialoguer::Confirm;
+use flate2::read::MultiGzDecoder;
+use structopt::StructOpt;
+
+use crate::errors::LostTheWay;
+use crate::language::Language;
+use crate::the_way::{field_map::FieldMap, snippet::Snippet, tag_rules, TheWay};
+use crate::utils;
+
+mod alfred;
+mod boostnote;
+mod cheat;
+mod csv;
+mod dir;
+mod gist;
+mod history;
+mod lepton;
+mod markdown;
+mod masscode;
+mod org;
+mod pet;
+mod snippetslab;
+mod sublime;
+mod tldr;
+mod vscode;
+mod yaml_toml;
+
+/// The first bytes of gzipped files
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// What `import --from` reads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    /// the-way's own JSON (the default)
+    Json,
+    /// Pet's snippet.toml
+    Pet,
+    /// A SnippetsLab library exported as JSON or XML
+    SnippetsLab,
+    /// massCode's db.json
+    MassCode,
+    /// Boostnote's .cson notes
+    Boostnote,
+    /// Lepton's Gist cache
+    Lepton,
+    /// VS Code user snippets
+    VsCode,
+    /// Sublime Text's .sublime-snippet files
+    Sublime,
+    /// Alfred's .alfredsnippets archives
+    Alfred,
+    /// Fenced code blocks in Markdown
+    Markdown,
+    /// Source blocks in org-mode files
+    Org,
+    /// cheat and cheat.sh cheatsheets
+    Cheat,
+    /// tldr pages
+    Tldr,
+}
+
+impl ImportFormat {
+    /// Possible format names
+    pub const VARIANTS: [&'static str; 13] = [
+        "json",
+        "pet",
+        "snippetslab",
+        "masscode",
+        "boostnote",
+        "lepton",
+        "vscode",
+        "sublime",
+        "alfred",
+        "markdown",
+        "org",
+        "cheat",
+        "tldr",
+    ];
+
+    /// Extension of files read from a folder, in formats that are one file per note or snippet
+    /// ("" for cheatsheets, which are named after their command)
+    fn folder_extension(self) -> Option<&'static str> {
+        match self {
+            Self::Boostnote => Some("cson"),
+            Self::Sublime => Some("sublime-snippet"),
+            Self::Alfred => Some("json"),
+            Self::Markdown => Some("md"),
+            Self::Org => Some("org"),
+            Self::Cheat => Some(""),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = LostTheWay;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "pet" => Ok(Self::Pet),
+            "snippetslab" => Ok(Self::SnippetsLab),
+            "masscode" => Ok(Self::MassCode),
+            "boostnote" => Ok(Self::Boostnote),
+            "lepton" => Ok(Self::Lepton),
+            "vscode" => Ok(Self::VsCode),
+            "sublime" => Ok(Self::Sublime),
+            "alfred" => Ok(Self::Alfred),
+            "markdown" => Ok(Self::Markdown),
+            "org" => Ok(Self::Org),
+            "cheat" => Ok(Self::Cheat),
+            "tldr" => Ok(Self::Tldr),
+            other => Err(LostTheWay::UsageError {
+                message: format!("Unknown import format {:?}", other),
+            }),
+        }
+    }
+}
+
+/// What `import --format` reads the-way's snippets as
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnippetFormat {
+    /// JSON objects, as `export` writes them (the default)
+    Json,
+    /// A CSV table with a header row, as `export --format csv` writes it
+    Csv,
+    /// A YAML list of snippets
+    Yaml,
+    /// TOML with a [[snippets]] table for each snippet
+    Toml,
+}
+
+impl SnippetFormat {
+    /// Possible format names
+    pub const VARIANTS: [&'static str; 4] = ["json", "csv", "yaml", "toml"];
+
+    /// Format of a file going by its extension (the one before .gz for a gzipped file),
+    /// JSON if it's not one of the others
+    fn from_extension(file: &Path) -> Self {
+        let file = match file.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("gz") => {
+                Path::new(file.file_stem().unwrap_or_default())
+            }
+            _ => file,
+        };
+        match file
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("csv") => Self::Csv,
+            Some("yaml") | Some("yml") => Self::Yaml,
+            Some("toml") => Self::Toml,
+            _ => Self::Json,
+        }
+    }
+}
+
+impl FromStr for SnippetFormat {
+    type Err = LostTheWay;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "yaml" | "yml" => Ok(Self::Yaml),
+            "toml" => Ok(Self::Toml),
+            other => Err(LostTheWay::UsageError {
+                message: format!("Unknown snippet format {:?}", other),
+            }),
+        }
+    }
+}
+
+/// Imported snippets in one language go in another (`import --map-language old=new`)
+#[derive(Debug)]
+pub struct LanguageMap {
+    from: String,
+    to: String,
+}
+
+impl FromStr for LanguageMap {
+    type Err = LostTheWay;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((from, to)) if !from.trim().is_empty() && !to.trim().is_empty() => Ok(Self {
+                from: from.trim().to_ascii_lowercase(),
+                to: to.trim().to_ascii_lowercase(),
+            }),
+            _ => Err(LostTheWay::UsageError {
+                message: format!("Expected old=new language, got {:?}", s),
+            }),
+        }
+    }
+}
+
+/// Where `import` reads snippets from, and how
+#[derive(StructOpt, Debug)]
+pub struct ImportArgs {
+    /// filename, reads from stdin if not given (with --history, the history file to read). Gzipped files are
+    /// decompressed
+    #[structopt(parse(from_os_str))]
+    pub(crate) file: Option<PathBuf>,
+    /// Format of the file (default "json"): "pet" for Pet's snippet.toml,
+    /// "snippetslab" for a SnippetsLab library exported as JSON or XML, "masscode" for massCode's db.json,
+    /// "boostnote" for a Boostnote .cson note (or a folder of them), "lepton" for Lepton's Gist cache,
+    /// "vscode" for VS Code user snippets (a <language>.json or .code-snippets file),
+    /// "sublime" for a Sublime Text .sublime-snippet file (or a folder of them),
+    /// "alfred" for an Alfred .alfredsnippets archive (or a folder of its JSON files),
+    /// "markdown" for the fenced code blocks in a Markdown file (or a folder of them),
+    /// "org" for the source blocks in an org-mode file (or a folder of them),
+    /// "cheat" for a cheat or cheat.sh cheatsheet (or a folder of them),
+    /// "tldr" for a tldr page (or a folder of them, or a .tar(.gz) or .zip archive)
+    #[structopt(long, possible_values = &ImportFormat::VARIANTS)]
+    pub(crate) from: Option<ImportFormat>,
+    /// Format of the-way's snippets in the file (by default from its extension, otherwise "json"):
+    /// "csv" for a table with a header row naming its columns (description, language, tags, code, date),
+    /// like `export --format csv` writes, "yaml" for a list of snippets, "toml" for a [[snippets]] table for each
+    #[structopt(long, possible_values = &SnippetFormat::VARIANTS, conflicts_with = "from")]
+    pub(crate) format: Option<SnippetFormat>,
+    /// Where to find snippet fields in JSON from other tools, e.g. 'description=title,code=body,tags=labels[]'
+    /// (or a file with one mapping per line). Nested fields work too: 'language=meta.lang,tags=labels[].name'
+    #[structopt(long)]
+    pub(crate) map: Option<FieldMap>,
+    /// Import every file of this GitHub user's Gists (secret ones too with a token for that user in
+    /// $THE_WAY_GITHUB_TOKEN or `github_access_token`), tagged with its file name
+    #[structopt(long, conflicts_with_all = &["file", "from", "format", "map"])]
+    pub(crate) gist: Option<String>,
+    /// Import every text file in this folder and its subfolders, described by its file name,
+    /// in the language of its extension
+    #[structopt(long, parse(from_os_str), conflicts_with_all = &["file", "from", "format", "map", "gist"])]
+    pub(crate) dir: Option<PathBuf>,
+    /// Tag files imported with --dir with the subfolders they're in
+    #[structopt(long, requires = "dir")]
+    pub(crate) dir_tags: bool,
+    /// Pick commands from your shell's history (bash, zsh, or fish, or the history file given)
+    /// in a search window, and save them as sh snippets
+    #[structopt(long, conflicts_with_all = &["from", "format", "map", "gist", "dir"])]
+    pub(crate) history: bool,
+    /// Import each SnippetsLab or massCode snippet with several fragments (or Boostnote note with several
+    /// code blocks) as one snippet, fragments one after another, instead of one snippet per fragment
+    #[structopt(long)]
+    pub(crate) join_fragments: bool,
+    /// Keep VS Code and Sublime Text placeholders like `${1:name}` (and tldr's `{{name}}`) in imported code,
+    /// instead of replacing them with their default text
+    #[structopt(long)]
+    pub(crate) keep_placeholders: bool,
+    /// Tag every imported snippet with <tag> (repeat for more: --tag a --tag b)
+    #[structopt(long = "tag", visible_alias = "add-tag", number_of_values = 1)]
+    pub(crate) tags: Vec<String>,
+    /// Leave <tag> off imported snippets (repeat for more)
+    #[structopt(long = "strip-tag", number_of_values = 1)]
+    pub(crate) strip_tags: Vec<String>,
+    /// Import snippets in language <old> as <new>, e.g. "shell=sh" (repeat for more)
+    #[structopt(long = "map-language", number_of_values = 1)]
+    pub(crate) language_maps: Vec<LanguageMap>,
+    /// Import snippets even if one with the same code and language is already saved
+    #[structopt(long, conflicts_with = "confirm-duplicates")]
+    pub(crate) allow_duplicates: bool,
+    /// Ask before importing each snippet that has the same code and language as a saved one
+    #[structopt(long)]
+    pub(crate) confirm_duplicates: bool,
+    /// Show the snippets that would be imported (and any errors reading them) without saving anything
+    #[structopt(long, conflicts_with = "confirm-duplicates")]
+    pub(crate) dry_run: bool,
+}
+
+impl ImportArgs {
+    /// What the-way's snippets in `file` are written as: `--format`, or going by the file's extension
+    fn snippet_format(&self, file: Option<&Path>) -> SnippetFormat {
+        self.format
+            .unwrap_or_else(|| file.map_or(SnippetFormat::Json, SnippetFormat::from_extension))
+    }
+}
+
+impl TheWay {
+    /// Imports snippets from a file in the format `args.from` (JSON by default),
+    /// with `args.gist`, from that GitHub user's Gists, with `args.dir`, from the files in a folder,
+    /// or with `args.history`, from the commands picked from shell history.
+    /// Snippets with the UUID of a saved snippet (from JSON `export`) get its index, to update it,
+    /// the rest get index 0, to be added.
+    /// Nothing is imported if any snippet breaks the `[validation]` rules.
+    /// Snippets with the same code and language as a saved one (or an earlier one in the import) are skipped,
+    /// unless `args.allow_duplicates`, or asked about one by one with `args.confirm_duplicates`.
+    pub(crate) fn import(&self, args: &ImportArgs) -> color_eyre::Result<Vec<Snippet>> {
+        let format = args.from.unwrap_or(ImportFormat::Json);
+        let snippet_format = args.snippet_format(args.file.as_deref());
+        if args.map.is_some()
+            && (format != ImportFormat::Json || snippet_format != SnippetFormat::Json)
+        {
+            let error: color_eyre::Result<()> = Err(LostTheWay::UsageError {
+                message: String::from("--map only works with JSON"),
+            }
+            .into());
+            error.suggestion("Leave out --map, --from, or --format")?;
+        }
+        let mut snippets = match (&args.gist, &args.dir, &args.file) {
+            (Some(username), _, _) => self.read_gists(username)?,
+            (None, Some(dir), _) => dir::read(dir, args.dir_tags, &self.languages)?,
+            (None, None, file) if args.history => self.read_history(file.as_deref())?,
+            (None, None, Some(dir)) if dir.is_dir() && format == ImportFormat::Tldr => {
+                tldr::read_dir(dir, args.keep_placeholders)?
+            }
+            (None, None, Some(dir)) if dir.is_dir() => {
+                let extension = format
+                    .folder_extension()
+                    .ok_or(LostTheWay::UsageError {
+                        message: format!("{} is a folder", dir.display()),
+                    })
+                    .suggestion(
+                        "Only --from alfred, boostnote, cheat, markdown, org, sublime, and tldr read whole folders, \
+                        import source files with --dir",
+                    )?;
+                let mut files: Vec<_> = fs::read_dir(dir)?
+                    .map(|entry| Ok(entry?.path()))
+                    .collect::<io::Result<_>>()?;
+                files.retain(|file| {
+                    file.is_file()
+                        && !file
+                            .file_name()
+                            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+                        && file.extension().unwrap_or_default() == extension
+                });
+                files.sort();
+                let mut snippets = Vec::new();
+                for file in files {
+                    let mut reader = io::BufReader::new(fs::File::open(&file)?);
+                    snippets.extend(self.read_snippets(format, Some(&file), &mut reader, args)?);
+                }
+                snippets
+            }
+            (None, None, file) => {
+                let reader: Box<dyn io::Read> = match file {
+                    Some(file) => Box::new(fs::File::open(file)?),
+                    None => Box::new(io::stdin()),
+                };
+                let mut buffered = io::BufReader::new(reader);
+                // tldr archives are read as they are, tar.gz and all
+                if format != ImportFormat::Tldr && buffered.fill_buf()?.starts_with(&GZIP_MAGIC) {
+                    let mut decoder = io::BufReader::new(MultiGzDecoder::new(buffered));
+                    self.read_snippets(format, file.as_deref(), &mut decoder, args)?
+                } else {
+                    self.read_snippets(format, file.as_deref(), &mut buffered, args)?
+                }
+            }
+        };
+        let rules = self.tag_rules()?;
+        for snippet in &mut snippets {
+            if let Some(map) = args
+                .language_maps
+                .iter()
+                .find(|map| snippet.language.eq_ignore_ascii_case(&map.from))
+            {
+                snippet.language = map.to.to_owned();
+            }
+            snippet.tags.retain(|tag| !args.strip_tags.contains(tag));
+            for tag in &args.tags {
+                if !snippet.has_tag(tag) {
+                    snippet.tags.push(tag.to_owned());
+                }
+            }
+            snippet.set_extension(&snippet.language.to_owned(), &self.languages);
+            tag_rules::apply_tag_rules(&rules, snippet);
+            snippet.validate(&self.config.validation)?;
+        }
+        let saved: HashMap<String, usize> = if snippets.iter().any(|s| s.uuid.is_some()) {
+            self.get_uuids()?
+                .into_iter()
+                .map(|(index, uuid)| (uuid, index))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        for snippet in &mut snippets {
+            snippet.index = snippet
+                .uuid
+                .as_ref()
+                .and_then(|uuid| saved.get(uuid))
+                .copied()
+                .unwrap_or_default();
+        }
+        if !args.allow_duplicates {
+            // Updates aren't duplicates of the snippets they update
+            let (mut updates, new): (Vec<_>, Vec<_>) =
+                snippets.into_iter().partition(|snippet| snippet.index != 0);
+            updates.extend(self.without_duplicates(new, args.confirm_duplicates)?);
+            snippets = updates;
+        }
+        Ok(snippets)
+    }
+
+    /// `snippets` without the ones whose code and language are already saved or earlier in `snippets`
+    /// (or only the ones not kept when asked, with `confirm`).
+    /// Compares hashes, so it takes one pass over the database.
+    fn without_duplicates(
+        &self,
+        snippets: Vec<Snippet>,
+        confirm: bool,
+    ) -> color_eyre::Result<Vec<Snippet>> {
+        let mut seen: HashSet<_> = self.list_snippets()?.iter().map(duplicate_key).collect();
+        let mut kept = Vec::with_capacity(snippets.len());
+        let mut skipped = 0;
+        for snippet in snippets {
+            if seen.insert(duplicate_key(&snippet))
+                || (confirm
+                    && Confirm::new()
+                        .with_prompt(format!(
+                            "{} is already saved, import it anyway?",
+                            snippet.description.lines().next().unwrap_or_default()
+                        ))
+                        .default(false)
+                        .interact()?)
+            {
+                kept.push(snippet);
+            } else {
+                skipped += 1;
+            }
+        }
+        if skipped > 0 {
+            eprintln!(
+                "Skipped {} duplicate snippet(s), import them with --allow-duplicates",
+                skipped
+            );
+        }
+        Ok(kept)
+    }
+
+    /// Snippets in `reader` (the contents of `file`, or stdin), read as `format`
+    fn read_snippets(
+        &self,
+        format: ImportFormat,
+        file: Option<&Path>,
+        reader: &mut dyn io::Read,
+        args: &ImportArgs,
+    ) -> color_eyre::Result<Vec<Snippet>> {
+        let file_stem = file
+            .and_then(|file| file.file_stem())
+            .and_then(|stem| stem.to_str());
+        Ok(match (format, &args.map) {
+            (ImportFormat::Json, map) => match (args.snippet_format(file), map) {
+                (SnippetFormat::Csv, _) => csv::read(reader)?,
+                (SnippetFormat::Yaml, _) => yaml_toml::read_yaml(reader)?,
+                (SnippetFormat::Toml, _) => yaml_toml::read_toml(reader)?,
+                (SnippetFormat::Json, Some(map)) => Snippet::read_mapped(reader, map)?,
+                (SnippetFormat::Json, None) => Snippet::read(reader)?,
+            },
+            (ImportFormat::Pet, _) => pet::read(reader)?,
+            (ImportFormat::SnippetsLab, _) => {
+                snippetslab::read(reader, &self.languages, args.join_fragments)?
+            }
+            (ImportFormat::MassCode, _) => {
+                masscode::read(reader, &self.languages, args.join_fragments)?
+            }
+            (ImportFormat::Boostnote, _) => boostnote::read(
+                &read_to_string(reader)?,
+                &self.languages,
+                args.join_fragments,
+            )?,
+            (ImportFormat::Lepton, _) => lepton::read(reader, &self.languages)?,
+            (ImportFormat::VsCode, _) => {
+                // <language>.json has snippets for that language
+                let file_language = file_stem.filter(|_| {
+                    file.and_then(Path::extension)
+                        .is_some_and(|extension| extension == "json")
+                });
+                vscode::read(
+                    reader,
+                    file_language,
+                    &self.languages,
+                    args.keep_placeholders,
+                )?
+            }
+            (ImportFormat::Sublime, _) => {
+                sublime::read(reader, file_stem, &self.languages, args.keep_placeholders)?
+            }
+            (ImportFormat::Alfred, _) => alfred::read(reader)?,
+            (ImportFormat::Markdown, _) => {
+                markdown::read(&read_to_string(reader)?, file_stem, &self.languages)
+            }
+            (ImportFormat::Org, _) => {
+                org::read(&read_to_string(reader)?, file_stem, &self.languages)
+            }
+            (ImportFormat::Tldr, _) => tldr::read(reader, file, args.keep_placeholders)?,
+            (ImportFormat::Cheat, _) => {
+                // Cheatsheets are named after their command, with no extension
+                let name = file
+                    .and_then(Path::file_name)
+                    .and_then(|name| name.to_str());
+                cheat::read(&read_to_string(reader)?, name, &self.languages)?
+            }
+        })
+    }
+}
+
+/// Everything `reader` has, as text
+fn read_to_string(reader: &mut dyn io::Read) -> color_eyre::Result<String> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+/// Error for a file that isn't what `import --from <format>` expects
+fn import_error(format: &str, error: impl std::fmt::Display) -> color_eyre::Report {
+    LostTheWay::ImportError {
+        message: format!("Couldn't read this as {}: {}", format, error),
+    }
+    .into()
+}
+
+/// An RFC 3339 date (as `export` writes them), or a date written by hand or changed by a spreadsheet,
+/// like "2020-01-02" or "2020-01-02 03:04" (taken as UTC)
+fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|date| date.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+                .map(|date| DateTime::from_utc(date, Utc))
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .ok()
+                .map(|date| DateTime::from_utc(date.and_hms(0, 0, 0), Utc))
+        })
+}
+
+/// What snippets with the same code in the same language have in common:
+/// a hash of the language and the code, ignoring trailing whitespace and blank lines around it
+fn duplicate_key(snippet: &Snippet) -> String {
+    let code: Vec<_> = snippet.code.lines().map(str::trim_end).collect();
+    utils::content_hash(&format!(
+        "{}\n{}",
+        snippet.language.to_ascii_lowercase(),
+        code.join("\n").trim_matches('\n')
+    ))
+}
+
+/// `name` as a single tag, e.g. "Shell Scripts" as "Shell-Scripts"
+fn as_tag(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("-")
+}
+
+/// A language called `name` (in any case) if there is one, otherwise "text"
+fn known_language(name: &str, languages: &HashMap<String, Language>) -> String {
+    let name = name.trim().to_ascii_lowercase();
+    if languages.contains_key(&name) {
+        name
+    } else {
+        String::from("text")
+    }
+}
+
+/// One part of a snippet made of several (SnippetsLab and massCode fragments)
+struct Fragment {
+    label: String,
+    language: String,
+    code: String,
+}
+
+/// Snippets for the multi-part snippet `title`: one per fragment (with the fragment's label if there are several),
+/// or with `join`, one with every fragment's code, in the first fragment's language
+fn fragment_snippets(
+    title: &str,
+    tags: &[String],
+    fragments: Vec<Fragment>,
+    join: bool,
+    (date, updated): (DateTime<Utc>, DateTime<Utc>),
+) -> Vec<Snippet> {
+    let snippet = |description: String, language: String, code: String| {
+        Snippet::new(
+            0,
+            description,
+            language,
+            String::new(),
+            &tags.join(" "),
+            date,
+            updated,
+            code,
+        )
+    };
+    if join && fragments.len() > 1 {
+        let language = fragments[0].language.to_owned();
+        let code: Vec<_> = fragments
+            .into_iter()
+            .map(|fragment| fragment.code.trim_end().to_owned())
+            .collect();
+        return vec![snippet(
+            title.to_owned(),
+            language,
+            format!("{}\n", code.join("\n\n")),
+        )];
+    }
+    let several = fragments.len() > 1;
+    fragments
+        .into_iter()
+        .map(|fragment| {
+            let description = if several && !fragment.label.trim().is_empty() {
+                format!("{} ({})", title, fragment.label)
+            } else {
+                title.to_owned()
+            };
+            snippet(description, fragment.language, fragment.code)
+        })
+        .collect()
+}
@@ -0,0 +1,179 @@
+//! `the-way import --from org`: each `#+BEGIN_SRC` block in an org-mode file, described by its headline
+//! (or `#+CAPTION:`) and tagged with the headline's tags, tags it inherits, and `#+FILETAGS:`
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::language::Language;
+use crate::the_way::import::known_language;
+use crate::the_way::snippet::Snippet;
+
+/// A headline's title and tags, without its stars, TODO keyword, and priority
+struct Headline {
+    level: usize,
+    title: String,
+    tags: Vec<String>,
+}
+
+/// Tags in org's `:tag1:tag2:` form (or separated by spaces, in #+FILETAGS:)
+fn org_tags(tags: &str) -> Vec<String> {
+    tags.split(|c: char| c == ':' || c.is_whitespace())
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// The headline in `line`, if it is one (i.e. "** TODO [#A] Title :tag1:tag2:")
+fn headline(line: &str) -> Option<Headline> {
+    let level = line.chars().take_while(|c| *c == '*').count();
+    if level == 0 {
+        return None;
+    }
+    let rest = line[level..].strip_prefix(' ')?;
+    let mut words: Vec<_> = rest.split_whitespace().collect();
+    let tags = match words.last() {
+        Some(last) if last.len() > 1 && last.starts_with(':') && last.ends_with(':') => {
+            org_tags(words.pop().unwrap_or_default())
+        }
+        _ => Vec::new(),
+    };
+    if words
+        .first()
+        .is_some_and(|word| ["TODO", "DONE"].contains(word))
+    {
+        words.remove(0);
+    }
+    if words
+        .first()
+        .is_some_and(|word| word.starts_with("[#") && word.ends_with(']'))
+    {
+        words.remove(0);
+    }
+    Some(Headline {
+        level,
+        title: words.join(" "),
+        tags,
+    })
+}
+
+/// Value of the `#+<keyword>:` setting in `line` (in any case)
+fn keyword<'a>(line: &'a str, keyword: &str) -> Option<&'a str> {
+    let line = line.trim_start();
+    let prefix = line.get(..keyword.len() + 3)?;
+    if prefix.eq_ignore_ascii_case(&format!("#+{}:", keyword)) {
+        Some(line[prefix.len()..].trim())
+    } else {
+        None
+    }
+}
+
+/// Header of the source block `line` starts, e.g. "sh :results output" for "#+BEGIN_SRC sh :results output"
+fn block_start(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    let header = line
+        .get(..11)
+        .filter(|start| start.eq_ignore_ascii_case("#+begin_src"))
+        .map(|_| &line[11..])?;
+    if header.is_empty() || header.starts_with(char::is_whitespace) {
+        Some(header.trim())
+    } else {
+        None
+    }
+}
+
+/// Snippets for the source blocks in an org file called `name`
+pub(super) fn read(
+    text: &str,
+    name: Option<&str>,
+    languages: &HashMap<String, Language>,
+) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+    let mut file_tags = Vec::new();
+    // The current headline and the ones it's under
+    let mut headlines: Vec<Headline> = Vec::new();
+    let mut caption = None;
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        if let Some(headline) = headline(line) {
+            headlines.retain(|parent| parent.level < headline.level);
+            headlines.push(headline);
+            caption = None;
+            continue;
+        }
+        if let Some(tags) = keyword(line, "FILETAGS") {
+            file_tags.extend(org_tags(tags));
+            continue;
+        }
+        if let Some(text) = keyword(line, "CAPTION") {
+            caption = Some(text.to_owned());
+            continue;
+        }
+        let header = match block_start(line) {
+            Some(header) => header,
+            None => {
+                // A caption is for the block right after it
+                if !line.trim().is_empty() && !line.trim_start().starts_with("#+") {
+                    caption = None;
+                }
+                continue;
+            }
+        };
+        let indent = line.len() - line.trim_start().len();
+        let language = header.split_whitespace().next().unwrap_or_default();
+        let mut code = String::new();
+        for line in lines.by_ref() {
+            if line
+                .trim()
+                .get(..9)
+                .is_some_and(|end| end.eq_ignore_ascii_case("#+end_src"))
+            {
+                break;
+            }
+            let dedent = line
+                .chars()
+                .take(indent)
+                .take_while(|c| *c == ' ' || *c == '\t')
+                .count();
+            let line = &line[dedent..];
+            // Org escapes lines that would be read as headlines or settings with a comma
+            // (after their indentation)
+            let rest = line.trim_start();
+            let indent = &line[..line.len() - rest.len()];
+            match rest.strip_prefix(',') {
+                Some(escaped) if escaped.starts_with('*') || escaped.starts_with("#+") => {
+                    code.push_str(indent);
+                    code.push_str(escaped);
+                }
+                _ => code.push_str(line),
+            }
+            code.push('\n');
+        }
+        let mut tags = file_tags.clone();
+        for tag in headlines.iter().flat_map(|headline| &headline.tags) {
+            if !tags.contains(tag) {
+                tags.push(tag.to_owned());
+            }
+        }
+        let description = caption
+            .take()
+            .or_else(|| {
+                headlines
+                    .last()
+                    .map(|headline| headline.title.to_owned())
+                    .filter(|title| !title.is_empty())
+            })
+            .or_else(|| name.map(str::to_owned))
+            .unwrap_or_else(|| String::from("Org source block"));
+        snippets.push(Snippet::new(
+            0,
+            description,
+            known_language(language, languages),
+            String::new(),
+            &tags.join(" "),
+            Utc::now(),
+            Utc::now(),
+            code,
+        ));
+    }
+    snippets
+}
@@ -0,0 +1,49 @@
+//! `the-way import --from pet`: Pet's snippet.toml, each command as a shell snippet
+use std::io;
+
+use chrono::Utc;
+
+use crate::the_way::import::{import_error, read_to_string};
+use crate::the_way::snippet::Snippet;
+
+#[derive(Deserialize, Debug)]
+struct PetSnippets {
+    #[serde(default)]
+    snippets: Vec<PetSnippet>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PetSnippet {
+    #[serde(default)]
+    description: String,
+    command: String,
+    #[serde(default)]
+    tag: Vec<String>,
+}
+
+/// Snippets in a Pet snippet.toml
+pub(super) fn read(reader: &mut dyn io::Read) -> color_eyre::Result<Vec<Snippet>> {
+    let pet: PetSnippets = toml::from_str(&read_to_string(reader)?)
+        .map_err(|e| import_error("a Pet snippet.toml", e))?;
+    Ok(pet
+        .snippets
+        .into_iter()
+        .map(|snippet| {
+            let description = if snippet.description.trim().is_empty() {
+                snippet.command.to_owned()
+            } else {
+                snippet.description
+            };
+            Snippet::new(
+                0,
+                description,
+                String::from("sh"),
+                String::new(),
+                &snippet.tag.join(" "),
+                Utc::now(),
+                Utc::now(),
+                snippet.command,
+            )
+        })
+        .collect())
+}
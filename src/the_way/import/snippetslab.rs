@@ -0,0 +1,149 @@
+//! `the-way import --from snippetslab`: a SnippetsLab library exported as JSON or XML,
+//! each fragment as a snippet, tagged with its folders (and SnippetsLab tags)
+use std::collections::HashMap;
+use std::io;
+
+use chrono::Utc;
+
+use crate::language::Language;
+use crate::the_way::import::{as_tag, fragment_snippets, import_error, known_language, Fragment};
+use crate::the_way::snippet::Snippet;
+
+/// Exported libraries keep everything under "contents"
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum Export {
+    Library { contents: Contents },
+    Contents(Contents),
+}
+
+#[derive(Deserialize, Debug)]
+struct Contents {
+    #[serde(default)]
+    folders: Vec<Folder>,
+    #[serde(default)]
+    tags: Vec<Tag>,
+    #[serde(default)]
+    snippets: Vec<LabSnippet>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Folder {
+    uuid: String,
+    title: String,
+    #[serde(default)]
+    parent: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Tag {
+    uuid: String,
+    title: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct LabSnippet {
+    #[serde(default)]
+    title: String,
+    /// UUID of the folder the snippet is in
+    #[serde(default)]
+    folder: Option<String>,
+    /// Tag UUIDs
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    fragments: Vec<LabFragment>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LabFragment {
+    #[serde(default)]
+    title: String,
+    /// Pygments lexer name, e.g. "PythonLexer"
+    #[serde(default)]
+    language: String,
+    #[serde(default)]
+    content: String,
+}
+
+/// Tags for the folder `uuid` and every folder it's in, outermost first
+fn folder_tags(uuid: &str, folders: &HashMap<&str, &Folder>) -> Vec<String> {
+    let mut tags = Vec::new();
+    let mut folder = folders.get(uuid);
+    // Stops at a cycle, or a folder that's missing
+    while let Some(current) = folder {
+        if tags.len() > folders.len() {
+            break;
+        }
+        tags.insert(0, as_tag(&current.title));
+        folder = current
+            .parent
+            .as_deref()
+            .and_then(|parent| folders.get(parent));
+    }
+    tags
+}
+
+/// Snippets in a SnippetsLab library export (JSON, or XML made by SnippetsLab)
+/// (with `join`, fragments of a snippet are joined into one)
+pub(super) fn read(
+    reader: &mut dyn io::Read,
+    languages: &HashMap<String, Language>,
+    join: bool,
+) -> color_eyre::Result<Vec<Snippet>> {
+    let mut contents = Vec::new();
+    reader.read_to_end(&mut contents)?;
+    let export: Export = if contents
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .is_some_and(|byte| *byte == b'{')
+    {
+        serde_json::from_slice(&contents).map_err(|e| import_error("a SnippetsLab export", e))?
+    } else {
+        plist::from_bytes(&contents).map_err(|e| import_error("a SnippetsLab export", e))?
+    };
+    let contents = match export {
+        Export::Library { contents } | Export::Contents(contents) => contents,
+    };
+    let folders: HashMap<_, _> = contents
+        .folders
+        .iter()
+        .map(|folder| (folder.uuid.as_str(), folder))
+        .collect();
+    let tag_names: HashMap<_, _> = contents
+        .tags
+        .iter()
+        .map(|tag| (tag.uuid.as_str(), tag.title.as_str()))
+        .collect();
+    let mut snippets = Vec::new();
+    for lab_snippet in contents.snippets {
+        let mut tags = lab_snippet
+            .folder
+            .as_deref()
+            .map(|folder| folder_tags(folder, &folders))
+            .unwrap_or_default();
+        tags.extend(
+            lab_snippet
+                .tags
+                .iter()
+                .map(|uuid| as_tag(tag_names.get(uuid.as_str()).copied().unwrap_or(uuid))),
+        );
+        let fragments = lab_snippet
+            .fragments
+            .into_iter()
+            .map(|fragment| Fragment {
+                label: fragment.title,
+                language: known_language(fragment.language.trim_end_matches("Lexer"), languages),
+                code: fragment.content,
+            })
+            .collect();
+        snippets.extend(fragment_snippets(
+            &lab_snippet.title,
+            &tags,
+            fragments,
+            join,
+            (Utc::now(), Utc::now()),
+        ));
+    }
+    Ok(snippets)
+}
@@ -0,0 +1,80 @@
+//! `the-way import --from sublime`: a Sublime Text `.sublime-snippet` file (or a folder of them),
+//! tagged with its tab trigger, in the language of its scope. Fields like `${1:name}` work as they do for VS Code
+use std::collections::HashMap;
+use std::io;
+
+use chrono::Utc;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::language::Language;
+use crate::the_way::import::{as_tag, import_error, known_language, vscode};
+use crate::the_way::snippet::Snippet;
+
+/// Language of a scope selector like "source.python" or "text.html.basic, source.js"
+fn scope_language(scope: &str) -> Option<&str> {
+    let scope = scope.split(',').next()?.split_whitespace().next()?;
+    match scope {
+        "text.plain" => Some("text"),
+        scope => scope.split('.').nth(1),
+    }
+}
+
+/// The snippet in a .sublime-snippet file called `name`, in "text" if it doesn't have a (known) scope.
+/// With `keep_placeholders`, code is as it is in Sublime Text
+pub(super) fn read(
+    reader: &mut dyn io::Read,
+    name: Option<&str>,
+    languages: &HashMap<String, Language>,
+    keep_placeholders: bool,
+) -> color_eyre::Result<Vec<Snippet>> {
+    let mut fields = HashMap::new();
+    let mut element = String::new();
+    for event in EventReader::new(reader) {
+        match event.map_err(|e| import_error("a Sublime Text snippet", e))? {
+            XmlEvent::StartElement { name, .. } => element = name.local_name,
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => fields
+                .entry(element.to_owned())
+                .or_insert_with(String::new)
+                .push_str(&text),
+            XmlEvent::EndElement { .. } => element.clear(),
+            _ => {}
+        }
+    }
+    let content = fields.remove("content").ok_or_else(|| {
+        import_error(
+            "a Sublime Text snippet",
+            "there's no <content> in the <snippet>",
+        )
+    })?;
+    let trigger = fields.remove("tabTrigger").unwrap_or_default();
+    let language = fields
+        .get("scope")
+        .and_then(|scope| scope_language(scope))
+        .map_or_else(
+            || String::from("text"),
+            |language| known_language(language, languages),
+        );
+    let description = fields
+        .remove("description")
+        .filter(|description| !description.trim().is_empty())
+        .or_else(|| Some(trigger.to_owned()).filter(|trigger| !trigger.trim().is_empty()))
+        .or_else(|| name.map(str::to_owned))
+        .unwrap_or_else(|| String::from("Sublime Text snippet"));
+    // Content starts on the line after <![CDATA[
+    let content = content.trim_start_matches(['\r', '\n']);
+    let code = if keep_placeholders {
+        content.to_owned()
+    } else {
+        vscode::without_placeholders(content)
+    };
+    Ok(vec![Snippet::new(
+        0,
+        description,
+        language,
+        String::new(),
+        &as_tag(&trigger),
+        Utc::now(),
+        Utc::now(),
+        format!("{}\n", code.trim_end()),
+    )])
+}
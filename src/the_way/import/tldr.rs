@@ -0,0 +1,152 @@
+//! `the-way import --from tldr`: [tldr](https://tldr.sh) pages, as a page, a folder of them (like tldr's `pages`),
+//! or a .tar(.gz) or .zip archive of one. Each example is a shell snippet described by its explanation,
+//! tagged with the page's command (and its platform, if it's not in `common`)
+use std::io::{self, Read};
+use std::path::Path;
+
+use chrono::Utc;
+use flate2::read::GzDecoder;
+
+use crate::the_way::import::{as_tag, dir, import_error};
+use crate::the_way::snippet::Snippet;
+use crate::zip;
+
+/// Whether `path` is an English page: a Markdown file that isn't in a translation's `pages.<language>` folder
+fn is_page(path: &str) -> bool {
+    path.ends_with(".md")
+        && !path.split('/').any(|folder| {
+            folder.starts_with("pages.") || (folder.starts_with('.') && folder != ".")
+        })
+}
+
+/// The platform of the page at `path` (its folder, as in pages/linux/ip.md), unless it's common to all of them
+fn platform(path: &str) -> Option<&str> {
+    let mut folders = path.rsplit('/').skip(1);
+    match (folders.next(), folders.next()) {
+        (Some(platform), Some("pages")) if platform != "common" => Some(platform),
+        _ => None,
+    }
+}
+
+/// An example's `{{placeholders}}` as their text, unless they're kept
+fn example_code(code: &str, keep_placeholders: bool) -> String {
+    if keep_placeholders {
+        code.to_owned()
+    } else {
+        code.replace("{{", "").replace("}}", "")
+    }
+}
+
+/// A snippet for each example in the page `text`, found at `path`
+fn page(text: &str, path: &str, keep_placeholders: bool) -> Vec<Snippet> {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let mut command = file_name.trim_end_matches(".md").to_owned();
+    let mut description = None;
+    let mut snippets = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if let Some(title) = line.strip_prefix("# ") {
+            command = title.trim().to_owned();
+        } else if let Some(explanation) = line.strip_prefix("- ") {
+            description = Some(explanation.trim().trim_end_matches(':').to_owned());
+        } else if line.len() > 1 && line.starts_with('`') && line.ends_with('`') {
+            let tags: Vec<_> = std::iter::once(as_tag(&command))
+                .chain(platform(path).map(as_tag))
+                .collect();
+            let code = example_code(&line[1..line.len() - 1], keep_placeholders);
+            snippets.push(Snippet::new(
+                0,
+                description.take().unwrap_or_else(|| command.to_owned()),
+                String::from("sh"),
+                String::new(),
+                &tags.join(" "),
+                Utc::now(),
+                Utc::now(),
+                format!("{}\n", code),
+            ));
+        }
+    }
+    snippets
+}
+
+/// Files in a tar archive, by path
+fn tar_files(bytes: &[u8]) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+    for entry in tar::Archive::new(bytes).entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        files.push((path, contents));
+    }
+    Ok(files)
+}
+
+/// Snippets in the pages in `files`, in order of their paths
+fn pages(
+    mut files: Vec<(String, Vec<u8>)>,
+    keep_placeholders: bool,
+) -> color_eyre::Result<Vec<Snippet>> {
+    files.retain(|(path, _)| is_page(path));
+    files.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut snippets = Vec::new();
+    for (path, contents) in files {
+        let text = String::from_utf8(contents)
+            .map_err(|_| import_error("a tldr page", format!("{} isn't UTF-8", path)))?;
+        snippets.extend(page(&text, &path, keep_placeholders));
+    }
+    Ok(snippets)
+}
+
+/// Snippets in a tldr page, or in every page in a .tar, .tar.gz, or .zip archive
+pub(super) fn read(
+    reader: &mut dyn io::Read,
+    file: Option<&Path>,
+    keep_placeholders: bool,
+) -> color_eyre::Result<Vec<Snippet>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut tar = Vec::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut tar)
+            .map_err(|e| import_error("a tldr archive", e))?;
+        bytes = tar;
+    }
+    if zip::is_zip(&bytes) {
+        let files = zip::files(&bytes).map_err(|e| import_error("a tldr archive", e))?;
+        return pages(files, keep_placeholders);
+    }
+    // Tar headers have "ustar" 257 bytes in
+    if bytes.get(257..262) == Some(b"ustar") {
+        let files = tar_files(&bytes).map_err(|e| import_error("a tldr archive", e))?;
+        return pages(files, keep_placeholders);
+    }
+    let text = String::from_utf8(bytes).map_err(|e| import_error("a tldr page", e))?;
+    let path = file.map_or_else(String::new, |file| {
+        file.to_string_lossy().replace('\\', "/")
+    });
+    Ok(page(&text, &path, keep_placeholders))
+}
+
+/// Snippets in every page in `folder` and its subfolders
+pub(super) fn read_dir(folder: &Path, keep_placeholders: bool) -> color_eyre::Result<Vec<Snippet>> {
+    let mut paths = Vec::new();
+    dir::walk(folder, &mut paths)?;
+    let mut files = Vec::new();
+    for path in paths {
+        // Paths from the folder down, so a page's platform is still there if it's tldr's `pages` folder
+        let name = folder
+            .file_name()
+            .map(Path::new)
+            .unwrap_or_else(|| Path::new(""))
+            .join(path.strip_prefix(folder)?);
+        files.push((
+            name.to_string_lossy().replace('\\', "/"),
+            std::fs::read(&path)?,
+        ));
+    }
+    pages(files, keep_placeholders)
+}
@@ -0,0 +1,272 @@
+//! `the-way import --from vscode`: VS Code user snippets (a `<language>.json` or `.code-snippets` file),
+//! each snippet tagged with its prefixes. Placeholders like `$1` and `${2:name}` are replaced
+//! with their default text, unless they're kept
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use chrono::Utc;
+
+use crate::language::Language;
+use crate::the_way::import::{as_tag, import_error, known_language, read_to_string};
+use crate::the_way::snippet::Snippet;
+
+/// Variables VS Code fills in (by name or prefix), as opposed to `$NAME`s that are part of the code
+const VARIABLES: [&str; 10] = [
+    "TM_",
+    "CURRENT_",
+    "CURSOR_",
+    "WORKSPACE_",
+    "BLOCK_COMMENT_",
+    "LINE_COMMENT",
+    "RELATIVE_FILEPATH",
+    "CLIPBOARD",
+    "RANDOM",
+    "UUID",
+];
+
+/// A string, or a list of them
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Self::One(value) => vec![value],
+            Self::Many(values) => values,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct VsCodeSnippet {
+    #[serde(default)]
+    prefix: Option<OneOrMany>,
+    body: OneOrMany,
+    #[serde(default)]
+    description: Option<String>,
+    /// Languages for snippets in .code-snippets files, e.g. "javascript,typescript"
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+/// VS Code's language IDs that aren't the-way's
+fn vscode_language(language: &str) -> &str {
+    match language {
+        "shellscript" => "sh",
+        "plaintext" => "text",
+        "javascriptreact" => "jsx",
+        "typescriptreact" => "tsx",
+        language => language,
+    }
+}
+
+/// `text` without comments and trailing commas, which VS Code allows in snippet files
+fn strip_jsonc(text: &str) -> String {
+    let mut json = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                json.push(c);
+                while let Some(c) = chars.next() {
+                    json.push(c);
+                    match c {
+                        '\\' => json.extend(chars.next()),
+                        '"' => break,
+                        _ => (),
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            '}' | ']' => {
+                let kept = json.trim_end().len();
+                if json[..kept].ends_with(',') {
+                    json.truncate(kept - 1);
+                }
+                json.push(c);
+            }
+            c => json.push(c),
+        }
+    }
+    json
+}
+
+/// A name made of letters, digits, and underscores (or digits only, with `digits`)
+fn take_name(chars: &mut Peekable<Chars<'_>>, digits: bool) -> String {
+    let mut name = String::new();
+    while let Some(c) = chars.peek() {
+        if c.is_ascii_digit() || (!digits && (c.is_ascii_alphabetic() || *c == '_')) {
+            name.push(*c);
+            chars.next();
+        } else {
+            break;
+        }
+    }
+    name
+}
+
+/// Skips to the `}` closing a variable transform like `${TM_FILENAME/(.*)/${1:/upcase}/}`
+fn skip_transform(chars: &mut Peekable<Chars<'_>>) {
+    let mut depth = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '{' => depth += 1,
+            '}' if depth == 0 => break,
+            '}' => depth -= 1,
+            _ => (),
+        }
+    }
+}
+
+/// What `$NAME` or `${NAME}` becomes: nothing for a VS Code variable, otherwise it's left as it is
+fn variable(name: &str, braced: bool) -> String {
+    if VARIABLES.iter().any(|variable| name.starts_with(variable)) {
+        String::new()
+    } else if braced {
+        format!("${{{}}}", name)
+    } else {
+        format!("${}", name)
+    }
+}
+
+/// Snippet `body` with tabstops and VS Code variables taken out, placeholders as their default text,
+/// and choices as their first choice. Reads up to the `}` closing a placeholder with `nested`
+fn strip_placeholders(chars: &mut Peekable<Chars<'_>>, nested: bool) -> String {
+    let mut code = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some('$') | Some('}') | Some('\\') => code.extend(chars.next()),
+                _ => code.push(c),
+            },
+            '}' if nested => return code,
+            '$' => match chars.peek() {
+                Some(c) if c.is_ascii_digit() => {
+                    take_name(chars, true);
+                }
+                Some(c) if c.is_ascii_alphabetic() || *c == '_' => {
+                    let name = take_name(chars, false);
+                    code.push_str(&variable(&name, false));
+                }
+                Some('{') => {
+                    chars.next();
+                    let name = take_name(chars, false);
+                    match chars.next() {
+                        Some(':') => code.push_str(&strip_placeholders(chars, true)),
+                        Some('|') => {
+                            let mut choices = String::new();
+                            while let Some(c) = chars.next() {
+                                match c {
+                                    '\\' => choices.extend(chars.next()),
+                                    '|' if chars.peek() == Some(&'}') => {
+                                        chars.next();
+                                        break;
+                                    }
+                                    c => choices.push(c),
+                                }
+                            }
+                            code.push_str(choices.split(',').next().unwrap_or_default());
+                        }
+                        Some('/') => skip_transform(chars),
+                        Some('}') if !name.chars().all(|c| c.is_ascii_digit()) => {
+                            code.push_str(&variable(&name, true))
+                        }
+                        Some('}') => (),
+                        other => {
+                            code.push_str("${");
+                            code.push_str(&name);
+                            code.extend(other);
+                        }
+                    }
+                }
+                _ => code.push(c),
+            },
+            c => code.push(c),
+        }
+    }
+    code
+}
+
+/// `body` with placeholders replaced by their default text (Sublime Text's fields are the same)
+pub(super) fn without_placeholders(body: &str) -> String {
+    strip_placeholders(&mut body.chars().peekable(), false)
+}
+
+/// Snippets in a VS Code snippets file, in the language of each snippet's `scope`, or if it doesn't have one,
+/// `file_language` (from the file name, i.e. `javascript.json`). With `keep_placeholders`, code is as it is in VS Code
+pub(super) fn read(
+    reader: &mut dyn io::Read,
+    file_language: Option<&str>,
+    languages: &HashMap<String, Language>,
+    keep_placeholders: bool,
+) -> color_eyre::Result<Vec<Snippet>> {
+    // By name, VS Code doesn't keep them in any order
+    let vscode_snippets: BTreeMap<String, VsCodeSnippet> =
+        serde_json::from_str(&strip_jsonc(&read_to_string(reader)?))
+            .map_err(|e| import_error("VS Code snippets", e))?;
+    Ok(vscode_snippets
+        .into_iter()
+        .map(|(name, snippet)| {
+            let language = snippet
+                .scope
+                .as_deref()
+                .and_then(|scope| scope.split(',').map(str::trim).find(|s| !s.is_empty()))
+                .or(file_language)
+                .map_or_else(
+                    || String::from("text"),
+                    |language| known_language(vscode_language(language), languages),
+                );
+            let tags: Vec<_> = snippet
+                .prefix
+                .map(OneOrMany::into_vec)
+                .unwrap_or_default()
+                .iter()
+                .map(|prefix| as_tag(prefix))
+                .filter(|tag| !tag.is_empty())
+                .collect();
+            let description = snippet
+                .description
+                .filter(|description| !description.trim().is_empty())
+                .unwrap_or(name);
+            let body = snippet.body.into_vec().join("\n");
+            let code = if keep_placeholders {
+                body
+            } else {
+                without_placeholders(&body)
+            };
+            Snippet::new(
+                0,
+                description,
+                language,
+                String::new(),
+                &tags.join(" "),
+                Utc::now(),
+                Utc::now(),
+                format!("{}\n", code.trim_end()),
+            )
+        })
+        .collect())
+}
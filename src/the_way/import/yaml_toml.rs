@@ -0,0 +1,134 @@
+//! `the-way import --format yaml` and `--format toml`: snippets written by hand, where multi-line code
+//! is easier to write than in JSON. YAML has a list of snippets (or a `snippets:` list), TOML a `[[snippets]]` table
+//! for each. Only description and code are needed, dates can be plain dates, and tags a list or one string
+use std::io;
+
+use chrono::Utc;
+use serde_json::Value;
+
+use crate::the_way::import::{import_error, parse_date, read_to_string};
+use crate::the_way::snippet::Snippet;
+
+/// A list of tags, or tags separated by spaces (or commas)
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum Tags {
+    List(Vec<String>),
+    Text(String),
+}
+
+impl Default for Tags {
+    fn default() -> Self {
+        Self::List(Vec::new())
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct SnippetFields {
+    description: String,
+    #[serde(default)]
+    language: Option<String>,
+    code: String,
+    #[serde(default)]
+    tags: Tags,
+    #[serde(default)]
+    date: Option<String>,
+    #[serde(default)]
+    updated: Option<String>,
+}
+
+/// `value` as JSON, with TOML's dates as strings
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(text) => Value::String(text),
+        toml::Value::Integer(number) => Value::from(number),
+        toml::Value::Float(number) => Value::from(number),
+        toml::Value::Boolean(boolean) => Value::Bool(boolean),
+        toml::Value::Datetime(date) => Value::String(date.to_string()),
+        toml::Value::Array(values) => Value::Array(values.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Snippets in `value`: a list of them, a `snippets` list, or one snippet
+fn snippets(value: Value, format: &str) -> color_eyre::Result<Vec<Snippet>> {
+    let values = match value {
+        Value::Array(values) => values,
+        Value::Object(mut object) if object.contains_key("snippets") => {
+            match object.remove("snippets") {
+                Some(Value::Array(values)) => values,
+                _ => return Err(import_error(format, "snippets isn't a list")),
+            }
+        }
+        Value::Null => Vec::new(),
+        value => vec![value],
+    };
+    let mut snippets = Vec::with_capacity(values.len());
+    for (number, value) in values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| (i + 1, value))
+    {
+        let fields: SnippetFields = serde_json::from_value(value)
+            .map_err(|e| import_error(format, format!("snippet {}: {}", number, e)))?;
+        let date = |date: &Option<String>| match date.as_deref().map(str::trim) {
+            None | Some("") => Ok(None),
+            Some(date) => parse_date(date).map(Some).ok_or_else(|| {
+                import_error(
+                    format,
+                    format!("snippet {} has an unknown date {:?}", number, date),
+                )
+            }),
+        };
+        let created = date(&fields.date)?.unwrap_or_else(Utc::now);
+        let updated = date(&fields.updated)?.unwrap_or(created);
+        let tags = match fields.tags {
+            Tags::List(tags) => tags,
+            Tags::Text(tags) => tags
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_owned)
+                .collect(),
+        };
+        let mut code = fields.code;
+        if !code.ends_with('\n') {
+            code.push('\n');
+        }
+        snippets.push(Snippet::new(
+            0,
+            fields.description,
+            fields
+                .language
+                .filter(|language| !language.trim().is_empty())
+                .map_or_else(
+                    || String::from("text"),
+                    |language| language.to_ascii_lowercase(),
+                ),
+            String::new(),
+            &tags.join(" "),
+            created,
+            updated,
+            code,
+        ));
+    }
+    Ok(snippets)
+}
+
+/// Snippets in a YAML file
+pub(super) fn read_yaml(reader: &mut dyn io::Read) -> color_eyre::Result<Vec<Snippet>> {
+    let value: Value =
+        serde_yaml::from_str(&read_to_string(reader)?).map_err(|e| import_error("YAML", e))?;
+    snippets(value, "YAML")
+}
+
+/// Snippets in a TOML file
+pub(super) fn read_toml(reader: &mut dyn io::Read) -> color_eyre::Result<Vec<Snippet>> {
+    let value: toml::Value =
+        toml::from_str(&read_to_string(reader)?).map_err(|e| import_error("TOML", e))?;
+    snippets(toml_to_json(value), "TOML")
+}
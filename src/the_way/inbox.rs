@@ -0,0 +1,165 @@
+//! Inbox for code saved without any questions (`the-way inbox add`, `watch-clipboard --queue`),
+//! described and tagged later with `the-way inbox triage`
+use std::io::{self, Read};
+
+use chrono::Utc;
+use color_eyre::Help;
+use dialoguer::{theme::ColorfulTheme, Select};
+
+use crate::errors::LostTheWay;
+use crate::language::Language;
+use crate::the_way::clipboard_watch::{guess_language, INBOX_TAG};
+use crate::the_way::tag_rules::{self, Rule};
+use crate::the_way::{snippet::Snippet, undo::UndoEntry, TheWay};
+use crate::utils;
+
+impl TheWay {
+    /// Saves `code` (or the clipboard, or stdin) to the inbox
+    pub(crate) fn inbox_add(
+        &mut self,
+        code: Option<&str>,
+        clipboard: bool,
+    ) -> color_eyre::Result<()> {
+        let code = match code {
+            Some(code) => code.to_owned(),
+            None if clipboard => utils::read_clipboard()
+                .suggestion("Make sure xclip (Linux) or pbpaste (macOS) is installed")?,
+            None => {
+                let mut code = String::new();
+                io::stdin().read_to_string(&mut code)?;
+                code
+            }
+        };
+        let code = code.trim();
+        if code.is_empty() {
+            let error: color_eyre::Result<()> = Err(LostTheWay::OutOfCheeseError {
+                message: String::from("No code to save"),
+            }
+            .into());
+            return error
+                .suggestion("Give the code as an argument, pipe it in, or use --clipboard");
+        }
+        let rules = self.tag_rules()?;
+        let index = self.save_to_inbox(code, "inbox add", &rules)?;
+        println!("Added snippet #{} to the inbox", index);
+        Ok(())
+    }
+
+    /// Saves code without asking anything: the first line is the description,
+    /// the language is guessed, and the snippet is tagged for the inbox (and by `rules`)
+    pub(crate) fn save_to_inbox(
+        &mut self,
+        code: &str,
+        command: &str,
+        rules: &[Rule],
+    ) -> color_eyre::Result<usize> {
+        let language = guess_language(code);
+        let extension = Language::get_extension(&language, &self.languages);
+        let mut snippet = Snippet::new(
+            self.get_current_snippet_index()? + 1,
+            code.lines().next().unwrap_or_default().trim().to_owned(),
+            language,
+            extension,
+            INBOX_TAG,
+            Utc::now(),
+            Utc::now(),
+            format!("{}\n", code),
+        );
+        tag_rules::apply_tag_rules(rules, &mut snippet);
+        snippet.validate(&self.config.validation)?;
+        let index = self.add_snippet(&snippet)?;
+        self.increment_snippet_index()?;
+        self.record_change(&UndoEntry::added(command, &[index]))?;
+        self.db.flush()?;
+        Ok(index)
+    }
+
+    /// Goes through inbox snippets (oldest first), asking for a description, language, and tags for each
+    pub(crate) fn inbox_triage(&mut self) -> color_eyre::Result<()> {
+        let mut snippets: Vec<_> = self
+            .list_snippets()?
+            .into_iter()
+            .filter(|snippet| snippet.has_tag(INBOX_TAG))
+            .collect();
+        snippets.sort_by_key(|snippet| snippet.index);
+        if snippets.is_empty() {
+            println!("Nothing in the inbox");
+            return Ok(());
+        }
+        let total = snippets.len();
+        let mut left = total;
+        for (i, snippet) in snippets.into_iter().enumerate() {
+            let lines = if self.cli.accessible {
+                snippet.plain_print(true)
+            } else {
+                snippet.pretty_print(
+                    &self.highlighter,
+                    self.languages
+                        .get(&snippet.language)
+                        .unwrap_or(&Language::default()),
+                )?
+            };
+            for line in lines {
+                print!("{}", line);
+            }
+            let choice = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("Inbox snippet {} of {}", i + 1, total))
+                .items(&["Describe and tag it", "Skip it", "Delete it", "Stop"])
+                .default(0)
+                .interact()?;
+            match choice {
+                0 => {
+                    let index = snippet.index;
+                    let description =
+                        utils::user_input("Description", Some(&snippet.description), true, false)?;
+                    let language =
+                        utils::user_input("Language", Some(&snippet.language), true, false)?
+                            .to_ascii_lowercase();
+                    let tags = snippet
+                        .tags
+                        .iter()
+                        .filter(|tag| tag.as_str() != INBOX_TAG)
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    let tags = utils::user_input(
+                        "Tags (space separated)",
+                        Some(&tags),
+                        !tags.is_empty(),
+                        true,
+                    )?;
+                    let extension = Language::get_extension(&language, &self.languages);
+                    let new_snippet = Snippet::new(
+                        index,
+                        description,
+                        language,
+                        extension,
+                        &tags,
+                        snippet.date,
+                        Utc::now(),
+                        snippet.code,
+                    );
+                    new_snippet.validate(&self.config.validation)?;
+                    let old_snippet = self.delete_snippet(index)?;
+                    self.add_snippet(&new_snippet)?;
+                    self.record_change(&UndoEntry::changed("inbox triage", old_snippet))?;
+                    println!("Snippet #{} filed", index);
+                    left -= 1;
+                }
+                1 => {}
+                2 => {
+                    let index = snippet.index;
+                    self.backup_before("inbox triage")?;
+                    let mut entry = UndoEntry::new("inbox triage");
+                    self.delete_for_undo(index, &mut entry)?;
+                    self.record_change(&entry)?;
+                    println!("Snippet #{} deleted", index);
+                    left -= 1;
+                }
+                _ => break,
+            }
+        }
+        println!("{} snippet(s) left in the inbox", left);
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+//! Man pages and Markdown docs generated from the `StructOpt` definitions
+use std::fs;
+use std::path::Path;
+
+use chrono::{TimeZone, Utc};
+
+use crate::the_way::completions::CommandSpec;
+
+/// Escapes text for troff
+fn escape(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let line = line.replace('\\', "\\e").replace('-', "\\-");
+            if line.starts_with('.') || line.starts_with('\'') {
+                format!("\\&{}", line)
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// "the-way themes set" -> "the-way-themes-set"
+fn page_name(full_name: &str) -> String {
+    full_name.replace(' ', "-")
+}
+
+/// Usage line, e.g. "the-way del [OPTIONS] <index>"
+fn usage(command: &CommandSpec, full_name: &str) -> String {
+    let mut usage = vec![full_name.to_owned()];
+    if !command.flags.is_empty() {
+        usage.push(String::from("[OPTIONS]"));
+    }
+    if !command.subcommands.is_empty() {
+        usage.push(String::from("<SUBCOMMAND>"));
+    }
+    for positional in &command.positionals {
+        if positional.required {
+            usage.push(format!("<{}>", positional.name));
+        } else {
+            usage.push(format!("[{}]", positional.name));
+        }
+    }
+    usage.join(" ")
+}
+
+/// Flag names, e.g. "-f, --force <force>"
+fn flag_names(short: Option<char>, long: Option<&str>, takes_value: bool) -> String {
+    let mut names = Vec::new();
+    if let Some(short) = short {
+        names.push(format!("-{}", short));
+    }
+    if let Some(long) = long {
+        names.push(format!("--{}", long));
+    }
+    let mut names = names.join(", ");
+    if takes_value {
+        names.push_str(&format!(" <{}>", long.unwrap_or("value")));
+    }
+    names
+}
+
+/// Date for the page footer, from $SOURCE_DATE_EPOCH if it's set (for reproducible builds),
+/// otherwise the version, so the same release always gives the same pages
+fn page_date() -> String {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse().ok())
+        .and_then(|seconds| Utc.timestamp_opt(seconds, 0).single())
+        .map_or_else(
+            || env!("CARGO_PKG_VERSION").to_owned(),
+            |date| date.format("%Y-%m-%d").to_string(),
+        )
+}
+
+/// troff man page for a single command
+fn man_page(command: &CommandSpec, full_name: &str) -> String {
+    let mut page = format!(
+        ".TH \"{}\" 1 \"{}\" \"the-way {}\" \"User Commands\"\n",
+        page_name(full_name).to_ascii_uppercase(),
+        page_date(),
+        env!("CARGO_PKG_VERSION")
+    );
+    page.push_str(&format!(
+        ".SH NAME\n{} \\- {}\n",
+        escape(&page_name(full_name)),
+        escape(&command.about)
+    ));
+    page.push_str(&format!(
+        ".SH SYNOPSIS\n\\fB{}\\fR\n",
+        escape(&usage(command, full_name))
+    ));
+    page.push_str(&format!(
+        ".SH DESCRIPTION\n{}\n",
+        escape(&command.long_about)
+    ));
+    if !command.aliases.is_empty() {
+        page.push_str(&format!(
+            ".PP\nAliases: {}\n",
+            escape(&command.aliases.join(", "))
+        ));
+    }
+    if !command.positionals.is_empty() {
+        page.push_str(".SH ARGUMENTS\n");
+        for positional in &command.positionals {
+            page.push_str(&format!(
+                ".TP\n\\fI{}\\fR\n{}\n",
+                escape(&positional.name),
+                escape(&positional.help)
+            ));
+            if !positional.possible_values.is_empty() {
+                page.push_str(&format!(
+                    "[possible values: {}]\n",
+                    escape(&positional.possible_values.join(", "))
+                ));
+            }
+        }
+    }
+    if !command.flags.is_empty() {
+        page.push_str(".SH OPTIONS\n");
+        for flag in &command.flags {
+            page.push_str(&format!(
+                ".TP\n\\fB{}\\fR\n{}\n",
+                escape(&flag_names(
+                    flag.short,
+                    flag.long.as_deref(),
+                    flag.takes_value
+                )),
+                escape(&flag.help)
+            ));
+            if !flag.possible_values.is_empty() {
+                page.push_str(&format!(
+                    "[possible values: {}]\n",
+                    escape(&flag.possible_values.join(", "))
+                ));
+            }
+        }
+    }
+    if !command.subcommands.is_empty() {
+        page.push_str(".SH SUBCOMMANDS\n");
+        for subcommand in &command.subcommands {
+            page.push_str(&format!(
+                ".TP\n\\fB{}\\fR(1)\n{}\n",
+                escape(&page_name(&format!("{} {}", full_name, subcommand.name))),
+                escape(&subcommand.about)
+            ));
+        }
+    }
+    page
+}
+
+/// Markdown page for a single command
+fn markdown_page(command: &CommandSpec, full_name: &str) -> String {
+    let mut page = format!(
+        "# {}\n\n{}\n\n```\n{}\n```\n",
+        full_name,
+        command.long_about,
+        usage(command, full_name)
+    );
+    if !command.aliases.is_empty() {
+        page.push_str(&format!("\nAliases: `{}`\n", command.aliases.join("`, `")));
+    }
+    if !command.positionals.is_empty() {
+        page.push_str("\n## Arguments\n\n");
+        for positional in &command.positionals {
+            page.push_str(&format!("* `{}` - {}\n", positional.name, positional.help));
+        }
+    }
+    if !command.flags.is_empty() {
+        page.push_str("\n## Options\n\n");
+        for flag in &command.flags {
+            page.push_str(&format!(
+                "* `{}` - {}\n",
+                flag_names(flag.short, flag.long.as_deref(), flag.takes_value),
+                flag.help
+            ));
+        }
+    }
+    if !command.subcommands.is_empty() {
+        page.push_str("\n## Subcommands\n\n");
+        for subcommand in &command.subcommands {
+            let name = page_name(&format!("{} {}", full_name, subcommand.name));
+            page.push_str(&format!(
+                "* [`{}`]({}.md) - {}\n",
+                subcommand.name, name, subcommand.about
+            ));
+        }
+    }
+    page
+}
+
+/// Writes one page per command (and subcommand) with `make_page` to `dir`, returns the number of pages
+fn write_pages(
+    command: &CommandSpec,
+    full_name: &str,
+    dir: &Path,
+    extension: &str,
+    make_page: fn(&CommandSpec, &str) -> String,
+) -> color_eyre::Result<usize> {
+    fs::write(
+        dir.join(format!("{}.{}", page_name(full_name), extension)),
+        make_page(command, full_name),
+    )?;
+    let mut num_pages = 1;
+    for subcommand in &command.subcommands {
+        num_pages += write_pages(
+            subcommand,
+            &format!("{} {}", full_name, subcommand.name),
+            dir,
+            extension,
+            make_page,
+        )?;
+    }
+    Ok(num_pages)
+}
+
+/// Writes man pages (or Markdown docs) for every command to `dir`.
+/// Prints the main page to stdout if no directory is given
+pub(crate) fn generate(dir: Option<&Path>, markdown: bool) -> color_eyre::Result<()> {
+    let command = CommandSpec::the_way();
+    let (extension, make_page): (_, fn(&CommandSpec, &str) -> String) = if markdown {
+        ("md", markdown_page)
+    } else {
+        ("1", man_page)
+    };
+    match dir {
+        Some(dir) => {
+            fs::create_dir_all(dir)?;
+            let num_pages = write_pages(&command, &command.name, dir, extension, make_page)?;
+            println!("Wrote {} pages to {}", num_pages, dir.display());
+        }
+        None => print!("{}", make_page(&command, &command.name)),
+    }
+    Ok(())
+}
@@ -0,0 +1,371 @@
+//! Three-pane merge view (here, remote, result) for a snippet changed in two places.
+//! The two versions are split into lines both share and conflicts,
+//! and each conflict takes one side (or both) until the result is saved or edited by hand
+use std::cmp::max;
+
+use tuikit::prelude::{Attr, Color, Effect, Event, Key, Term, TermOptions};
+
+use crate::errors::LostTheWay;
+use crate::utils;
+
+const HELP: &str =
+    "↑/↓ conflict  ←/h here  →/l remote  b both  e edit  s keep both snippets  enter save  q skip";
+
+/// Which side a conflict takes in the result
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Take {
+    Here,
+    There,
+    Both,
+}
+
+/// Lines the two versions share, or lines where they differ
+#[derive(Debug)]
+enum Chunk {
+    Same(Vec<String>),
+    Conflict {
+        here: Vec<String>,
+        there: Vec<String>,
+        take: Take,
+    },
+}
+
+impl Chunk {
+    fn result(&self) -> Vec<&String> {
+        match self {
+            Self::Same(lines) => lines.iter().collect(),
+            Self::Conflict { here, there, take } => match take {
+                Take::Here => here.iter().collect(),
+                Take::There => there.iter().collect(),
+                Take::Both => here.iter().chain(there).collect(),
+            },
+        }
+    }
+}
+
+/// What to do with the two versions
+#[derive(Debug, PartialEq)]
+pub(crate) enum MergeChoice {
+    /// Keep this version
+    Here,
+    /// Keep the remote version
+    There,
+    /// Replace both with this code
+    Merged(String),
+    /// Keep both, as two snippets
+    KeepBoth,
+    /// Leave both alone for now
+    Skip,
+}
+
+/// Splits `here` and `there` into shared lines and conflicts (by longest common subsequence)
+fn diff_chunks(here: &str, there: &str) -> Vec<Chunk> {
+    let here: Vec<_> = here.lines().collect();
+    let there: Vec<_> = there.lines().collect();
+    let (n, m) = (here.len(), there.len());
+    // lengths[i][j]: length of the longest common subsequence of here[i..] and there[j..]
+    let mut lengths = vec![vec![0; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if here[i] == there[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                max(lengths[i + 1][j], lengths[i][j + 1])
+            };
+        }
+    }
+    let mut chunks = Vec::new();
+    let (mut same, mut ours, mut theirs) = (Vec::new(), Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && here[i] == there[j] {
+            if !ours.is_empty() || !theirs.is_empty() {
+                chunks.push(Chunk::Conflict {
+                    here: std::mem::take(&mut ours),
+                    there: std::mem::take(&mut theirs),
+                    take: Take::Here,
+                });
+            }
+            same.push(here[i].to_owned());
+            i += 1;
+            j += 1;
+        } else {
+            if !same.is_empty() {
+                chunks.push(Chunk::Same(std::mem::take(&mut same)));
+            }
+            if j >= m || (i < n && lengths[i + 1][j] >= lengths[i][j + 1]) {
+                ours.push(here[i].to_owned());
+                i += 1;
+            } else {
+                theirs.push(there[j].to_owned());
+                j += 1;
+            }
+        }
+    }
+    if !same.is_empty() {
+        chunks.push(Chunk::Same(same));
+    }
+    if !ours.is_empty() || !theirs.is_empty() {
+        chunks.push(Chunk::Conflict {
+            here: ours,
+            there: theirs,
+            take: Take::Here,
+        });
+    }
+    chunks
+}
+
+fn term_error(error: Box<dyn std::error::Error>) -> LostTheWay {
+    LostTheWay::OutOfCheeseError {
+        message: format!("Couldn't draw the merge view: {}", error),
+    }
+}
+
+/// One row of the here and remote panes: (here line, remote line, conflict number)
+type Row<'a> = (Option<&'a str>, Option<&'a str>, Option<usize>);
+/// One row of the result pane: (line, conflict number)
+type ResultRow<'a> = (Option<&'a str>, Option<usize>);
+
+struct MergeView<'a> {
+    title: &'a str,
+    here_label: &'a str,
+    there_label: &'a str,
+    extension: &'a str,
+    /// Whether the result should end with a newline
+    newline: bool,
+    chunks: Vec<Chunk>,
+    /// Conflict being changed
+    current: usize,
+    conflicts: usize,
+    /// Result edited by hand, replaces the one made from the chunks
+    edited: Option<String>,
+    scroll: usize,
+}
+
+impl<'a> MergeView<'a> {
+    fn result(&self) -> String {
+        if let Some(edited) = &self.edited {
+            return edited.to_owned();
+        }
+        let lines: Vec<_> = self
+            .chunks
+            .iter()
+            .flat_map(|chunk| chunk.result())
+            .map(String::as_str)
+            .collect();
+        let mut result = lines.join("\n");
+        if self.newline && !result.is_empty() {
+            result.push('\n');
+        }
+        result
+    }
+
+    /// Sets the side taken by the current conflict
+    fn take(&mut self, side: Take) {
+        self.edited = None;
+        if let Some(Chunk::Conflict { take, .. }) = self
+            .chunks
+            .iter_mut()
+            .filter(|chunk| matches!(chunk, Chunk::Conflict { .. }))
+            .nth(self.current)
+        {
+            *take = side;
+        }
+    }
+
+    /// Rows of the here and remote panes, and of the result pane, lined up by chunk
+    fn rows(&self) -> (Vec<Row<'_>>, Vec<ResultRow<'_>>) {
+        let (mut rows, mut result_rows) = (Vec::new(), Vec::new());
+        let mut conflict = 0;
+        for chunk in &self.chunks {
+            match chunk {
+                Chunk::Same(lines) => {
+                    for line in lines {
+                        rows.push((Some(line.as_str()), Some(line.as_str()), None));
+                        result_rows.push((Some(line.as_str()), None));
+                    }
+                }
+                Chunk::Conflict { here, there, .. } => {
+                    let result = chunk.result();
+                    for row in 0..max(max(here.len(), there.len()), result.len()).max(1) {
+                        rows.push((
+                            here.get(row).map(String::as_str),
+                            there.get(row).map(String::as_str),
+                            Some(conflict),
+                        ));
+                        result_rows
+                            .push((result.get(row).map(|line| line.as_str()), Some(conflict)));
+                    }
+                    conflict += 1;
+                }
+            }
+        }
+        (rows, result_rows)
+    }
+
+    fn draw(&mut self, term: &Term) -> color_eyre::Result<()> {
+        let (width, height) = term.term_size().map_err(term_error)?;
+        let pane = width.saturating_sub(2) / 3;
+        let body = height.saturating_sub(3);
+        term.clear().map_err(term_error)?;
+        let bold = Attr {
+            effect: Effect::BOLD,
+            ..Attr::default()
+        };
+        term.print_with_attr(0, 0, &fit(self.title, width), bold)
+            .map_err(term_error)?;
+        let conflicts = if self.edited.is_some() {
+            String::from("Result (edited)")
+        } else {
+            format!(
+                "Result (conflict {} of {})",
+                self.current + 1,
+                self.conflicts
+            )
+        };
+        for (column, label) in [self.here_label, self.there_label, &conflicts]
+            .iter()
+            .enumerate()
+        {
+            term.print_with_attr(1, column * (pane + 1), &fit(label, pane), bold)
+                .map_err(term_error)?;
+        }
+
+        // Keep the current conflict on screen
+        let first = self
+            .rows()
+            .0
+            .iter()
+            .position(|(_, _, conflict)| *conflict == Some(self.current));
+        if let Some(first) = first {
+            if first < self.scroll || first >= self.scroll + body {
+                self.scroll = first.saturating_sub(body / 3);
+            }
+        }
+        let result = self.result();
+        let (rows, result_rows) = self.rows();
+        let result_rows = if self.edited.is_some() {
+            result.lines().map(|line| (Some(line), None)).collect()
+        } else {
+            result_rows
+        };
+        let attr = |conflict: Option<usize>| match conflict {
+            Some(conflict) if conflict == self.current => Attr {
+                fg: Color::YELLOW,
+                effect: Effect::BOLD,
+                ..Attr::default()
+            },
+            Some(_) => Attr::from(Color::CYAN),
+            None => Attr::default(),
+        };
+        for (row, screen_row) in (self.scroll..).zip(2..2 + body) {
+            let (here, there, conflict) = rows.get(row).copied().unwrap_or((None, None, None));
+            let (result_line, result_conflict) =
+                result_rows.get(row).copied().unwrap_or((None, None));
+            for (column, line, conflict) in &[
+                (0, here, conflict),
+                (1, there, conflict),
+                (2, result_line, result_conflict),
+            ] {
+                if let Some(line) = line {
+                    term.print_with_attr(
+                        screen_row,
+                        column * (pane + 1),
+                        &fit(line, pane),
+                        attr(*conflict),
+                    )
+                    .map_err(term_error)?;
+                }
+                if *column < 2 {
+                    term.print(screen_row, column * (pane + 1) + pane, "│")
+                        .map_err(term_error)?;
+                }
+            }
+        }
+        term.print_with_attr(
+            height.saturating_sub(1),
+            0,
+            &fit(HELP, width),
+            Attr::from(Color::LIGHT_BLACK),
+        )
+        .map_err(term_error)?;
+        term.present().map_err(term_error)?;
+        Ok(())
+    }
+
+    fn run(&mut self, term: &Term) -> color_eyre::Result<MergeChoice> {
+        loop {
+            self.draw(term)?;
+            match term.poll_event().map_err(term_error)? {
+                Event::Key(Key::Down) | Event::Key(Key::Char('j')) | Event::Key(Key::Tab) => {
+                    self.current = (self.current + 1).min(self.conflicts.saturating_sub(1));
+                }
+                Event::Key(Key::Up) | Event::Key(Key::Char('k')) | Event::Key(Key::BackTab) => {
+                    self.current = self.current.saturating_sub(1);
+                }
+                Event::Key(Key::Left) | Event::Key(Key::Char('h')) => self.take(Take::Here),
+                Event::Key(Key::Right) | Event::Key(Key::Char('l')) => self.take(Take::There),
+                Event::Key(Key::Char('b')) => self.take(Take::Both),
+                Event::Key(Key::Char('e')) => {
+                    let result = self.result();
+                    term.pause().map_err(term_error)?;
+                    let edited = utils::external_editor_input(Some(&result), self.extension);
+                    term.restart().map_err(term_error)?;
+                    self.edited = Some(edited?);
+                }
+                Event::Key(Key::Char('s')) => return Ok(MergeChoice::KeepBoth),
+                Event::Key(Key::Enter) => return Ok(MergeChoice::Merged(self.result())),
+                Event::Key(Key::Char('q')) | Event::Key(Key::ESC) | Event::Key(Key::Ctrl('c')) => {
+                    return Ok(MergeChoice::Skip)
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// `text` cut (or padded) to `width` columns, with tabs as spaces
+fn fit(text: &str, width: usize) -> String {
+    let text = text.replace('\t', "    ");
+    let mut fitted: String = text.chars().take(width).collect();
+    let length = fitted.chars().count();
+    fitted.extend(std::iter::repeat(' ').take(width - length));
+    fitted
+}
+
+/// Shows `here` and `there` side by side with the merged result, and lets the user pick lines from each
+pub(crate) fn merge(
+    title: &str,
+    here_label: &str,
+    there_label: &str,
+    here: &str,
+    there: &str,
+    extension: &str,
+) -> color_eyre::Result<MergeChoice> {
+    let chunks = diff_chunks(here, there);
+    let conflicts = chunks
+        .iter()
+        .filter(|chunk| matches!(chunk, Chunk::Conflict { .. }))
+        .count();
+    let mut view = MergeView {
+        title,
+        here_label,
+        there_label,
+        extension,
+        newline: here.ends_with('\n') || there.ends_with('\n'),
+        chunks,
+        current: 0,
+        conflicts,
+        edited: None,
+        scroll: 0,
+    };
+    let term =
+        Term::with_options(TermOptions::default().clear_on_exit(true)).map_err(term_error)?;
+    let choice = view.run(&term);
+    drop(term);
+    Ok(match choice? {
+        MergeChoice::Merged(result) if result == here => MergeChoice::Here,
+        MergeChoice::Merged(result) if result == there => MergeChoice::There,
+        choice => choice,
+    })
+}
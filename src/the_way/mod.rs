@@ -1,29 +1,61 @@
 //! CLI code
+use std::cell::Cell;
 use std::collections::HashMap;
-use std::path::Path;
-use std::{fs, io};
+use std::fs;
 
 use color_eyre::Help;
 use dialoguer::Confirm;
-use structopt::clap::Shell;
-use structopt::StructOpt;
 
 use crate::configuration::{ConfigCommand, TheWayConfig};
 use crate::errors::LostTheWay;
-use crate::language::{CodeHighlight, Language};
+use crate::http::HttpClient;
+use crate::language::{self, CodeHighlight, Language};
+use crate::terminal_palette::Palette;
 use crate::the_way::{
-    cli::{TheWayCLI, ThemeCommand},
+    cli::{
+        FilterCommand, InboxCommand, LanguagesCommand, SnippetCommand, SyncCommand, TheWayCLI,
+        TheWayCommand, ThemeCommand,
+    },
     filter::Filters,
     snippet::Snippet,
+    undo::UndoEntry,
 };
 use crate::utils;
 
+mod backup;
 pub mod cli;
+mod clipboard_watch;
+pub mod completions;
+#[cfg(unix)]
+mod daemon;
 mod database;
+mod doctor;
+pub mod export;
+pub mod field_map;
 mod filter;
-mod gist;
+mod git_storage;
+mod history;
+pub mod ids;
+pub mod import;
+mod inbox;
+mod manpage;
+mod merge;
+mod profiles;
+mod requires;
 mod search;
+mod search_index;
+mod selftest;
+mod serve;
+mod share;
+mod snapshot;
 pub mod snippet;
+mod stats;
+pub mod sync;
+mod sync_log;
+mod tag_rules;
+mod track;
+mod triage;
+mod undo;
 
 /// Stores
 /// - project directory information from `directories`
@@ -40,6 +72,8 @@ pub struct TheWay {
     languages: HashMap<String, Language>,
     /// for `syntect` code highlighting
     highlighter: CodeHighlight,
+    /// Whether snippets changed during the current command, so the snapshot needs rewriting
+    snippets_changed: Cell<bool>,
 }
 
 // All command-line related functions
@@ -48,54 +82,250 @@ impl TheWay {
     /// Reads `sled` trees and metadata file from the locations specified in config.
     /// (makes new ones the first time).
     pub fn start(cli: TheWayCLI, languages: HashMap<String, Language>) -> color_eyre::Result<()> {
-        if let TheWayCLI::Config { cmd } = &cli {
-            if let ConfigCommand::Default { file } = cmd {
-                TheWayConfig::default_config(file.as_deref())?;
-                return Ok(());
-            }
+        if let TheWayCommand::Config {
+            cmd: ConfigCommand::Default { file },
+        } = &cli.cmd
+        {
+            TheWayConfig::default_config(file.as_deref())?;
+            return Ok(());
+        }
+        if let TheWayCommand::Doctor = &cli.cmd {
+            return doctor::doctor();
+        }
+        if let TheWayCommand::Selftest { fixtures } = &cli.cmd {
+            return selftest::selftest(fixtures, &languages);
+        }
+        if cli.accessible {
+            console::set_colors_enabled(false);
         }
         let config = TheWayConfig::load()?;
-        let mut the_way = Self {
+        // Kept until the-way exits, the daemon waits for it to close before opening the database again
+        #[cfg(unix)]
+        let _lent_database = {
+            if let TheWayCommand::Daemon { stop: false } = &cli.cmd {
+                return daemon::daemon(languages, config);
+            }
+            match daemon::forward(&cli, &config)? {
+                daemon::Forwarded::Ran(code) => std::process::exit(code),
+                daemon::Forwarded::Lent(connection) => Some(connection),
+                daemon::Forwarded::NoDaemon => None,
+            }
+        };
+        #[cfg(not(unix))]
+        {
+            if let TheWayCommand::Daemon { .. } = &cli.cmd {
+                let error: color_eyre::Result<()> = Err(LostTheWay::OutOfCheeseError {
+                    message: String::from("The daemon needs unix sockets"),
+                }
+                .into());
+                return error.suggestion("Run commands directly instead");
+            }
+        }
+        let mut the_way = Self::open(cli, languages, config)?;
+        the_way.periodic_backup()?;
+        // Needs the database closed
+        if let TheWayCommand::Restore { file, force } = &the_way.cli.cmd {
+            let (file, force) = (file.clone(), *force);
+            return the_way.restore(&file, force);
+        }
+        the_way.run()?;
+        Ok(())
+    }
+
+    /// Opens the database and loads languages and themes, as set in `config`
+    fn open(
+        cli: TheWayCLI,
+        mut languages: HashMap<String, Language>,
+        config: TheWayConfig,
+    ) -> color_eyre::Result<Self> {
+        language::apply_language_config(&mut languages, &config.languages)?;
+        let mut highlighter = CodeHighlight::new(
+            &config.theme,
+            config.themes_dir.clone(),
+            config.colorblind_mode,
+        )?;
+        highlighter.set_syntax_overrides(&config.languages, &languages)?;
+        let the_way = Self {
             db: Self::get_db(&config.db_dir)?,
             cli,
             languages,
-            highlighter: CodeHighlight::new(&config.theme, config.themes_dir.clone())?,
+            highlighter,
             config,
+            snippets_changed: Cell::new(false),
         };
         the_way.set_merge()?;
-        the_way.run()?;
-        Ok(())
+        the_way.update_search_index()?;
+        Ok(the_way)
     }
 
     fn run(&mut self) -> color_eyre::Result<()> {
-        match &self.cli {
-            TheWayCLI::New => self.the_way(),
-            TheWayCLI::Search { filters } => self.search(filters),
-            TheWayCLI::Cp { index } => self.copy(*index),
-            TheWayCLI::Edit { index } => {
-                let index = *index;
-                self.edit(index)
+        let result = self.run_command();
+        // Some snippets may have changed even if the command failed
+        self.update_snapshot();
+        result
+    }
+
+    fn run_command(&mut self) -> color_eyre::Result<()> {
+        match &self.cli.cmd {
+            TheWayCommand::New => self.the_way(),
+            TheWayCommand::Search {
+                filters,
+                all_profiles,
+            } => {
+                let snippets = self.search_snippets(filters, *all_profiles)?;
+                let highlight_color = self.highlight_color();
+                self.make_search(snippets, &highlight_color)
+            }
+            // `index` is only missing with `--last`
+            TheWayCommand::Cp { index, .. } => match index {
+                Some(index) => self.copy(self.resolve_index(index)?),
+                None => self.copy_last(),
+            },
+            TheWayCommand::Serve {
+                address,
+                port,
+                sync,
+            } => self.serve(address, *port, *sync),
+            TheWayCommand::History { number } => self.history(*number),
+            TheWayCommand::Edit { index, editor } => {
+                let (index, editor) = (self.resolve_index(index)?, *editor);
+                self.edit(index, editor)
             }
-            TheWayCLI::Del { index, force } => {
-                let (index, force) = (*index, *force);
+            TheWayCommand::Del { index, force } => {
+                let (index, force) = (self.resolve_index(index)?, *force);
                 self.delete(index, force)
             }
-            TheWayCLI::View { index } => self.view(*index),
-            TheWayCLI::List { filters } => self.list(filters),
-            TheWayCLI::Import { file } => {
-                let mut num = 0;
-                for mut snippet in self.import(file.as_deref())? {
-                    snippet.index = self.get_current_snippet_index()? + 1;
-                    self.add_snippet(&snippet)?;
-                    self.increment_snippet_index()?;
-                    num += 1;
+            TheWayCommand::View { index } => self.view(self.resolve_index(index)?),
+            TheWayCommand::Run { index } => self.run_snippet(self.resolve_index(index)?),
+            TheWayCommand::List { filters } => self.list(filters),
+            TheWayCommand::Stats { filters } => self.stats(filters),
+            TheWayCommand::Retag { rules } => {
+                if !*rules {
+                    let error: color_eyre::Result<()> = Err(LostTheWay::DoingNothing.into());
+                    return error.suggestion("Use `the-way retag --rules` to apply the tag rules");
+                }
+                self.retag()
+            }
+            TheWayCommand::Triage { filters } => {
+                let snippets = self.filter_snippets(filters)?;
+                self.triage(snippets)
+            }
+            TheWayCommand::Import { args } => {
+                let snippets = self.import(args)?;
+                if args.dry_run {
+                    println!("Would import {} snippets", snippets.len());
+                    for snippet in &snippets {
+                        let update = if snippet.index == 0 {
+                            String::new()
+                        } else {
+                            format!(", updating #{}", snippet.index)
+                        };
+                        println!(
+                            "  {} ({}){}",
+                            snippet.description.lines().next().unwrap_or_default(),
+                            snippet.language,
+                            update
+                        );
+                    }
+                    return Ok(());
+                }
+                let mut changes = Vec::new();
+                let (mut added, mut updated) = (0, 0);
+                for mut snippet in snippets {
+                    if snippet.index == 0 {
+                        snippet.index = self.get_current_snippet_index()? + 1;
+                        self.add_snippet(&snippet)?;
+                        self.increment_snippet_index()?;
+                        // So exporting and importing again updates it
+                        if let Some(uuid) = &snippet.uuid {
+                            self.set_uuid(snippet.index, uuid)?;
+                        }
+                        changes.push((snippet.index, None));
+                        added += 1;
+                    } else if !self.get_snippet(snippet.index)?.same_as(&snippet) {
+                        snippet.updated = chrono::Utc::now();
+                        let old_snippet = self.delete_snippet(snippet.index)?;
+                        self.add_snippet(&snippet)?;
+                        changes.push((snippet.index, Some(old_snippet)));
+                        updated += 1;
+                    }
+                }
+                if !changes.is_empty() {
+                    self.record_change(&UndoEntry {
+                        snippets: changes,
+                        ..UndoEntry::new("import")
+                    })?;
+                }
+                println!("Imported {} snippets", added);
+                if updated > 0 {
+                    println!("Updated {} snippets", updated);
                 }
-                println!("Imported {} snippets", num);
                 Ok(())
             }
-            TheWayCLI::Export { filters, file } => self.export(filters, file.as_deref()),
-            TheWayCLI::Complete { shell } => Self::complete(*shell),
-            TheWayCLI::Themes { cmd } => match cmd {
+            TheWayCommand::Export { args } => self.export(args),
+            TheWayCommand::Complete { shell } => {
+                completions::generate(*shell, &snapshot::snapshot_file(&self.config))
+            }
+            TheWayCommand::Manpage { dir, markdown } => {
+                manpage::generate(dir.as_deref(), *markdown)
+            }
+            TheWayCommand::Doctor => doctor::doctor(), //Already handled
+            TheWayCommand::Daemon { .. } => Ok(()),    //Already handled
+            TheWayCommand::Selftest { fixtures } => selftest::selftest(fixtures, &self.languages), //Already handled
+            TheWayCommand::Languages { cmd } => match cmd {
+                LanguagesCommand::Update { url } => {
+                    language::update_languages(&HttpClient::new(&self.config.http), url)
+                }
+                LanguagesCommand::Reset => language::reset_languages(),
+            },
+            TheWayCommand::WatchClipboard { queue, interval } => {
+                let (queue, interval) = (*queue, *interval);
+                self.watch_clipboard(interval, queue)
+            }
+            TheWayCommand::Inbox { cmd } => match cmd {
+                InboxCommand::Add { code, clipboard } => {
+                    let (code, clipboard) = (code.clone(), *clipboard);
+                    self.inbox_add(code.as_deref(), clipboard)
+                }
+                InboxCommand::Triage => self.inbox_triage(),
+            },
+            TheWayCommand::Filter { cmd } => match cmd {
+                FilterCommand::Save { name, filters } => {
+                    let (name, filters) = (name.clone(), filters.clone());
+                    self.save_filter(&name, &filters)
+                }
+                FilterCommand::List => self.list_filters(),
+                FilterCommand::Del { name } => {
+                    let name = name.clone();
+                    self.delete_filter(&name)
+                }
+            },
+            TheWayCommand::Snippet { cmd } => match cmd {
+                SnippetCommand::Track { index, file } => {
+                    self.track(self.resolve_index(index)?, file)
+                }
+                SnippetCommand::Untrack { index } => self.untrack(self.resolve_index(index)?),
+                SnippetCommand::Requires {
+                    index,
+                    requirements,
+                    clear,
+                } => self.requires(self.resolve_index(index)?, requirements, *clear),
+                SnippetCommand::Share { index, public } => {
+                    let (index, public) = (self.resolve_index(index)?, *public);
+                    self.share(index, public)
+                }
+                SnippetCommand::Refresh { watch, interval } => {
+                    if *watch {
+                        self.watch_tracked(*interval)
+                    } else {
+                        if self.refresh_tracked()? == 0 {
+                            println!("Everything up to date");
+                        }
+                        Ok(())
+                    }
+                }
+            },
+            TheWayCommand::Themes { cmd } => match cmd {
                 ThemeCommand::List => self.list_themes(),
                 ThemeCommand::Set { theme } => {
                     self.highlighter.set_theme(theme.to_owned())?;
@@ -104,24 +334,65 @@ impl TheWay {
                     Ok(())
                 }
                 ThemeCommand::Add { file } => self.highlighter.add_theme(file),
+                ThemeCommand::FromTerminal { name } => {
+                    let palette = Palette::from_terminal()?;
+                    self.highlighter
+                        .add_theme_from_string(name, &palette.to_tmtheme(name))?;
+                    println!(
+                        "Added theme {}, use `the-way themes set {}` to enable it",
+                        name, name
+                    );
+                    Ok(())
+                }
                 ThemeCommand::Language { file } => self.highlighter.add_syntax(file),
                 ThemeCommand::Get => self.get_theme(),
+                ThemeCommand::Export { file } => self.highlighter.export_themes(file),
+                ThemeCommand::Import { file } => {
+                    let theme = self.highlighter.import_themes(file)?;
+                    if let Some(theme) = theme {
+                        self.config.theme = theme;
+                        self.config.store()?;
+                    }
+                    Ok(())
+                }
             },
-            TheWayCLI::Clear { force } => self.clear(*force),
-            TheWayCLI::Config { cmd } => match cmd {
+            TheWayCommand::Undo => self.undo(),
+            TheWayCommand::Clear { force } => self.clear(*force),
+            TheWayCommand::Backup { file } => self.backup(file),
+            TheWayCommand::Restore { .. } => Ok(()), //Already handled
+            TheWayCommand::Config { cmd } => match cmd {
                 ConfigCommand::Default { file } => TheWayConfig::default_config(file.as_deref()), //Already handled
                 ConfigCommand::Get => TheWayConfig::print_config_location(),
+                ConfigCommand::Snapshot => {
+                    println!("{}", snapshot::snapshot_file(&self.config).display());
+                    Ok(())
+                }
             },
-            TheWayCLI::Sync => self.sync(),
+            TheWayCommand::Sync {
+                cmd: Some(SyncCommand::Log { number }),
+                ..
+            } => self.sync_log(*number),
+            TheWayCommand::Sync {
+                conflicts,
+                dry_run,
+                cmd,
+            } => self.sync(
+                *conflicts,
+                *dry_run || matches!(cmd, Some(SyncCommand::Status)),
+            ),
         }
     }
 
     /// Adds a new snippet
     fn the_way(&mut self) -> color_eyre::Result<()> {
-        let snippet =
+        let mut snippet =
             Snippet::from_user(self.get_current_snippet_index()? + 1, &self.languages, None)?;
-        println!("Added snippet #{}", self.add_snippet(&snippet)?);
+        tag_rules::apply_tag_rules(&self.tag_rules()?, &mut snippet);
+        snippet.validate(&self.config.validation)?;
+        let index = self.add_snippet(&snippet)?;
         self.increment_snippet_index()?;
+        self.record_change(&UndoEntry::added("new", &[index]))?;
+        println!("Added snippet #{}", index);
         Ok(())
     }
 
@@ -129,11 +400,14 @@ impl TheWay {
     fn delete(&mut self, index: usize, force: bool) -> color_eyre::Result<()> {
         if force
             || Confirm::new()
-                .with_prompt(&format!("Delete snippet #{}?", index))
+                .with_prompt(format!("Delete snippet #{}?", index))
                 .default(false)
                 .interact()?
         {
-            self.delete_snippet(index)?;
+            self.backup_before("del")?;
+            let mut entry = UndoEntry::new("del");
+            self.delete_for_undo(index, &mut entry)?;
+            self.record_change(&entry)?;
             println!("Snippet #{} deleted", index);
             Ok(())
         } else {
@@ -142,12 +416,19 @@ impl TheWay {
         }
     }
 
-    /// Modify a stored snippet's information
-    fn edit(&mut self, index: usize) -> color_eyre::Result<()> {
+    /// Modify a stored snippet's information, either with prompts or as a document in $EDITOR
+    fn edit(&mut self, index: usize, editor: bool) -> color_eyre::Result<()> {
         let old_snippet = self.get_snippet(index)?;
-        let new_snippet = Snippet::from_user(index, &self.languages, Some(&old_snippet))?;
-        self.delete_snippet(index)?;
+        let new_snippet = if editor {
+            Snippet::from_editor(&self.languages, &old_snippet)?
+        } else {
+            Snippet::from_user(index, &self.languages, Some(&old_snippet))?
+        };
+        new_snippet.validate(&self.config.validation)?;
+        self.backup_before("edit")?;
+        let old_snippet = self.delete_snippet(index)?;
         self.add_snippet(&new_snippet)?;
+        self.record_change(&UndoEntry::changed("edit", old_snippet))?;
         println!("Snippet #{} changed", index);
         Ok(())
     }
@@ -155,12 +436,19 @@ impl TheWay {
     /// Pretty prints a snippet to terminal
     fn view(&self, index: usize) -> color_eyre::Result<()> {
         let snippet = self.get_snippet(index)?;
-        for line in snippet.pretty_print(
-            &self.highlighter,
-            self.languages
-                .get(&snippet.language)
-                .unwrap_or(&Language::default()),
-        )? {
+        let requirements = self.get_requirements(index)?;
+        let lines = if self.cli.accessible {
+            snippet.plain_print_full(&requirements)
+        } else {
+            snippet.pretty_print_full(
+                &self.highlighter,
+                self.languages
+                    .get(&snippet.language)
+                    .unwrap_or(&Language::default()),
+                &requirements,
+            )?
+        };
+        for line in lines {
             print!("{}", line)
         }
         Ok(())
@@ -170,6 +458,7 @@ impl TheWay {
     fn copy(&self, index: usize) -> color_eyre::Result<()> {
         let snippet = self.get_snippet(index)?;
         utils::copy_to_clipboard(&snippet.code)?;
+        self.add_to_copy_history(index)?;
         println!("Snippet #{} copied to clipboard", index);
         Ok(())
     }
@@ -188,50 +477,24 @@ impl TheWay {
         Ok(())
     }
 
-    /// Imports snippets from a JSON file (ignores indices and appends to existing snippets)
-    /// TODO: It may be nice to check for duplicates somehow, too expensive?
-    fn import(&self, file: Option<&Path>) -> color_eyre::Result<Vec<Snippet>> {
-        let reader: Box<dyn io::Read> = match file {
-            Some(file) => Box::new(fs::File::open(file)?),
-            None => Box::new(io::stdin()),
-        };
-        let mut buffered = io::BufReader::new(reader);
-        let mut snippets = Snippet::read(&mut buffered).collect::<Result<Vec<_>, _>>()?;
-        for snippet in &mut snippets {
-            snippet.set_extension(&snippet.language.to_owned(), &self.languages);
-        }
-        Ok(snippets)
-    }
-
-    /// Saves (optionally filtered) snippets to a JSON file
-    fn export(&self, filters: &Filters, file: Option<&Path>) -> color_eyre::Result<()> {
-        let writer: Box<dyn io::Write> = match file {
-            Some(file) => Box::new(fs::File::create(file)?),
-            None => Box::new(io::stdout()),
-        };
-        let mut buffered = io::BufWriter::new(writer);
-        self.filter_snippets(filters)?
-            .into_iter()
-            .map(|snippet| snippet.to_json(&mut buffered))
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(())
-    }
-
     /// Lists snippets (optionally filtered)
     fn list(&self, filters: &Filters) -> color_eyre::Result<()> {
-        let mut snippets = self.filter_snippets(filters)?;
-        snippets.sort_by(|a, b| a.index.cmp(&b.index));
+        let snippets = self.filter_snippets(filters)?;
         let mut colorized = Vec::new();
         let default_language = Language::default();
         for snippet in &snippets {
-            colorized.extend_from_slice(
-                &snippet.pretty_print(
-                    &self.highlighter,
-                    self.languages
-                        .get(&snippet.language)
-                        .unwrap_or(&default_language),
-                )?,
-            );
+            if self.cli.accessible {
+                colorized.extend(snippet.plain_print(false));
+            } else {
+                colorized.extend_from_slice(
+                    &snippet.pretty_print(
+                        &self.highlighter,
+                        self.languages
+                            .get(&snippet.language)
+                            .unwrap_or(&default_language),
+                    )?,
+                );
+            }
         }
         for line in colorized {
             print!("{}", line);
@@ -239,29 +502,27 @@ impl TheWay {
         Ok(())
     }
 
-    /// Displays all snippet descriptions in a skim fuzzy search window
-    /// A preview window on the right shows the indices of snippets matching the query
-    fn search(&self, filters: &Filters) -> color_eyre::Result<()> {
-        let mut snippets = self.filter_snippets(filters)?;
-        snippets.sort_by(|a, b| a.index.cmp(&b.index));
-        self.make_search(
-            snippets,
-            &format!(
-                "#{}",
-                hex::encode(vec![
-                    self.highlighter.highlight_style.foreground.r,
-                    self.highlighter.highlight_style.foreground.g,
-                    self.highlighter.highlight_style.foreground.b,
-                ])
-            ),
-        )?;
-        Ok(())
-    }
-
-    /// Generates shell completions
-    fn complete(shell: Shell) -> color_eyre::Result<()> {
-        TheWayCLI::clap().gen_completions_to(utils::NAME, shell, &mut io::stdout());
-        Ok(())
+    /// Snippets to show in the search window (optionally filtered), without a profile name.
+    /// With `all_profiles`, snippets from every other profile are listed too, with the profile name
+    fn search_snippets(
+        &self,
+        filters: &Filters,
+        all_profiles: bool,
+    ) -> color_eyre::Result<Vec<(Option<String>, Snippet)>> {
+        let mut snippets: Vec<_> = self
+            .filter_snippets(filters)?
+            .into_iter()
+            .map(|snippet| (None, snippet))
+            .collect();
+        if all_profiles {
+            snippets.extend(
+                self.profile_snippets(filters)?
+                    .into_iter()
+                    .map(|(profile, snippet)| (Some(profile), snippet)),
+            );
+            filters.truncate(&mut snippets);
+        }
+        Ok(snippets)
     }
 
     /// Removes all `sled` trees
@@ -272,6 +533,7 @@ impl TheWay {
                 .default(false)
                 .interact()?
         {
+            self.backup_before("clear")?;
             for path in fs::read_dir(&self.config.db_dir)? {
                 let path = path?.path();
                 if path.is_dir() {
@@ -281,35 +543,11 @@ impl TheWay {
                 }
             }
             self.reset_index()?;
+            self.write_snapshot(Vec::new())?;
             Ok(())
         } else {
             let error: color_eyre::Result<()> = Err(LostTheWay::DoingNothing.into());
             error.suggestion("Press Y next time!")
         }
     }
-
-    /// Syncs snippets to Gist
-    fn sync(&mut self) -> color_eyre::Result<()> {
-        // Check if environment variable has changed
-        self.config.github_access_token = std::env::var("THE_WAY_GITHUB_TOKEN")
-            .ok()
-            .or_else(|| self.config.github_access_token.clone());
-        // Get token from user if not set
-        if self.config.github_access_token.is_none() {
-            println!("Get a GitHub access token from https://github.com/settings/tokens/new (add the \"gist\" scope)\n");
-            self.config.github_access_token = Some(
-                dialoguer::Password::with_theme(&dialoguer::theme::ColorfulTheme::default())
-                    .with_prompt("GitHub access token")
-                    .interact()?,
-            );
-        }
-        if self.config.gist_id.is_some() {
-            self.sync_gist()?;
-        } else {
-            self.config.gist_id =
-                Some(self.make_gist(self.config.github_access_token.as_ref().unwrap())?);
-        }
-        self.config.store()?;
-        Ok(())
-    }
 }
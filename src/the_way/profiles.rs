@@ -0,0 +1,76 @@
+//! Reading and changing snippets from other profiles (other the-way setups listed in the `[profiles]` config section)
+use std::mem;
+
+use crate::configuration::TheWayConfig;
+use crate::the_way::{filter::Filters, snippet::Snippet, TheWay};
+
+impl TheWay {
+    /// Snippets (optionally filtered) from every profile in the `[profiles]` config section, with the profile name.
+    /// sled opens profile databases read-write and locks them,
+    /// so profiles that can't be opened (e.g. in use by another the-way) are skipped with a warning.
+    pub(crate) fn profile_snippets(
+        &self,
+        filters: &Filters,
+    ) -> color_eyre::Result<Vec<(String, Snippet)>> {
+        let mut snippets = Vec::new();
+        for (name, file) in &self.config.profiles {
+            let config = TheWayConfig::load_profile(name, file)?;
+            // Skip profiles pointing at the current database, it's already being searched
+            if config.db_dir == self.config.db_dir || !config.db_dir.exists() {
+                continue;
+            }
+            let db = match sled::open(&config.db_dir) {
+                Ok(db) => db,
+                Err(e) => {
+                    eprintln!(
+                        "Skipped profile {}: couldn't open its database at {} ({})",
+                        name,
+                        config.db_dir.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            let mut profile_snippets = db
+                .open_tree("snippets")?
+                .iter()
+                .values()
+                .map(|snippet| Snippet::from_bytes(&snippet?))
+                .collect::<color_eyre::Result<Vec<_>>>()?;
+            profile_snippets.retain(|snippet| filters.matches(snippet));
+            filters.sort(&mut profile_snippets);
+            filters.truncate(&mut profile_snippets);
+            snippets.extend(
+                profile_snippets
+                    .into_iter()
+                    .map(|snippet| (name.to_owned(), snippet)),
+            );
+        }
+        filters.truncate(&mut snippets);
+        Ok(snippets)
+    }
+
+    /// Runs `change` with the database and config of profile `name` in place of the current ones,
+    /// so edits and deletes go to the database the snippets belong to (with its backups, undo log, and tombstones)
+    pub(crate) fn in_profile<T>(
+        &mut self,
+        name: &str,
+        change: impl FnOnce(&mut Self) -> color_eyre::Result<T>,
+    ) -> color_eyre::Result<T> {
+        let file = self.config.profiles[name].clone();
+        let config = TheWayConfig::load_profile(name, &file)?;
+        let db = Self::get_db(&config.db_dir)?;
+        let config = mem::replace(&mut self.config, config);
+        let db = mem::replace(&mut self.db, db);
+        let snippets_changed = self.snippets_changed.replace(false);
+        let result = self
+            .set_merge()
+            .and_then(|_| self.update_search_index())
+            .and_then(|_| change(self));
+        self.update_snapshot();
+        self.config = config;
+        self.db = db;
+        self.snippets_changed.set(snippets_changed);
+        result
+    }
+}
@@ -0,0 +1,78 @@
+//! What snippets need to run, for `the-way snippet requires` and `the-way run`
+use std::env;
+use std::process::Command;
+
+use crate::errors::LostTheWay;
+use crate::the_way::TheWay;
+use crate::utils;
+
+/// Requirements whose program (the first word, e.g. "jq" in "jq >= 1.6") isn't in $PATH
+fn missing_requirements(requirements: &[String]) -> Vec<&String> {
+    requirements
+        .iter()
+        .filter(|requirement| {
+            requirement
+                .split_whitespace()
+                .next()
+                .is_some_and(|program| utils::find_command(program).is_none())
+        })
+        .collect()
+}
+
+impl TheWay {
+    /// Sets (or clears, or shows) what a snippet needs to run
+    pub(crate) fn requires(
+        &self,
+        index: usize,
+        requirements: &[String],
+        clear: bool,
+    ) -> color_eyre::Result<()> {
+        // Make sure the snippet exists
+        self.get_snippet(index)?;
+        if clear {
+            self.set_requirements(index, &[])?;
+            println!("Snippet #{} has no requirements", index);
+        } else if requirements.is_empty() {
+            let requirements = self.get_requirements(index)?;
+            if requirements.is_empty() {
+                println!("Snippet #{} has no requirements", index);
+            } else {
+                for requirement in requirements {
+                    println!("{}", requirement);
+                }
+            }
+        } else {
+            let requirements: Vec<_> = requirements
+                .iter()
+                .map(|requirement| requirement.trim().to_owned())
+                .filter(|requirement| !requirement.is_empty())
+                .collect();
+            self.set_requirements(index, &requirements)?;
+            println!("Snippet #{} requires {}", index, requirements.join(", "));
+        }
+        Ok(())
+    }
+
+    /// Runs a snippet's code with $SHELL (or sh), warning about requirements that aren't in $PATH
+    pub(crate) fn run_snippet(&self, index: usize) -> color_eyre::Result<()> {
+        let snippet = self.get_snippet(index)?;
+        let requirements = self.get_requirements(index)?;
+        for requirement in missing_requirements(&requirements) {
+            eprintln!(
+                "Warning: snippet #{} requires {}, which isn't in $PATH",
+                index, requirement
+            );
+        }
+        let shell = env::var("SHELL").unwrap_or_else(|_| String::from("sh"));
+        let status = Command::new(shell).arg("-c").arg(&snippet.code).status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(LostTheWay::RunError {
+                index,
+                status: status.to_string(),
+            }
+            .into())
+        }
+    }
+}
@@ -1,35 +1,61 @@
 //! Fuzzy search capabilities
 use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{self, Command, Stdio};
+use std::rc::Rc;
 use std::sync::Arc;
 
-use skim::prelude::{unbounded, SkimOptionsBuilder};
-use skim::{AnsiString, ItemPreview, Skim, SkimItem, SkimItemReceiver, SkimItemSender};
+use color_eyre::Help;
+use dialoguer::{theme::ColorfulTheme, Confirm, Select};
+use skim::prelude::{
+    unbounded, AndOrEngineFactory, ExactOrFuzzyEngineFactory, FuzzyAlgorithm, SkimOptionsBuilder,
+};
+use skim::{
+    AnsiString, AsAny, CaseMatching, ItemPreview, MatchEngineFactory, Skim, SkimItem,
+    SkimItemReceiver, SkimItemSender,
+};
 
+use crate::configuration::SearchConfig;
 use crate::errors::LostTheWay;
 use crate::language::Language;
-use crate::the_way::{snippet::Snippet, TheWay};
-use crate::utils::copy_to_clipboard;
+use crate::the_way::{snippet::Snippet, undo::UndoEntry, TheWay};
+use crate::utils::{self, copy_to_clipboard};
 
 /// searchable snippet information
 #[derive(Debug)]
 struct SearchSnippet {
-    index: usize,
+    /// Profile the snippet belongs to, if not the current one
+    profile: Option<String>,
     /// Highlighted title
     text_highlight: String,
     /// Plain text title
     text: String,
-    /// Highlighted code
+    /// Highlighted code with line numbers, for the preview
     code_highlight: String,
-    /// Plain code for copying
-    code: String,
+    /// The snippet itself, for copying, deleting, or exporting
+    snippet: Snippet,
 }
 
-impl<'a> SkimItem for SearchSnippet {
-    fn display(&self) -> Cow<AnsiString> {
+impl SearchSnippet {
+    /// "snippet #3", or "snippet #3 from profile work" if it's from another profile
+    fn label(&self) -> String {
+        match &self.profile {
+            Some(profile) => format!("snippet #{} from profile {}", self.snippet.index, profile),
+            None => format!("snippet #{}", self.snippet.index),
+        }
+    }
+}
+
+impl SkimItem for SearchSnippet {
+    fn display(&self) -> Cow<'_, AnsiString<'_>> {
         Cow::Owned(AnsiString::parse(&self.text_highlight))
     }
 
-    fn text(&self) -> Cow<str> {
+    fn text(&self) -> Cow<'_, str> {
         Cow::Owned(self.text.to_owned())
     }
 
@@ -37,51 +63,435 @@ impl<'a> SkimItem for SearchSnippet {
         ItemPreview::AnsiText(self.code_highlight.to_owned())
     }
 
-    fn output(&self) -> Cow<str> {
-        copy_to_clipboard(&self.code).expect("Clipboard Error");
-        let text = format!("Copied snippet #{} to clipboard", self.index);
-        Cow::Owned(text)
+    fn output(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.snippet.code)
     }
 }
 
+/// Key in the search window to edit the picked snippets
+const EDIT_KEY: &str = "ctrl-e";
+/// Key in the search window to delete the picked snippets
+const DELETE_KEY: &str = "ctrl-d";
+/// Key in the search window to view the picked snippets in full
+const VIEW_KEY: &str = "ctrl-v";
+
+/// What to do with several snippets picked in a search window (with <RET>)
+const PICKED_ACTIONS: [&str; 4] = [
+    "Copy them (one after another)",
+    "Delete them",
+    "Export them to a file",
+    "Cancel",
+];
+
 impl TheWay {
-    /// Converts a list of snippets into searchable objects and opens the search window
+    /// Converts a list of snippets (with the profile they belong to, if not the current one)
+    /// into searchable objects and opens the search window.
+    /// One picked snippet is copied, for several there's a choice of copying, deleting, or exporting them.
     pub(crate) fn make_search(
-        &self,
-        snippets: Vec<Snippet>,
+        &mut self,
+        snippets: Vec<(Option<String>, Snippet)>,
         highlight_color: &str,
     ) -> color_eyre::Result<()> {
         let default_language = Language::default();
         let search_snippets: Vec<_> = snippets
             .into_iter()
-            .map(|snippet| SearchSnippet {
-                code_highlight: snippet
-                    .pretty_print_code(&self.highlighter)
-                    .unwrap_or_default()
-                    .join(""),
-                text_highlight: snippet
-                    .pretty_print_header(
-                        &self.highlighter,
-                        self.languages
-                            .get(&snippet.language)
-                            .unwrap_or(&default_language),
+            .map(|(profile, snippet)| {
+                let prefix = profile
+                    .as_ref()
+                    .map(|profile| format!("[{}] ", profile))
+                    .unwrap_or_default();
+                let (text_highlight, code_highlight) = if self.cli.accessible {
+                    (snippet.plain_header(), snippet.plain_code().join(""))
+                } else {
+                    (
+                        snippet
+                            .pretty_print_header(
+                                &self.highlighter,
+                                self.languages
+                                    .get(&snippet.language)
+                                    .unwrap_or(&default_language),
+                            )
+                            .unwrap_or_default()
+                            .join(""),
+                        snippet
+                            .pretty_print_numbered_code(&self.highlighter)
+                            .unwrap_or_default()
+                            .join(""),
                     )
-                    .unwrap_or_default()
-                    .join(""),
-                text: snippet.get_header(),
-                code: snippet.code,
-                index: snippet.index,
+                };
+                SearchSnippet {
+                    code_highlight,
+                    text_highlight: format!("{}{}", prefix, text_highlight),
+                    text: format!("{}{}", prefix, snippet.get_header()),
+                    profile,
+                    snippet,
+                }
             })
             .collect();
-        search(search_snippets, highlight_color)?;
+        let history = self.get_search_history()?;
+        let selected = search(
+            search_snippets,
+            highlight_color,
+            &self.config.search,
+            history,
+        )?;
+        if !selected.items.is_empty() {
+            self.add_to_search_history(&selected.query)?;
+        }
+        let picked: Vec<_> = selected
+            .items
+            .iter()
+            .filter_map(|item| AsAny::as_any(&**item).downcast_ref::<SearchSnippet>())
+            .collect();
+        match selected.key.as_deref() {
+            Some(EDIT_KEY) => return self.edit_picked(&picked),
+            Some(DELETE_KEY) => return self.delete_picked(&picked),
+            Some(VIEW_KEY) => return self.view_picked(&picked),
+            _ => {}
+        }
+        if picked.len() < 2 {
+            return self.copy_picked(&picked);
+        }
+        let choice = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("{} snippets picked", picked.len()))
+            .items(&PICKED_ACTIONS)
+            .default(0)
+            .interact()?;
+        match choice {
+            0 => self.copy_picked(&picked),
+            1 => self.delete_picked(&picked),
+            2 => self.export_picked(&picked),
+            _ => Ok(()),
+        }
+    }
+
+    /// Edits `picked` snippets one after another, with prompts, each in the database of its profile
+    fn edit_picked(&mut self, picked: &[&SearchSnippet]) -> color_eyre::Result<()> {
+        for item in picked {
+            let index = item.snippet.index;
+            match &item.profile {
+                Some(profile) => {
+                    println!("Editing {}", item.label());
+                    self.in_profile(profile, |the_way| the_way.edit(index, false))?
+                }
+                None => self.edit(index, false)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints `picked` snippets in full, like `view`
+    fn view_picked(&self, picked: &[&SearchSnippet]) -> color_eyre::Result<()> {
+        for item in picked {
+            match &item.profile {
+                Some(profile) => {
+                    let snippet = &item.snippet;
+                    let lines = if self.cli.accessible {
+                        snippet.plain_print_full(&[])
+                    } else {
+                        snippet.pretty_print_full(
+                            &self.highlighter,
+                            self.languages
+                                .get(&snippet.language)
+                                .unwrap_or(&Language::default()),
+                            &[],
+                        )?
+                    };
+                    println!("From profile {}:", profile);
+                    for line in lines {
+                        print!("{}", line)
+                    }
+                }
+                None => self.view(item.snippet.index)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the code of `picked` snippets to the clipboard, one after another
+    fn copy_picked(&self, picked: &[&SearchSnippet]) -> color_eyre::Result<()> {
+        if picked.is_empty() {
+            return Ok(());
+        }
+        let mut code = String::new();
+        for item in picked {
+            if !code.is_empty() && !code.ends_with('\n') {
+                code.push('\n');
+            }
+            code.push_str(&item.snippet.code);
+        }
+        copy_to_clipboard(&code)?;
+        for item in picked {
+            if item.profile.is_none() {
+                self.add_to_copy_history(item.snippet.index)?;
+            }
+            println!("Copied {} to clipboard", item.label());
+        }
+        Ok(())
+    }
+
+    /// Deletes `picked` snippets (after asking), as one change for `undo` in the database of each profile
+    fn delete_picked(&mut self, picked: &[&SearchSnippet]) -> color_eyre::Result<()> {
+        if picked.is_empty() {
+            return Ok(());
+        }
+        if !Confirm::new()
+            .with_prompt(format!("Delete {} snippets?", picked.len()))
+            .default(false)
+            .interact()?
+        {
+            let error: color_eyre::Result<()> = Err(LostTheWay::DoingNothing.into());
+            return error.suggestion("Press Y next time!");
+        }
+        let mut by_profile: BTreeMap<Option<&str>, Vec<usize>> = BTreeMap::new();
+        for item in picked {
+            by_profile
+                .entry(item.profile.as_deref())
+                .or_default()
+                .push(item.snippet.index);
+        }
+        for (profile, indices) in by_profile {
+            match profile {
+                Some(profile) => {
+                    self.in_profile(profile, |the_way| the_way.delete_indices(&indices))?;
+                    for index in indices {
+                        println!("Snippet #{} deleted from profile {}", index, profile);
+                    }
+                }
+                None => {
+                    self.delete_indices(&indices)?;
+                    for index in indices {
+                        println!("Snippet #{} deleted", index);
+                    }
+                }
+            }
+        }
         Ok(())
     }
+
+    /// Deletes snippets as one change for `undo`
+    fn delete_indices(&mut self, indices: &[usize]) -> color_eyre::Result<()> {
+        self.backup_before("search")?;
+        let mut entry = UndoEntry::new("search");
+        for index in indices {
+            self.delete_for_undo(*index, &mut entry)?;
+        }
+        self.record_change(&entry)
+    }
+
+    /// Writes `picked` snippets to a file (asked for) as JSON, for `import` to read back
+    fn export_picked(&self, picked: &[&SearchSnippet]) -> color_eyre::Result<()> {
+        let file = utils::user_input("File to export to", None, false, false)?;
+        let mut snippets = Vec::new();
+        for item in picked {
+            let mut snippet = item.snippet.clone();
+            // Other profiles' UUIDs aren't in this database
+            if item.profile.is_none() {
+                snippet.uuid = Some(self.get_uuid(snippet.index)?);
+            }
+            snippets.push(snippet);
+        }
+        self.write_json_file(&snippets, Path::new(&file))?;
+        println!("Exported {} snippets to {}", snippets.len(), file);
+        Ok(())
+    }
+
+    /// The theme's highlight color, for the selected line in search windows
+    pub(crate) fn highlight_color(&self) -> String {
+        format!(
+            "#{}",
+            hex::encode(vec![
+                self.highlighter.highlight_style.foreground.r,
+                self.highlighter.highlight_style.foreground.g,
+                self.highlighter.highlight_style.foreground.b,
+            ])
+        )
+    }
+
+    /// Opens a search window over `lines` (each shown on one line, in full above) and returns the ones picked,
+    /// for choosing what to save as snippets
+    pub(crate) fn pick_lines(&self, lines: Vec<String>) -> color_eyre::Result<Vec<String>> {
+        let items = lines
+            .into_iter()
+            .map(|line| PickLine {
+                text: line.replace('\n', " "),
+                line,
+            })
+            .map(|item| Arc::new(item) as Arc<dyn SkimItem>)
+            .collect();
+        Ok(
+            run_finder(items, &self.highlight_color(), &self.config.search, None)?
+                .items
+                .iter()
+                .filter_map(|item| AsAny::as_any(&**item).downcast_ref::<PickLine>())
+                .map(|item| item.line.to_owned())
+                .collect(),
+        )
+    }
+}
+
+/// A line to pick in `pick_lines`
+#[derive(Debug)]
+struct PickLine {
+    /// On one line, for the list
+    text: String,
+    line: String,
+}
+
+impl SkimItem for PickLine {
+    fn display(&self) -> Cow<'_, AnsiString<'_>> {
+        Cow::Owned(AnsiString::parse(&self.text))
+    }
+
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.text)
+    }
+
+    fn preview(&self) -> ItemPreview {
+        ItemPreview::Text(self.line.to_owned())
+    }
+}
+
+/// Possible `tiebreak` criteria in the `[search]` config section
+const TIEBREAK_CRITERIA: [&str; 8] = [
+    "score", "index", "begin", "end", "-score", "-index", "-begin", "-end",
+];
+
+/// Error for a value in the `[search]` config section that `skim` doesn't know
+fn search_config_error<T>(key: &str, value: &str, possible: &[&str]) -> color_eyre::Result<T> {
+    let error: color_eyre::Result<T> = Err(LostTheWay::ConfigError {
+        message: format!("Unknown search {} {:?}", key, value),
+    }
+    .into());
+    error.suggestion(format!(
+        "Set `{}` in the [search] section of your config file to one of {}",
+        key,
+        possible.join(", ")
+    ))
+}
+
+/// Reads the `[search]` config section into `skim`'s matcher settings:
+/// the match engine (with the fuzzy algorithm), case matching, and tiebreak criteria
+fn matcher_settings(
+    config: &SearchConfig,
+) -> color_eyre::Result<(Rc<dyn MatchEngineFactory>, CaseMatching, Option<String>)> {
+    let algorithm = match config.algorithm.as_deref().map(str::to_ascii_lowercase) {
+        None => FuzzyAlgorithm::default(),
+        Some(algorithm) => match algorithm.as_str() {
+            "skim_v2" | "skim" => FuzzyAlgorithm::SkimV2,
+            "skim_v1" => FuzzyAlgorithm::SkimV1,
+            "clangd" => FuzzyAlgorithm::Clangd,
+            _ => {
+                return search_config_error(
+                    "algorithm",
+                    &algorithm,
+                    &["skim_v2", "skim_v1", "clangd"],
+                )
+            }
+        },
+    };
+    let case = match config.case.as_deref().map(str::to_ascii_lowercase) {
+        None => CaseMatching::default(),
+        Some(case) => match case.as_str() {
+            "smart" => CaseMatching::Smart,
+            "ignore" => CaseMatching::Ignore,
+            "respect" => CaseMatching::Respect,
+            _ => return search_config_error("case", &case, &["smart", "ignore", "respect"]),
+        },
+    };
+    for criterion in &config.tiebreak {
+        if !TIEBREAK_CRITERIA.contains(&criterion.to_ascii_lowercase().as_str()) {
+            return search_config_error("tiebreak", criterion, &TIEBREAK_CRITERIA);
+        }
+    }
+    let tiebreak = if config.tiebreak.is_empty() {
+        None
+    } else {
+        Some(config.tiebreak.join(","))
+    };
+    // skim doesn't pass its `algorithm` option on to the default engine, so the engine is built here
+    let engine_factory: Rc<dyn MatchEngineFactory> = Rc::new(AndOrEngineFactory::new(
+        ExactOrFuzzyEngineFactory::builder()
+            .exact_mode(config.exact)
+            .fuzzy_algorithm(algorithm)
+            .build(),
+    ));
+    Ok((engine_factory, case, tiebreak))
 }
 
 /// Makes a fuzzy search window with the bottom panel listing each snippet's index, description,
-/// language and tags (all searchable) and the top panel showing the code for the selected snippet.
-fn search(input: Vec<SearchSnippet>, highlight_color: &str) -> color_eyre::Result<()> {
+/// language and tags (all searchable) and the top panel showing the highlighted code for the selected snippet,
+/// with line numbers.
+/// Past queries (oldest first) can be brought back with Ctrl-P and Ctrl-N.
+/// Returns the key pressed to pick snippets, the query, and the picked snippets.
+fn search(
+    input: Vec<SearchSnippet>,
+    highlight_color: &str,
+    config: &SearchConfig,
+    history: Vec<String>,
+) -> color_eyre::Result<Picked> {
+    let items = input
+        .into_iter()
+        .map(|item| Arc::new(item) as Arc<dyn SkimItem>)
+        .collect();
+    let extras = SearchExtras {
+        keys: [EDIT_KEY, DELETE_KEY, VIEW_KEY].join(","),
+        header: format!(
+            "<RET> copy, {} edit, {} delete, {} view, <TAB> pick several, C-p/C-n past searches",
+            EDIT_KEY, DELETE_KEY, VIEW_KEY
+        ),
+        history,
+    };
+    run_finder(items, highlight_color, config, Some(&extras))
+}
+
+/// What was picked in a search window
+#[derive(Default)]
+struct Picked {
+    /// Key pressed to pick items, `None` for <RET>
+    key: Option<String>,
+    /// Query typed in
+    query: String,
+    items: Vec<Arc<dyn SkimItem>>,
+}
+
+/// Keys (besides <RET>) that pick items in a search window, a line explaining them, and past queries to bring back
+struct SearchExtras {
+    /// Comma-separated, e.g. "ctrl-e,ctrl-d"
+    keys: String,
+    header: String,
+    /// Oldest first
+    history: Vec<String>,
+}
+
+/// Opens a search window (with the `[search]` config section's settings, and `extras`) over `items`
+/// and returns what was picked.
+/// The window is skim's, or an external fzf's with `selector = "fzf"`
+fn run_finder(
+    items: Vec<Arc<dyn SkimItem>>,
+    highlight_color: &str,
+    config: &SearchConfig,
+    extras: Option<&SearchExtras>,
+) -> color_eyre::Result<Picked> {
+    match config.selector.as_deref().map(str::to_ascii_lowercase) {
+        None => run_skim(items, highlight_color, config, extras),
+        Some(selector) => match selector.as_str() {
+            "skim" => run_skim(items, highlight_color, config, extras),
+            "fzf" => run_fzf(items, config, extras),
+            _ => search_config_error("selector", &selector, &["skim", "fzf"]),
+        },
+    }
+}
+
+/// Opens skim's search window over `items`
+fn run_skim(
+    items: Vec<Arc<dyn SkimItem>>,
+    highlight_color: &str,
+    config: &SearchConfig,
+    extras: Option<&SearchExtras>,
+) -> color_eyre::Result<Picked> {
     let color = format!("bg+:{}", highlight_color);
+    let (engine_factory, case, tiebreak) = matcher_settings(config)?;
     let options = SkimOptionsBuilder::default()
         .height(Some("100%"))
         .preview(Some(""))
@@ -89,19 +499,147 @@ fn search(input: Vec<SearchSnippet>, highlight_color: &str) -> color_eyre::Resul
         .multi(true)
         .reverse(true)
         .color(Some(&color))
+        .engine_factory(Some(engine_factory))
+        .case(case)
+        .tiebreak(tiebreak)
+        .expect(extras.map(|extras| extras.keys.to_owned()))
+        .header(extras.map(|extras| extras.header.as_str()))
+        .query_history(extras.map_or(&[], |extras| &extras.history))
+        .bind(if extras.is_some() {
+            vec!["ctrl-p:previous-history", "ctrl-n:next-history"]
+        } else {
+            Vec::new()
+        })
         .build()
         .map_err(|_| LostTheWay::SearchError)?;
 
     let (tx_item, rx_item): (SkimItemSender, SkimItemReceiver) = unbounded();
-    for item in input {
-        let _ = tx_item.send(Arc::new(item));
+    for item in items {
+        let _ = tx_item.send(item);
     }
     drop(tx_item); // so that skim could know when to stop waiting for more items.
 
-    let selected_items =
-        Skim::run_with(&options, Some(rx_item)).map_or_else(Vec::new, |out| out.selected_items);
-    for item in &selected_items {
-        print!("{}{}", item.output(), "\n");
+    Ok(
+        Skim::run_with(&options, Some(rx_item)).map_or_else(Picked::default, |out| Picked {
+            // <RET> is "" when there are other keys
+            key: out.accept_key.filter(|key| !key.is_empty()),
+            query: out.query,
+            items: out.selected_items,
+        }),
+    )
+}
+
+/// Opens an external fzf over `items`, sent as "<number>\t<text>" lines with only the text shown.
+/// Previews (and past queries) are written to files in a temporary folder for fzf to show.
+/// Only the `exact` and `case` settings are passed on, fzf's own settings (e.g. `$FZF_DEFAULT_OPTS`) do the rest
+fn run_fzf(
+    items: Vec<Arc<dyn SkimItem>>,
+    config: &SearchConfig,
+    extras: Option<&SearchExtras>,
+) -> color_eyre::Result<Picked> {
+    let preview_dir = env::temp_dir().join(format!("the-way-fzf-{}", process::id()));
+    fs::create_dir_all(&preview_dir)?;
+    let picked = fzf_pick(items, config, extras, &preview_dir);
+    fs::remove_dir_all(&preview_dir)?;
+    picked
+}
+
+/// Runs fzf for `run_fzf`, with previews in `preview_dir`
+fn fzf_pick(
+    items: Vec<Arc<dyn SkimItem>>,
+    config: &SearchConfig,
+    extras: Option<&SearchExtras>,
+    preview_dir: &Path,
+) -> color_eyre::Result<Picked> {
+    let mut lines = String::new();
+    for (number, item) in items.iter().enumerate() {
+        let preview = match item.preview() {
+            ItemPreview::Text(text) | ItemPreview::AnsiText(text) => text,
+            _ => String::new(),
+        };
+        fs::write(preview_dir.join(number.to_string()), preview)?;
+        lines.push_str(&format!("{}\t{}\n", number, item.text().replace('\n', " ")));
+    }
+    let mut command = Command::new("fzf");
+    command
+        .args([
+            "--multi",
+            "--ansi",
+            "--reverse",
+            "--delimiter",
+            "\t",
+            "--with-nth",
+            "2..",
+        ])
+        .arg("--preview")
+        .arg(format!("cat '{}'/{{1}}", preview_dir.display()))
+        .args(["--preview-window", "up:70%"]);
+    if config.exact {
+        command.arg("--exact");
+    }
+    match config
+        .case
+        .as_deref()
+        .map(str::to_ascii_lowercase)
+        .as_deref()
+    {
+        Some("ignore") => {
+            command.arg("-i");
+        }
+        Some("respect") => {
+            command.arg("+i");
+        }
+        _ => {}
+    }
+    if let Some(extras) = extras {
+        // fzf binds Ctrl-P and Ctrl-N to going through the history file, and adds the query to it
+        let history_file = preview_dir.join("history");
+        fs::write(&history_file, extras.history.join("\n"))?;
+        command
+            .arg(format!("--expect={}", extras.keys))
+            .arg(format!("--header={}", extras.header))
+            .arg(format!("--history={}", history_file.display()))
+            .arg("--print-query");
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| LostTheWay::ConfigError {
+            message: format!("Couldn't run fzf: {}", e),
+        })
+        .suggestion("Install fzf, or take `selector = \"fzf\"` out of the [search] section of your config file")?;
+    {
+        let stdin = child.stdin.as_mut().ok_or(LostTheWay::SearchError)?;
+        stdin.write_all(lines.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    match output.status.code() {
+        Some(0) => {}
+        // Nothing matched, or the search was closed with <ESC> or Ctrl-C
+        Some(1) | Some(130) => return Ok(Picked::default()),
+        _ => return Err(LostTheWay::SearchError.into()),
     }
-    Ok(())
+    let stdout = String::from_utf8(output.stdout)?;
+    let mut stdout_lines = stdout.lines();
+    // With --print-query and --expect, the first lines are the query and the key pressed ("" for <RET>)
+    let (query, key) = match extras {
+        Some(_) => (
+            stdout_lines.next().unwrap_or_default().to_owned(),
+            stdout_lines
+                .next()
+                .filter(|key| !key.is_empty())
+                .map(String::from),
+        ),
+        None => (String::new(), None),
+    };
+    let picked = stdout_lines
+        .filter_map(|line| line.split('\t').next()?.parse::<usize>().ok())
+        .filter_map(|number| items.get(number).cloned())
+        .collect();
+    Ok(Picked {
+        key,
+        query,
+        items: picked,
+    })
 }
@@ -0,0 +1,111 @@
+//! An index of the words in snippets' code, for `--code` to look snippets up in rather than reading each one
+//! (opt-in with `index = true` in the `[search]` config section).
+//! Kept up to date as snippets are saved and deleted, built the first time it's turned on,
+//! and dropped when it's turned off
+use std::collections::{BTreeSet, HashSet};
+
+use crate::the_way::{snippet::Snippet, TheWay};
+use crate::utils;
+
+/// Key in the database saying the index has every saved snippet in it
+const BUILT_KEY: &str = "search_index_built";
+
+/// The distinct words in `text`, in lowercase: runs of letters and digits
+fn words(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+impl TheWay {
+    /// Get the word: snippet indices tree
+    pub(crate) fn word_tree(&self) -> color_eyre::Result<sled::Tree> {
+        Ok(self.db.open_tree("word_to_snippet")?)
+    }
+
+    /// Builds the index if it's turned on and hasn't been, drops it if it's turned off
+    pub(crate) fn update_search_index(&self) -> color_eyre::Result<()> {
+        let built = self.db.contains_key(BUILT_KEY)?;
+        if self.config.search.index && !built {
+            self.word_tree()?.clear()?;
+            for snippet in self.list_snippets()? {
+                self.index_words(&snippet)?;
+            }
+            self.db.insert(BUILT_KEY, "true")?;
+        } else if !self.config.search.index && built {
+            self.db.drop_tree("word_to_snippet")?;
+            self.db.remove(BUILT_KEY)?;
+        }
+        Ok(())
+    }
+
+    /// Adds `snippet`'s index to each of the words in its code
+    pub(crate) fn index_words(&self, snippet: &Snippet) -> color_eyre::Result<()> {
+        let index_key = snippet.index.to_string();
+        let word_tree = self.word_tree()?;
+        for word in words(&snippet.code) {
+            word_tree.merge(word.as_bytes(), index_key.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Takes `snippet`'s index off each of the words in its code
+    pub(crate) fn unindex_words(&self, snippet: &Snippet) -> color_eyre::Result<()> {
+        let word_tree = self.word_tree()?;
+        let mut batch = sled::Batch::default();
+        for word in words(&snippet.code) {
+            if let Some(indices) = word_tree.get(word.as_bytes())? {
+                let new_indices: Vec<_> = utils::split_indices_usize(&indices)?
+                    .into_iter()
+                    .filter(|index| *index != snippet.index)
+                    .collect();
+                if new_indices.is_empty() {
+                    batch.remove(word.as_bytes());
+                } else {
+                    batch.insert(word.as_bytes(), utils::make_indices_string(&new_indices)?);
+                }
+            }
+        }
+        word_tree.apply_batch(batch)?;
+        Ok(())
+    }
+
+    /// Whether the index is there to look snippets up in
+    pub(crate) fn search_index_enabled(&self) -> bool {
+        self.config.search.index
+    }
+
+    /// Indices of the snippets that may have `text` in their code: the ones with a word starting with each of
+    /// the words in `text` after the first (which can be the end of a longer word in the code unless
+    /// something comes before it). `None` if there's no index or no words to look up, to read every snippet instead
+    pub(crate) fn search_index_lookup(
+        &self,
+        text: &str,
+    ) -> color_eyre::Result<Option<HashSet<usize>>> {
+        // Starts with "" if `text` starts with something other than a letter or digit
+        let words: BTreeSet<_> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .skip(1)
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        if !self.search_index_enabled() || words.is_empty() {
+            return Ok(None);
+        }
+        let word_tree = self.word_tree()?;
+        let mut found: Option<HashSet<usize>> = None;
+        for word in words {
+            let mut indices = HashSet::new();
+            for item in word_tree.scan_prefix(word.as_bytes()) {
+                let (_, value) = item?;
+                indices.extend(utils::split_indices_usize(&value)?);
+            }
+            found = Some(match found {
+                Some(found) => found.intersection(&indices).copied().collect(),
+                None => indices,
+            });
+        }
+        Ok(found)
+    }
+}
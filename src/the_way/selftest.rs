@@ -0,0 +1,173 @@
+//! Round-trips import/export formats against fixture files, for `the-way selftest`
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use color_eyre::Help;
+use serde_json::Value;
+
+use crate::errors::LostTheWay;
+use crate::language::Language;
+use crate::the_way::{field_map::FieldMap, snippet, snippet::Snippet};
+
+/// An import/export format that snippets can make a round trip through
+struct Format {
+    name: &'static str,
+    /// Extension of fixture files in this format
+    extension: &'static str,
+    import: fn(&mut dyn io::Read) -> color_eyre::Result<Vec<Snippet>>,
+    export: fn(&[Snippet], &mut dyn io::Write) -> color_eyre::Result<()>,
+}
+
+/// Every format `import` and `export` both understand
+const FORMATS: [Format; 1] = [Format {
+    name: "json",
+    extension: "json",
+    import: import_json,
+    export: export_json,
+}];
+
+fn import_json(reader: &mut dyn io::Read) -> color_eyre::Result<Vec<Snippet>> {
+    Snippet::read(reader)
+}
+
+fn export_json(snippets: &[Snippet], writer: &mut dyn io::Write) -> color_eyre::Result<()> {
+    for snippet in snippets {
+        snippet.to_json_with_fields(writer, &snippet::FIELDS)?;
+    }
+    Ok(())
+}
+
+/// Fields that differ between two snippets
+fn differences(before: &Snippet, after: &Snippet) -> color_eyre::Result<Vec<String>> {
+    let (before, after) = (serde_json::to_value(before)?, serde_json::to_value(after)?);
+    Ok(snippet::FIELDS
+        .iter()
+        .filter(|field| before.get(**field) != after.get(**field))
+        .map(|field| {
+            format!(
+                "{} {} became {}",
+                field,
+                before.get(*field).unwrap_or(&Value::Null),
+                after.get(*field).unwrap_or(&Value::Null)
+            )
+        })
+        .collect())
+}
+
+/// Imports `file` (with a field mapping file, if given), exports the snippets and imports them again.
+/// Returns the number of snippets and what changed on the way
+fn round_trip(
+    file: &Path,
+    map_file: Option<&Path>,
+    format: &Format,
+    languages: &HashMap<String, Language>,
+) -> color_eyre::Result<(usize, Vec<String>)> {
+    let mut reader = io::BufReader::new(fs::File::open(file)?);
+    let mut snippets = match map_file {
+        Some(map_file) => {
+            let map: FieldMap = map_file.to_string_lossy().parse()?;
+            Snippet::read_mapped(&mut reader, &map)?
+        }
+        None => (format.import)(&mut reader)?,
+    };
+    for snippet in &mut snippets {
+        snippet.set_extension(&snippet.language.to_owned(), languages);
+    }
+    let mut exported = Vec::new();
+    (format.export)(&snippets, &mut exported)?;
+    let reimported = (format.import)(&mut exported.as_slice())?;
+
+    let mut mismatches = Vec::new();
+    if reimported.len() != snippets.len() {
+        mismatches.push(format!(
+            "{} snippets became {}",
+            snippets.len(),
+            reimported.len()
+        ));
+    }
+    for (i, (before, after)) in snippets.iter().zip(&reimported).enumerate() {
+        for difference in differences(before, after)? {
+            mismatches.push(format!("snippet {}: {}", i + 1, difference));
+        }
+    }
+    Ok((snippets.len(), mismatches))
+}
+
+/// Round-trips every fixture in `fixtures` through its format and prints mismatches
+pub(crate) fn selftest(
+    fixtures: &Path,
+    languages: &HashMap<String, Language>,
+) -> color_eyre::Result<()> {
+    let mut files: Vec<_> = fs::read_dir(fixtures)
+        .map_err(|e| LostTheWay::OutOfCheeseError {
+            message: format!("Couldn't read fixtures from {}: {}", fixtures.display(), e),
+        })
+        .suggestion("Point --fixtures at a directory of exported snippet files")?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<color_eyre::Result<_>>()?;
+    files.sort();
+
+    let mut failed = 0;
+    for file in files.iter().filter(|file| file.is_file()) {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let extension = file
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_ascii_lowercase();
+        // Field mappings go with the fixture of the same name
+        if extension == "map" {
+            continue;
+        }
+        let format = match FORMATS.iter().find(|format| format.extension == extension) {
+            Some(format) => format,
+            None => {
+                println!(
+                    "{} {}: no format for .{} files",
+                    console::style("[skip]").yellow(),
+                    name,
+                    extension
+                );
+                continue;
+            }
+        };
+        let map_file = Some(file.with_extension("map")).filter(|map_file| map_file.is_file());
+        let format_name = if map_file.is_some() {
+            format!("{} --map", format.name)
+        } else {
+            format.name.to_owned()
+        };
+        let (num_snippets, mismatches) =
+            match round_trip(file, map_file.as_deref(), format, languages) {
+                Ok(result) => result,
+                Err(e) => (0, vec![format!("{}", e)]),
+            };
+        if mismatches.is_empty() {
+            println!(
+                "{} {} ({}): {} snippets",
+                console::style("[ok]  ").green(),
+                name,
+                format_name,
+                num_snippets
+            );
+        } else {
+            failed += 1;
+            println!(
+                "{} {} ({})",
+                console::style("[FAIL]").red(),
+                name,
+                format_name
+            );
+            for mismatch in mismatches {
+                println!("       {}", mismatch);
+            }
+        }
+    }
+    if failed > 0 {
+        Err(LostTheWay::SelftestError { failed }.into())
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+//! Minimal HTTP server for `the-way serve`, so snippets can be fetched from other machines
+//! (e.g. `curl http://host:8000/raw/12 | bash`).
+//! With `--sync`, other the-way instances can also sync against it (`sync_provider = "server"`)
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use color_eyre::Help;
+use ring::constant_time;
+
+use crate::errors::LostTheWay;
+use crate::sync::server::{ServerFile, SYNC_PATH};
+use crate::sync::uri_decode;
+use crate::the_way::{ids::SnippetId, TheWay};
+use crate::utils;
+
+/// Largest request body accepted, in bytes
+const MAX_BODY: usize = 16 * 1024 * 1024;
+/// Longest a client can take to send its request or read the response, requests are answered one at a time
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Request {
+    method: String,
+    /// Without the query string
+    path: String,
+    /// By lowercase name
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// Reads a request, `None` if its body is larger than `MAX_BODY` (it's answered with 413 then)
+fn read_request(stream: &mut TcpStream) -> color_eyre::Result<Option<Request>> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut headers = HashMap::new();
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_owned());
+        }
+        header.clear();
+    }
+    let length = headers
+        .get("content-length")
+        .and_then(|length| length.parse::<usize>().ok())
+        .unwrap_or_default();
+    if length > MAX_BODY {
+        respond(stream, "413 Payload Too Large", &[], b"")?;
+        return Ok(None);
+    }
+    let mut body = Vec::with_capacity(length);
+    reader.take(length as u64).read_to_end(&mut body)?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_owned();
+    let target = parts.next().unwrap_or_default();
+    Ok(Some(Request {
+        method,
+        path: target.split('?').next().unwrap_or_default().to_owned(),
+        headers,
+        body,
+    }))
+}
+
+/// Content type to serve a snippet's code as, by its extension
+fn content_type(extension: &str) -> &'static str {
+    match extension {
+        ".sh" | ".bash" | ".zsh" => "text/x-shellscript; charset=utf-8",
+        ".py" => "text/x-python; charset=utf-8",
+        ".js" => "text/javascript; charset=utf-8",
+        ".json" => "application/json",
+        ".html" => "text/html; charset=utf-8",
+        ".css" => "text/css; charset=utf-8",
+        ".xml" => "application/xml",
+        ".yml" | ".yaml" => "application/yaml",
+        ".md" => "text/markdown; charset=utf-8",
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    headers: &[(&str, String)],
+    body: &[u8],
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        body.len()
+    )?;
+    for (name, value) in headers {
+        write!(stream, "{}: {}\r\n", name, value)?;
+    }
+    write!(stream, "\r\n")?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+impl TheWay {
+    /// Serves snippets over HTTP until killed.
+    /// `GET /raw/<index>` returns a snippet's code as is, named snippet_<index>.<ext>.
+    /// With `sync`, files synced by other the-way instances are kept under /sync/
+    pub(crate) fn serve(&self, address: &str, port: u16, sync: bool) -> color_eyre::Result<()> {
+        let listener = TcpListener::bind((address, port))
+            .map_err(|e| LostTheWay::ServeError {
+                message: format!("Couldn't listen on {}:{}: {}", address, port, e),
+            })
+            .suggestion("Pick another --port, or check nothing else is listening there")?;
+        println!(
+            "Serving snippets at http://{}/raw/<index>",
+            listener.local_addr()?
+        );
+        let token = std::env::var("THE_WAY_SERVER_TOKEN")
+            .ok()
+            .or_else(|| self.config.server.token.clone());
+        if sync {
+            println!(
+                "Syncing at http://{} (sync_provider = \"server\")",
+                listener.local_addr()?
+            );
+            if token.is_none() {
+                println!("No `token` in the [server] section, anyone who can reach this address can change synced snippets");
+            }
+        }
+        for stream in listener.incoming() {
+            // One bad connection shouldn't stop the server
+            if let Err(e) = stream
+                .map_err(color_eyre::Report::from)
+                .and_then(|mut stream| self.handle_request(&mut stream, sync, token.as_deref()))
+            {
+                eprintln!("Couldn't answer request: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(
+        &self,
+        stream: &mut TcpStream,
+        sync: bool,
+        token: Option<&str>,
+    ) -> color_eyre::Result<()> {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let request = match read_request(stream)? {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+        if sync && request.path.starts_with(SYNC_PATH) {
+            return self.handle_sync(stream, &request, token);
+        }
+        if request.method != "GET" {
+            respond(
+                stream,
+                "405 Method Not Allowed",
+                &[("Allow", "GET".to_owned())],
+                b"",
+            )?;
+            return Ok(());
+        }
+        let snippet = request
+            .path
+            .strip_prefix("/raw/")
+            .and_then(|id| id.parse::<SnippetId>().ok())
+            .and_then(|id| self.resolve_index(&id).ok())
+            .and_then(|index| self.get_snippet(index).ok());
+        match snippet {
+            Some(snippet) => {
+                let headers = [
+                    ("Content-Type", content_type(&snippet.extension).to_owned()),
+                    (
+                        "Content-Disposition",
+                        format!(
+                            "inline; filename=\"snippet_{}{}\"",
+                            snippet.index, snippet.extension
+                        ),
+                    ),
+                ];
+                respond(stream, "200 OK", &headers, snippet.code.as_bytes())?;
+            }
+            None => respond(
+                stream,
+                "404 Not Found",
+                &[("Content-Type", content_type("").to_owned())],
+                b"No such snippet\n",
+            )?,
+        }
+        Ok(())
+    }
+
+    /// Answers a sync client: `GET /sync/` lists the synced files,
+    /// `GET`, `PUT`, and `DELETE /sync/<name>` read, write, and delete one
+    fn handle_sync(
+        &self,
+        stream: &mut TcpStream,
+        request: &Request,
+        token: Option<&str>,
+    ) -> color_eyre::Result<()> {
+        let text = || ("Content-Type", content_type("").to_owned());
+        if let Some(token) = token {
+            let authorized = request
+                .headers
+                .get("authorization")
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .is_some_and(|given| {
+                    constant_time::verify_slices_are_equal(given.as_bytes(), token.as_bytes())
+                        .is_ok()
+                });
+            if !authorized {
+                return Ok(respond(
+                    stream,
+                    "401 Unauthorized",
+                    &[text(), ("WWW-Authenticate", "Bearer".to_owned())],
+                    b"Wrong or missing token\n",
+                )?);
+            }
+        }
+        let name = uri_decode(&request.path[SYNC_PATH.len()..]);
+        if name.contains('/') {
+            return Ok(respond(
+                stream,
+                "404 Not Found",
+                &[text()],
+                b"No such file\n",
+            )?);
+        }
+        match (request.method.as_str(), name.is_empty()) {
+            ("GET", true) => {
+                let files: Vec<_> = self
+                    .list_sync_files()?
+                    .into_iter()
+                    .map(|(name, modified)| ServerFile { name, modified })
+                    .collect();
+                respond(
+                    stream,
+                    "200 OK",
+                    &[("Content-Type", content_type(".json").to_owned())],
+                    &serde_json::to_vec(&files)?,
+                )?;
+            }
+            ("GET", false) => match self.get_sync_file(&name)? {
+                Some((content, _)) => {
+                    let etag = format!("\"{}\"", utils::content_hash(&content));
+                    if request.headers.get("if-none-match") == Some(&etag) {
+                        respond(stream, "304 Not Modified", &[("ETag", etag)], b"")?;
+                    } else {
+                        respond(
+                            stream,
+                            "200 OK",
+                            &[text(), ("ETag", etag)],
+                            content.as_bytes(),
+                        )?;
+                    }
+                }
+                None => respond(stream, "404 Not Found", &[text()], b"No such file\n")?,
+            },
+            ("PUT", false) => match String::from_utf8(request.body.clone()) {
+                Ok(content) => {
+                    self.put_sync_file(&name, &content)?;
+                    respond(stream, "204 No Content", &[], b"")?;
+                }
+                Err(_) => respond(
+                    stream,
+                    "400 Bad Request",
+                    &[text()],
+                    b"Files have to be UTF-8\n",
+                )?,
+            },
+            ("DELETE", false) => {
+                if self.delete_sync_file(&name)? {
+                    respond(stream, "204 No Content", &[], b"")?;
+                } else {
+                    respond(stream, "404 Not Found", &[text()], b"No such file\n")?;
+                }
+            }
+            _ => respond(
+                stream,
+                "405 Method Not Allowed",
+                &[("Allow", "GET, PUT, DELETE".to_owned())],
+                b"",
+            )?,
+        }
+        Ok(())
+    }
+}
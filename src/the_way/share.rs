@@ -0,0 +1,50 @@
+//! `the-way snippet share`: a snippet as a Gist of its own, to send someone a link to
+use std::collections::HashMap;
+
+use crate::gist::{CreateGistPayload, GistClient, GistContent};
+use crate::http::HttpClient;
+use crate::the_way::{
+    sync::{get_access_token, USER_AGENT},
+    TheWay,
+};
+use crate::utils;
+
+impl TheWay {
+    /// Makes a Gist (secret unless `public`) with the snippet's code, then prints its URL and copies it to the clipboard
+    pub(crate) fn share(&mut self, index: usize, public: bool) -> color_eyre::Result<()> {
+        let snippet = self.get_snippet(index)?;
+        let access_token = get_access_token(
+            "THE_WAY_GITHUB_TOKEN",
+            self.config.github_access_token.as_deref(),
+            "Get a GitHub access token from https://github.com/settings/tokens/new (add the \"gist\" scope)",
+            "GitHub access token",
+        )?;
+        let client = GistClient::new(
+            HttpClient::new(&self.config.http),
+            &access_token,
+            USER_AGENT,
+        )?;
+        let mut files = HashMap::new();
+        files.insert(
+            format!("snippet_{}{}", snippet.index, snippet.extension),
+            GistContent {
+                content: &snippet.code,
+            },
+        );
+        let spinner = utils::get_spinner("Sharing snippet...");
+        let gist = client.create_gist(&CreateGistPayload {
+            description: &snippet.description,
+            public,
+            files,
+        })?;
+        spinner.finish_and_clear();
+        if self.config.github_access_token.is_none() {
+            self.config.github_access_token = Some(access_token);
+            self.config.store()?;
+        }
+        println!("{}", gist.html_url);
+        utils::copy_to_clipboard(&gist.html_url)?;
+        println!("Copied the link to clipboard");
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+//! Read-only JSON snapshot of every snippet's index, ID, description, language, and tags,
+//! rewritten whenever snippets change. Shell completions, prompts, and launchers read it
+//! instead of opening the database, so they're instant and never wait for its lock
+use std::fs;
+use std::path::PathBuf;
+
+use crate::configuration::TheWayConfig;
+use crate::the_way::{snippet::Snippet, TheWay};
+
+/// A snippet as it's listed in the snapshot
+#[derive(Serialize)]
+struct SnapshotSnippet<'a> {
+    index: usize,
+    /// What to type to refer to the snippet (the index, or its short ID with `index_format` set)
+    id: String,
+    /// First line of the description
+    description: &'a str,
+    language: &'a str,
+    tags: &'a [String],
+}
+
+#[derive(Serialize)]
+struct Snapshot<'a> {
+    snippets: Vec<SnapshotSnippet<'a>>,
+}
+
+/// Where the snapshot of the database in `config` is kept (next to the database)
+pub(crate) fn snapshot_file(config: &TheWayConfig) -> PathBuf {
+    config.db_dir.with_extension("json")
+}
+
+impl TheWay {
+    /// Notes that snippets changed, so the snapshot is rewritten once the command is done
+    pub(crate) fn snippets_changed(&self) {
+        self.snippets_changed.set(true);
+    }
+
+    /// Rewrites the snapshot if snippets changed (or there isn't one yet).
+    /// The snippets are already saved by now, so problems are only warned about
+    pub(crate) fn update_snapshot(&self) {
+        if !self.snippets_changed.replace(false) && snapshot_file(&self.config).exists() {
+            return;
+        }
+        if let Err(e) = self
+            .list_snippets()
+            .and_then(|snippets| self.write_snapshot(snippets))
+        {
+            eprintln!(
+                "Couldn't write the snippet snapshot to {}: {}",
+                snapshot_file(&self.config).display(),
+                e
+            );
+        }
+    }
+
+    /// Writes `snippets` to the snapshot
+    pub(crate) fn write_snapshot(&self, mut snippets: Vec<Snippet>) -> color_eyre::Result<()> {
+        snippets.sort_by_key(|snippet| snippet.index);
+        let snapshot = Snapshot {
+            snippets: snippets
+                .iter()
+                .map(|snippet| SnapshotSnippet {
+                    index: snippet.index,
+                    id: snippet.label(),
+                    description: snippet.summary(),
+                    language: &snippet.language,
+                    tags: &snippet.tags,
+                })
+                .collect(),
+        };
+        let path = snapshot_file(&self.config);
+        // Renamed into place, so readers never see half a snapshot
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_vec(&snapshot)?)?;
+        fs::rename(&partial, &path)?;
+        Ok(())
+    }
+}
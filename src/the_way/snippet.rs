@@ -1,14 +1,33 @@
 //! Snippet information and methods
 use std::collections::HashMap;
-use std::io;
+use std::io::{self, BufRead};
 
 use chrono::{DateTime, Utc};
 
+use color_eyre::Help;
+use serde_json::Value;
+
+use crate::configuration::ValidationConfig;
+use crate::errors::LostTheWay;
 use crate::language::{CodeHighlight, Language};
+use crate::the_way::field_map::FieldMap;
 use crate::utils;
 
+/// Names of the snippet fields written to JSON
+pub(crate) const FIELDS: [&str; 9] = [
+    "index",
+    "description",
+    "language",
+    "code",
+    "extension",
+    "tags",
+    "date",
+    "updated",
+    "uuid",
+];
+
 /// Stores information about a quote
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Snippet {
     /// Snippet index, used to retrieve, copy, or modify a snippet
     #[serde(default)]
@@ -31,11 +50,50 @@ pub struct Snippet {
     /// Time of last update
     #[serde(default = "Utc::now")]
     pub updated: DateTime<Utc>,
+    /// How the index is shown (see the [display] config section), set when read from the database
+    #[serde(skip)]
+    pub(crate) label: Option<String>,
+    /// The snippet's UUID, set when exported to JSON and when read from JSON that has one
+    /// (kept in its own database tree, not with the snippet)
+    #[serde(skip)]
+    pub(crate) uuid: Option<String>,
+}
+
+/// A snippet in JSON, which may have the UUID it was exported with
+#[derive(Deserialize, Debug)]
+struct JsonSnippet {
+    #[serde(flatten)]
+    snippet: Snippet,
+    #[serde(default)]
+    uuid: Option<String>,
+}
+
+impl JsonSnippet {
+    fn into_snippet(self) -> Snippet {
+        Snippet {
+            uuid: self.uuid,
+            ..self.snippet
+        }
+    }
+}
+
+/// Snippet metadata written as YAML front matter when editing a whole snippet in $EDITOR
+#[derive(Serialize, Deserialize, Debug)]
+struct FrontMatter {
+    description: String,
+    language: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    date: DateTime<Utc>,
 }
 
+/// Separates the front matter from the code
+const FRONT_MATTER_FENCE: &str = "---\n";
+
 impl Snippet {
     /// New snippet
-    fn new(
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
         index: usize,
         description: String,
         language: String,
@@ -54,9 +112,19 @@ impl Snippet {
             date,
             updated,
             code,
+            label: None,
+            uuid: None,
         }
     }
 
+    /// Whether `other` has the same description, language, tags, and code
+    pub(crate) fn same_as(&self, other: &Self) -> bool {
+        self.description == other.description
+            && self.language == other.language
+            && self.tags == other.tags
+            && self.code == other.code
+    }
+
     pub(crate) fn set_extension(
         &mut self,
         language_name: &str,
@@ -82,7 +150,27 @@ impl Snippet {
             None => (None, None, None, None, None),
         };
 
-        let description = utils::user_input("Description", old_description, true, false)?;
+        let description = match old_description {
+            Some(old_description) => utils::unescape_newlines(&utils::user_input(
+                "Description",
+                Some(&utils::escape_newlines(old_description)),
+                true,
+                false,
+            )?),
+            None => utils::unescape_newlines(&utils::user_input(
+                "Description (<RET> to edit in external editor)",
+                None,
+                false,
+                true,
+            )?),
+        };
+        let description = if description.is_empty() {
+            utils::external_editor_input(None, ".txt")?
+                .trim()
+                .to_owned()
+        } else {
+            description
+        };
         let language =
             utils::user_input("Language", old_language, true, false)?.to_ascii_lowercase();
         let extension = Language::get_extension(&language, languages);
@@ -104,7 +192,7 @@ impl Snippet {
             true,
         )?;
         if code.is_empty() {
-            code = utils::external_editor_input(old_code.as_deref(), &extension)?;
+            code = utils::external_editor_input(old_code, &extension)?;
         }
         Ok(Self::new(
             index,
@@ -118,6 +206,94 @@ impl Snippet {
         ))
     }
 
+    /// Checks the snippet against the rules in the `[validation]` config section
+    pub(crate) fn validate(&self, rules: &ValidationConfig) -> color_eyre::Result<()> {
+        let mut problems = Vec::new();
+        if rules.require_tag && self.tags.iter().all(|tag| tag.is_empty()) {
+            problems.push(String::from("needs at least one tag"));
+        }
+        if let Some(max_code_lines) = rules.max_code_lines {
+            let num_lines = self.code.lines().count();
+            if num_lines > max_code_lines {
+                problems.push(format!(
+                    "has {} lines of code, the maximum is {}",
+                    num_lines, max_code_lines
+                ));
+            }
+        }
+        if rules
+            .forbidden_languages
+            .iter()
+            .any(|language| language.eq_ignore_ascii_case(&self.language))
+        {
+            problems.push(format!(
+                "is written in a forbidden language ({})",
+                self.language
+            ));
+        }
+        if let Some(min_description_length) = rules.min_description_length {
+            let length = self.description.trim().chars().count();
+            if length < min_description_length {
+                problems.push(format!(
+                    "has a {} character description, the minimum is {}",
+                    length, min_description_length
+                ));
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            let error: color_eyre::Result<()> = Err(LostTheWay::ValidationError {
+                message: format!("Snippet {:?} {}", self.summary(), problems.join(", ")),
+            }
+            .into());
+            error.suggestion(
+                "Fix the snippet or change the [validation] section of your config file \
+                (`the-way config get` shows where it is)",
+            )
+        }
+    }
+
+    /// Opens the whole snippet in $EDITOR, metadata as YAML front matter followed by the code,
+    /// and reads the changed snippet back on save
+    pub(crate) fn from_editor(
+        languages: &HashMap<String, Language>,
+        old_snippet: &Self,
+    ) -> color_eyre::Result<Self> {
+        let front_matter = serde_yaml::to_string(&FrontMatter {
+            description: old_snippet.description.to_owned(),
+            language: old_snippet.language.to_owned(),
+            tags: old_snippet.tags.to_owned(),
+            date: old_snippet.date,
+        })?;
+        let document = format!(
+            "{}\n{}{}",
+            front_matter.trim_end_matches('\n'),
+            FRONT_MATTER_FENCE,
+            old_snippet.code
+        );
+        let document = utils::external_editor_input(Some(&document), ".md")?;
+        let suggestion = "Keep the metadata between the two \"---\" lines at the start of the file";
+        let (front_matter, code) = document
+            .strip_prefix(FRONT_MATTER_FENCE)
+            .and_then(|rest| rest.split_once(&format!("\n{}", FRONT_MATTER_FENCE)))
+            .ok_or(LostTheWay::EditorError)
+            .suggestion(suggestion)?;
+        let front_matter: FrontMatter =
+            serde_yaml::from_str(front_matter).suggestion(suggestion)?;
+        let language = front_matter.language.to_ascii_lowercase();
+        Ok(Self::new(
+            old_snippet.index,
+            front_matter.description.trim().to_owned(),
+            language.to_owned(),
+            Language::get_extension(&language, languages),
+            &front_matter.tags.join(" "),
+            front_matter.date,
+            Utc::now(),
+            code.to_owned(),
+        ))
+    }
+
     /// write snippet to database
     pub(crate) fn to_bytes(&self) -> color_eyre::Result<Vec<u8>> {
         Ok(bincode::serialize(&self)?)
@@ -128,19 +304,114 @@ impl Snippet {
         Ok(bincode::deserialize(bytes)?)
     }
 
-    /// Read snippets from a JSON stream and return consumable iterator
-    pub(crate) fn read(
+    /// Read snippets from JSON: a stream of objects (e.g. one per line, as `export` writes them),
+    /// or an array of them (`export --array`)
+    pub(crate) fn read(json_reader: &mut dyn io::Read) -> color_eyre::Result<Vec<Self>> {
+        let mut reader = io::BufReader::new(json_reader);
+        // Skip to the first character to see which it is
+        let is_array = loop {
+            let buffer = reader.fill_buf()?;
+            if buffer.is_empty() {
+                break false;
+            }
+            match buffer.iter().position(|byte| !byte.is_ascii_whitespace()) {
+                Some(start) => {
+                    let is_array = buffer[start] == b'[';
+                    reader.consume(start);
+                    break is_array;
+                }
+                None => {
+                    let length = buffer.len();
+                    reader.consume(length);
+                }
+            }
+        };
+        let snippets = if is_array {
+            serde_json::from_reader::<_, Vec<JsonSnippet>>(reader)?
+        } else {
+            serde_json::Deserializer::from_reader(reader)
+                .into_iter::<JsonSnippet>()
+                .collect::<Result<_, _>>()?
+        };
+        Ok(snippets
+            .into_iter()
+            .map(JsonSnippet::into_snippet)
+            .collect())
+    }
+
+    /// Read snippets from a JSON stream written by another tool, using `map` to find snippet fields.
+    /// Top-level arrays are read as lists of snippets
+    pub(crate) fn read_mapped(
         json_reader: &mut dyn io::Read,
-    ) -> impl Iterator<Item = serde_json::Result<Self>> + '_ {
-        serde_json::Deserializer::from_reader(json_reader).into_iter::<Self>()
+        map: &FieldMap,
+    ) -> color_eyre::Result<Vec<Self>> {
+        let mut snippets = Vec::new();
+        for value in serde_json::Deserializer::from_reader(json_reader).into_iter::<Value>() {
+            let values = match value? {
+                Value::Array(values) => values,
+                value => vec![value],
+            };
+            for value in values {
+                let uuid = value.get("uuid").and_then(Value::as_str).map(str::to_owned);
+                let snippet: Self = serde_json::from_value(map.apply(value)?)?;
+                snippets.push(Self { uuid, ..snippet });
+            }
+        }
+        Ok(snippets)
     }
 
-    /// Appends a snippet to a JSON object/file
-    pub(crate) fn to_json(&self, json_writer: &mut dyn io::Write) -> color_eyre::Result<()> {
-        serde_json::to_writer(json_writer, self)?;
+    /// Appends a snippet to a JSON object/file, keeping only `fields`
+    pub(crate) fn to_json_with_fields(
+        &self,
+        json_writer: &mut dyn io::Write,
+        fields: &[&str],
+    ) -> color_eyre::Result<()> {
+        let uuid = self.uuid.as_ref().filter(|_| fields.contains(&"uuid"));
+        if uuid.is_none() && FIELDS.iter().all(|field| fields.contains(field)) {
+            serde_json::to_writer(json_writer, self)?;
+            return Ok(());
+        }
+        let value = match serde_json::to_value(self)? {
+            Value::Object(object) => {
+                let mut object: serde_json::Map<_, _> = object
+                    .into_iter()
+                    .filter(|(key, _)| fields.contains(&key.as_str()))
+                    .collect();
+                if let Some(uuid) = uuid {
+                    object.insert(String::from("uuid"), Value::from(uuid.as_str()));
+                }
+                Value::Object(object)
+            }
+            value => value,
+        };
+        serde_json::to_writer(json_writer, &value)?;
         Ok(())
     }
 
+    /// Checks field names given on the command line, "dates" stands for both date and updated
+    pub(crate) fn expand_fields(names: &[String]) -> color_eyre::Result<Vec<&'static str>> {
+        let mut fields = Vec::new();
+        for name in names {
+            let name = name.trim().to_ascii_lowercase();
+            if name == "dates" {
+                fields.extend_from_slice(&["date", "updated"]);
+            } else if let Some(field) = FIELDS.iter().find(|field| **field == name) {
+                fields.push(*field);
+            } else {
+                let error: color_eyre::Result<Vec<&'static str>> =
+                    Err(LostTheWay::OutOfCheeseError {
+                        message: format!("Unknown snippet field {:?}", name),
+                    }
+                    .into());
+                return error.suggestion(format!(
+                    "Snippet fields are {} (or dates for both date fields)",
+                    FIELDS.join(", ")
+                ));
+            }
+        }
+        Ok(fields)
+    }
+
     /// Filters snippets in date range
     pub(crate) fn filter_in_date_range(
         snippets: Vec<Self>,
@@ -163,19 +434,101 @@ impl Snippet {
         self.tags.contains(&tag.into())
     }
 
-    /// Gets the title as plain text for searching
+    /// Index as shown in titles
+    pub(crate) fn label(&self) -> String {
+        self.label.clone().unwrap_or_else(|| self.index.to_string())
+    }
+
+    /// First line of the description, shown in compact listings
+    pub(crate) fn summary(&self) -> &str {
+        self.description.lines().next().unwrap_or_default()
+    }
+
+    /// Gets the title as plain text for searching (includes every description line)
     pub(crate) fn get_header(&self) -> String {
         format!(
             "{} #{}. {} | {} :{}:\n",
             utils::BOX,
-            self.index,
-            self.description,
+            self.label(),
+            self.description.lines().collect::<Vec<_>>().join(" "),
             self.language,
             self.tags.join(":")
         )
     }
 
-    /// Highlights the title: "■ #index. description | language :tag1:tag2:\n"
+    /// Labelled title for screen readers: "Snippet 1: summary. Language: rust. Tags: tag1, tag2."
+    pub(crate) fn plain_header(&self) -> String {
+        format!(
+            "Snippet {}: {}. Language: {}. Tags: {}.",
+            self.label(),
+            self.summary(),
+            self.language,
+            self.plain_tags()
+        )
+    }
+
+    /// Comma-separated tags, "none" if there aren't any
+    fn plain_tags(&self) -> String {
+        let tags: Vec<_> = self
+            .tags
+            .iter()
+            .filter(|tag| !tag.is_empty())
+            .cloned()
+            .collect();
+        if tags.is_empty() {
+            String::from("none")
+        } else {
+            tags.join(", ")
+        }
+    }
+
+    /// Labelled code lines for screen readers: "Code line 1: ..."
+    pub(crate) fn plain_code(&self) -> Vec<String> {
+        self.code
+            .lines()
+            .enumerate()
+            .map(|(i, line)| format!("Code line {}: {}\n", i + 1, line))
+            .collect()
+    }
+
+    /// Every field on its own labelled line, without colors or symbols (for screen readers).
+    /// Only shows the first line of the description unless `full_description` is set
+    pub(crate) fn plain_print(&self, full_description: bool) -> Vec<String> {
+        self.plain_print_with_requirements(full_description, &[])
+    }
+
+    /// Like `plain_print` with the full description, plus what the snippet needs to run
+    pub(crate) fn plain_print_full(&self, requirements: &[String]) -> Vec<String> {
+        self.plain_print_with_requirements(true, requirements)
+    }
+
+    fn plain_print_with_requirements(
+        &self,
+        full_description: bool,
+        requirements: &[String],
+    ) -> Vec<String> {
+        let mut lines = vec![format!("Snippet number: {}\n", self.label())];
+        let mut description = self.description.lines();
+        lines.push(format!(
+            "Description: {}\n",
+            description.next().unwrap_or_default()
+        ));
+        if full_description {
+            for (i, line) in description.enumerate() {
+                lines.push(format!("Description line {}: {}\n", i + 2, line));
+            }
+        }
+        lines.push(format!("Language: {}\n", self.language));
+        lines.push(format!("Tags: {}\n", self.plain_tags()));
+        if !requirements.is_empty() {
+            lines.push(format!("Requires: {}\n", requirements.join(", ")));
+        }
+        lines.extend(self.plain_code());
+        lines.push(String::from("\n"));
+        lines
+    }
+
+    /// Highlights the title: "■ #index. summary | language :tag1:tag2:\n"
     /// the block is colored according to the language
     /// language uses `accent_style`
     /// tags use `dim_style`
@@ -186,9 +539,9 @@ impl Snippet {
         language: &Language,
     ) -> color_eyre::Result<Vec<String>> {
         let mut colorized = Vec::new();
-        let block = CodeHighlight::highlight_block(language.color)?;
+        let block = highlighter.highlight_block(language.color)?;
         colorized.push(block);
-        let text = format!("#{}. {} ", self.index, self.description);
+        let text = format!("#{}. {} ", self.label(), self.summary());
         colorized.push(CodeHighlight::highlight_string(
             &text,
             highlighter.main_style,
@@ -219,13 +572,78 @@ impl Snippet {
         Ok(colorized)
     }
 
+    /// Highlights code with each line's number in a gutter in front of it, like `bat` does
+    pub(crate) fn pretty_print_numbered_code(
+        &self,
+        highlighter: &CodeHighlight,
+    ) -> color_eyre::Result<Vec<String>> {
+        let lines = highlighter.highlight_code(&self.code, &self.extension)?;
+        let width = lines.len().to_string().len();
+        let mut colorized = Vec::new();
+        for (i, line) in lines.into_iter().enumerate() {
+            colorized.push(CodeHighlight::highlight_string(
+                &format!("{:>width$} │ ", i + 1, width = width),
+                highlighter.accent_style,
+            ));
+            colorized.push(line);
+        }
+        colorized.push(String::from(utils::END_ANSI));
+        Ok(colorized)
+    }
+
+    /// Highlights the description lines after the summary
+    pub(crate) fn pretty_print_description(&self, highlighter: &CodeHighlight) -> Vec<String> {
+        let mut colorized: Vec<_> = self
+            .description
+            .lines()
+            .skip(1)
+            .map(|line| {
+                CodeHighlight::highlight_string(&format!("{}\n", line), highlighter.main_style)
+            })
+            .collect();
+        colorized.push(String::from(utils::END_ANSI));
+        colorized
+    }
+
+    /// Highlights header and code, only showing the first line of the description
     pub(crate) fn pretty_print(
         &self,
         highlighter: &CodeHighlight,
         language: &Language,
+    ) -> color_eyre::Result<Vec<String>> {
+        self.pretty_print_with_description(highlighter, language, false, &[])
+    }
+
+    /// Highlights header, full description, and code
+    /// (and what it needs to run, under the description)
+    pub(crate) fn pretty_print_full(
+        &self,
+        highlighter: &CodeHighlight,
+        language: &Language,
+        requirements: &[String],
+    ) -> color_eyre::Result<Vec<String>> {
+        self.pretty_print_with_description(highlighter, language, true, requirements)
+    }
+
+    fn pretty_print_with_description(
+        &self,
+        highlighter: &CodeHighlight,
+        language: &Language,
+        full_description: bool,
+        requirements: &[String],
     ) -> color_eyre::Result<Vec<String>> {
         let mut colorized = vec![String::from("\n")];
         colorized.extend_from_slice(&self.pretty_print_header(highlighter, language)?);
+        if full_description {
+            colorized.extend_from_slice(&self.pretty_print_description(highlighter));
+        }
+        if !requirements.is_empty() {
+            colorized.push(CodeHighlight::highlight_string(
+                &format!("Requires: {}\n", requirements.join(", ")),
+                highlighter.accent_style,
+            ));
+            colorized.push(String::from(utils::END_ANSI));
+        }
         colorized.push(String::from("\n"));
         colorized.extend_from_slice(&self.pretty_print_code(highlighter)?);
         colorized.push(String::from("\n"));
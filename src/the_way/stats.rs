@@ -0,0 +1,231 @@
+//! Library statistics for `the-way stats`: snippets added per month and per-tag activity
+use std::collections::HashMap;
+
+use chrono::Datelike;
+use syntect::highlighting::Style;
+
+use crate::language::CodeHighlight;
+use crate::the_way::{filter::Filters, snippet::Snippet, TheWay};
+use crate::utils;
+
+/// Sparkline levels, lowest to highest
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Partial block characters, in eighths
+const PARTIAL_BLOCKS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// Width of the longest bar
+const BAR_WIDTH: usize = 30;
+
+/// Months since year 0, so consecutive months are consecutive numbers
+fn month_number(snippet: &Snippet) -> i32 {
+    snippet.date.year() * 12 + snippet.date.month0() as i32
+}
+
+/// "2020-09" from a month number
+fn month_label(month: i32) -> String {
+    format!("{}-{:02}", month.div_euclid(12), month.rem_euclid(12) + 1)
+}
+
+/// One character per count, scaled to the largest count; zero is a space
+fn sparkline(counts: &[usize]) -> String {
+    let max = counts.iter().copied().max().unwrap_or_default();
+    counts
+        .iter()
+        .map(|&count| match (count, max) {
+            (0, _) => ' ',
+            (_, 1) => SPARKS[SPARKS.len() - 1],
+            _ => SPARKS[(count - 1) * (SPARKS.len() - 1) / (max - 1)],
+        })
+        .collect()
+}
+
+/// Horizontal bar, `BAR_WIDTH` long for `max`
+fn bar(count: usize, max: usize) -> String {
+    let eighths = (count * BAR_WIDTH * 8 / max.max(1)).max(1);
+    let mut bar = SPARKS[SPARKS.len() - 1].to_string().repeat(eighths / 8);
+    if eighths % 8 != 0 {
+        bar.push(PARTIAL_BLOCKS[eighths % 8]);
+    }
+    bar
+}
+
+/// Counts sorted by count (descending), then name
+fn sorted_counts(counts: HashMap<String, usize>) -> Vec<(String, usize)> {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|(name_1, count_1), (name_2, count_2)| {
+        count_2.cmp(count_1).then_with(|| name_1.cmp(name_2))
+    });
+    counts
+}
+
+/// Statistics for a set of snippets
+struct Stats {
+    num_snippets: usize,
+    /// First month with a snippet
+    first_month: i32,
+    /// Snippets added in each month from `first_month` to the last month with a snippet
+    monthly: Vec<usize>,
+    languages: Vec<(String, usize)>,
+    /// (tag, number of snippets, snippets added each month)
+    tags: Vec<(String, usize, Vec<usize>)>,
+}
+
+impl Stats {
+    fn new(snippets: &[Snippet]) -> Self {
+        let first_month = snippets.iter().map(month_number).min().unwrap_or_default();
+        let last_month = snippets.iter().map(month_number).max().unwrap_or_default();
+        let num_months = (last_month - first_month + 1) as usize;
+        let mut monthly = vec![0; num_months];
+        let mut languages = HashMap::new();
+        let mut tag_counts = HashMap::new();
+        let mut tag_monthly: HashMap<String, Vec<usize>> = HashMap::new();
+        for snippet in snippets {
+            let month = (month_number(snippet) - first_month) as usize;
+            monthly[month] += 1;
+            *languages.entry(snippet.language.to_owned()).or_insert(0) += 1;
+            for tag in snippet.tags.iter().filter(|tag| !tag.is_empty()) {
+                *tag_counts.entry(tag.to_owned()).or_insert(0) += 1;
+                tag_monthly
+                    .entry(tag.to_owned())
+                    .or_insert_with(|| vec![0; num_months])[month] += 1;
+            }
+        }
+        let tags = sorted_counts(tag_counts)
+            .into_iter()
+            .map(|(tag, count)| {
+                let monthly = tag_monthly.remove(&tag).unwrap_or_default();
+                (tag, count, monthly)
+            })
+            .collect();
+        Self {
+            num_snippets: snippets.len(),
+            first_month,
+            monthly,
+            languages: sorted_counts(languages),
+            tags,
+        }
+    }
+
+    fn last_month(&self) -> i32 {
+        self.first_month + self.monthly.len() as i32 - 1
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{} snippets, {} languages, {} tags, {} to {}\n",
+            self.num_snippets,
+            self.languages.len(),
+            self.tags.len(),
+            month_label(self.first_month),
+            month_label(self.last_month())
+        )
+    }
+
+    /// Sparklines and bars, colored with the current theme
+    fn pretty_print(&self, highlighter: &CodeHighlight) -> Vec<String> {
+        let styled = |text: &str, style: Style| CodeHighlight::highlight_string(text, style);
+        let name_width = self
+            .languages
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(self.tags.iter().map(|(name, _, _)| name.len()))
+            .max()
+            .unwrap_or_default();
+        let max_language = self.languages.first().map(|(_, c)| *c).unwrap_or_default();
+        let max_tag = self.tags.first().map(|(_, c, _)| *c).unwrap_or_default();
+        let mut lines = vec![styled(&self.summary(), highlighter.main_style)];
+        lines.push(styled("\nAdded per month\n", highlighter.main_style));
+        lines.push(format!(
+            "{} {} {} (max {} in a month)\n",
+            month_label(self.first_month),
+            styled(&sparkline(&self.monthly), highlighter.accent_style),
+            month_label(self.last_month()),
+            self.monthly.iter().max().unwrap_or(&0)
+        ));
+        lines.push(styled("\nLanguages\n", highlighter.main_style));
+        for (language, count) in &self.languages {
+            lines.push(format!(
+                "{:width$} {} {}\n",
+                language,
+                styled(&bar(*count, max_language), highlighter.accent_style),
+                count,
+                width = name_width
+            ));
+        }
+        if !self.tags.is_empty() {
+            lines.push(styled(
+                &format!(
+                    "\nTags (activity {} to {})\n",
+                    month_label(self.first_month),
+                    month_label(self.last_month())
+                ),
+                highlighter.main_style,
+            ));
+            for (tag, count, monthly) in &self.tags {
+                let bar = bar(*count, max_tag);
+                lines.push(format!(
+                    "{:width$} {}{} {:>4} {}\n",
+                    tag,
+                    styled(&bar, highlighter.tag_style),
+                    " ".repeat(BAR_WIDTH + 1 - bar.chars().count()),
+                    count,
+                    styled(&sparkline(monthly), highlighter.tag_style),
+                    width = name_width
+                ));
+            }
+        }
+        lines.push(utils::END_ANSI.to_owned());
+        lines
+    }
+
+    /// Numbers only, for `--accessible`
+    fn plain_print(&self) -> Vec<String> {
+        let mut lines = vec![self.summary(), String::from("\nAdded per month:\n")];
+        for (i, count) in self.monthly.iter().enumerate() {
+            if *count > 0 {
+                lines.push(format!(
+                    "{}: {}\n",
+                    month_label(self.first_month + i as i32),
+                    count
+                ));
+            }
+        }
+        lines.push(String::from("\nLanguages:\n"));
+        for (language, count) in &self.languages {
+            lines.push(format!("{}: {}\n", language, count));
+        }
+        if !self.tags.is_empty() {
+            lines.push(String::from("\nTags:\n"));
+            for (tag, count, monthly) in &self.tags {
+                let active = monthly.iter().filter(|count| **count > 0).count();
+                lines.push(format!(
+                    "{}: {} snippets, added in {} months\n",
+                    tag, count, active
+                ));
+            }
+        }
+        lines
+    }
+}
+
+impl TheWay {
+    /// Prints snippets added per month and per-tag activity (optionally filtered)
+    pub(crate) fn stats(&self, filters: &Filters) -> color_eyre::Result<()> {
+        let snippets = self.filter_snippets(filters)?;
+        if snippets.is_empty() {
+            println!("No snippets");
+            return Ok(());
+        }
+        let stats = Stats::new(&snippets);
+        let lines = if self.cli.accessible {
+            stats.plain_print()
+        } else {
+            stats.pretty_print(&self.highlighter)
+        };
+        for line in lines {
+            print!("{}", line);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,981 @@
+//! Code related to syncing snippets to Gist (or GitLab or Bitbucket snippets, an S3 bucket, a WebDAV folder)
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use color_eyre::Help;
+
+use crate::bitbucket::{BitbucketClient, BITBUCKET_API_URL};
+use crate::errors::LostTheWay;
+use crate::gist::{CreateGistPayload, Gist, GistClient, GistContent, UpdateGistPayload};
+use crate::gitlab::{GitlabClient, GITLAB_URL};
+use crate::http::HttpClient;
+use crate::language::Language;
+use crate::sync::{
+    encrypted::EncryptedClient, s3, s3::S3Store, server::ServerStore, webdav::WebdavStore,
+    FileStoreClient, SyncClient,
+};
+use crate::the_way::merge::{self, MergeChoice};
+use crate::the_way::{
+    clipboard_watch::guess_language, snippet::Snippet, sync_log::SyncLogEntry, TheWay,
+};
+use crate::utils;
+
+/// Gist description
+const DESCRIPTION: &str = "The Way Code Snippets";
+/// Heading for the index.md file
+const INDEX: &str = "# Is it not written...\n";
+pub(crate) const USER_AGENT: &str = "the-way";
+
+/// How to settle a snippet that changed both locally and remotely since the last sync
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConflictStrategy {
+    /// Keep whichever side was updated last
+    KeepNewest,
+    /// Keep the local version, and add the remote one as a new snippet
+    KeepBoth,
+    /// Show both versions and ask
+    Ask,
+}
+
+impl ConflictStrategy {
+    /// Possible strategy names
+    pub const VARIANTS: [&'static str; 3] = ["keep-newest", "keep-both", "ask"];
+}
+
+impl FromStr for ConflictStrategy {
+    type Err = LostTheWay;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "keep-newest" => Ok(Self::KeepNewest),
+            "keep-both" => Ok(Self::KeepBoth),
+            "ask" => Ok(Self::Ask),
+            other => Err(LostTheWay::ConfigError {
+                message: format!("Unknown conflict strategy {:?}", other),
+            }),
+        }
+    }
+}
+
+/// What `sync` does with the differences it finds
+#[derive(Debug, Clone, Copy)]
+enum SyncMode {
+    /// Settles them, with this strategy for conflicts
+    Sync(ConflictStrategy),
+    /// Only lists them (`sync --dry-run` and `sync status`)
+    DryRun,
+}
+
+/// Which side changed a snippet that's both local and remote since the last sync
+enum Changed {
+    Neither,
+    Here,
+    There,
+    Both,
+    /// No way to tell which side is newer
+    Unknown,
+}
+
+/// What happens to a snippet that's both local and remote
+enum Resolution {
+    /// Both sides have the same code
+    Same,
+    Upload,
+    Download,
+    /// Upload the local code and add the remote code as a new snippet
+    KeepBoth,
+    /// Replace both sides with this code
+    Merged(String),
+    /// No way to tell which side is newer, so neither is touched
+    Undecided,
+}
+
+impl TheWay {
+    /// Syncs snippets with the provider set in `sync_provider` ("github" by default).
+    /// Conflicts are settled with `conflicts`, or `sync_conflicts` in the config
+    /// (asking by default, or keeping both versions when there's no one to ask).
+    /// With `dry_run`, only lists what a sync would do
+    pub(crate) fn sync(
+        &mut self,
+        conflicts: Option<ConflictStrategy>,
+        dry_run: bool,
+    ) -> color_eyre::Result<()> {
+        let conflicts = match (conflicts, &self.config.sync_conflicts) {
+            (Some(conflicts), _) => conflicts,
+            (None, Some(conflicts)) => conflicts.parse().suggestion(format!(
+                "Set `sync_conflicts` in your config file to one of {}",
+                ConflictStrategy::VARIANTS.join(", ")
+            ))?,
+            (None, None) if console::user_attended() => ConflictStrategy::Ask,
+            (None, None) => ConflictStrategy::KeepBoth,
+        };
+        let mode = if dry_run {
+            SyncMode::DryRun
+        } else {
+            // Syncing can overwrite local snippets
+            self.backup_before("sync")?;
+            SyncMode::Sync(conflicts)
+        };
+        let provider = self
+            .config
+            .sync_provider
+            .as_deref()
+            .unwrap_or("github")
+            .to_ascii_lowercase();
+        match provider.as_str() {
+            "github" => self.sync_github(mode)?,
+            "gitlab" => self.sync_gitlab(mode)?,
+            "bitbucket" => self.sync_bitbucket(mode)?,
+            "s3" => self.sync_s3(mode)?,
+            "webdav" => self.sync_webdav(mode)?,
+            "server" => self.sync_server(mode)?,
+            _ => {
+                let error: color_eyre::Result<()> = Err(LostTheWay::ConfigError {
+                    message: format!("Unknown sync provider {:?}", provider),
+                }
+                .into());
+                return error.suggestion(
+                    "Set `sync_provider` in your config file to \"github\", \"gitlab\", \"bitbucket\", \"s3\", \"webdav\", or \"server\"",
+                );
+            }
+        }
+        if !dry_run {
+            self.config.store()?;
+        }
+        Ok(())
+    }
+
+    /// Syncs snippets to a Gist, making one the first time
+    fn sync_github(&mut self, mode: SyncMode) -> color_eyre::Result<()> {
+        let access_token = get_access_token(
+            "THE_WAY_GITHUB_TOKEN",
+            self.config.github_access_token.as_deref(),
+            "Get a GitHub access token from https://github.com/settings/tokens/new (add the \"gist\" scope)",
+            "GitHub access token",
+        )?;
+        self.config.github_access_token = Some(access_token.to_owned());
+        let client = GistClient::new(
+            HttpClient::new(&self.config.http),
+            &access_token,
+            USER_AGENT,
+        )?;
+        let gist_id = self.config.gist_id.clone();
+        self.config.gist_id = self.sync_or_make(&client, gist_id.as_deref(), mode)?;
+        Ok(())
+    }
+
+    /// Syncs snippets to a GitLab snippet (on gitlab.com or the instance in the `[gitlab]` section),
+    /// making one the first time
+    fn sync_gitlab(&mut self, mode: SyncMode) -> color_eyre::Result<()> {
+        let url = self
+            .config
+            .gitlab
+            .url
+            .clone()
+            .unwrap_or_else(|| GITLAB_URL.to_owned());
+        let access_token = get_access_token(
+            "THE_WAY_GITLAB_TOKEN",
+            self.config.gitlab.access_token.as_deref(),
+            &format!(
+                "Get a GitLab access token from {}/-/profile/personal_access_tokens (add the \"api\" scope)",
+                url.trim_end_matches('/')
+            ),
+            "GitLab access token",
+        )?;
+        self.config.gitlab.access_token = Some(access_token.to_owned());
+        let client = GitlabClient::new(
+            HttpClient::new(&self.config.http),
+            &url,
+            &access_token,
+            USER_AGENT,
+        )?;
+        let snippet_id = self.config.gitlab.snippet_id.clone();
+        self.config.gitlab.snippet_id = self.sync_or_make(&client, snippet_id.as_deref(), mode)?;
+        Ok(())
+    }
+
+    /// Syncs snippets to a Bitbucket snippet (in the workspace in the `[bitbucket]` section),
+    /// making one the first time
+    fn sync_bitbucket(&mut self, mode: SyncMode) -> color_eyre::Result<()> {
+        let bitbucket = &self.config.bitbucket;
+        let url = bitbucket
+            .url
+            .clone()
+            .unwrap_or_else(|| BITBUCKET_API_URL.to_owned());
+        let username = bitbucket
+            .username
+            .clone()
+            .ok_or(LostTheWay::ConfigError {
+                message: String::from("No Bitbucket username to sync with"),
+            })
+            .suggestion("Set `username` in the [bitbucket] section of your config file")?;
+        let workspace = bitbucket
+            .workspace
+            .clone()
+            .unwrap_or_else(|| username.to_owned());
+        let app_password = get_access_token(
+            "THE_WAY_BITBUCKET_APP_PASSWORD",
+            bitbucket.app_password.as_deref(),
+            "Make a Bitbucket app password from https://bitbucket.org/account/settings/app-passwords/ \
+            (with the \"snippet:write\" permission)",
+            "Bitbucket app password",
+        )?;
+        self.config.bitbucket.app_password = Some(app_password.to_owned());
+        let client = BitbucketClient::new(
+            HttpClient::new(&self.config.http),
+            &url,
+            &workspace,
+            &username,
+            &app_password,
+            USER_AGENT,
+        )?;
+        let snippet_id = self.config.bitbucket.snippet_id.clone();
+        self.config.bitbucket.snippet_id =
+            self.sync_or_make(&client, snippet_id.as_deref(), mode)?;
+        Ok(())
+    }
+
+    /// Syncs snippets to the bucket (and key prefix) in the `[s3]` section
+    fn sync_s3(&mut self, mode: SyncMode) -> color_eyre::Result<()> {
+        let s3 = &self.config.s3;
+        let bucket = s3
+            .bucket
+            .clone()
+            .ok_or(LostTheWay::ConfigError {
+                message: String::from("No S3 bucket to sync to"),
+            })
+            .suggestion("Set `bucket` in the [s3] section of your config file")?;
+        let access_key_id = get_access_token(
+            "AWS_ACCESS_KEY_ID",
+            s3.access_key_id.as_deref(),
+            "Make an access key with read and write access to the bucket",
+            "S3 access key ID",
+        )?;
+        let secret_access_key = get_access_token(
+            "AWS_SECRET_ACCESS_KEY",
+            s3.secret_access_key.as_deref(),
+            "Make an access key with read and write access to the bucket",
+            "S3 secret access key",
+        )?;
+        let store = S3Store::new(
+            HttpClient::new(&self.config.http),
+            s3.endpoint.as_deref(),
+            &bucket,
+            s3.region.as_deref().unwrap_or(s3::DEFAULT_REGION),
+            s3.prefix.as_deref().unwrap_or(s3::DEFAULT_PREFIX),
+            &access_key_id,
+            &secret_access_key,
+        )?;
+        // Keys from the environment are read from there every time, only typed ones are saved
+        if std::env::var_os("AWS_ACCESS_KEY_ID").is_none() {
+            self.config.s3.access_key_id = Some(access_key_id);
+        }
+        if std::env::var_os("AWS_SECRET_ACCESS_KEY").is_none() {
+            self.config.s3.secret_access_key = Some(secret_access_key);
+        }
+        let client = FileStoreClient::new(store);
+        self.sync_or_make(&client, Some(&client.location()), mode)?;
+        Ok(())
+    }
+
+    /// Syncs snippets to the folder in the `[webdav]` section
+    fn sync_webdav(&mut self, mode: SyncMode) -> color_eyre::Result<()> {
+        let webdav = &self.config.webdav;
+        let url = webdav
+            .url
+            .clone()
+            .ok_or(LostTheWay::ConfigError {
+                message: String::from("No WebDAV folder to sync to"),
+            })
+            .suggestion("Set `url` in the [webdav] section of your config file")?;
+        let password = std::env::var("THE_WAY_WEBDAV_PASSWORD")
+            .ok()
+            .or_else(|| webdav.password.clone());
+        let store = WebdavStore::new(
+            HttpClient::new(&self.config.http),
+            &url,
+            webdav.username.as_deref(),
+            password.as_deref(),
+        )?;
+        let client = FileStoreClient::new(store);
+        self.sync_or_make(&client, Some(&client.location()), mode)?;
+        Ok(())
+    }
+
+    /// Syncs snippets to the machine running `the-way serve --sync` in the `[server]` section
+    fn sync_server(&mut self, mode: SyncMode) -> color_eyre::Result<()> {
+        let url = self
+            .config
+            .server
+            .url
+            .clone()
+            .ok_or(LostTheWay::ConfigError {
+                message: String::from("No sync server to sync to"),
+            })
+            .suggestion("Set `url` in the [server] section of your config file")?;
+        let token = std::env::var("THE_WAY_SERVER_TOKEN")
+            .ok()
+            .or_else(|| self.config.server.token.clone());
+        let store = ServerStore::new(HttpClient::new(&self.config.http), &url, token.as_deref())?;
+        let client = FileStoreClient::new(store);
+        self.sync_or_make(&client, Some(&client.location()), mode)?;
+        Ok(())
+    }
+
+    /// Syncs with the remote collection `id`, or makes a new one if there isn't one yet
+    /// (encrypting everything sent if the `[encryption]` section says so).
+    /// Returns the ID of the collection (still `id` on a dry run)
+    fn sync_or_make(
+        &mut self,
+        client: &dyn SyncClient,
+        id: Option<&str>,
+        mode: SyncMode,
+    ) -> color_eyre::Result<Option<String>> {
+        let passphrase = if self.config.encryption.enabled {
+            Some(get_access_token(
+                "THE_WAY_SYNC_PASSPHRASE",
+                self.config.encryption.passphrase.as_deref(),
+                "Snippets are encrypted before syncing, use the same passphrase on every machine",
+                "Sync passphrase",
+            )?)
+        } else {
+            None
+        };
+        let client = EncryptedClient::new(client, passphrase)?;
+        let conflicts = match mode {
+            SyncMode::Sync(conflicts) => conflicts,
+            SyncMode::DryRun => {
+                self.sync_status(&client.read_only(), id)?;
+                return Ok(id.map(String::from));
+            }
+        };
+        let id = match id {
+            Some(id) => self.sync_gist(&client, id, conflicts)?,
+            None => self.make_gist(&client)?,
+        };
+        // Every deletion is either gone remotely now or was never there
+        self.clear_tombstones()?;
+        Ok(Some(id))
+    }
+
+    /// Prints what syncing with the remote collection `id` would upload, download, and delete,
+    /// and which snippets changed on both sides, without changing anything
+    fn sync_status(&self, client: &dyn SyncClient, id: Option<&str>) -> color_eyre::Result<()> {
+        let snippets = self.sync_snippets()?;
+        let gist = match id.map(|id| client.get_gist(id)) {
+            Some(Ok(gist)) => gist,
+            Some(Err(error))
+                if !matches!(
+                    error.downcast_ref::<LostTheWay>(),
+                    Some(LostTheWay::CollectionNotFound { .. })
+                ) =>
+            {
+                return Err(error);
+            }
+            _ => {
+                println!(
+                    "No {} yet, `sync` would make one with {} snippet(s)",
+                    client.name(),
+                    snippets.len()
+                );
+                return Ok(());
+            }
+        };
+        let (mut uploads, mut downloads, mut conflicts) = (Vec::new(), Vec::new(), Vec::new());
+        let (mut deletes, mut removes) = (Vec::new(), Vec::new());
+        let summaries = index_summaries(client, &gist);
+        let mut names: Vec<_> = gist.files.keys().collect();
+        names.sort_by_key(|name| snippet_file_index(name).ok());
+        for file in names {
+            if !file.contains("snippet_") {
+                continue;
+            }
+            let snippet_id = snippet_file_index(file)?;
+            if self.get_snippet(snippet_id).is_ok() {
+                continue;
+            }
+            let name = match summaries.get(file.as_str()) {
+                Some(summary) => format!("{} ({})", file, summary),
+                None => file.to_owned(),
+            };
+            let changed_remotely = self
+                .get_synced_hash(snippet_id)?
+                .is_some_and(|hash| hash != utils::content_hash(&gist.files[file].content));
+            if self.is_tombstoned(snippet_id)? && !changed_remotely {
+                deletes.push(name);
+            } else {
+                downloads.push(format!("{} (new)", name));
+            }
+        }
+        for snippet in &snippets {
+            let name = format!("#{} {}", snippet.label(), snippet.summary());
+            let filename = format!("snippet_{}{}", snippet.index, snippet.extension);
+            match gist.files.get(&filename) {
+                Some(gist_file) => {
+                    match self.changed(snippet, &gist_file.content, &gist.updated_at)? {
+                        Changed::Neither => {}
+                        Changed::Here => uploads.push(name),
+                        Changed::There => downloads.push(name),
+                        Changed::Both | Changed::Unknown => conflicts.push(name),
+                    }
+                }
+                None => match self.get_synced_hash(snippet.index)? {
+                    Some(hash) if hash == utils::content_hash(&snippet.code) => removes.push(name),
+                    _ => uploads.push(format!("{} (new)", name)),
+                },
+            }
+        }
+        let sections = [
+            (format!("Would upload to the {}", client.name()), uploads),
+            (
+                format!("Would download from the {}", client.name()),
+                downloads,
+            ),
+            (
+                format!("Changed here and in the {}", client.name()),
+                conflicts,
+            ),
+            (
+                format!("Would delete from the {} (deleted here)", client.name()),
+                deletes,
+            ),
+            (
+                format!("Would delete here (deleted from the {})", client.name()),
+                removes,
+            ),
+        ];
+        if sections.iter().all(|(_, names)| names.is_empty()) {
+            println!("Everything up to date");
+        }
+        for (heading, names) in sections.iter().filter(|(_, names)| !names.is_empty()) {
+            println!("{}:", heading);
+            for name in names {
+                println!("  {}", name);
+            }
+        }
+        println!("\n{}: {}", client.name(), gist.html_url);
+        Ok(())
+    }
+
+    /// Creates a Gist with each code snippet as a separate file (named snippet_<index>.<ext>)
+    /// and an index file (index.md) listing each snippet's description
+    fn make_gist(&self, client: &dyn SyncClient) -> color_eyre::Result<String> {
+        // Start creating
+        let spinner = utils::get_spinner(&format!("Creating {}...", client.name()));
+
+        // Make snippet files
+        let mut files = HashMap::new();
+        let snippets = self.sync_snippets()?;
+        for snippet in &snippets {
+            let filename = format!("snippet_{}{}", snippet.index, snippet.extension);
+            files.insert(
+                filename,
+                GistContent {
+                    content: snippet.code.as_str(),
+                },
+            );
+        }
+        let payload = CreateGistPayload {
+            description: DESCRIPTION,
+            public: false,
+            files,
+        };
+        // Upload snippet files to Gist
+        let result = client.create_gist(&payload)?;
+
+        // Make index file
+        let mut index = String::from(INDEX);
+        for snippet in &snippets {
+            index.push_str(&format!(
+                "* [{}]({})\n",
+                snippet.summary(),
+                client.file_url(
+                    &result.html_url,
+                    &format!("snippet_{}{}", snippet.index, snippet.extension)
+                )
+            ));
+        }
+        let mut update_files = HashMap::new();
+        update_files.insert(
+            String::from("index.md"),
+            Some(GistContent {
+                content: index.as_str(),
+            }),
+        );
+        let update_payload = UpdateGistPayload {
+            description: DESCRIPTION,
+            files: update_files,
+        };
+        // Upload index file to Gist
+        let result = client.update_gist(&result.id, &update_payload)?;
+        spinner.finish_with_message(&format!(
+            "Created {} at {} with {} snippets",
+            client.name(),
+            result.html_url,
+            result.files.len()
+        ));
+
+        for snippet in &snippets {
+            self.set_synced_code(snippet.index, &snippet.code)?;
+        }
+        self.add_sync_log_entry(&SyncLogEntry {
+            created: true,
+            uploaded: snippets.len(),
+            ..SyncLogEntry::new(client.name(), &result.html_url)
+        })?;
+
+        // Return created Gist ID
+        Ok(result.id)
+    }
+
+    /// Syncs local and Gist snippets, returns the Gist ID (a new one if the Gist was gone).
+    /// Snippets changed on both sides since the last sync are settled with `conflicts`
+    fn sync_gist(
+        &mut self,
+        client: &dyn SyncClient,
+        gist_id: &str,
+        conflicts: ConflictStrategy,
+    ) -> color_eyre::Result<String> {
+        // Start sync
+        let spinner = utils::get_spinner("Syncing...");
+
+        let mut updated = 0;
+        let mut added = 0;
+        let mut downloaded = 0;
+        let mut deleted = 0;
+        let mut removed = Vec::new();
+        let mut resolved = 0;
+        let mut index = String::from(INDEX);
+
+        // Retrieve gist
+
+        let gist = match client.get_gist(gist_id) {
+            Ok(gist) => gist,
+            Err(error)
+                if matches!(
+                    error.downcast_ref::<LostTheWay>(),
+                    Some(LostTheWay::CollectionNotFound { .. })
+                ) =>
+            {
+                spinner.finish_with_message(&format!("{} not found.", client.name()));
+                return self.make_gist(client);
+            }
+            // Making a new one would upload snippets deleted elsewhere again (or replace one that can't be read)
+            Err(error) => {
+                spinner.finish_and_clear();
+                return Err(error);
+            }
+        };
+        // Retrieve local snippets
+        let mut snippets = self.sync_snippets()?;
+        // Remote versions kept as new snippets
+        let mut copies = Vec::new();
+        // Snippets with the same code on both sides after syncing
+        let mut synced = Vec::new();
+        let mut uploads = HashSet::new();
+
+        let mut files = HashMap::new();
+        // Snippets only in Gist, first so that new snippets made here get indices after theirs
+        let summaries = index_summaries(client, &gist);
+        let mut pulled = Vec::new();
+        for (file, gist_file) in &gist.files {
+            if file.contains("snippet_") {
+                let snippet_id = snippet_file_index(file)?;
+                if self.get_snippet(snippet_id).is_ok() {
+                    continue;
+                }
+                let changed_remotely = self
+                    .get_synced_hash(snippet_id)?
+                    .is_some_and(|hash| hash != utils::content_hash(&gist_file.content));
+                if self.is_tombstoned(snippet_id)? && !changed_remotely {
+                    // Snippet deleted locally => delete from Gist
+                    files.insert(file.to_owned(), None);
+                    self.delete_synced_hash(snippet_id)?;
+                    deleted += 1;
+                } else {
+                    // New in Gist (or changed since it was deleted here) => download
+                    let extension = file.find('.').map_or("", |dot| &file[dot..]);
+                    let snippet = Snippet::new(
+                        snippet_id,
+                        summaries
+                            .get(file.as_str())
+                            .cloned()
+                            .unwrap_or_else(|| file.to_owned()),
+                        Language::from_extension(
+                            extension,
+                            &guess_language(&gist_file.content),
+                            &self.languages,
+                        ),
+                        extension.to_owned(),
+                        // Keeps it synced
+                        &self.config.sync_tags.join(" "),
+                        Utc::now(),
+                        Utc::now(),
+                        gist_file.content.clone(),
+                    );
+                    self.add_snippet(&snippet)?;
+                    self.raise_snippet_index(snippet_id)?;
+                    synced.push((snippet_id, snippet.code.clone()));
+                    pulled.push(snippet);
+                    downloaded += 1;
+                }
+            }
+        }
+        for snippet in &mut snippets {
+            let filename = format!("snippet_{}{}", snippet.index, snippet.extension);
+            // Check if snippet exists in Gist
+            match gist.files.get(&filename) {
+                Some(gist_file) => {
+                    let resolution = self.resolve(
+                        snippet,
+                        &gist_file.content,
+                        &gist.updated_at,
+                        client.name(),
+                        conflicts,
+                        &spinner,
+                    )?;
+                    let upload = match resolution {
+                        Resolution::Same => false,
+                        Resolution::Undecided => {
+                            // Synced again once one side changes
+                            self.delete_synced_hash(snippet.index)?;
+                            continue;
+                        }
+                        Resolution::Download => {
+                            // Snippet updated in Gist => download to local
+                            snippet.code = gist_file.content.clone();
+                            self.add_to_snippet(
+                                snippet.index.to_string().as_bytes(),
+                                &snippet.to_bytes()?,
+                            )?;
+                            downloaded += 1;
+                            false
+                        }
+                        Resolution::Merged(code) => {
+                            snippet.code = code;
+                            self.add_to_snippet(
+                                snippet.index.to_string().as_bytes(),
+                                &snippet.to_bytes()?,
+                            )?;
+                            resolved += 1;
+                            true
+                        }
+                        Resolution::KeepBoth => {
+                            let copy = Snippet {
+                                index: self.get_current_snippet_index()? + 1,
+                                description: format!(
+                                    "{} (from the {})",
+                                    snippet.description,
+                                    client.name()
+                                ),
+                                language: snippet.language.clone(),
+                                code: gist_file.content.clone(),
+                                extension: snippet.extension.clone(),
+                                tags: snippet.tags.clone(),
+                                date: Utc::now(),
+                                updated: Utc::now(),
+                                label: None,
+                                uuid: None,
+                            };
+                            self.add_snippet(&copy)?;
+                            self.increment_snippet_index()?;
+                            copies.push(copy);
+                            resolved += 1;
+                            true
+                        }
+                        // Snippet updated locally => update Gist
+                        Resolution::Upload => {
+                            updated += 1;
+                            true
+                        }
+                    };
+                    if upload {
+                        uploads.insert(snippet.index);
+                    }
+                    synced.push((snippet.index, snippet.code.clone()));
+                }
+                None => match self.get_synced_hash(snippet.index)? {
+                    // Deleted from Gist (e.g. on another machine) and unchanged here => delete locally
+                    Some(hash) if hash == utils::content_hash(&snippet.code) => {
+                        removed.push(snippet.index);
+                    }
+                    // Not in Gist (or changed since it was deleted there) => add
+                    _ => {
+                        uploads.insert(snippet.index);
+                        added += 1;
+                        synced.push((snippet.index, snippet.code.clone()));
+                    }
+                },
+            }
+        }
+        for snippet_index in &removed {
+            self.delete_snippet(*snippet_index)?;
+            self.delete_tracked_file(*snippet_index)?;
+            self.set_requirements(*snippet_index, &[])?;
+            self.delete_synced_hash(*snippet_index)?;
+        }
+        snippets.retain(|snippet| !removed.contains(&snippet.index));
+        // Remote versions of conflicting snippets are new snippets on both sides now
+        for copy in &copies {
+            uploads.insert(copy.index);
+            synced.push((copy.index, copy.code.clone()));
+        }
+        for snippet in snippets.iter().chain(&copies).chain(&pulled) {
+            if uploads.contains(&snippet.index) {
+                files.insert(
+                    format!("snippet_{}{}", snippet.index, snippet.extension),
+                    Some(GistContent {
+                        content: snippet.code.as_str(),
+                    }),
+                );
+            }
+            // Add to index
+            index.push_str(&format!(
+                "* [{}]({})\n",
+                snippet.summary(),
+                client.file_url(
+                    &gist.html_url,
+                    &format!("snippet_{}{}", snippet.index, snippet.extension)
+                )
+            ));
+        }
+        // Update Gist
+        if let Some(index_file) = gist.files.get("index.md") {
+            if index_file.content != index {
+                files.insert(
+                    "index.md".to_owned(),
+                    Some(GistContent {
+                        content: index.as_str(),
+                    }),
+                );
+            }
+        }
+        if !files.is_empty() {
+            client.update_gist(
+                &gist.id,
+                &UpdateGistPayload {
+                    description: DESCRIPTION,
+                    files,
+                },
+            )?;
+        }
+        for (index, code) in &synced {
+            self.set_synced_code(*index, code)?;
+        }
+        spinner.finish_with_message("Done!");
+        if added > 0 {
+            println!("Added {} snippet(s)", added);
+        }
+        if updated > 0 {
+            println!("Updated {} snippet(s)", updated);
+        }
+        if deleted > 0 {
+            println!("Deleted {} snippet(s)", deleted);
+        }
+        if downloaded > 0 {
+            println!("Downloaded {} snippet(s)", downloaded);
+        }
+        if !removed.is_empty() {
+            println!(
+                "Removed {} snippet(s) deleted from the {}",
+                removed.len(),
+                client.name()
+            );
+        }
+        if resolved > 0 {
+            println!("Resolved {} conflict(s)", resolved);
+        }
+        if added + updated + downloaded + deleted + removed.len() + resolved == 0 {
+            println!("Everything up to date");
+        }
+        println!("\n{}: {}", client.name(), gist.html_url);
+        self.add_sync_log_entry(&SyncLogEntry {
+            uploaded: added + updated,
+            downloaded,
+            deleted_remotely: deleted,
+            deleted_locally: removed.len(),
+            resolved,
+            ..SyncLogEntry::new(client.name(), &gist.html_url)
+        })?;
+        Ok(gist.id)
+    }
+
+    /// Snippets that are synced: the ones with a tag in `sync_tags` (if set) and none in `sync_exclude_tags`.
+    /// The rest are left alone on both sides, as if they weren't here
+    fn sync_snippets(&self) -> color_eyre::Result<Vec<Snippet>> {
+        let mut snippets = self.list_snippets()?;
+        snippets.retain(|snippet| {
+            (self.config.sync_tags.is_empty()
+                || self.config.sync_tags.iter().any(|tag| snippet.has_tag(tag)))
+                && !self
+                    .config
+                    .sync_exclude_tags
+                    .iter()
+                    .any(|tag| snippet.has_tag(tag))
+        });
+        Ok(snippets)
+    }
+
+    /// Works out which side changed `snippet` since the last sync: the code it had then
+    /// (by hash) tells a local change from a remote one. Without it, the newer side wins
+    fn changed(
+        &self,
+        snippet: &Snippet,
+        remote: &str,
+        remote_updated: &DateTime<Utc>,
+    ) -> color_eyre::Result<Changed> {
+        let (local_hash, remote_hash) = (
+            utils::content_hash(&snippet.code),
+            utils::content_hash(remote),
+        );
+        if local_hash == remote_hash {
+            return Ok(Changed::Neither);
+        }
+        Ok(match self.get_synced_hash(snippet.index)? {
+            Some(synced) if synced == remote_hash => Changed::Here,
+            Some(synced) if synced == local_hash => Changed::There,
+            Some(_) => Changed::Both,
+            // Synced before hashes were kept
+            None => match snippet.updated.cmp(remote_updated) {
+                std::cmp::Ordering::Less => Changed::There,
+                std::cmp::Ordering::Greater => Changed::Here,
+                std::cmp::Ordering::Equal => Changed::Unknown,
+            },
+        })
+    }
+
+    /// What to do with `snippet`, settling changes on both sides with `conflicts`
+    fn resolve(
+        &self,
+        snippet: &Snippet,
+        remote: &str,
+        remote_updated: &DateTime<Utc>,
+        remote_name: &str,
+        conflicts: ConflictStrategy,
+        spinner: &indicatif::ProgressBar,
+    ) -> color_eyre::Result<Resolution> {
+        match self.changed(snippet, remote, remote_updated)? {
+            Changed::Neither => Ok(Resolution::Same),
+            Changed::Here => Ok(Resolution::Upload),
+            Changed::There => Ok(Resolution::Download),
+            Changed::Unknown => Ok(Resolution::Undecided),
+            Changed::Both => match conflicts {
+                ConflictStrategy::KeepNewest if snippet.updated >= *remote_updated => {
+                    Ok(Resolution::Upload)
+                }
+                ConflictStrategy::KeepNewest => Ok(Resolution::Download),
+                ConflictStrategy::KeepBoth => Ok(Resolution::KeepBoth),
+                ConflictStrategy::Ask => {
+                    // The spinner would draw over the question
+                    spinner.finish_and_clear();
+                    ask_resolution(snippet, remote, remote_updated, remote_name)
+                }
+            },
+        }
+    }
+}
+
+/// Index of the snippet in `file` (named snippet_<index>.<ext>)
+fn snippet_file_index(file: &str) -> color_eyre::Result<usize> {
+    let suggestion =
+        "Make sure snippet files in the Gist are of the form \'snippet_<index>.<ext>\'";
+    file.split('.')
+        .next()
+        .ok_or(LostTheWay::SyncError {
+            message: "Invalid filename".into(),
+        })
+        .suggestion(suggestion)?
+        .split('_')
+        .next_back()
+        .ok_or(LostTheWay::SyncError {
+            message: "Invalid filename".into(),
+        })
+        .suggestion(suggestion)?
+        .parse::<usize>()
+        .map_err(|e| LostTheWay::SyncError {
+            message: format!("Invalid filename: {}", e),
+        })
+        .suggestion(suggestion)
+}
+
+/// Shows both versions next to the merged result, to pick lines from each side (or keep both)
+fn ask_resolution(
+    snippet: &Snippet,
+    remote: &str,
+    remote_updated: &DateTime<Utc>,
+    remote_name: &str,
+) -> color_eyre::Result<Resolution> {
+    let choice = merge::merge(
+        &format!(
+            "Snippet #{} ({}) changed here and in the {} since the last sync",
+            snippet.label(),
+            snippet.summary(),
+            remote_name
+        ),
+        &format!(
+            "Here (updated {})",
+            snippet.updated.format("%Y-%m-%d %H:%M")
+        ),
+        &format!(
+            "{} (updated {})",
+            remote_name,
+            remote_updated.format("%Y-%m-%d %H:%M")
+        ),
+        &snippet.code,
+        remote,
+        &snippet.extension,
+    )?;
+    Ok(match choice {
+        MergeChoice::Here => Resolution::Upload,
+        MergeChoice::There => Resolution::Download,
+        MergeChoice::Merged(code) => Resolution::Merged(code),
+        MergeChoice::KeepBoth => Resolution::KeepBoth,
+        MergeChoice::Skip => Resolution::Undecided,
+    })
+}
+
+/// First line of each snippet's description in the remote index file, by file name
+fn index_summaries(client: &dyn SyncClient, gist: &Gist) -> HashMap<String, String> {
+    let names: HashMap<_, _> = gist
+        .files
+        .keys()
+        .map(|name| (client.file_url(&gist.html_url, name), name))
+        .collect();
+    gist.files
+        .get("index.md")
+        .map(|index| {
+            index
+                .content
+                .lines()
+                .filter_map(|line| {
+                    let (summary, url) = line.strip_prefix("* [")?.rsplit_once("](")?;
+                    let name = names.get(url.strip_suffix(')')?)?;
+                    Some((name.to_string(), summary.to_owned()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Access token from `env_var`, or the one in the config file, or else asks for one
+pub(super) fn get_access_token(
+    env_var: &str,
+    saved: Option<&str>,
+    instructions: &str,
+    prompt: &str,
+) -> color_eyre::Result<String> {
+    // Check if environment variable has changed
+    if let Some(access_token) = std::env::var(env_var)
+        .ok()
+        .or_else(|| saved.map(String::from))
+    {
+        return Ok(access_token);
+    }
+    println!("{}\n", instructions);
+    Ok(
+        dialoguer::Password::with_theme(&dialoguer::theme::ColorfulTheme::default())
+            .with_prompt(prompt)
+            .interact()?,
+    )
+}
@@ -0,0 +1,111 @@
+//! Sync log for `the-way sync log`: what each past sync changed, on which side
+use chrono::{DateTime, Utc};
+
+use crate::language::CodeHighlight;
+use crate::the_way::TheWay;
+
+/// What one sync did
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct SyncLogEntry {
+    pub(crate) time: DateTime<Utc>,
+    /// Where the snippets were synced to, e.g. "Gist"
+    pub(crate) provider: String,
+    /// URL (or location) of the remote collection
+    pub(crate) location: String,
+    /// Whether the sync made the remote collection
+    pub(crate) created: bool,
+    /// Snippets added to or updated in the remote collection
+    pub(crate) uploaded: usize,
+    /// Snippets added or updated here
+    pub(crate) downloaded: usize,
+    /// Snippets deleted from the remote collection (because they were deleted here)
+    pub(crate) deleted_remotely: usize,
+    /// Snippets deleted here (because they were deleted remotely)
+    pub(crate) deleted_locally: usize,
+    /// Snippets changed on both sides and settled
+    pub(crate) resolved: usize,
+}
+
+impl SyncLogEntry {
+    pub(crate) fn new(provider: &str, location: &str) -> Self {
+        Self {
+            time: Utc::now(),
+            provider: provider.to_owned(),
+            location: location.to_owned(),
+            created: false,
+            uploaded: 0,
+            downloaded: 0,
+            deleted_remotely: 0,
+            deleted_locally: 0,
+            resolved: 0,
+        }
+    }
+
+    /// e.g. "uploaded 2, downloaded 1, resolved 1 conflict(s)"
+    pub(crate) fn describe(&self) -> String {
+        let mut changes = Vec::new();
+        if self.created {
+            changes.push(format!("created with {} snippet(s)", self.uploaded));
+        } else if self.uploaded > 0 {
+            changes.push(format!("uploaded {}", self.uploaded));
+        }
+        for (count, change) in &[
+            (self.downloaded, "downloaded"),
+            (self.deleted_remotely, "deleted remotely"),
+            (self.deleted_locally, "deleted here"),
+        ] {
+            if *count > 0 {
+                changes.push(format!("{} {}", change, count));
+            }
+        }
+        if self.resolved > 0 {
+            changes.push(format!("resolved {} conflict(s)", self.resolved));
+        }
+        if changes.is_empty() {
+            String::from("nothing changed")
+        } else {
+            changes.join(", ")
+        }
+    }
+
+    /// write entry to database
+    pub(crate) fn to_bytes(&self) -> color_eyre::Result<Vec<u8>> {
+        Ok(bincode::serialize(&self)?)
+    }
+
+    /// read entry from database
+    pub(crate) fn from_bytes(bytes: &[u8]) -> color_eyre::Result<Self> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+}
+
+impl TheWay {
+    /// Lists the last `number` syncs, most recent first
+    pub(crate) fn sync_log(&self, number: usize) -> color_eyre::Result<()> {
+        let entries = self.get_sync_log(number)?;
+        if entries.is_empty() {
+            println!("No syncs yet");
+            return Ok(());
+        }
+        for entry in entries {
+            let time = entry
+                .time
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string();
+            let time = if self.cli.accessible {
+                time
+            } else {
+                CodeHighlight::highlight_string(&time, self.highlighter.accent_style)
+            };
+            println!(
+                "{} {} {}: {}",
+                time,
+                entry.provider,
+                entry.location,
+                entry.describe()
+            );
+        }
+        Ok(())
+    }
+}
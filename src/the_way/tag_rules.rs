@@ -0,0 +1,130 @@
+//! Automatic tagging (`[[tag_rules]]` in the config file): new and imported snippets matching a rule's
+//! language, code pattern, or URL domain get its tag. `the-way retag --rules` applies the rules to every snippet
+use color_eyre::Help;
+use regex::Regex;
+
+use crate::configuration::TagRule;
+use crate::errors::LostTheWay;
+use crate::the_way::{snippet::Snippet, undo::UndoEntry, TheWay};
+
+/// A tag rule ready to be matched
+pub(crate) struct Rule {
+    tag: String,
+    language: Option<String>,
+    code: Option<Regex>,
+    domain: Option<String>,
+}
+
+impl Rule {
+    fn new(rule: &TagRule) -> color_eyre::Result<Self> {
+        if rule.language.is_none() && rule.code.is_none() && rule.domain.is_none() {
+            let error: color_eyre::Result<Self> = Err(LostTheWay::ConfigError {
+                message: format!("The tag rule for {:?} has no conditions", rule.tag),
+            }
+            .into());
+            return error.suggestion("Give it a `language`, `code`, or `domain` to match");
+        }
+        let code = rule
+            .code
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| LostTheWay::ConfigError {
+                message: format!("Bad code pattern in the tag rule for {:?}: {}", rule.tag, e),
+            })?;
+        Ok(Self {
+            tag: rule.tag.clone(),
+            language: rule.language.as_ref().map(|l| l.to_ascii_lowercase()),
+            code,
+            domain: rule
+                .domain
+                .as_ref()
+                .map(|domain| domain.trim_start_matches('.').to_ascii_lowercase()),
+        })
+    }
+
+    fn matches(&self, snippet: &Snippet) -> bool {
+        self.language.as_ref().map_or(true, |language| {
+            snippet.language.to_ascii_lowercase() == *language
+        }) && self
+            .code
+            .as_ref()
+            .map_or(true, |code| code.is_match(&snippet.code))
+            && self.domain.as_ref().map_or(true, |domain| {
+                url_hosts(&snippet.description)
+                    .chain(url_hosts(&snippet.code))
+                    .any(|host| host == *domain || host.ends_with(&format!(".{}", domain)))
+            })
+    }
+}
+
+/// Hosts of the http(s) URLs in `text`, lowercased
+fn url_hosts(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| c.is_whitespace() || "\"'<>()[]`".contains(c))
+        .filter_map(|word| {
+            let rest = word
+                .strip_prefix("https://")
+                .or_else(|| word.strip_prefix("http://"))?;
+            let host = rest.split(['/', '?', '#']).next()?;
+            // Without user info and port
+            let host = host.rsplit('@').next()?.split(':').next()?;
+            Some(host.to_ascii_lowercase())
+        })
+}
+
+/// Adds the tags of every rule `snippet` matches, returns the ones it didn't have yet
+pub(crate) fn apply_tag_rules(rules: &[Rule], snippet: &mut Snippet) -> Vec<String> {
+    let mut added = Vec::new();
+    for rule in rules {
+        if !snippet.has_tag(&rule.tag) && rule.matches(snippet) {
+            snippet.tags.push(rule.tag.clone());
+            added.push(rule.tag.clone());
+        }
+    }
+    added
+}
+
+impl TheWay {
+    /// The config's tag rules with their patterns compiled, to match any number of snippets against
+    pub(crate) fn tag_rules(&self) -> color_eyre::Result<Vec<Rule>> {
+        self.config.tag_rules.iter().map(Rule::new).collect()
+    }
+
+    /// Applies the tag rules to every snippet
+    pub(crate) fn retag(&mut self) -> color_eyre::Result<()> {
+        if self.config.tag_rules.is_empty() {
+            let error: color_eyre::Result<()> = Err(LostTheWay::ConfigError {
+                message: String::from("No tag rules"),
+            }
+            .into());
+            return error.suggestion("Add [[tag_rules]] entries to your config file");
+        }
+        let rules = self.tag_rules()?;
+        let mut snippets = self.list_snippets()?;
+        snippets.sort_by_key(|snippet| snippet.index);
+        let mut changed = Vec::new();
+        for mut snippet in snippets {
+            let added = apply_tag_rules(&rules, &mut snippet);
+            if added.is_empty() {
+                continue;
+            }
+            if changed.is_empty() {
+                self.backup_before("retag")?;
+            }
+            let old_snippet = self.delete_snippet(snippet.index)?;
+            self.add_snippet(&snippet)?;
+            println!("Snippet #{} tagged {}", snippet.label(), added.join(", "));
+            changed.push((snippet.index, Some(old_snippet)));
+        }
+        if changed.is_empty() {
+            println!("Every snippet already has the tags of the rules it matches");
+            return Ok(());
+        }
+        println!("Retagged {} snippet(s)", changed.len());
+        self.record_change(&UndoEntry {
+            snippets: changed,
+            ..UndoEntry::new("retag")
+        })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+//! Code related to keeping snippets in sync with files on disk
+use std::path::Path;
+use std::time::Duration;
+use std::{fs, thread};
+
+use chrono::Utc;
+use color_eyre::Help;
+
+use crate::errors::LostTheWay;
+use crate::the_way::TheWay;
+
+impl TheWay {
+    /// Links a snippet to a file and reads the file into the snippet
+    pub(crate) fn track(&self, index: usize, file: &Path) -> color_eyre::Result<()> {
+        // Make sure the snippet exists
+        self.get_snippet(index)?;
+        let file = fs::canonicalize(file)
+            .map_err(|e| LostTheWay::OutOfCheeseError {
+                message: format!("Couldn't find {}: {}", file.display(), e),
+            })
+            .suggestion("Make sure the file exists")?;
+        self.add_tracked_file(index, &file)?;
+        println!("Snippet #{} tracks {}", index, file.display());
+        self.refresh_tracked()?;
+        Ok(())
+    }
+
+    /// Removes the link between a snippet and its file
+    pub(crate) fn untrack(&self, index: usize) -> color_eyre::Result<()> {
+        match self.delete_tracked_file(index)? {
+            Some(file) => println!("Snippet #{} no longer tracks {}", index, file.display()),
+            None => println!("Snippet #{} isn't tracking a file", index),
+        }
+        Ok(())
+    }
+
+    /// Replaces the code of each tracked snippet with its file contents (if they changed).
+    /// Returns the number of refreshed snippets
+    pub(crate) fn refresh_tracked(&self) -> color_eyre::Result<usize> {
+        let mut refreshed = 0;
+        for (index, file) in self.get_tracked_files()? {
+            let code = match fs::read_to_string(&file) {
+                Ok(code) => code,
+                Err(e) => {
+                    eprintln!(
+                        "Couldn't read {} for snippet #{}: {}",
+                        file.display(),
+                        index,
+                        e
+                    );
+                    continue;
+                }
+            };
+            let mut snippet = self.get_snippet(index)?;
+            if snippet.code != code {
+                snippet.code = code;
+                snippet.updated = Utc::now();
+                self.add_to_snippet(index.to_string().as_bytes(), &snippet.to_bytes()?)?;
+                println!("Refreshed snippet #{} from {}", index, file.display());
+                refreshed += 1;
+            }
+        }
+        if refreshed > 0 {
+            self.commit_to_git(&format!("refresh: {} tracked snippets", refreshed));
+        }
+        Ok(refreshed)
+    }
+
+    /// Checks tracked files every `interval` seconds and refreshes snippets when they change
+    pub(crate) fn watch_tracked(&self, interval: u64) -> color_eyre::Result<()> {
+        println!("Watching tracked files, press Ctrl-C to stop");
+        loop {
+            self.refresh_tracked()?;
+            self.db.flush()?;
+            thread::sleep(Duration::from_secs(interval));
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Bulk triage (`the-way triage`): steps through (optionally filtered) snippets,
+//! keeping, editing, retagging, archiving, or deleting each with a single key
+use chrono::Utc;
+use color_eyre::Help;
+
+use crate::errors::LostTheWay;
+use crate::language::Language;
+use crate::the_way::{snippet::Snippet, undo::UndoEntry, TheWay};
+use crate::utils;
+
+/// Tag given to archived snippets
+const ARCHIVE_TAG: &str = "archived";
+const KEYS: &str = "[k]eep [e]dit [r]etag [a]rchive [d]elete [s]kip [q]uit";
+
+/// What to do with a snippet
+enum Action {
+    Keep,
+    Edit,
+    Retag,
+    Archive,
+    Delete,
+    Skip,
+    Quit,
+}
+
+/// Waits for one of the action keys
+fn read_action() -> color_eyre::Result<Action> {
+    let term = console::Term::stdout();
+    loop {
+        let key = term
+            .read_char()
+            .map_err(|_| LostTheWay::OutOfCheeseError {
+                message: String::from("Couldn't read a key"),
+            })
+            .suggestion("Run `the-way triage` in a terminal")?;
+        let action = match key.to_ascii_lowercase() {
+            'k' => Action::Keep,
+            'e' => Action::Edit,
+            'r' => Action::Retag,
+            'a' => Action::Archive,
+            'd' => Action::Delete,
+            's' => Action::Skip,
+            'q' => Action::Quit,
+            _ => continue,
+        };
+        return Ok(action);
+    }
+}
+
+/// How many snippets got each action
+#[derive(Default)]
+struct Tally {
+    kept: usize,
+    edited: usize,
+    retagged: usize,
+    archived: usize,
+    deleted: usize,
+    skipped: usize,
+}
+
+impl TheWay {
+    /// Steps through `snippets` (oldest first), asking what to do with each.
+    /// Every change is undone together by `the-way undo`
+    pub(crate) fn triage(&mut self, mut snippets: Vec<Snippet>) -> color_eyre::Result<()> {
+        snippets.sort_by_key(|snippet| snippet.index);
+        if snippets.is_empty() {
+            println!("No snippets to triage");
+            return Ok(());
+        }
+        let total = snippets.len();
+        let mut tally = Tally::default();
+        let mut entry = UndoEntry::new("triage");
+        let result = self.triage_snippets(snippets, &mut tally, &mut entry);
+        if !entry.snippets.is_empty() {
+            self.record_change(&entry)?;
+        }
+        result?;
+        println!(
+            "Kept {}, edited {}, retagged {}, archived {}, deleted {}, skipped {} (of {})",
+            tally.kept,
+            tally.edited,
+            tally.retagged,
+            tally.archived,
+            tally.deleted,
+            tally.skipped,
+            total
+        );
+        Ok(())
+    }
+
+    /// Asks what to do with each of `snippets`, counting actions in `tally`
+    /// and adding the snippets as they were before each change to `entry`
+    fn triage_snippets(
+        &mut self,
+        snippets: Vec<Snippet>,
+        tally: &mut Tally,
+        entry: &mut UndoEntry,
+    ) -> color_eyre::Result<()> {
+        let total = snippets.len();
+        for (i, mut snippet) in snippets.into_iter().enumerate() {
+            let lines = if self.cli.accessible {
+                snippet.plain_print(true)
+            } else {
+                snippet.pretty_print(
+                    &self.highlighter,
+                    self.languages
+                        .get(&snippet.language)
+                        .unwrap_or(&Language::default()),
+                )?
+            };
+            for line in lines {
+                print!("{}", line);
+            }
+            println!("Snippet {} of {}: {}", i + 1, total, KEYS);
+            let index = snippet.index;
+            let done = match read_action()? {
+                Action::Keep => {
+                    tally.kept += 1;
+                    continue;
+                }
+                Action::Skip => {
+                    tally.skipped += 1;
+                    continue;
+                }
+                Action::Quit => break,
+                Action::Delete => {
+                    if entry.snippets.is_empty() {
+                        self.backup_before("triage")?;
+                    }
+                    self.delete_for_undo(index, entry)?;
+                    println!("Snippet #{} deleted\n", snippet.label());
+                    tally.deleted += 1;
+                    continue;
+                }
+                Action::Edit => {
+                    snippet = Snippet::from_editor(&self.languages, &snippet)?;
+                    tally.edited += 1;
+                    "changed"
+                }
+                Action::Retag => {
+                    let tags = utils::user_input(
+                        "Tags (space separated)",
+                        Some(&snippet.tags.join(" ")),
+                        true,
+                        true,
+                    )?;
+                    snippet.tags = utils::split_tags(&tags);
+                    snippet.updated = Utc::now();
+                    tally.retagged += 1;
+                    "retagged"
+                }
+                Action::Archive => {
+                    if !snippet.has_tag(ARCHIVE_TAG) {
+                        snippet.tags.push(ARCHIVE_TAG.to_owned());
+                        snippet.updated = Utc::now();
+                    }
+                    tally.archived += 1;
+                    "archived"
+                }
+            };
+            snippet.validate(&self.config.validation)?;
+            if entry.snippets.is_empty() {
+                self.backup_before("triage")?;
+            }
+            let old_snippet = self.delete_snippet(index)?;
+            self.add_snippet(&snippet)?;
+            entry.snippets.push((index, Some(old_snippet)));
+            println!("Snippet #{} {}\n", snippet.label(), done);
+        }
+        Ok(())
+    }
+}
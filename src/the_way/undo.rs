@@ -0,0 +1,145 @@
+//! Undo log for `the-way undo`
+use std::path::PathBuf;
+
+use color_eyre::Help;
+
+use crate::errors::LostTheWay;
+use crate::the_way::{snippet::Snippet, TheWay};
+
+/// What a mutating command changed, enough to reverse it
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct UndoEntry {
+    /// Command that made the change, e.g. "edit"
+    pub(crate) command: String,
+    /// (snippet index, snippet before the command), `None` if the command added the snippet
+    pub(crate) snippets: Vec<(usize, Option<Snippet>)>,
+    /// (snippet index, what it was linked to) for snippets the command deleted
+    pub(crate) links: Vec<(usize, Links)>,
+}
+
+/// A deleted snippet's tracked file and requirements, for `undo` to put back with it
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct Links {
+    pub(crate) tracked_file: Option<PathBuf>,
+    pub(crate) requirements: Vec<String>,
+}
+
+impl UndoEntry {
+    /// Entry for a command which hasn't changed anything yet
+    pub(crate) fn new(command: &str) -> Self {
+        Self {
+            command: command.to_owned(),
+            snippets: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+
+    /// Entry for a command which added new snippets
+    pub(crate) fn added(command: &str, indices: &[usize]) -> Self {
+        Self {
+            snippets: indices.iter().map(|index| (*index, None)).collect(),
+            ..Self::new(command)
+        }
+    }
+
+    /// Entry for a command which changed a snippet
+    pub(crate) fn changed(command: &str, snippet: Snippet) -> Self {
+        Self {
+            snippets: vec![(snippet.index, Some(snippet))],
+            ..Self::new(command)
+        }
+    }
+
+    /// Commit message for the change, e.g. "del: snippet #2"
+    pub(crate) fn describe(&self) -> String {
+        let indices: Vec<_> = self
+            .snippets
+            .iter()
+            .map(|(index, _)| format!("#{}", index))
+            .collect();
+        format!(
+            "{}: snippet{} {}",
+            self.command,
+            if indices.len() == 1 { "" } else { "s" },
+            indices.join(", ")
+        )
+    }
+
+    /// write entry to database
+    pub(crate) fn to_bytes(&self) -> color_eyre::Result<Vec<u8>> {
+        Ok(bincode::serialize(&self)?)
+    }
+
+    /// read entry from database
+    pub(crate) fn from_bytes(bytes: &[u8]) -> color_eyre::Result<Self> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+}
+
+impl TheWay {
+    /// Deletes a snippet along with its tracked file link and requirements, leaving a tombstone for sync.
+    /// Adds all of it to `entry`, for `undo` to put back
+    pub(crate) fn delete_for_undo(
+        &mut self,
+        index: usize,
+        entry: &mut UndoEntry,
+    ) -> color_eyre::Result<()> {
+        let snippet = self.delete_snippet(index)?;
+        let links = Links {
+            tracked_file: self.delete_tracked_file(index)?,
+            requirements: self.get_requirements(index)?,
+        };
+        self.set_requirements(index, &[])?;
+        self.add_tombstone(index)?;
+        entry.snippets.push((index, Some(snippet)));
+        entry.links.push((index, links));
+        Ok(())
+    }
+
+    /// Reverses the most recent add, edit, delete, or import
+    pub(crate) fn undo(&mut self) -> color_eyre::Result<()> {
+        let entry = self
+            .pop_undo_entry()?
+            .ok_or(LostTheWay::NothingToUndo)
+            .suggestion(
+                "Only `new`, `edit`, `del`, `import`, and `watch-clipboard` can be undone",
+            )?;
+        self.backup_before("undo")?;
+        let description = entry.describe();
+        let (mut removed, mut restored) = (Vec::new(), Vec::new());
+        for (index, old_snippet) in entry.snippets {
+            if self.get_snippet(index).is_ok() {
+                self.delete_snippet(index)?;
+            }
+            match old_snippet {
+                Some(old_snippet) => {
+                    self.add_snippet(&old_snippet)?;
+                    if let Some((_, links)) =
+                        entry.links.iter().find(|(linked, _)| *linked == index)
+                    {
+                        if let Some(file) = &links.tracked_file {
+                            self.add_tracked_file(index, file)?;
+                        }
+                        self.set_requirements(index, &links.requirements)?;
+                    }
+                    restored.push(format!("#{}", index));
+                }
+                None => {
+                    self.delete_tracked_file(index)?;
+                    self.add_tombstone(index)?;
+                    removed.push(format!("#{}", index));
+                }
+            }
+        }
+        self.db.flush()?;
+        self.commit_to_git(&format!("undo {}", description));
+        println!("Undid `{}`", entry.command);
+        if !removed.is_empty() {
+            println!("Removed snippet {}", removed.join(", "));
+        }
+        if !restored.is_empty() {
+            println!("Restored snippet {}", restored.join(", "));
+        }
+        Ok(())
+    }
+}
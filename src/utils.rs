@@ -1,8 +1,10 @@
+use std::env;
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::str;
 
-use chrono::{Date, DateTime, Utc, MAX_DATE, MIN_DATE};
+use chrono::{Date, DateTime, Datelike, Duration, TimeZone, Utc, MAX_DATE, MIN_DATE};
 use chrono_english::{parse_date_string, Dialect};
 use color_eyre::Help;
 use dialoguer::{theme, Editor, Input};
@@ -51,6 +53,27 @@ pub fn copy_to_clipboard(text: &str) -> color_eyre::Result<()> {
     Ok(())
 }
 
+/// Get clipboard contents
+pub fn read_clipboard() -> color_eyre::Result<String> {
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("pbpaste");
+
+    #[cfg(target_os = "linux")]
+    let mut command = {
+        let mut c = Command::new("xclip");
+        c.arg("-out");
+        c.arg("-selection");
+        c.arg("clipboard");
+        c
+    };
+
+    let output = command
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|_| LostTheWay::ClipboardError)?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
 /// Splits input by space
 pub fn split_tags(input: &str) -> Vec<String> {
     input
@@ -59,6 +82,32 @@ pub fn split_tags(input: &str) -> Vec<String> {
         .collect::<Vec<String>>()
 }
 
+/// Turns literal `\n` escapes typed at a prompt into newlines
+pub fn unescape_newlines(input: &str) -> String {
+    input.replace("\\n", "\n")
+}
+
+/// Turns newlines into literal `\n` escapes so multi-line text fits on a prompt
+pub fn escape_newlines(input: &str) -> String {
+    input.replace('\n', "\\n")
+}
+
+/// `text` with HTML and XML's special characters escaped
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 /// Converts an array of bytes to a string
 pub fn u8_to_str(input: &[u8]) -> color_eyre::Result<String> {
     Ok(str::from_utf8(input)?.to_owned())
@@ -84,10 +133,58 @@ pub fn make_indices_string(index_list: &[usize]) -> color_eyre::Result<Vec<u8>>
         .to_vec())
 }
 
-/// Makes a date from a string, can be colloquial like "next Friday"
+/// `date` moved `months` months back, on the last day of the month if it's too short
+fn months_before(date: Date<Utc>, months: u32) -> Option<Date<Utc>> {
+    let months = date.year() * 12 + date.month0() as i32 - months as i32;
+    let (year, month) = (months.div_euclid(12), months.rem_euclid(12) as u32 + 1);
+    (1..=date.day())
+        .rev()
+        .find_map(|day| Utc.ymd_opt(year, month, day).single())
+}
+
+/// Dates relative to `today`: "2d", "3 weeks", "1y ago", "last month", "this week", and so on
+fn parse_relative_date(date_string: &str, today: Date<Utc>) -> Option<Date<Utc>> {
+    let date_string = date_string.trim().to_lowercase();
+    let date_string = date_string
+        .strip_suffix(" ago")
+        .unwrap_or(&date_string)
+        .trim();
+    let (count, unit) = match date_string.split_once(' ') {
+        Some(("last", unit)) => (1, unit.trim()),
+        Some(("this", unit)) => {
+            return match unit.trim() {
+                "week" => {
+                    Some(today - Duration::days(today.weekday().num_days_from_monday().into()))
+                }
+                "month" => today.with_day(1),
+                "year" => today.with_ordinal(1),
+                _ => None,
+            };
+        }
+        _ => {
+            let digits = date_string
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(date_string.len());
+            let (count, unit) = date_string.split_at(digits);
+            (count.parse().ok()?, unit.trim())
+        }
+    };
+    match unit {
+        "d" | "day" | "days" => today.checked_sub_signed(Duration::days(count.into())),
+        "w" | "week" | "weeks" => today.checked_sub_signed(Duration::weeks(count.into())),
+        "m" | "mo" | "month" | "months" => months_before(today, count),
+        "y" | "year" | "years" => months_before(today, count.checked_mul(12)?),
+        _ => None,
+    }
+}
+
+/// Makes a date from a string, can be colloquial like "next Friday", "yesterday", "last week", or "2d"
 pub fn parse_date(date_string: &str) -> color_eyre::Result<Date<Utc>> {
-    if date_string.to_ascii_lowercase() == "today" {
-        Ok(Utc::now().date())
+    let today = Utc::now().date();
+    if date_string.eq_ignore_ascii_case("today") {
+        Ok(today)
+    } else if let Some(date) = parse_relative_date(date_string, today) {
+        Ok(date)
     } else {
         Ok(parse_date_string(date_string, Utc::now(), Dialect::Uk)?.date())
     }
@@ -113,12 +210,17 @@ pub fn date_end(to_date: Option<Date<Utc>>) -> DateTime<Utc> {
 
 /// Gets input from external editor, optionally displays default text in editor
 pub fn external_editor_input(default: Option<&str>, extension: &str) -> color_eyre::Result<String> {
-    Ok(Editor::new()
+    Editor::new()
         .extension(extension)
         .edit(default.unwrap_or(""))
         .suggestion("Set your default editor using the $EDITOR or $VISUAL environment variables")?
         .ok_or(LostTheWay::EditorError)
-        .suggestion("Make sure to save next time if you want to record a snippet!")?)
+        .suggestion("Make sure to save next time if you want to record a snippet!")
+}
+
+/// SHA-256 of `text` as hex, to tell whether two versions of a snippet are the same
+pub fn content_hash(text: &str) -> String {
+    hex::encode(ring::digest::digest(&ring::digest::SHA256, text.as_bytes()))
 }
 
 /// Takes user input from terminal, optionally has a default and optionally displays it.
@@ -159,3 +261,18 @@ pub fn get_spinner(message: &str) -> indicatif::ProgressBar {
     spinner.set_message(message);
     spinner
 }
+
+/// Finds `command` in `$PATH` (or checks it directly if it's a path)
+pub fn find_command(command: &str) -> Option<PathBuf> {
+    let path = Path::new(command);
+    if path.components().count() > 1 {
+        return if path.is_file() {
+            Some(path.to_owned())
+        } else {
+            None
+        };
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(command))
+        .find(|candidate| candidate.is_file())
+}
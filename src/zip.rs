@@ -0,0 +1,197 @@
+//! Just enough of the zip format to read the files in an archive (stored or deflated entries,
+//! without encryption or zip64, i.e. more than 4GB or 65535 entries) and to write deflated ones
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+use chrono::{Datelike, Timelike, Utc};
+
+const END_OF_DIRECTORY: u32 = 0x0605_4b50;
+const DIRECTORY_ENTRY: u32 = 0x0201_4b50;
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+/// Most the files in an archive can add up to, uncompressed (snippets are much smaller)
+const MAX_SIZE: usize = 256 * 1024 * 1024;
+/// Version 2.0, for deflate
+const VERSION: u16 = 20;
+/// File names are UTF-8
+const UTF8_NAMES: u16 = 1 << 11;
+
+fn u16_at(bytes: &[u8], offset: usize) -> Result<u16, String> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| String::from("the archive is cut short"))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Result<u32, String> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| String::from("the archive is cut short"))
+}
+
+/// Whether `bytes` start like a zip archive
+pub(crate) fn is_zip(bytes: &[u8]) -> bool {
+    u32_at(bytes, 0) == Ok(LOCAL_HEADER)
+}
+
+/// Name and contents of each file in the zip archive `bytes`, in the archive's order (folders are left out).
+/// Each file has to have the size and checksum the archive says it has
+pub(crate) fn files(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    // The end of central directory record is last, followed by a comment of up to 65535 bytes
+    let end = (0..bytes.len().saturating_sub(21))
+        .rev()
+        .take(22 + usize::from(u16::MAX))
+        .find(|&offset| u32_at(bytes, offset) == Ok(END_OF_DIRECTORY))
+        .ok_or_else(|| String::from("it isn't a zip archive"))?;
+    let count = u16_at(bytes, end + 10)?;
+    let mut entry = u32_at(bytes, end + 16)? as usize;
+    let mut files = Vec::with_capacity(usize::from(count));
+    let mut total_size = 0;
+    for _ in 0..count {
+        if u32_at(bytes, entry)? != DIRECTORY_ENTRY {
+            return Err(String::from("its central directory is broken"));
+        }
+        let flags = u16_at(bytes, entry + 8)?;
+        let method = u16_at(bytes, entry + 10)?;
+        let crc = u32_at(bytes, entry + 16)?;
+        let compressed_size = u32_at(bytes, entry + 20)? as usize;
+        let size = u32_at(bytes, entry + 24)? as usize;
+        let name_length = usize::from(u16_at(bytes, entry + 28)?);
+        let extra_length = usize::from(u16_at(bytes, entry + 30)?);
+        let comment_length = usize::from(u16_at(bytes, entry + 32)?);
+        let header = u32_at(bytes, entry + 42)? as usize;
+        let name = bytes
+            .get(entry + 46..entry + 46 + name_length)
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .ok_or_else(|| String::from("the archive is cut short"))?;
+        entry += 46 + name_length + extra_length + comment_length;
+        if name.ends_with('/') {
+            continue;
+        }
+        if flags & 1 == 1 {
+            return Err(format!("{} is encrypted", name));
+        }
+        if u32_at(bytes, header)? != LOCAL_HEADER {
+            return Err(format!("{} is missing from the archive", name));
+        }
+        let start = header
+            + 30
+            + usize::from(u16_at(bytes, header + 26)?)
+            + usize::from(u16_at(bytes, header + 28)?);
+        total_size += size;
+        if total_size > MAX_SIZE {
+            return Err(format!(
+                "its files add up to more than {}MB uncompressed",
+                MAX_SIZE / 1024 / 1024
+            ));
+        }
+        let data = bytes
+            .get(start..start + compressed_size)
+            .ok_or_else(|| String::from("the archive is cut short"))?;
+        let contents = match method {
+            STORED => data.to_vec(),
+            DEFLATED => {
+                let mut contents = Vec::new();
+                // One byte more than it should be is enough to tell it's wrong
+                flate2::read::DeflateDecoder::new(data)
+                    .take(size as u64 + 1)
+                    .read_to_end(&mut contents)
+                    .map_err(|e| format!("{} can't be decompressed: {}", name, e))?;
+                contents
+            }
+            method => {
+                return Err(format!(
+                    "{} is compressed in a way that isn't supported ({})",
+                    name, method
+                ))
+            }
+        };
+        if contents.len() != size {
+            return Err(format!("{} isn't the size the archive says it is", name));
+        }
+        let mut check = flate2::Crc::new();
+        check.update(&contents);
+        if check.sum() != crc {
+            return Err(format!(
+                "{} is corrupted (its checksum doesn't match)",
+                name
+            ));
+        }
+        files.push((name, contents));
+    }
+    Ok(files)
+}
+
+/// The current time as an MS-DOS time and date, which zip archives use
+fn dos_time() -> (u16, u16) {
+    let now = Utc::now().naive_utc();
+    let time = (now.hour() << 11) | (now.minute() << 5) | (now.second() / 2);
+    let date = ((now.year().max(1980) as u32 - 1980) << 9) | (now.month() << 5) | now.day();
+    (time as u16, date as u16)
+}
+
+/// `value` as a zip header field, or an error if it doesn't fit (which would need zip64)
+fn field<T: TryFrom<usize>>(value: usize) -> io::Result<T> {
+    T::try_from(value).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Too much for a zip archive (more than 65535 files, or 4GB)",
+        )
+    })
+}
+
+/// A zip archive of `files` (name and contents), each deflated
+pub(crate) fn write(files: &[(String, Vec<u8>)]) -> io::Result<Vec<u8>> {
+    let (time, date) = dos_time();
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    for (name, contents) in files {
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(contents)?;
+        let data = encoder.finish()?;
+        let mut crc = flate2::Crc::new();
+        crc.update(contents);
+        // Fields shared by the local header and the central directory entry, from "flags" to "extra length"
+        let mut fields = Vec::new();
+        for value in [UTF8_NAMES, DEFLATED, time, date] {
+            fields.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [crc.sum(), field(data.len())?, field(contents.len())?] {
+            fields.extend_from_slice(&value.to_le_bytes());
+        }
+        fields.extend_from_slice(&field::<u16>(name.len())?.to_le_bytes());
+        fields.extend_from_slice(&0_u16.to_le_bytes());
+
+        let offset: u32 = field(archive.len())?;
+        archive.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+        archive.extend_from_slice(&VERSION.to_le_bytes());
+        archive.extend_from_slice(&fields);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(&data);
+
+        directory.extend_from_slice(&DIRECTORY_ENTRY.to_le_bytes());
+        directory.extend_from_slice(&VERSION.to_le_bytes());
+        directory.extend_from_slice(&VERSION.to_le_bytes());
+        directory.extend_from_slice(&fields);
+        // Comment length, disk number, and internal and external attributes
+        directory.extend_from_slice(&[0; 10]);
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+    let directory_offset: u32 = field(archive.len())?;
+    archive.extend_from_slice(&directory);
+    archive.extend_from_slice(&END_OF_DIRECTORY.to_le_bytes());
+    // This disk and the disk the directory starts on
+    archive.extend_from_slice(&[0; 4]);
+    for _ in 0..2 {
+        archive.extend_from_slice(&field::<u16>(files.len())?.to_le_bytes());
+    }
+    archive.extend_from_slice(&field::<u32>(directory.len())?.to_le_bytes());
+    archive.extend_from_slice(&directory_offset.to_le_bytes());
+    // Comment length
+    archive.extend_from_slice(&0_u16.to_le_bytes());
+    Ok(archive)
+}
@@ -60,6 +60,114 @@ fn change_config_file() -> color_eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn completions() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let expected = [
+        ("zsh", "#compdef the-way"),
+        ("fig", "const completionSpec: Fig.Spec"),
+        ("carapace", "name: the-way"),
+        ("nushell", "export extern \"the-way themes set\""),
+    ];
+    for (shell, start) in &expected {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        cmd.env("THE_WAY_CONFIG", &config_file)
+            .args(["complete", shell])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(*start));
+    }
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn manpages() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let man_dir = temp_dir.path().join("man");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .env_remove("SOURCE_DATE_EPOCH")
+        .arg("manpage")
+        .arg(&man_dir)
+        .assert()
+        .success();
+    for page in &["the-way.1", "the-way-new.1", "the-way-themes-set.1"] {
+        let contents = std::fs::read_to_string(man_dir.join(page))?;
+        assert!(contents.starts_with(".TH"));
+        assert!(contents.contains(&format!(" 1 \"{}\" ", env!("CARGO_PKG_VERSION"))));
+    }
+
+    // Dated for reproducible builds
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .env("SOURCE_DATE_EPOCH", "1600000000")
+        .arg("manpage")
+        .arg(&man_dir)
+        .assert()
+        .success();
+    let contents = std::fs::read_to_string(man_dir.join("the-way.1"))?;
+    assert!(contents.starts_with(".TH \"THE-WAY\" 1 \"2020-09-13\" "));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["manpage", "--markdown"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("# the-way"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn doctor() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .env("LANG", "C")
+        .env_remove("LC_ALL")
+        .env_remove("LC_CTYPE")
+        .arg("doctor")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(config_file.to_string_lossy()))
+        .stdout(predicate::str::is_match(r"\[ok\] +database")?)
+        .stdout(predicate::str::is_match(
+            r"\[FAIL\] locale +C \(from \$LANG\)",
+        )?);
+
+    // Broken config
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", temp_dir.path().join("missing.toml"))
+        .arg("doctor")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("the-way config default"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn languages_update_failure() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args([
+            "languages",
+            "update",
+            "--url",
+            "http://127.0.0.1:1/languages.yml",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Check your internet connection"));
+    temp_dir.close()?;
+    Ok(())
+}
+
 #[test]
 fn change_theme() -> color_eyre::Result<()> {
     let temp_dir = tempdir()?;
@@ -93,6 +201,94 @@ fn change_theme() -> color_eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn export_import_themes() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let theme = "base16-mocha.dark";
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["themes", "set", theme])
+        .assert()
+        .success();
+    let bundle = temp_dir.path().join("themes.tar.gz");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["themes", "export"])
+        .arg(&bundle)
+        .assert()
+        .success();
+
+    // Import into a fresh setup
+    let new_temp_dir = tempdir()?;
+    let new_config_file = make_config_file(&new_temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &new_config_file)
+        .args(["themes", "import"])
+        .arg(&bundle)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &new_config_file)
+        .args(["themes", "get"])
+        .assert()
+        .stdout(predicate::str::contains(theme));
+    temp_dir.close()?;
+    new_temp_dir.close()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn theme_from_terminal_rexpect(config_file: &std::path::Path) -> rexpect::errors::Result<()> {
+    let mut p = spawn_bash(Some(5000))?;
+    let executable = env!("CARGO_BIN_EXE_the-way");
+    // Answers the color queries like a terminal would, leaving out most of the ANSI colors
+    p.send_line(&format!(
+        "THE_WAY_CONFIG={} {} themes from-terminal 'Mine & <Yours>'",
+        config_file.to_string_lossy(),
+        executable
+    ))?;
+    p.exp_string("\x1b]4;15;?\x07")?;
+    p.send(
+        "\x1b]10;rgb:ffff/ffff/ffff\x07\x1b]11;rgb:0000/0000/0000\x1b\\\x1b]4;2;rgb:00/cd/00\x07",
+    )?;
+    p.flush()?;
+    p.exp_string("Added theme Mine & <Yours>")?;
+    p.wait_for_prompt()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn theme_from_terminal() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    assert!(theme_from_terminal_rexpect(&config_file).is_ok());
+    let theme = fs::read_to_string(
+        temp_dir
+            .path()
+            .join("themes")
+            .join("Mine & <Yours>.tmTheme"),
+    )?;
+    assert!(theme.contains("<key>name</key><string>Mine &amp; &lt;Yours&gt;</string>"));
+    assert!(theme.contains("<key>background</key><string>#000000</string>"));
+    assert!(theme.contains("<key>foreground</key><string>#FFFFFF</string>"));
+    // Strings are ANSI green, colors the terminal didn't report are the foreground
+    assert!(theme.contains(
+        "<key>scope</key><string>string</string><key>settings</key><dict><key>foreground</key><string>#00CD00</string>"
+    ));
+    assert!(theme.contains(
+        "<key>scope</key><string>comment</string><key>settings</key><dict><key>foreground</key><string>#FFFFFF</string>"
+    ));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["themes", "set", "Mine & <Yours>"])
+        .assert()
+        .success();
+    temp_dir.close()?;
+    Ok(())
+}
+
 fn add_snippet_rexpect(config_file: PathBuf) -> rexpect::errors::Result<PtyReplSession> {
     let mut p = spawn_bash(Some(3000))?;
     p.send_line(&format!(
@@ -171,6 +367,199 @@ fn change_snippet_rexpect(config_file: PathBuf) -> rexpect::errors::Result<()> {
     Ok(())
 }
 
+fn inbox_rexpect(config_file: PathBuf) -> rexpect::errors::Result<()> {
+    let mut p = spawn_bash(Some(5000))?;
+    p.send_line(&format!(
+        "export THE_WAY_CONFIG={}",
+        config_file.to_string_lossy()
+    ))?;
+    let executable = env!("CARGO_BIN_EXE_the-way");
+    p.wait_for_prompt()?;
+    p.send_line(&format!(
+        "{} inbox add 'fn main() {{ let x = 1; }}'",
+        executable
+    ))?;
+    p.exp_string("Added snippet #1 to the inbox")?;
+    p.wait_for_prompt()?;
+    p.send_line(&format!("echo 'ls -la' | {} inbox add", executable))?;
+    p.exp_string("Added snippet #2 to the inbox")?;
+    p.wait_for_prompt()?;
+    p.execute(
+        &format!("{} inbox triage", executable),
+        "Inbox snippet 1 of 2",
+    )?;
+    p.send_line("")?;
+    p.exp_string("Description")?;
+    p.send_line("entry point")?;
+    p.exp_string("Language")?;
+    p.send_line("")?;
+    p.exp_regex("Tags")?;
+    p.send_line("tag1")?;
+    p.exp_string("Snippet #1 filed")?;
+    p.exp_string("Inbox snippet 2 of 2")?;
+    // Stop
+    p.send("\x1b[A")?;
+    p.send_line("")?;
+    p.exp_string("1 snippet(s) left in the inbox")?;
+    p.wait_for_prompt()?;
+    p.send_line(&format!("{} --accessible view 1", executable))?;
+    let view = p.wait_for_prompt()?;
+    assert!(
+        view.contains("entry point") && view.contains("Language: rust") && !view.contains("inbox")
+    );
+    Ok(())
+}
+
+#[test]
+fn inbox() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    assert!(inbox_rexpect(config_file).is_ok());
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn watch_clipboard_queue() -> color_eyre::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    // "xclip" that gives the next copied text each time, and stops the-way when there's none left
+    let clips = temp_dir.path().join("clips");
+    fs::create_dir(&clips)?;
+    let copied = [
+        "already there",
+        "Meeting moved to Thursday, bring the budget notes",
+        "fn main() {\n    let x = vec![1, 2, 3];\n}",
+        "https://example.com/some/long/path?query=1&other=2",
+        "git log --oneline | grep fix",
+        "git log --oneline | grep fix",
+    ];
+    for (i, text) in copied.iter().enumerate() {
+        fs::write(clips.join(i.to_string()), text)?;
+    }
+    let bin = temp_dir.path().join("bin");
+    fs::create_dir(&bin)?;
+    let xclip = bin.join("xclip");
+    fs::write(
+        &xclip,
+        "#!/bin/sh\nn=$(cat \"$CLIPS/count\" 2>/dev/null || echo 0)\n\
+        [ -f \"$CLIPS/$n\" ] || { kill \"$PPID\"; exit 1; }\n\
+        echo $((n + 1)) > \"$CLIPS/count\"\ncat \"$CLIPS/$n\"\n",
+    )?;
+    fs::set_permissions(&xclip, fs::Permissions::from_mode(0o755))?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .env("CLIPS", &clips)
+        .env(
+            "PATH",
+            format!("{}:{}", bin.display(), std::env::var("PATH")?),
+        )
+        .args(["watch-clipboard", "--queue"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "Queued snippet #1 (tagged inbox)\nQueued snippet #2 (tagged inbox)\n",
+        ))
+        .stdout(predicate::str::contains("#3").not());
+
+    // Only the code was saved, with a guessed language
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["--accessible", "view", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Language: rust"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["--accessible", "view", "2"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("Language: shell").and(predicate::str::contains("git log")),
+        );
+
+    // Checking the clipboard can't busy-loop
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["watch-clipboard", "--interval", "0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--interval"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+fn triage_rexpect(config_file: PathBuf) -> rexpect::errors::Result<()> {
+    let mut p = spawn_bash(Some(5000))?;
+    p.send_line(&format!(
+        "export THE_WAY_CONFIG={}",
+        config_file.to_string_lossy()
+    ))?;
+    let executable = env!("CARGO_BIN_EXE_the-way");
+    p.wait_for_prompt()?;
+    p.execute(
+        &format!("{} triage --languages sh", executable),
+        "Snippet 1 of 4",
+    )?;
+    p.send("k")?;
+    p.flush()?;
+    p.exp_string("Snippet 2 of 4")?;
+    p.send("r")?;
+    p.flush()?;
+    p.exp_string("Tags")?;
+    p.send_line("cleanup")?;
+    p.exp_string("Snippet #2 retagged")?;
+    p.exp_string("Snippet 3 of 4")?;
+    p.send("a")?;
+    p.flush()?;
+    p.exp_string("Snippet #3 archived")?;
+    p.exp_string("Snippet 4 of 4")?;
+    p.send("d")?;
+    p.flush()?;
+    p.exp_string("Snippet #4 deleted")?;
+    p.exp_string("Kept 1, edited 0, retagged 1, archived 1, deleted 1, skipped 0 (of 4)")?;
+    p.wait_for_prompt()?;
+    p.send_line(&format!("{} --accessible view 2", executable))?;
+    assert!(p.wait_for_prompt()?.contains("Tags: cleanup"));
+    p.send_line(&format!("{} --accessible view 3", executable))?;
+    assert!(p.wait_for_prompt()?.contains("archived"));
+    p.send_line(&format!("{} view 4", executable))?;
+    assert!(!p.wait_for_prompt()?.contains("four"));
+
+    // Undone together
+    p.send_line(&format!("{} undo", executable))?;
+    p.wait_for_prompt()?;
+    p.send_line(&format!("{} --accessible view 4", executable))?;
+    assert!(p.wait_for_prompt()?.contains("Description: four"));
+    p.send_line(&format!("{} --accessible view 3", executable))?;
+    assert!(!p.wait_for_prompt()?.contains("archived"));
+    Ok(())
+}
+
+#[test]
+fn triage() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(
+            r#"{"description":"one","language":"sh","code":"ls"}
+            {"description":"two","language":"sh","code":"pwd"}
+            {"description":"three","language":"sh","code":"cd"}
+            {"description":"four","language":"sh","code":"du"}
+            {"description":"other","language":"rust","code":"fn main() {}"}"#,
+        )
+        .assert()
+        .success();
+    assert!(triage_rexpect(config_file).is_ok());
+    temp_dir.close()?;
+    Ok(())
+}
+
 #[ignore] // expensive, and change_snippet tests both
 #[test]
 fn add_snippet() -> color_eyre::Result<()> {
@@ -199,8 +588,11 @@ fn change_snippet() -> color_eyre::Result<()> {
     Ok(())
 }
 
+#[cfg(unix)]
 #[test]
-fn import_single_show() -> color_eyre::Result<()> {
+fn change_snippet_editor() -> color_eyre::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
     let contents = r#"{"description":"test description","language":"rust","tags":["tag1","tag2"],"code":"some\ntest\ncode\n"}"#;
     let temp_dir = tempdir()?;
     let config_file = make_config_file(&temp_dir)?;
@@ -210,22 +602,42 @@ fn import_single_show() -> color_eyre::Result<()> {
         .write_stdin(contents)
         .assert()
         .success();
+
+    // "editor" that changes the description, language, and code in place
+    let editor = temp_dir.path().join("editor.sh");
+    fs::write(
+        &editor,
+        "#!/bin/sh\nsed -i -e 's/test description/edited description/' -e 's/rust/python/' -e 's/^some$/more/' \"$1\"\n",
+    )?;
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755))?;
     let mut cmd = Command::cargo_bin("the-way")?;
     cmd.env("THE_WAY_CONFIG", &config_file)
-        .arg("view")
+        .env("VISUAL", &editor)
+        .arg("edit")
         .arg("1")
+        .arg("--editor")
         .assert()
-        .stdout(predicate::str::contains("test description"));
+        .stdout(predicate::str::starts_with("Snippet #1 changed"));
+
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("list")
+        .arg("--languages")
+        .arg("python")
+        .assert()
+        .stdout(
+            predicate::str::contains("edited description")
+                .and(predicate::str::contains("more"))
+                .and(predicate::str::contains("tag2")),
+        );
     temp_dir.close()?;
     Ok(())
 }
 
 #[test]
-fn import_multiple_no_tags() -> color_eyre::Result<()> {
-    let contents_1 = r#"{"description":"test description 1","language":"rust","tags":["tag1","tag2"],"code":"some\ntest\ncode\n"}"#;
-    let contents_2 =
-        r#"{"description":"test description 2","language":"python","code":"some\ntest\ncode\n"}"#;
-    let contents = format!("{}{}", contents_1, contents_2);
+fn track_file() -> color_eyre::Result<()> {
+    let contents =
+        r#"{"description":"test description","language":"rust","code":"some\ntest\ncode\n"}"#;
     let temp_dir = tempdir()?;
     let config_file = make_config_file(&temp_dir)?;
     let mut cmd = Command::cargo_bin("the-way")?;
@@ -233,62 +645,75 @@ fn import_multiple_no_tags() -> color_eyre::Result<()> {
         .arg("import")
         .write_stdin(contents)
         .assert()
-        .stdout(predicate::str::starts_with("Imported 2 snippets"));
+        .success();
+    let tracked = temp_dir.path().join("tracked.rs");
+    fs::write(&tracked, "tracked code\n")?;
+
+    // Test nonexistent snippet
     let mut cmd = Command::cargo_bin("the-way")?;
     cmd.env("THE_WAY_CONFIG", &config_file)
-        .arg("list")
+        .args(["snippet", "track", "2"])
+        .arg(&tracked)
         .assert()
-        .stdout(
-            predicate::str::contains("test description 1")
-                .and(predicate::str::contains("test description 2")),
-        );
+        .failure();
+
+    // Tracking reads the file
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["snippet", "track", "1"])
+        .arg(&tracked)
+        .assert()
+        .stdout(predicate::str::contains("Refreshed snippet #1"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1"])
+        .assert()
+        .stdout(predicate::str::contains("tracked"));
+
+    // Refresh picks up changes
+    fs::write(&tracked, "changed code\n")?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["snippet", "refresh"])
+        .assert()
+        .stdout(predicate::str::contains("Refreshed snippet #1"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["snippet", "refresh"])
+        .assert()
+        .stdout(predicate::str::starts_with("Everything up to date"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1"])
+        .assert()
+        .stdout(predicate::str::contains("changed"));
     temp_dir.close()?;
     Ok(())
 }
 
 #[test]
-fn export() -> color_eyre::Result<()> {
-    use the_way::the_way::snippet::Snippet;
-
-    let contents_1 = r#"{"description":"test description 1","language":"rust","tags":["tag1","tag2"],"code":"some\ntest\ncode\n"}"#;
-    let contents_2 =
-        r#"{"description":"test description 2","language":"python","code":"some\ntest\ncode\n"}"#;
-    let contents = format!("{}{}", contents_1, contents_2);
+fn import_single_show() -> color_eyre::Result<()> {
+    let contents = r#"{"description":"test description","language":"rust","tags":["tag1","tag2"],"code":"some\ntest\ncode\n"}"#;
     let temp_dir = tempdir()?;
     let config_file = make_config_file(&temp_dir)?;
-
-    // import
     let mut cmd = Command::cargo_bin("the-way")?;
     cmd.env("THE_WAY_CONFIG", &config_file)
         .arg("import")
         .write_stdin(contents)
         .assert()
-        .stdout(predicate::str::starts_with("Imported 2 snippets"));
-
-    // export
+        .success();
     let mut cmd = Command::cargo_bin("the-way")?;
-    let file = temp_dir.path().join("snippets.json");
     cmd.env("THE_WAY_CONFIG", &config_file)
-        .arg("export")
-        .arg(file.to_str().unwrap())
+        .arg("view")
+        .arg("1")
         .assert()
-        .success();
-
-    let snippets = serde_json::Deserializer::from_reader(fs::File::open(&file)?)
-        .into_iter::<Snippet>()
-        .collect::<Result<Vec<_>, _>>()?;
-
-    assert_eq!(snippets.len(), 2);
-
-    for snippet in snippets {
-        assert_eq!(snippet.code, "some\ntest\ncode\n");
-    }
+        .stdout(predicate::str::contains("test description"));
     temp_dir.close()?;
     Ok(())
 }
 
 #[test]
-fn delete() -> color_eyre::Result<()> {
+fn import_multiple_no_tags() -> color_eyre::Result<()> {
     let contents_1 = r#"{"description":"test description 1","language":"rust","tags":["tag1","tag2"],"code":"some\ntest\ncode\n"}"#;
     let contents_2 =
         r#"{"description":"test description 2","language":"python","code":"some\ntest\ncode\n"}"#;
@@ -309,206 +734,5295 @@ fn delete() -> color_eyre::Result<()> {
             predicate::str::contains("test description 1")
                 .and(predicate::str::contains("test description 2")),
         );
+    temp_dir.close()?;
+    Ok(())
+}
 
-    // Test bad index
+#[test]
+fn import_field_map() -> color_eyre::Result<()> {
+    let contents = r#"[{"title":"mapped description","meta":{"lang":"rust"},"body":"fn main() {}","labels":[{"name":"tag1"},{"name":"tag2"}],"created":"2020-01-05"}]"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
     let mut cmd = Command::cargo_bin("the-way")?;
     cmd.env("THE_WAY_CONFIG", &config_file)
-        .arg("del")
-        .arg("-f")
-        .arg("3")
+        .args([
+            "import",
+            "--map",
+            "description=title,language=meta.lang,code=body,tags=labels[].name,date=created",
+        ])
+        .write_stdin(contents)
+        .assert()
+        .stdout(predicate::str::starts_with("Imported 1 snippets"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: mapped description")
+                .and(predicate::str::contains("Language: rust"))
+                .and(predicate::str::contains("Tags: tag1, tag2")),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--from", "2020-01-05", "--to", "2020-01-05"])
+        .assert()
+        .stdout(predicate::str::contains("mapped description"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn list_relative_dates() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"old snippet","language":"rust","code":"fn old() {}","date":"2020-01-05T00:00:00Z"}"#,
+        r#"{"description":"new snippet","language":"rust","code":"fn new() {}"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    for from in ["2d", "last week", "3 months ago", "this year"] {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        cmd.env("THE_WAY_CONFIG", &config_file)
+            .args(["list", "--accessible", "--from", from])
+            .assert()
+            .success()
+            .stdout(
+                predicate::str::contains("new snippet")
+                    .and(predicate::str::contains("old snippet").not()),
+            );
+    }
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--accessible", "--to", "1y ago"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("old snippet")
+                .and(predicate::str::contains("new snippet").not()),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn list_sort() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"beta","language":"rust","code":"1","date":"2021-01-01T00:00:00Z"}"#,
+        r#"{"description":"Alpha","language":"python","code":"2","date":"2022-01-01T00:00:00Z"}"#,
+        r#"{"description":"gamma","language":"go","code":"3","date":"2020-01-01T00:00:00Z"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let descriptions = |args: &[&str]| -> color_eyre::Result<Vec<String>> {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        let output = cmd
+            .env("THE_WAY_CONFIG", &config_file)
+            .args(["list", "--accessible"])
+            .args(args)
+            .output()?;
+        assert!(output.status.success());
+        Ok(String::from_utf8(output.stdout)?
+            .lines()
+            .filter_map(|line| line.strip_prefix("Description: "))
+            .map(String::from)
+            .collect())
+    };
+    assert_eq!(descriptions(&[])?, ["beta", "Alpha", "gamma"]);
+    assert_eq!(descriptions(&["--reverse"])?, ["gamma", "Alpha", "beta"]);
+    assert_eq!(
+        descriptions(&["--sort", "description"])?,
+        ["Alpha", "beta", "gamma"]
+    );
+    assert_eq!(
+        descriptions(&["--sort", "date"])?,
+        ["gamma", "beta", "Alpha"]
+    );
+    assert_eq!(
+        descriptions(&["--sort", "language", "--reverse"])?,
+        ["beta", "Alpha", "gamma"]
+    );
+    assert_eq!(descriptions(&["--limit", "2"])?, ["beta", "Alpha"]);
+    assert_eq!(
+        descriptions(&["--sort", "date", "--reverse", "--limit", "1"])?,
+        ["Alpha"]
+    );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args([
+            "export",
+            "--sort",
+            "date",
+            "--reverse",
+            "--fields",
+            "description",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match("(?s)Alpha.*beta.*gamma")?);
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn list_code_filter() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"wal mode","language":"sql","tags":["db"],"code":"PRAGMA journal_mode = WAL;\n"}"#,
+        r#"{"description":"count rows","language":"sql","tags":["db"],"code":"SELECT count(*) FROM t;\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--accessible", "--code", "pragma journal_mode"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("wal mode").and(predicate::str::contains("count rows").not()),
+        );
+    // With the other filters too
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--accessible", "--tags", "db", "--code", "count("])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("count rows").and(predicate::str::contains("wal mode").not()),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn list_code_relevance() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"part of a word","language":"sh","code":"grepping\n"}"#,
+        r#"{"description":"whole word","language":"sh","code":"ls | grep x\n"}"#,
+        r#"{"description":"in the tags","language":"sh","tags":["grep"],"code":"egrep x\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--accessible", "--code", "grep"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            "(?s)in the tags.*whole word.*part of a word",
+        )?);
+    // --sort still wins
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--accessible", "--code", "grep", "--sort", "index"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            "(?s)part of a word.*whole word.*in the tags",
+        )?);
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn saved_filters() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"rust cli","language":"rust","tags":["cli"],"code":"fn main() {}\n"}"#,
+        r#"{"description":"rust lib","language":"rust","tags":["lib"],"code":"pub fn f() {}\n"}"#,
+        r#"{"description":"python cli","language":"python","tags":["cli"],"code":"print()\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args([
+            "filter",
+            "save",
+            "rust-cli",
+            "--languages",
+            "rust",
+            "--tags",
+            "cli",
+        ])
+        .assert()
+        .success()
+        .stdout("Saved filter rust-cli, use it as @rust-cli\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["--accessible", "list", "@rust-cli"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("rust cli")
+                .and(predicate::str::contains("rust lib").not())
+                .and(predicate::str::contains("python cli").not()),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["filter", "list"])
+        .assert()
+        .success()
+        .stdout("@rust-cli: --languages rust --tags cli\n");
+    // Filters that `list` wouldn't take aren't saved
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["filter", "save", "bad", "--no-such-filter"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--no-such-filter"));
+    // Only subcommands that take filters expand @names
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["filter", "del", "@rust-cli"])
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["filter", "list"])
+        .assert()
+        .success()
+        .stdout("No saved filters\n");
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn list_exclude_filters() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"kept","language":"rust","tags":["cli"],"code":"fn main() {}\n"}"#,
+        r#"{"description":"scratch pad","language":"rust","tags":["cli","scratch"],"code":"todo!()\n"}"#,
+        r#"{"description":"notes","language":"markdown","code":"Notes\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args([
+            "list",
+            "--accessible",
+            "--not-tag",
+            "scratch",
+            "--not-lang",
+            "Markdown",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("kept")
+                .and(predicate::str::contains("scratch pad").not())
+                .and(predicate::str::contains("notes").not()),
+        );
+    // Along with the other filters
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args([
+            "list",
+            "--accessible",
+            "--tags",
+            "cli",
+            "--not-tag",
+            "scratch",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("kept").and(predicate::str::contains("scratch pad").not()),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn list_tag_logic() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"rust cli","language":"rust","tags":["rust","cli"],"code":"one\n"}"#,
+        r#"{"description":"rust lib","language":"rust","tags":["rust"],"code":"two\n"}"#,
+        r#"{"description":"python script","language":"python","tags":["python"],"code":"three\n"}"#,
+        r#"{"description":"cpp thing","language":"c++","tags":["c++"],"code":"four\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let list = |tags: &str| -> color_eyre::Result<String> {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        let output = cmd
+            .env("THE_WAY_CONFIG", &config_file)
+            .args(["list", "--accessible", "--tags", tags])
+            .output()?;
+        Ok(String::from_utf8(output.stdout)?)
+    };
+    let found = list("rust+cli")?;
+    assert!(found.contains("rust cli") && !found.contains("rust lib"));
+    let found = list("rust+cli,python")?;
+    assert!(found.contains("rust cli") && found.contains("python script"));
+    assert!(!found.contains("rust lib"));
+    // Tags with + in them still work
+    assert!(list("c++")?.contains("cpp thing"));
+    // And with a language filter, which reads snippets differently
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args([
+            "list",
+            "--accessible",
+            "--languages",
+            "rust",
+            "--tags",
+            "cli+rust",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("rust cli").and(predicate::str::contains("rust lib").not()),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_indices() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents: String = (1..=6)
+        .map(|i| {
+            format!(
+                r#"{{"description":"snippet {}","language":"rust","code":"code {}\n"}}"#,
+                i, i
+            )
+        })
+        .collect();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let output = cmd
+        .env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--indices", "1,3-4,6-", "--fields", "index"])
+        .output()?;
+    let indices: Vec<u64> = String::from_utf8(output.stdout)?
+        .lines()
+        .map(|line| {
+            serde_json::from_str::<serde_json::Value>(line).unwrap()["index"]
+                .as_u64()
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(indices, [1, 3, 4, 6]);
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--indices", "2-x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Expected indices like 3,7,10-20"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn search_index() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    let contents = [
+        r#"{"description":"wal mode","language":"sql","code":"PRAGMA journal_mode = WAL;\n"}"#,
+        r#"{"description":"count rows","language":"sql","code":"SELECT count(*) FROM t;\n"}"#,
+    ]
+    .join("");
+    // Saved before the index is turned on, so it's built from them
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    fs::write(
+        &config_file,
+        format!("{}\n[search]\nindex = true\n", config_contents),
+    )?;
+    let list = |text: &str| -> color_eyre::Result<String> {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        let output = cmd
+            .env("THE_WAY_CONFIG", &config_file)
+            .args(["list", "--accessible", "--code", text])
+            .output()?;
+        Ok(String::from_utf8(output.stdout)?)
+    };
+    let found = list("journal_mode = wal")?;
+    assert!(found.contains("wal mode") && !found.contains("count rows"));
+    // The whole text still has to be there
+    assert!(list("sel")?.contains("count rows"));
+    assert!(!list("select t")?.contains("count rows"));
+    // Finds the same snippets as without the index, even from the middle of a word
+    assert!(list("ournal")?.contains("wal mode"));
+    assert!(list("ournal_mo")?.contains("wal mode"));
+    assert!(!list("ournal_mo = t")?.contains("wal mode"));
+
+    // Kept up to date
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(r#"{"description":"vacuum","language":"sql","code":"PRAGMA auto_vacuum;\n"}"#)
+        .assert()
+        .success();
+    let found = list("pragma")?;
+    assert!(found.contains("wal mode") && found.contains("vacuum"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["del", "-f", "1"])
+        .assert()
+        .success();
+    let found = list("pragma")?;
+    assert!(!found.contains("wal mode") && found.contains("vacuum"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_pet() -> color_eyre::Result<()> {
+    let contents = r#"[[snippets]]
+  description = "ping google"
+  command = "ping 8.8.8.8"
+  tag = ["network", "google"]
+  output = ""
+
+[[snippets]]
+  description = ""
+  command = "echo $HOME"
+"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "pet"])
+        .write_stdin(contents)
+        .assert()
+        .success()
+        .stdout("Imported 2 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: ping google")
+                .and(predicate::str::contains("Language: sh"))
+                .and(predicate::str::contains("Tags: network, google"))
+                .and(predicate::str::contains("ping 8.8.8.8")),
+        );
+    // No description: the command is the description
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "2", "--accessible"])
+        .assert()
+        .stdout(predicate::str::contains("Description: echo $HOME"));
+
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "pet"])
+        .write_stdin("not toml")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "Couldn't read this as a Pet snippet.toml",
+        ));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "pet", "--map", "code=command"])
+        .write_stdin(contents)
+        .assert()
+        .code(2);
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_snippetslab() -> color_eyre::Result<()> {
+    let json = r#"{"contents": {
+        "folders": [{"uuid": "f1", "title": "Work"}, {"uuid": "f2", "title": "Shell Scripts", "parent": "f1"}],
+        "tags": [{"uuid": "t1", "title": "handy"}],
+        "snippets": [{"title": "Hello", "folder": "f2", "tags": ["t1"], "fragments": [
+            {"title": "Python", "language": "PythonLexer", "content": "print('hello')"},
+            {"title": "Rust", "language": "RustLexer", "content": "println!(\"hello\");"}
+        ]}]
+    }}"#;
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>contents</key>
+    <dict>
+        <key>snippets</key>
+        <array>
+            <dict>
+                <key>title</key><string>From XML</string>
+                <key>fragments</key>
+                <array>
+                    <dict>
+                        <key>title</key><string>Fragment</string>
+                        <key>language</key><string>NoSuchLexer</string>
+                        <key>content</key><string>plain text</string>
+                    </dict>
+                </array>
+            </dict>
+        </array>
+    </dict>
+</dict>
+</plist>"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    for (contents, imported) in &[(json, 2), (xml, 1)] {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        cmd.env("THE_WAY_CONFIG", &config_file)
+            .args(["import", "--from", "snippetslab"])
+            .write_stdin(*contents)
+            .assert()
+            .success()
+            .stdout(format!("Imported {} snippets\n", imported));
+    }
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Hello (Python)")
+                .and(predicate::str::contains("Language: python"))
+                .and(predicate::str::contains("Tags: Work, Shell-Scripts, handy")),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "2", "--accessible"])
+        .assert()
+        .stdout(predicate::str::contains("Language: rust"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "3", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: From XML\n")
+                .and(predicate::str::contains("Language: text")),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_masscode() -> color_eyre::Result<()> {
+    let contents = r#"{
+        "folders": [{"id": "f1", "name": "Inbox", "parentId": null}, {"id": "f2", "name": "Go", "parentId": "f1"}],
+        "tags": [{"id": "t1", "name": "web"}],
+        "snippets": [
+            {"name": "Server", "folderId": "f2", "tagsIds": ["t1"], "isDeleted": false,
+             "createdAt": 1577836800000, "updatedAt": 1577836800000, "content": [
+                {"label": "main", "language": "golang", "value": "package main"},
+                {"label": "notes", "language": "plain_text", "value": "run it"}
+            ]},
+            {"name": "Trashed", "isDeleted": true, "content": [{"label": "x", "language": "sh", "value": "rm -rf"}]}
+        ]
+    }"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "masscode"])
+        .write_stdin(contents)
+        .assert()
+        .success()
+        .stdout("Imported 2 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Server (main)")
+                .and(predicate::str::contains("Language: go"))
+                .and(predicate::str::contains("Tags: Inbox, Go, web")),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args([
+            "list",
+            "--from",
+            "2020-01-01",
+            "--to",
+            "2020-01-01",
+            "--accessible",
+        ])
+        .assert()
+        .stdout(predicate::str::contains("Server (notes)"));
+
+    // Joined, the fragments are one snippet in the first one's language
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "masscode", "--join-fragments"])
+        .write_stdin(contents)
+        .assert()
+        .success()
+        .stdout("Imported 1 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "3", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Server\n")
+                .and(predicate::str::contains("Language: go"))
+                .and(predicate::str::contains("Code line 3: run it")),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_boostnote_lepton() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let notes = temp_dir.path().join("notes");
+    fs::create_dir(&notes)?;
+    fs::write(
+        notes.join("a.cson"),
+        r#"createdAt: "2020-01-01T00:00:00.000Z"
+updatedAt: "2020-01-02T00:00:00.000Z"
+type: "MARKDOWN_NOTE"
+folder: "1234"
+title: "Git tricks"
+tags: [
+  "git"
+  "cli tools"
+]
+content: '''
+  # Git tricks
+  Undo the last commit:
+  ```sh
+  git reset HEAD~
+  ```
+  ~~~python
+  print("it's \\ fine")
+  ~~~
+'''
+isStarred: false
+isTrashed: false
+"#,
+    )?;
+    fs::write(
+        notes.join("b.cson"),
+        r#"type: "SNIPPET_NOTE"
+title: "Hello"
+tags: []
+snippets: [
+  {
+    linesHighlighted: []
+    name: "hello.js"
+    mode: "javascript"
+    content: '''
+      console.log("hello")
+    '''
+  }
+]
+isTrashed: false
+"#,
+    )?;
+    fs::write(
+        notes.join("c.cson"),
+        "title: \"Gone\"\ncontent: \"```sh\\nrm\\n```\"\nisTrashed: true\n",
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "boostnote"])
+        .arg(&notes)
+        .assert()
+        .success()
+        .stdout("Imported 3 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Git tricks")
+                .and(predicate::str::contains("Language: sh"))
+                .and(predicate::str::contains("Tags: git, cli-tools"))
+                .and(predicate::str::contains("Code line 1: git reset HEAD~")),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "2", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Language: python").and(predicate::str::contains(
+                r#"Code line 1: print("it's \ fine")"#,
+            )),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "3", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Hello\n")
+                .and(predicate::str::contains("Language: javascript"))
+                .and(predicate::str::contains(
+                    r#"Code line 1: console.log("hello")"#,
+                )),
+        );
+
+    let lepton = r#"[{
+        "description": "[Docker] Clean up everything #tags: docker, ops",
+        "created_at": "2020-01-01T00:00:00Z",
+        "files": {
+            "clean.sh": {"filename": "clean.sh", "language": "Shell", "content": "docker system prune"},
+            "notes.md": {"filename": "notes.md", "language": "Markdown", "content": "Images:\n```sh\ndocker image prune\n```\n"}
+        }
+    }]"#;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "lepton"])
+        .write_stdin(lepton)
+        .assert()
+        .success()
+        .stdout("Imported 2 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--accessible", "--tags", "docker"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Docker")
+                .count(2)
+                .and(predicate::str::contains("docker system prune"))
+                .and(predicate::str::contains("Code line 1: docker image prune"))
+                .and(predicate::str::contains("Tags: docker, ops")),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_vscode() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let snippets_file = temp_dir.path().join("javascript.json");
+    fs::write(
+        &snippets_file,
+        r#"{
+    // Place your snippets for javascript here
+    "Print to console": {
+        "prefix": ["log", "console log"],
+        "body": [
+            "${2|console,window.console|}.log('${1:message}', \\$x, $TM_FILENAME);",
+            "$0"
+        ],
+        "description": "Log output to console",
+    },
+    /* no description */
+    "Require": {
+        "prefix": "req",
+        "body": "const ${1:name} = require('${1}');"
+    },
+}"#,
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "vscode"])
+        .arg(&snippets_file)
+        .assert()
+        .success()
+        .stdout("Imported 2 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Log output to console")
+                .and(predicate::str::contains("Language: javascript"))
+                .and(predicate::str::contains("Tags: log, console-log"))
+                .and(predicate::str::contains(
+                    "Code line 1: console.log('message', $x, );",
+                )),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "2", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Require").and(predicate::str::contains(
+                "Code line 1: const name = require('');",
+            )),
+        );
+
+    let code_snippets = r#"{"Hello": {"scope": "shellscript,zsh", "body": ["echo ${1:hello}"]}}"#;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "vscode", "--keep-placeholders"])
+        .write_stdin(code_snippets)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "3", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Language: sh")
+                .and(predicate::str::contains("Code line 1: echo ${1:hello}")),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_sublime() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let snippets = temp_dir.path().join("User");
+    fs::create_dir(&snippets)?;
+    fs::write(
+        snippets.join("main.sublime-snippet"),
+        r#"<snippet>
+    <content><![CDATA[
+if __name__ == "__main__":
+    ${1:main}()
+]]></content>
+    <tabTrigger>ifmain</tabTrigger>
+    <scope>source.python</scope>
+    <description>If main</description>
+</snippet>"#,
+    )?;
+    fs::write(
+        snippets.join("todo.sublime-snippet"),
+        "<snippet><content>TODO($1): \\$0 &amp; more</content><tabTrigger>todo</tabTrigger></snippet>",
+    )?;
+    fs::write(snippets.join("notes.txt"), "not a snippet")?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "sublime"])
+        .arg(&snippets)
+        .assert()
+        .success()
+        .stdout("Imported 2 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: If main")
+                .and(predicate::str::contains("Language: python"))
+                .and(predicate::str::contains("Tags: ifmain"))
+                .and(predicate::str::contains("Code line 1: if __name__"))
+                .and(predicate::str::contains("Code line 2:     main()")),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "2", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: todo")
+                .and(predicate::str::contains("Language: text"))
+                .and(predicate::str::contains("Code line 1: TODO(): $0 & more")),
+        );
+
+    // Only formats with one snippet (or note) per file read folders
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "pet"])
+        .arg(&snippets)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is a folder"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_alfred() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args([
+            "import",
+            "--from",
+            "alfred",
+            "tests/fixtures/alfred/Work.alfredsnippets",
+        ])
+        .assert()
+        .success()
+        .stdout("Imported 2 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Signature")
+                .and(predicate::str::contains("Language: text"))
+                .and(predicate::str::contains("Tags: sig"))
+                .and(predicate::str::contains("Code line 2: A. Person"))
+                .and(predicate::str::contains("Code line 3: {clipboard}")),
+        );
+    // Without a name, the keyword describes it
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "2", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: glog").and(predicate::str::contains(
+                "Code line 1: git log --oneline --graph",
+            )),
+        );
+
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "alfred"])
+        .write_stdin(
+            r#"{"alfredsnippet": {"snippet": "¯\\_(ツ)_/¯", "keyword": "shrug", "name": "Shrug"}}"#,
+        )
+        .assert()
+        .success()
+        .stdout("Imported 1 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "alfred"])
+        .write_stdin("PK\u{3}\u{4} broken")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Couldn't read this as an Alfred snippets archive",
+        ));
+    // The first file's checksum (in the central directory) doesn't match its contents
+    let mut archive = fs::read("tests/fixtures/alfred/Work.alfredsnippets")?;
+    let entry = archive
+        .windows(4)
+        .position(|bytes| bytes == b"PK\x01\x02")
+        .unwrap();
+    archive[entry + 16] ^= 0xFF;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "alfred"])
+        .write_stdin(archive)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("checksum doesn't match"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_markdown() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let notes = temp_dir.path().join("notes.md");
+    fs::write(
+        &notes,
+        r#"```
+echo no heading yet
+```
+
+# Docker
+
+Remove stopped containers
+and dangling images:
+
+    ```sh
+    docker system prune
+    ```
+
+Rust
+----
+```rust {.numberLines}
+fn main() {}
+```
+~~~python
+print("same heading")
+~~~
+"#,
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args([
+            "import", "--from", "markdown", "--tag", "notes", "--tag", "md",
+        ])
+        .arg(&notes)
+        .assert()
+        .success()
+        .stdout("Imported 4 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: notes\n")
+                .and(predicate::str::contains("Language: text"))
+                .and(predicate::str::contains("Tags: notes, md")),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "2", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains(
+                "Description: Remove stopped containers and dangling images\n",
+            )
+            .and(predicate::str::contains("Language: sh"))
+            .and(predicate::str::contains("Code line 1: docker system prune")),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "3", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Rust\n")
+                .and(predicate::str::contains("Language: rust")),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "4", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Rust\n")
+                .and(predicate::str::contains("Language: python")),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_org() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let notes = temp_dir.path().join("snippets.org");
+    fs::write(
+        &notes,
+        r#"#+TITLE: Snippets
+#+FILETAGS: :snippets:
+* Shell                                                          :shell:
+** TODO [#A] List big files                                      :disk:
+   #+begin_src sh :results output
+     du -sh * | sort -h
+   #+end_src
+** Python
+#+CAPTION: Read a file
+#+BEGIN_SRC python
+,* not a headline
+print(open("f").read())
+#+END_SRC
+* Notes
+Some text.
+"#,
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "org"])
+        .arg(&notes)
+        .assert()
+        .success()
+        .stdout("Imported 2 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: List big files\n")
+                .and(predicate::str::contains("Language: sh"))
+                .and(predicate::str::contains("Tags: snippets, shell, disk"))
+                .and(predicate::str::contains(
+                    "Code line 1:   du -sh * | sort -h",
+                )),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "2", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Read a file\n")
+                .and(predicate::str::contains("Language: python"))
+                .and(predicate::str::contains("Tags: snippets, shell\n"))
+                .and(predicate::str::contains("Code line 1: * not a headline")),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_cheat() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let sheets = temp_dir.path().join("cheatsheets");
+    fs::create_dir(&sheets)?;
+    fs::write(
+        sheets.join("tar"),
+        "---\nsyntax: bash\ntags: [ compression ]\n---\n# tar\n# Archiving utility.\n\n\
+        # To extract an uncompressed archive:\ntar -xvf /path/to/foo.tar\n\n\
+        # To create an archive\n# (gzipped):\ntar -czvf /path/to/foo.tgz \\\n  /path/to/foo/\n",
+    )?;
+    fs::write(sheets.join("git-log"), "git log --oneline --graph\n")?;
+    fs::write(sheets.join("README.md"), "# Cheatsheets\n")?;
+    fs::write(sheets.join(".notes"), "# Not a sheet\nls\n")?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "cheat"])
+        .arg(&sheets)
+        .assert()
+        .success()
+        .stdout("Imported 3 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: git-log\n")
+                .and(predicate::str::contains("Language: sh"))
+                .and(predicate::str::contains("Tags: git-log\n")),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "2", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: To extract an uncompressed archive\n")
+                .and(predicate::str::contains("Language: bash"))
+                .and(predicate::str::contains("Tags: tar, compression\n"))
+                .and(predicate::str::contains(
+                    "Code line 1: tar -xvf /path/to/foo.tar\n",
+                )),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "3", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: To create an archive (gzipped)\n")
+                .and(predicate::str::contains("Code line 2:   /path/to/foo/\n")),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_tldr() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let tar = "# tar\n\n> Archiving utility.\n> More information: <https://www.gnu.org/software/tar>.\n\n\
+        - [c]reate an archive from files:\n\n`tar cf {{target.tar}} {{file1 file2 ...}}`\n\n\
+        - E[x]tract an archive:\n\n`tar xf {{source.tar}}`\n";
+    let ip = "# ip\n\n> Show routing.\n\n- List interfaces with detailed info:\n\n`ip address`\n\n\
+        - Print the first field:\n\n`ip route | awk '{print $1}'`\n";
+    let pages = temp_dir.path().join("tldr").join("pages");
+    fs::create_dir_all(pages.join("common"))?;
+    fs::create_dir_all(pages.join("linux"))?;
+    fs::create_dir_all(temp_dir.path().join("tldr").join("pages.fr").join("common"))?;
+    fs::write(pages.join("common").join("tar.md"), tar)?;
+    fs::write(pages.join("linux").join("ip.md"), ip)?;
+    fs::write(
+        temp_dir
+            .path()
+            .join("tldr")
+            .join("pages.fr")
+            .join("common")
+            .join("tar.md"),
+        "# tar\n\n- Créer une archive:\n\n`tar cf {{cible.tar}}`\n",
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--from", "tldr"])
+        .arg(temp_dir.path().join("tldr"))
+        .assert()
+        .success()
+        .stdout("Imported 4 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: [c]reate an archive from files\n")
+                .and(predicate::str::contains("Language: sh"))
+                .and(predicate::str::contains("Tags: tar\n"))
+                .and(predicate::str::contains(
+                    "Code line 1: tar cf target.tar file1 file2 ...\n",
+                )),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "4", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Print the first field\n")
+                .and(predicate::str::contains("Tags: ip, linux\n"))
+                .and(predicate::str::contains(
+                    "Code line 1: ip route | awk '{print $1}'\n",
+                )),
+        );
+
+    // The same pages in a .tar.gz
+    let archive_file = temp_dir.path().join("tldr.tar.gz");
+    let encoder = flate2::write::GzEncoder::new(
+        fs::File::create(&archive_file)?,
+        flate2::Compression::default(),
+    );
+    let mut archive = tar::Builder::new(encoder);
+    archive.append_dir_all("pages", &pages)?;
+    archive.into_inner()?.finish()?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args([
+            "import",
+            "--from",
+            "tldr",
+            "--keep-placeholders",
+            "--allow-duplicates",
+        ])
+        .arg(&archive_file)
+        .assert()
+        .success()
+        .stdout("Imported 4 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "5", "--accessible"])
+        .assert()
+        .stdout(predicate::str::contains(
+            "Code line 1: tar cf {{target.tar}} {{file1 file2 ...}}\n",
+        ));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_history_errors() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let history_file = temp_dir.path().join(".zsh_history");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--history"])
+        .arg(&history_file)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Couldn't read history file"));
+    // Only timestamps and blank lines
+    fs::write(&history_file, ": 1600000000:0;\n\n")?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--history"])
+        .arg(&history_file)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("There are no commands in"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--history", "--from", "pet"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_dir() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let scripts = temp_dir.path().join("scripts");
+    fs::create_dir_all(scripts.join("db backups"))?;
+    fs::create_dir_all(scripts.join(".git"))?;
+    fs::write(scripts.join("backup.sh"), "tar czf backup.tgz ~/work\n")?;
+    fs::write(
+        scripts.join("db backups").join("dump.py"),
+        "import sqlite3\n",
+    )?;
+    fs::write(scripts.join("tool.bash"), "#!/bin/bash\necho hi\n")?;
+    fs::write(scripts.join("empty.txt"), "")?;
+    fs::write(scripts.join("blob.bin"), [0u8, 159, 146, 150])?;
+    fs::write(scripts.join(".git").join("config"), "[core]\n")?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--dir-tags", "--dir"])
+        .arg(&scripts)
+        .assert()
+        .success()
+        .stdout("Imported 3 snippets\n")
+        .stderr(predicate::str::contains(
+            "Skipped 2 empty or binary file(s)",
+        ));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: backup.sh")
+                .and(predicate::str::contains("Language: shell"))
+                .and(predicate::str::contains("Tags: none")),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "2", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: dump.py")
+                .and(predicate::str::contains("Language: python"))
+                .and(predicate::str::contains("Tags: db-backups")),
+        );
+    // No language uses .bash, so it's guessed from the shebang
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "3", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: tool.bash")
+                .and(predicate::str::contains("Language: shell")),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn stats() -> color_eyre::Result<()> {
+    let contents = [
+        r#"{"description":"one","language":"rust","tags":["tag1"],"code":"code one","date":"2020-01-05T00:00:00Z"}"#,
+        r#"{"description":"two","language":"rust","tags":["tag1","tag2"],"code":"code two","date":"2020-01-20T00:00:00Z"}"#,
+        r#"{"description":"three","language":"python","tags":["tag2"],"code":"code three","date":"2020-03-01T00:00:00Z"}"#,
+    ]
+    .join("");
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("stats")
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("3 snippets, 2 languages, 2 tags, 2020-01 to 2020-03")
+                .and(predicate::str::contains("\u{2588}")),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["stats", "--accessible", "-t", "tag2"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("2020-01: 1")
+                .and(predicate::str::contains("2020-03: 1"))
+                .and(predicate::str::contains(
+                    "tag2: 2 snippets, added in 2 months",
+                )),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn accessible_output() -> color_eyre::Result<()> {
+    let contents = r#"{"description":"test description","language":"rust","tags":["tag1","tag2"],"code":"some\ntest\ncode\n"}"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: test description")
+                .and(predicate::str::contains("Language: rust"))
+                .and(predicate::str::contains("Tags: tag1, tag2"))
+                .and(predicate::str::contains("Code line 2: test"))
+                .and(predicate::str::contains("\x1b").not())
+                .and(predicate::str::contains("\u{25a0}").not()),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn colorblind_mode() -> color_eyre::Result<()> {
+    let contents =
+        r#"{"description":"test description","language":"rust","code":"fn main() {}\n"}"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!("colorblind_mode = true\n{}", config_contents),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1"])
+        .assert()
+        .stdout(predicate::str::contains("test description"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn search_config() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\n[search]\nalgorithm = \"no-such-algorithm\"\ntiebreak = [\"index\"]\n",
+            config_contents
+        ),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("search")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown search algorithm"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn search_fzf() -> color_eyre::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!("{}\n[search]\nselector = \"fzf\"\n", config_contents),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(
+            r#"{"description":"alpha","language":"sh","code":"ls"}
+            {"description":"beta","language":"sh","code":"pwd"}"#,
+        )
+        .assert()
+        .success();
+
+    // "fzf" that searches for "beta" and picks the second snippet with Ctrl-V,
+    // keeping the search history it was given
+    let bin = temp_dir.path().join("bin");
+    fs::create_dir(&bin)?;
+    let fzf = bin.join("fzf");
+    fs::write(
+        &fzf,
+        "#!/bin/sh\nfor arg; do case \"$arg\" in --history=*) cp \"${arg#--history=}\" \"$(dirname \"$0\")/history\";; esac; done\n\
+        echo beta\necho ctrl-v\nsed -n 2p\n",
+    )?;
+    fs::set_permissions(&fzf, fs::Permissions::from_mode(0o755))?;
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH")?);
+    for _ in 0..2 {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        cmd.env("THE_WAY_CONFIG", &config_file)
+            .env("PATH", &path)
+            .args(["--accessible", "search"])
+            .assert()
+            .success()
+            .stdout(
+                predicate::str::contains("Description: beta")
+                    .and(predicate::str::contains("Code line 1: pwd"))
+                    .and(predicate::str::contains("alpha").not()),
+            );
+    }
+    // The second search was given the first one's query
+    assert_eq!(fs::read_to_string(bin.join("history"))?, "beta");
+
+    fs::write(
+        &config_file,
+        format!("{}\n[search]\nselector = \"fzy\"\n", config_contents),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("search")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown search selector"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+/// Imports three snippets and makes a config file using "fzf" that searches for $FZF_QUERY, presses $FZF_KEY
+/// (Enter if not set), and picks lines $FZF_PICKS (e.g. "1,2") of the list, copying the search history
+/// it was given to $FZF_HISTORY if that's set. Returns the config file and a $PATH with the "fzf" in it
+#[cfg(unix)]
+fn fake_fzf_search(temp_dir: &TempDir) -> color_eyre::Result<(PathBuf, String)> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let config_file = make_config_file(temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!("{}\n[search]\nselector = \"fzf\"\n", config_contents),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(
+            r#"{"description":"alpha","language":"sh","code":"ls"}
+            {"description":"beta","language":"sh","code":"pwd"}
+            {"description":"gamma","language":"sh","code":"cd"}"#,
+        )
+        .assert()
+        .success();
+    let bin = temp_dir.path().join("bin");
+    fs::create_dir(&bin)?;
+    let fzf = bin.join("fzf");
+    fs::write(
+        &fzf,
+        "#!/bin/sh\nfor arg; do case \"$arg\" in --history=*) [ -n \"$FZF_HISTORY\" ] && cp \"${arg#--history=}\" \"$FZF_HISTORY\";; esac; done\n\
+        printf '%s\\n%s\\n' \"$FZF_QUERY\" \"$FZF_KEY\"\nsed -n \"${FZF_PICKS}p\"\n",
+    )?;
+    fs::set_permissions(&fzf, fs::Permissions::from_mode(0o755))?;
+    Ok((
+        config_file,
+        format!("{}:{}", bin.display(), std::env::var("PATH")?),
+    ))
+}
+
+#[cfg(unix)]
+fn search_multi_pick_rexpect(
+    config_file: &std::path::Path,
+    path: &str,
+    export_file: &std::path::Path,
+) -> rexpect::errors::Result<()> {
+    let mut p = spawn_bash(Some(5000))?;
+    p.send_line(&format!(
+        "export THE_WAY_CONFIG={} PATH={}",
+        config_file.to_string_lossy(),
+        path
+    ))?;
+    let executable = env!("CARGO_BIN_EXE_the-way");
+    p.wait_for_prompt()?;
+    // Export
+    p.execute(
+        &format!("FZF_PICKS=1,2 {} search", executable),
+        "2 snippets picked",
+    )?;
+    p.send("jj")?;
+    p.send_line("")?;
+    p.exp_string("File to export to")?;
+    p.send_line(&export_file.to_string_lossy())?;
+    p.exp_string("Exported 2 snippets")?;
+    p.wait_for_prompt()?;
+    // Delete
+    p.execute(
+        &format!("FZF_PICKS=1,2 {} search", executable),
+        "2 snippets picked",
+    )?;
+    p.send("j")?;
+    p.send_line("")?;
+    p.exp_string("Delete 2 snippets?")?;
+    p.send("y")?;
+    p.flush()?;
+    p.exp_string("Snippet #1 deleted")?;
+    p.exp_string("Snippet #2 deleted")?;
+    p.wait_for_prompt()?;
+    p.send_line(&format!("{} --accessible list", executable))?;
+    let list = p.wait_for_prompt()?;
+    assert!(!list.contains("alpha") && !list.contains("beta") && list.contains("gamma"));
+
+    // Undone together
+    p.send_line(&format!("{} undo", executable))?;
+    p.wait_for_prompt()?;
+    p.send_line(&format!("{} --accessible list", executable))?;
+    let list = p.wait_for_prompt()?;
+    assert!(list.contains("alpha") && list.contains("beta") && list.contains("gamma"));
+    Ok(())
+}
+
+#[cfg(unix)]
+fn search_keys_rexpect(config_file: &std::path::Path, path: &str) -> rexpect::errors::Result<()> {
+    let mut p = spawn_bash(Some(5000))?;
+    p.send_line(&format!(
+        "export THE_WAY_CONFIG={} PATH={}",
+        config_file.to_string_lossy(),
+        path
+    ))?;
+    let executable = env!("CARGO_BIN_EXE_the-way");
+    p.wait_for_prompt()?;
+    // Ctrl-E edits
+    p.execute(
+        &format!("FZF_KEY=ctrl-e FZF_PICKS=2 {} search", executable),
+        "Description",
+    )?;
+    p.send_line("beta edited")?;
+    p.exp_string("Language")?;
+    p.send_line("")?;
+    p.exp_regex("Tags")?;
+    p.send_line("")?;
+    p.exp_regex("Date")?;
+    p.send_line("")?;
+    p.exp_regex("Code snippet")?;
+    p.send_line("pwd -P")?;
+    p.exp_string("Snippet #2 changed")?;
+    p.wait_for_prompt()?;
+    p.send_line(&format!("{} --accessible view 2", executable))?;
+    assert!(p.wait_for_prompt()?.contains("Description: beta edited"));
+    // Ctrl-D deletes
+    p.execute(
+        &format!("FZF_KEY=ctrl-d FZF_PICKS=1 {} search", executable),
+        "Delete 1 snippets?",
+    )?;
+    p.send("y")?;
+    p.flush()?;
+    p.exp_string("Snippet #1 deleted")?;
+    p.wait_for_prompt()?;
+    p.send_line(&format!("{} --accessible list", executable))?;
+    let list = p.wait_for_prompt()?;
+    assert!(!list.contains("alpha") && list.contains("beta edited"));
+    Ok(())
+}
+
+#[cfg(unix)]
+fn search_history_rexpect(
+    config_file: &std::path::Path,
+    path: &str,
+    history_file: &std::path::Path,
+) -> rexpect::errors::Result<()> {
+    // Long enough for a search more than the history holds, but bash is still killed quickly at the end
+    let mut p = spawn_bash(Some(300_000))?;
+    p.process.set_kill_timeout(Some(5000));
+    p.send_line(&format!(
+        "export THE_WAY_CONFIG={} PATH={} FZF_KEY=ctrl-v FZF_PICKS=2",
+        config_file.to_string_lossy(),
+        path
+    ))?;
+    let executable = env!("CARGO_BIN_EXE_the-way");
+    p.wait_for_prompt()?;
+    // Saved once when searched for twice in a row
+    for query in ["beta", "beta", "gamma"] {
+        p.execute(
+            &format!("FZF_QUERY={} {} --accessible search", query, executable),
+            "Description: beta",
+        )?;
+        p.wait_for_prompt()?;
+    }
+    p.send_line(&format!(
+        "FZF_HISTORY={} {} --accessible search",
+        history_file.to_string_lossy(),
+        executable
+    ))?;
+    p.wait_for_prompt()?;
+    assert_eq!(
+        std::fs::read_to_string(history_file).unwrap(),
+        "beta\ngamma"
+    );
+    // Only the last 100 are kept
+    p.send_line(&format!(
+        "for i in $(seq 1 101); do FZF_QUERY=query$i {} --accessible search > /dev/null; done",
+        executable
+    ))?;
+    p.wait_for_prompt()?;
+    p.send_line(&format!(
+        "FZF_HISTORY={} {} --accessible search",
+        history_file.to_string_lossy(),
+        executable
+    ))?;
+    p.wait_for_prompt()?;
+    let history = std::fs::read_to_string(history_file).unwrap();
+    let expected: Vec<_> = (2..=101).map(|i| format!("query{}", i)).collect();
+    assert_eq!(history.lines().collect::<Vec<_>>(), expected);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn search_history() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let (config_file, path) = fake_fzf_search(&temp_dir)?;
+    let history_file = temp_dir.path().join("history");
+    assert!(search_history_rexpect(&config_file, &path, &history_file).is_ok());
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn search_keys() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let (config_file, path) = fake_fzf_search(&temp_dir)?;
+    assert!(search_keys_rexpect(&config_file, &path).is_ok());
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn search_multi_pick() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let (config_file, path) = fake_fzf_search(&temp_dir)?;
+    let export_file = temp_dir.path().join("picked.json");
+    assert!(search_multi_pick_rexpect(&config_file, &path, &export_file).is_ok());
+    let exported = fs::read_to_string(&export_file)?;
+    assert!(
+        exported.contains("alpha")
+            && exported.contains("beta")
+            && !exported.contains("gamma")
+            && exported.contains("uuid")
+    );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn tag_rules() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\n[[tag_rules]]\ntag = \"k8s\"\ncode = \"kubectl|helm\"\n\
+            [[tag_rules]]\ntag = \"docs\"\ndomain = \"rust-lang.org\"\n",
+            config_contents
+        ),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(
+            r#"{"description":"pods","language":"sh","code":"kubectl get pods"}
+            {"description":"from https://doc.rust-lang.org/std/","language":"rust","code":"fn main() {}"}
+            {"description":"other","language":"sh","code":"ls"}"#,
+        )
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--tags", "k8s", "--accessible"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("Description: pods")
+                .and(predicate::str::contains("Description: other").not()),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "2", "--accessible"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tags: docs"));
+
+    // Backfill a rule added later
+    fs::write(
+        &config_file,
+        format!(
+            "{}\n[[tag_rules]]\ntag = \"shell\"\nlanguage = \"sh\"\n",
+            fs::read_to_string(&config_file)?
+        ),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("retag")
+        .assert()
+        .failure();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["retag", "--rules"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("Snippet #1 tagged shell")
+                .and(predicate::str::contains("Snippet #3 tagged shell"))
+                .and(predicate::str::contains("Retagged 2 snippet(s)")),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["retag", "--rules"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Every snippet already has"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--tags", "shell", "--accessible"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Description: other"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("undo")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Restored snippet #1, #3"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn snippet_ids() -> color_eyre::Result<()> {
+    let contents =
+        r#"{"description":"test description","language":"rust","code":"fn main() {}\n"}"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!("{}\n[display]\nindex_width = 3\n", config_contents),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--accessible"])
+        .assert()
+        .stdout(predicate::str::contains("Snippet number: 001"));
+
+    fs::write(
+        &config_file,
+        format!("{}\n[display]\nindex_format = \"hex\"\n", config_contents),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let output = cmd
+        .env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--accessible"])
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let id = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Snippet number: "))
+        .unwrap()
+        .to_owned();
+    assert_eq!(id.len(), 8);
+    assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", &id[..4], "--accessible"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Description: test description"));
+    // Plain indices still work
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("Snippet number: {}", id)));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "zzzz"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "You haven't written a snippet with ID",
+        ));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn snapshot() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(
+            r#"{"description":"first","language":"rust","code":"fn main() {}","tags":["a","b"]}
+            {"description":"second","language":"python","code":"print(1)"}"#,
+        )
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let output = cmd
+        .env("THE_WAY_CONFIG", &config_file)
+        .args(["config", "snapshot"])
+        .output()?;
+    let snapshot_file = PathBuf::from(String::from_utf8(output.stdout)?.trim());
+    let snapshot: serde_json::Value = serde_json::from_str(&fs::read_to_string(&snapshot_file)?)?;
+    assert_eq!(
+        snapshot,
+        serde_json::json!({"snippets": [
+            {"index": 1, "id": "1", "description": "first", "language": "rust", "tags": ["a", "b"]},
+            {"index": 2, "id": "2", "description": "second", "language": "python", "tags": []},
+        ]})
+    );
+
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["del", "-f", "1"])
+        .assert()
+        .success();
+    let snapshot: serde_json::Value = serde_json::from_str(&fs::read_to_string(&snapshot_file)?)?;
+    assert_eq!(snapshot["snippets"].as_array().unwrap().len(), 1);
+    assert_eq!(snapshot["snippets"][0]["description"], "second");
+
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["complete", "nushell"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(snapshot_file.to_str().unwrap()));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn sync_gitlab_failure() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\nsync_provider = \"gitlab\"\n[gitlab]\nurl = \"http://127.0.0.1:1\"\n",
+            config_contents
+        ),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .env("THE_WAY_GITLAB_TOKEN", "not-a-token")
+        .arg("sync")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("GitLab access token"));
+
+    fs::write(
+        &config_file,
+        format!("{}\nsync_provider = \"dropbox\"\n", config_contents),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("sync")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown sync provider"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn sync_bitbucket_failure() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = format!(
+        "{}\nsync_provider = \"bitbucket\"\n[bitbucket]\nurl = \"http://127.0.0.1:1\"\n",
+        fs::read_to_string(&config_file)?
+    );
+    fs::write(&config_file, &config_contents)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("sync")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No Bitbucket username"));
+
+    fs::write(
+        &config_file,
+        format!("{}username = \"me\"\n", config_contents),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .env("THE_WAY_BITBUCKET_APP_PASSWORD", "not-a-password")
+        .arg("sync")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Bitbucket username and app password",
+        ));
+    temp_dir.close()?;
+    Ok(())
+}
+
+/// An S3 bucket kept in memory, for syncing to: answers ListObjectsV2, GET, PUT, and DELETE (signatures aren't checked).
+/// Listing fails with 403 while the returned flag is set
+#[allow(clippy::type_complexity)]
+fn fake_s3() -> color_eyre::Result<(
+    String,
+    std::sync::Arc<std::sync::atomic::AtomicBool>,
+    std::sync::Arc<std::sync::Mutex<std::collections::BTreeMap<String, String>>>,
+)> {
+    use std::collections::BTreeMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let listing_fails = Arc::new(AtomicBool::new(false));
+    let fail = Arc::clone(&listing_fails);
+    let objects = Arc::new(Mutex::new(BTreeMap::new()));
+    let stored = Arc::clone(&objects);
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            let mut length = 0;
+            reader.read_line(&mut request_line).unwrap();
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                match header.trim().split_once(':') {
+                    Some((name, value)) if name.eq_ignore_ascii_case("content-length") => {
+                        length = value.trim().parse().unwrap()
+                    }
+                    Some(_) => {}
+                    None => break,
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let mut parts = request_line.split_whitespace();
+            let (method, target) = (parts.next().unwrap(), parts.next().unwrap());
+            let (path, query) = target.split_once('?').unwrap_or((target, ""));
+            // Path-style: /<bucket>/<key>
+            let key = path.splitn(3, '/').nth(2).unwrap_or_default().to_owned();
+            let mut objects = stored.lock().unwrap();
+            let (status, response) = match method {
+                "GET" if query.contains("list-type=2") => {
+                    if fail.load(Ordering::SeqCst) {
+                        (
+                            403,
+                            String::from("<Error><Code>AccessDenied</Code></Error>"),
+                        )
+                    } else {
+                        let contents: String = objects
+                            .keys()
+                            .map(|key| {
+                                format!(
+                                    "<Contents><Key>{}</Key><LastModified>2020-01-01T00:00:00Z</LastModified></Contents>",
+                                    key
+                                )
+                            })
+                            .collect();
+                        (
+                            200,
+                            format!("<ListBucketResult>{}</ListBucketResult>", contents),
+                        )
+                    }
+                }
+                "GET" => match objects.get(&key) {
+                    Some(content) => (200, String::clone(content)),
+                    None => (404, String::new()),
+                },
+                "PUT" => {
+                    objects.insert(key, String::from_utf8_lossy(&body).into_owned());
+                    (200, String::new())
+                }
+                "DELETE" => {
+                    objects.remove(&key);
+                    (204, String::new())
+                }
+                _ => (400, String::new()),
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {} Fake\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                response.len(),
+                response
+            );
+        }
+    });
+    Ok((url, listing_fails, objects))
+}
+
+#[test]
+fn sync_s3_env_keys() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let (url, _, _) = fake_s3()?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\nsync_provider = \"s3\"\n[http]\nno_cache = true\n[s3]\nendpoint = \"{}\"\nbucket = \"snippets\"\n",
+            fs::read_to_string(&config_file)?,
+            url
+        ),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(r#"{"description":"first","language":"rust","code":"fn main() {}"}"#)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .env("AWS_ACCESS_KEY_ID", "env-key-id")
+        .env("AWS_SECRET_ACCESS_KEY", "env-secret")
+        .arg("sync")
+        .assert()
+        .success();
+    // Keys from the environment stay there
+    let config_contents = fs::read_to_string(&config_file)?;
+    assert!(config_contents.contains("snippets"));
+    assert!(!config_contents.contains("env-key-id") && !config_contents.contains("env-secret"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn sync_s3_listing_error() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let (url, listing_fails, objects) = fake_s3()?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\nsync_provider = \"s3\"\n[http]\nno_cache = true\n[s3]\nendpoint = \"{}\"\nbucket = \"snippets\"\n",
+            fs::read_to_string(&config_file)?,
+            url
+        ),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(r#"{"description":"first","language":"rust","code":"fn main() {}"}"#)
+        .assert()
+        .success();
+    // Nothing there yet, so a new collection is made
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .env("AWS_ACCESS_KEY_ID", "key-id")
+        .env("AWS_SECRET_ACCESS_KEY", "secret")
+        .arg("sync")
+        .assert()
+        .success();
+    let synced = objects.lock().unwrap().clone();
+    assert!(synced.keys().any(|key| key.contains("snippet_1")));
+
+    // Failing to list it isn't the same as not finding it: nothing is uploaded again
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(r#"{"description":"second","language":"rust","code":"fn second() {}"}"#)
+        .assert()
+        .success();
+    listing_fails.store(true, std::sync::atomic::Ordering::SeqCst);
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .env("AWS_ACCESS_KEY_ID", "key-id")
+        .env("AWS_SECRET_ACCESS_KEY", "secret")
+        .arg("sync")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("AccessDenied"));
+    assert_eq!(*objects.lock().unwrap(), synced);
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn sync_file_store_failure() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\nsync_provider = \"webdav\"\n[webdav]\nurl = \"http://127.0.0.1:1/the-way\"\n",
+            config_contents
+        ),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("sync")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("[webdav] section"));
+
+    fs::write(
+        &config_file,
+        format!("{}\nsync_provider = \"s3\"\n", config_contents),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("sync")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No S3 bucket"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn search_all_profiles_missing() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\n[profiles]\nwork = \"{}\"\n",
+            config_contents,
+            temp_dir.path().join("work.toml").display()
+        ),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["search", "--all-profiles"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("for profile work"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn search_other_profile_rexpect(
+    config_file: &std::path::Path,
+    work_config: &std::path::Path,
+    path: &str,
+) -> rexpect::errors::Result<()> {
+    let mut p = spawn_bash(Some(5000))?;
+    p.send_line(&format!(
+        "export THE_WAY_CONFIG={} PATH={}",
+        config_file.to_string_lossy(),
+        path
+    ))?;
+    let executable = env!("CARGO_BIN_EXE_the-way");
+    p.wait_for_prompt()?;
+    // Ctrl-E edits in the profile's database
+    p.execute(
+        &format!(
+            "FZF_KEY=ctrl-e FZF_PICKS=1 {} search --all-profiles",
+            executable
+        ),
+        "Editing snippet #1 from profile work",
+    )?;
+    p.exp_string("Description")?;
+    p.send_line("work edited")?;
+    p.exp_string("Language")?;
+    p.send_line("")?;
+    p.exp_regex("Tags")?;
+    p.send_line("")?;
+    p.exp_regex("Date")?;
+    p.send_line("")?;
+    p.exp_regex("Code snippet")?;
+    p.send_line("make deploy")?;
+    p.exp_string("Snippet #1 changed")?;
+    p.wait_for_prompt()?;
+    p.send_line(&format!(
+        "THE_WAY_CONFIG={} {} --accessible view 1",
+        work_config.to_string_lossy(),
+        executable
+    ))?;
+    let view = p.wait_for_prompt()?;
+    assert!(view.contains("Description: work edited") && view.contains("make deploy"));
+    p.send_line(&format!("{} --accessible list", executable))?;
+    assert!(!p.wait_for_prompt()?.contains("work edited"));
+    // Ctrl-D deletes from the profile's database
+    p.execute(
+        &format!(
+            "FZF_KEY=ctrl-d FZF_PICKS=1 {} search --all-profiles",
+            executable
+        ),
+        "Delete 1 snippets?",
+    )?;
+    p.send("y")?;
+    p.flush()?;
+    p.exp_string("Snippet #1 deleted from profile work")?;
+    p.wait_for_prompt()?;
+    p.send_line(&format!(
+        "THE_WAY_CONFIG={} {} --accessible list",
+        work_config.to_string_lossy(),
+        executable
+    ))?;
+    assert!(!p.wait_for_prompt()?.contains("work edited"));
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn search_other_profile() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let (config_file, path) = fake_fzf_search(&temp_dir)?;
+    let work_config = temp_dir.path().join("work.toml");
+    fs::write(
+        &work_config,
+        format!(
+            "theme = 'base16-ocean.dark'\ndb_dir = \"{}\"\nthemes_dir = \"{}\"\n",
+            temp_dir.path().join("work_db").display(),
+            temp_dir.path().join("themes").display()
+        ),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &work_config)
+        .arg("import")
+        .write_stdin(r#"{"description":"work","language":"sh","code":"make"}"#)
+        .assert()
+        .success();
+    // The current profile's own snippets come first in the search window, so remove them
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["clear", "--force"])
+        .assert()
+        .success();
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\n[profiles]\nwork = \"{}\"\n",
+            config_contents,
+            work_config.display()
+        ),
+    )?;
+    assert!(search_other_profile_rexpect(&config_file, &work_config, &path).is_ok());
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn search_all_profiles_unopenable() -> color_eyre::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    // A file where the profile's database folder should be
+    let work_db = temp_dir.path().join("work_db");
+    fs::write(&work_db, "")?;
+    let work_config = temp_dir.path().join("work.toml");
+    fs::write(
+        &work_config,
+        format!(
+            "theme = 'base16-ocean.dark'\ndb_dir = \"{}\"\nthemes_dir = \"{}\"\n",
+            work_db.display(),
+            temp_dir.path().join("themes").display()
+        ),
+    )?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\n[profiles]\nwork = \"{}\"\n[search]\nselector = \"fzf\"\n",
+            config_contents,
+            work_config.display()
+        ),
+    )?;
+    // "fzf" that's cancelled right away
+    let bin = temp_dir.path().join("bin");
+    fs::create_dir(&bin)?;
+    let fzf = bin.join("fzf");
+    fs::write(&fzf, "#!/bin/sh\nexit 130\n")?;
+    fs::set_permissions(&fzf, fs::Permissions::from_mode(0o755))?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .env(
+            "PATH",
+            format!("{}:{}", bin.display(), std::env::var("PATH")?),
+        )
+        .args(["search", "--all-profiles"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Skipped profile work"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn language_config() -> color_eyre::Result<()> {
+    let contents = r#"{"description":"in-house","language":"mylang","code":"fn main() {}\n"}"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\n[languages.mylang]\nextension = \".myl\"\ncolor = \"#FF8800\"\nsyntax = \"Rust\"\n",
+            config_contents
+        ),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Couldn't find language").not());
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("export")
+        .assert()
+        .stdout(predicate::str::contains(r#""extension":".myl""#));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[38;2;255;136;0m"));
+
+    // Unknown syntax
+    fs::write(
+        &config_file,
+        format!(
+            "{}\n[languages.mylang]\nextension = \".myl\"\nsyntax = \"No such syntax\"\n",
+            config_contents
+        ),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No such syntax"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn multi_line_description() -> color_eyre::Result<()> {
+    let contents = r#"{"description":"summary line\nmore details","language":"rust","code":"some\ntest\ncode\n"}"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("list")
+        .assert()
+        .stdout(
+            predicate::str::contains("summary line")
+                .and(predicate::str::contains("more details").not()),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("view")
+        .arg("1")
+        .assert()
+        .stdout(
+            predicate::str::contains("summary line").and(predicate::str::contains("more details")),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_validation() -> color_eyre::Result<()> {
+    let contents_1 = r#"{"description":"test description 1","language":"rust","tags":["tag1","tag2"],"code":"some\ntest\ncode\n"}"#;
+    let contents_2 =
+        r#"{"description":"test description 2","language":"python","code":"some\ntest\ncode\n"}"#;
+    let contents = format!("{}{}", contents_1, contents_2);
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\n[validation]\nrequire_tag = true\nmax_code_lines = 5",
+            config_contents
+        ),
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("needs at least one tag"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("list")
+        .assert()
+        .stdout(predicate::str::contains("test description 1").not());
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents_1)
+        .assert()
+        .stdout(predicate::str::starts_with("Imported 1 snippets"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export() -> color_eyre::Result<()> {
+    use the_way::the_way::snippet::Snippet;
+
+    let contents_1 = r#"{"description":"test description 1","language":"rust","tags":["tag1","tag2"],"code":"some\ntest\ncode\n"}"#;
+    let contents_2 =
+        r#"{"description":"test description 2","language":"python","code":"some\ntest\ncode\n"}"#;
+    let contents = format!("{}{}", contents_1, contents_2);
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+
+    // import
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .stdout(predicate::str::starts_with("Imported 2 snippets"));
+
+    // export
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let file = temp_dir.path().join("snippets.json");
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("export")
+        .arg(file.to_str().unwrap())
+        .assert()
+        .success();
+
+    let snippets = serde_json::Deserializer::from_reader(fs::File::open(&file)?)
+        .into_iter::<Snippet>()
+        .collect::<Result<Vec<_>, _>>()?;
+
+    assert_eq!(snippets.len(), 2);
+
+    for snippet in snippets {
+        assert_eq!(snippet.code, "some\ntest\ncode\n");
+    }
+
+    // export only some fields
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--fields", "description,code"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains(
+                r#"{"code":"some\ntest\ncode\n","description":"test description 1"}"#,
+            )
+            .and(predicate::str::contains("language").not()),
+        );
+
+    // export without dates
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--omit", "dates"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("test description 2")
+                .and(predicate::str::contains("date").not())
+                .and(predicate::str::contains("updated").not()),
+        );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_import_csv() -> color_eyre::Result<()> {
+    let contents = r#"{"description":"say \"hi\", twice","language":"sh","tags":["greet","demo"],"code":"echo hi\necho hi\n","date":"2021-03-04T05:06:07Z","updated":"2021-03-04T05:06:07Z"}"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let csv = String::from_utf8(
+        cmd.env("THE_WAY_CONFIG", &config_file)
+            .args(["export", "--format", "csv"])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone(),
+    )?;
+    assert_eq!(
+        csv,
+        "index,description,language,tags,code,date\r\n\
+        1,\"say \"\"hi\"\", twice\",sh,greet demo,\"echo hi\necho hi\n\",2021-03-04T05:06:07Z\r\n"
+    );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "csv", "--fields", "description,code"])
+        .assert()
+        .stdout(predicate::str::starts_with("description,code\r\n"));
+
+    // Back in, into a fresh setup, along with a table made in a spreadsheet
+    let new_temp_dir = tempdir()?;
+    let new_config_file = make_config_file(&new_temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &new_config_file)
+        .args(["import", "--format", "csv"])
+        .write_stdin(csv)
+        .assert()
+        .success()
+        .stdout("Imported 1 snippets\n");
+    let spreadsheet = "\u{feff}Code,Notes,Language,Tags,Date\nprint(1),ignored,Python,\"a, b\",2020-01-02\n,,,,\n";
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &new_config_file)
+        .args(["import", "--format", "csv"])
+        .write_stdin(spreadsheet)
+        .assert()
+        .success()
+        .stdout("Imported 1 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &new_config_file)
+        .args(["export", "--format", "csv", "--omit", "index"])
+        .assert()
+        .stdout(
+            "description,language,tags,code,date\r\n\
+            \"say \"\"hi\"\", twice\",sh,greet demo,\"echo hi\necho hi\n\",2021-03-04T05:06:07Z\r\n\
+            print(1),python,a b,print(1),2020-01-02T00:00:00Z\r\n",
+        );
+
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &new_config_file)
+        .args(["import", "--format", "csv"])
+        .write_stdin("description\nno code\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("there's no code column"));
+    temp_dir.close()?;
+    new_temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_markdown() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"Fenced code\nin a doc comment","language":"rust","tags":["docs","md"],"code":"/// ```\n/// x\n/// ```\n"}"#,
+        r#"{"description":"List files","language":"sh","tags":[],"code":"ls -la"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "markdown"])
+        .assert()
+        .success()
+        .stdout(
+            "# Snippets\n\n## Fenced code\n\nin a doc comment\n\nTags: `docs` `md`\n\n\
+            ````rust\n/// ```\n/// x\n/// ```\n````\n\n## List files\n\n```sh\nls -la\n```\n",
+        );
+    let dir = temp_dir.path().join("markdown");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("export")
+        .arg(&dir)
+        .args(["--format", "markdown", "--split", "--omit", "tags"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Exported 2 snippets to"));
+    assert_eq!(
+        fs::read_to_string(dir.join("snippet_2.md"))?,
+        "# List files\n\n```sh\nls -la\n```\n"
+    );
+    assert!(fs::read_to_string(dir.join("snippet_1.md"))?
+        .starts_with("# Fenced code\n\nin a doc comment\n\n````rust\n"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--split"])
+        .arg(&dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--split only works with --format markdown",
+        ));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_html() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r##"{"description":"Include <vector>","language":"c++","tags":["c++","std"],"code":"#include <vector>\n"}"##,
+        r#"{"description":"List files","language":"sh","tags":["std"],"code":"ls -la\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let site = temp_dir.path().join("site");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "html", "--out"])
+        .arg(&site)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Exported 2 snippets to"));
+    let index = fs::read_to_string(site.join("index.html"))?;
+    assert!(index.contains("<input id=\"search\""));
+    assert!(index
+        .contains("data-search=\"include &lt;vector&gt; c++ c++ std #include &lt;vector&gt;\n\""));
+    assert!(index.contains("<a href=\"snippets/1.html\">#1 Include &lt;vector&gt;</a>"));
+    let snippet = fs::read_to_string(site.join("snippets").join("1.html"))?;
+    assert!(snippet.contains("<h1>Include &lt;vector&gt;</h1>"));
+    assert!(snippet.contains("<pre style=\"background-color:"));
+    assert!(snippet.contains("href=\"../tags/c~2b~2b.html\""));
+    let tags = fs::read_to_string(site.join("tags.html"))?;
+    assert!(tags.contains("<a href=\"tags/c~2b~2b.html\">c++</a> (1)"));
+    assert!(tags.contains("<a href=\"tags/std.html\">std</a> (2)"));
+    let std_page = fs::read_to_string(site.join("tags").join("std.html"))?;
+    assert!(std_page.contains("snippets/1.html") && std_page.contains("snippets/2.html"));
+    assert!(site.join("languages").join("sh.html").exists());
+    assert!(site.join("style.css").exists());
+
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "html"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("writes files to a folder"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_pdf() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"List files (all)","language":"sh","tags":["files"],"code":"ls -la\n"}"#,
+        r#"{"description":"Print","language":"python","tags":["py"],"code":"print(1)\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let pdf_file = temp_dir.path().join("sheet.pdf");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "pdf"])
+        .arg(&pdf_file)
+        .assert()
+        .success();
+    let pdf = fs::read_to_string(&pdf_file)?;
+    assert!(pdf.starts_with("%PDF-1.4\n"));
+    assert!(pdf.ends_with("%%EOF\n"));
+    assert!(pdf.contains("/BaseFont /Courier"));
+    assert!(pdf.contains(r"(#1 List files \(all\)) Tj"));
+    assert!(pdf.contains("(python) Tj") && pdf.contains("(sh) Tj"));
+
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let output = cmd
+        .env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "pdf", "--group-by", "tag"])
+        .output()?;
+    assert!(output.status.success());
+    let pdf = String::from_utf8(output.stdout)?;
+    assert!(pdf.contains("(files) Tj") && pdf.contains("(py) Tj"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_ultisnips() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"List files (all)","language":"sh","code":"ls -la $HOME"}"#,
+        r#"{"description":"Say \"hi\"","language":"sh","code":"echo `whoami`\n"}"#,
+        r#"{"description":"Print vector","language":"c++","code":"std::cout << v[0];\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let dir = temp_dir.path().join("UltiSnips");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "ultisnips", "--out"])
+        .arg(&dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Exported 3 snippets to"));
+    assert_eq!(
+        fs::read_to_string(dir.join("sh.snippets"))?,
+        "# Exported from the-way\n\n\
+        snippet list_files_all \"List files (all)\"\nls -la \\$HOME\nendsnippet\n\n\
+        snippet say_hi \"Say 'hi'\"\necho \\`whoami\\`\nendsnippet\n"
+    );
+    assert!(fs::read_to_string(dir.join("cpp.snippets"))?
+        .contains("snippet print_vector \"Print vector\"\nstd::cout << v[0];\nendsnippet\n"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_import_sublime() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = r#"{"description":"Print a price","language":"python","code":"print(\"$5\\n\")\nx = a[b[0]]>1\n"}"#;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let out = temp_dir.path().join("sublime");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "sublime", "--out"])
+        .arg(&out)
+        .assert()
+        .success();
+    let file = out.join("1-print-a-price.sublime-snippet");
+    let xml = fs::read_to_string(&file)?;
+    assert!(xml.contains("print(\"\\$5\\\\n\")\nx = a[b[0]]]]><![CDATA[>1\n]]></content>"));
+    assert!(xml.contains("<tabTrigger>print_a_price</tabTrigger>"));
+    assert!(xml.contains("<scope>source.python</scope>"));
+
+    // And back
+    let other_dir = tempdir()?;
+    let other_config = make_config_file(&other_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &other_config)
+        .args(["import", "--from", "sublime"])
+        .arg(&file)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let output = cmd
+        .env("THE_WAY_CONFIG", &other_config)
+        .arg("export")
+        .output()?;
+    let snippet: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(snippet["description"], "Print a price");
+    assert_eq!(snippet["language"], "python");
+    assert_eq!(snippet["code"], "print(\"$5\\n\")\nx = a[b[0]]>1\n");
+    other_dir.close()?;
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_alfred() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = r#"{"description":"Sign off","language":"text","code":"Best,\nA. Person\n"}"#;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let collection = temp_dir.path().join("Work.alfredsnippets");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "alfred"])
+        .arg(&collection)
+        .assert()
+        .success();
+
+    // Reads back in as the same snippet, tagged with its keyword
+    let other_dir = tempdir()?;
+    let other_config = make_config_file(&other_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &other_config)
+        .args(["import", "--from", "alfred"])
+        .arg(&collection)
+        .assert()
+        .success()
+        .stdout("Imported 1 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &other_config)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Sign off")
+                .and(predicate::str::contains("Tags: sign_off"))
+                .and(predicate::str::contains("Code line 2: A. Person")),
+        );
+    temp_dir.close()?;
+    other_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_org() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = r#"{"description":"Headings\nIn a list","language":"markdown","tags":["docs","read-me"],"code":"* one\n  #+not a setting\n","date":"2021-03-04T10:00:00Z"}"#;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let org_file = temp_dir.path().join("snippets.org");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "org"])
+        .arg(&org_file)
+        .args(["--omit", "updated"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&org_file)?,
+        "#+TITLE: Snippets\n\n\
+        * Headings :docs:read_me:\n\
+        :PROPERTIES:\n:INDEX: 1\n:LANGUAGE: markdown\n:TAGS: docs read-me\n:DATE: [2021-03-04 Thu 10:00]\n:END:\n\
+        In a list\n\
+        #+BEGIN_SRC markdown\n,* one\n  ,#+not a setting\n#+END_SRC\n"
+    );
+
+    // Reads back in with its code unescaped
+    let other_dir = tempdir()?;
+    let other_config = make_config_file(&other_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &other_config)
+        .args(["import", "--from", "org"])
+        .arg(&org_file)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &other_config)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(
+            predicate::str::contains("Description: Headings")
+                .and(predicate::str::contains("Code line 1: * one"))
+                .and(predicate::str::contains("Code line 2:   #+not a setting")),
+        );
+    temp_dir.close()?;
+    other_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_navi() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r##"{"description":"Show a branch's log","language":"sh","tags":["git"],"code":"# newest first\ngit log <branch>\n\n"}"##,
+        r#"{"description":"Disk usage","language":"bash","code":"du -sh *\n"}"#,
+        r#"{"description":"Not a command","language":"rust","tags":["git"],"code":"fn main() {}\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "navi"])
+        .assert()
+        .success()
+        .stdout(
+            "% git\n\n# Show a branch's log\ngit log <branch>\n\n\
+            % the-way\n\n# Disk usage\ndu -sh *\n",
+        )
+        .stderr("Left out 1 snippets that aren't shell commands\n");
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_files() -> color_eyre::Result<()> {
+    use std::io::Read;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"List files (all of them)","language":"sh","tags":["files"],"code":"ls -la\n"}"#,
+        r#"{"description":"Hello","language":"rust","code":"fn main() {}\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let archive = temp_dir.path().join("snippets.tar.gz");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "files", "--out"])
+        .arg(&archive)
+        .assert()
+        .success();
+    let mut files = Vec::new();
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(fs::File::open(&archive)?));
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents)?;
+        files.push((entry.path()?.to_string_lossy().into_owned(), contents));
+    }
+    assert_eq!(
+        files[0],
+        ("1-list-files-all-of-them.sh".into(), "ls -la\n".into())
+    );
+    assert_eq!(files[1], ("2-hello.rs".into(), "fn main() {}\n".into()));
+    assert_eq!(files[2].0, "metadata.json");
+    let metadata: serde_json::Value = serde_json::from_str(&files[2].1)?;
+    assert_eq!(metadata[0]["file"], "1-list-files-all-of-them.sh");
+    assert_eq!(metadata[0]["tags"][0], "files");
+    assert!(metadata[1].get("code").is_none());
+
+    // A zip archive for a .zip file
+    let archive = temp_dir.path().join("snippets.zip");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "files", "--out"])
+        .arg(&archive)
+        .assert()
+        .success();
+    assert!(fs::read(&archive)?.starts_with(b"PK\x03\x04"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_import_uuids() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"first","language":"rust","code":"code one\n"}"#,
+        r#"{"description":"second","language":"rust","code":"code two\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let output = cmd
+        .env("THE_WAY_CONFIG", &config_file)
+        .arg("export")
+        .output()?;
+    let exported = String::from_utf8(output.stdout)?;
+    let snippets: Vec<serde_json::Value> = serde_json::Deserializer::from_str(&exported)
+        .into_iter()
+        .collect::<Result<_, _>>()?;
+    let uuid = snippets[0]["uuid"].as_str().unwrap_or_default().to_owned();
+    assert_eq!(uuid.len(), 36);
+
+    // Edited elsewhere and imported again, it updates the snippet instead of adding one
+    let edited = exported.replace("\"first\"", "\"first, edited\"");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--dry-run"])
+        .write_stdin(edited.as_str())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "first, edited (rust), updating #1",
+        ));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(edited.as_str())
+        .assert()
+        .success()
+        .stdout("Imported 0 snippets\nUpdated 1 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(predicate::str::contains("Description: first, edited"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("undo")
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .stdout(predicate::str::contains("Description: first\n"));
+
+    // Imported somewhere else, snippets keep their UUIDs
+    let other_dir = tempdir()?;
+    let other_config = make_config_file(&other_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &other_config)
+        .arg("import")
+        .write_stdin(exported.as_str())
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &other_config)
+        .args(["export", "--fields", "uuid"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(format!(
+            "{{\"uuid\":\"{}\"}}",
+            uuid
+        )));
+    temp_dir.close()?;
+    other_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_json_lines_and_array() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"first","language":"rust","code":"code one\n"}"#,
+        r#"{"description":"second","language":"rust","code":"code two\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let output = cmd
+        .env("THE_WAY_CONFIG", &config_file)
+        .arg("export")
+        .output()?;
+    let lines = String::from_utf8(output.stdout)?;
+    assert_eq!(lines.lines().count(), 2);
+    for line in lines.lines() {
+        serde_json::from_str::<serde_json::Value>(line)?;
+    }
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let output = cmd
+        .env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--array"])
+        .output()?;
+    let array: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(array.as_array().map(Vec::len), Some(2));
+    assert_eq!(array[1]["description"], "second");
+
+    // Both read back in
+    for json in [lines, String::from_utf8(output.stdout)?] {
+        let other_dir = tempdir()?;
+        let other_config = make_config_file(&other_dir)?;
+        let mut cmd = Command::cargo_bin("the-way")?;
+        cmd.env("THE_WAY_CONFIG", &other_config)
+            .arg("import")
+            .write_stdin(json)
+            .assert()
+            .success()
+            .stdout("Imported 2 snippets\n");
+        other_dir.close()?;
+    }
+
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--array", "--format", "csv"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--array only works with JSON"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_import_gzip() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"first","language":"rust","code":"code one\n"}"#,
+        r#"{"description":"second","language":"rust","code":"code two\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    for name in ["snippets.json.gz", "snippets.csv.gz"] {
+        let file = temp_dir.path().join(name);
+        let mut cmd = Command::cargo_bin("the-way")?;
+        let mut args = vec!["export"];
+        if name.contains("csv") {
+            args.extend(["--format", "csv"]);
+        }
+        cmd.env("THE_WAY_CONFIG", &config_file)
+            .args(args)
+            .arg(&file)
+            .assert()
+            .success();
+        assert!(fs::read(&file)?.starts_with(&[0x1f, 0x8b]));
+
+        // Read from the file, and piped in
+        for piped in [false, true] {
+            let other_dir = tempdir()?;
+            let other_config = make_config_file(&other_dir)?;
+            let mut cmd = Command::cargo_bin("the-way")?;
+            cmd.env("THE_WAY_CONFIG", &other_config).arg("import");
+            if piped {
+                if name.contains("csv") {
+                    cmd.args(["--format", "csv"]);
+                }
+                cmd.write_stdin(fs::read(&file)?);
+            } else {
+                cmd.arg(&file);
+            }
+            cmd.assert().success().stdout("Imported 2 snippets\n");
+            other_dir.close()?;
+        }
+    }
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_normalize() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents =
+        r#"{"description":"messy","language":"sh","code":"echo a  \r\n\techo b\t\r\n\r\n\n"}"#;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "markdown", "--normalize"])
+        .assert()
+        .success()
+        .stdout("# Snippets\n\n## messy\n\n```sh\necho a\n\techo b\n```\n");
+    // Left as it is without
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "markdown"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("echo a  \r\n"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_split_by() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"both","language":"sh","tags":["git","shell"],"code":"git status\n"}"#,
+        r#"{"description":"one","language":"sh","tags":["shell"],"code":"ls\n"}"#,
+        r#"{"description":"none","language":"rust","code":"fn main() {}\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let out = temp_dir.path().join("tags");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args([
+            "export",
+            "--split-by",
+            "tag",
+            "--format",
+            "markdown",
+            "--out",
+        ])
+        .arg(&out)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Exported 3 snippets to 3 files"));
+    let shell = fs::read_to_string(out.join("shell.md"))?;
+    assert!(shell.contains("## both") && shell.contains("## one"));
+    let git = fs::read_to_string(out.join("git.md"))?;
+    assert!(git.contains("## both") && !git.contains("## one"));
+    assert!(fs::read_to_string(out.join("Untagged.md"))?.contains("## none"));
+
+    // A folder each for formats written to folders
+    let out = temp_dir.path().join("languages");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args([
+            "export",
+            "--split-by",
+            "language",
+            "--format",
+            "hugo",
+            "--out",
+        ])
+        .arg(&out)
+        .assert()
+        .success();
+    assert!(out.join("sh").join("2-one.md").exists());
+    assert!(out.join("rust").join("3-none.md").exists());
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_hugo() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = r#"{"description":"List files\nWith hidden ones","language":"sh","tags":["files","shell"],"code":"ls -la\n","date":"2021-03-04T10:00:00Z","updated":"2021-03-04T10:00:00Z"}"#;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let out = temp_dir.path().join("posts");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "hugo", "--out"])
+        .arg(&out)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(out.join("1-list-files.md"))?,
+        "+++\ntitle = \"List files\"\ndate = \"2021-03-04T10:00:00Z\"\ntags = [\"files\", \"shell\"]\nlanguage = \"sh\"\n+++\n\nWith hidden ones\n\n```sh\nls -la\n```\n"
+    );
+
+    // Jekyll wants YAML, and the date at the start of the file name
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "jekyll", "--omit", "tags", "--out"])
+        .arg(&out)
+        .assert()
+        .success();
+    let post = fs::read_to_string(out.join("2021-03-04-1-list-files.md"))?;
+    assert!(post.starts_with("---\ntitle: List files\n"));
+    assert!(post.contains("language: sh\n---\n"));
+    assert!(!post.contains("tags"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_anki() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = r##"{"description":"# Print <b>\nwith a tab","language":"sh","tags":["shell","two words"],"code":"printf 'a\tb'\n"}"##;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let output = cmd
+        .env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "anki"])
+        .output()?;
+    let notes = String::from_utf8(output.stdout)?;
+    let mut lines = notes.lines();
+    assert_eq!(lines.next(), Some("#separator:tab"));
+    let note = lines.find(|line| !line.starts_with('#')).unwrap();
+    let fields: Vec<_> = note.split('\t').collect();
+    assert_eq!(fields.len(), 3);
+    assert_eq!(fields[0], "&#35; Print &lt;b&gt;<br>with a tab");
+    assert!(fields[1].starts_with("<pre"));
+    assert!(fields[1].contains("&#9;"));
+    assert_eq!(fields[2], "shell two_words");
+    assert_eq!(lines.next(), None);
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn export_sql() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = r#"{"description":"It's quoted","language":"sh","tags":["shell","quotes"],"code":"echo 'hi'\n","date":"2021-03-04T10:00:00Z","updated":"2021-03-04T10:00:00Z"}"#;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let output = cmd
+        .env("THE_WAY_CONFIG", &config_file)
+        .args([
+            "export",
+            "--format",
+            "sql",
+            "--fields",
+            "description,code,tags,date",
+        ])
+        .output()?;
+    let sql = String::from_utf8(output.stdout)?;
+    assert!(sql.contains(
+        "CREATE TABLE snippets (\n    id INTEGER PRIMARY KEY,\n    description TEXT NOT NULL,\n    \
+        code TEXT NOT NULL,\n    date TIMESTAMP NOT NULL\n);\n"
+    ));
+    assert!(sql.contains(
+        "INSERT INTO snippets (id, description, code, date) \
+        VALUES (1, 'It''s quoted', 'echo ''hi''\n', '2021-03-04T10:00:00Z');\n"
+    ));
+    assert!(sql.contains("INSERT INTO snippet_tags (snippet_id, tag) VALUES (1, 'quotes');\n"));
+    assert!(sql.ends_with("COMMIT;\n"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_yaml_toml() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let yaml_file = temp_dir.path().join("snippets.yml");
+    fs::write(
+        &yaml_file,
+        r#"- description: Count lines
+  language: sh
+  tags: [files, wc]
+  date: 2021-03-04
+  code: |
+    find . -name '*.rs' |
+      xargs wc -l
+- description: Hello
+  code: print("hello")
+  tags: greet demo
+"#,
+    )?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .arg(&yaml_file)
+        .assert()
+        .success()
+        .stdout("Imported 2 snippets\n");
+    let toml = r#"
+[[snippets]]
+description = "Serve this folder"
+language = "Python"
+tags = ["http"]
+date = 2020-01-02T03:04:05Z
+code = """
+python -m http.server
+"""
+"#;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--format", "toml"])
+        .write_stdin(toml)
+        .assert()
+        .success()
+        .stdout("Imported 1 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["export", "--format", "csv"])
+        .assert()
+        .stdout(predicate::str::starts_with(
+            "index,description,language,tags,code,date\r\n\
+            1,Count lines,sh,files wc,\"find . -name '*.rs' |\n  xargs wc -l\n\",2021-03-04T00:00:00Z\r\n\
+            2,Hello,text,greet demo,\"print(\"\"hello\"\")\n\",",
+        ))
+        .stdout(predicate::str::ends_with(
+            "3,Serve this folder,python,http,\"python -m http.server\n\",2020-01-02T03:04:05Z\r\n",
+        ));
+
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--format", "yaml"])
+        .write_stdin("snippets:\n  - description: x\n    code: y\n    date: last week\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Couldn't read this as YAML: snippet 1 has an unknown date",
+        ));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_duplicates() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let first = [
+        r#"{"description":"ls","language":"sh","tags":[],"code":"ls -la\n"}"#,
+        r#"{"description":"list files","language":"Sh","tags":[],"code":"\nls -la   \n\n"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(first)
+        .assert()
+        .success()
+        .stdout("Imported 1 snippets\n")
+        .stderr(predicate::str::contains("Skipped 1 duplicate snippet(s)"));
+    let second = [
+        r#"{"description":"again","language":"sh","tags":[],"code":"ls -la"}"#,
+        r#"{"description":"in python","language":"python","tags":[],"code":"ls -la"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(second.to_owned())
+        .assert()
+        .success()
+        .stdout("Imported 1 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--allow-duplicates"])
+        .write_stdin(second)
+        .assert()
+        .success()
+        .stdout("Imported 2 snippets\n");
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_dry_run() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"ls","language":"sh","tags":[],"code":"ls -la"}"#,
+        r#"{"description":"hello\nin python","language":"python","tags":[],"code":"print(1)"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--dry-run"])
+        .write_stdin(contents.to_owned())
+        .assert()
+        .success()
+        .stdout("Would import 2 snippets\n  ls (sh)\n  hello (python)\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--dry-run"])
+        .write_stdin(r#"{"description":"broken""#)
+        .assert()
+        .failure();
+    // Nothing was saved
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success()
+        .stdout("Imported 2 snippets\n");
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn import_remap_tags_languages() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = [
+        r#"{"description":"ls","language":"Shell","tags":["imported","unix"],"code":"ls -la"}"#,
+        r#"{"description":"hello","language":"python","tags":["imported"],"code":"print(1)"}"#,
+    ]
+    .join("");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args([
+            "import",
+            "--map-language",
+            "shell=sh",
+            "--map-language",
+            "python=py3",
+            "--strip-tag",
+            "imported",
+            "--add-tag",
+            "work",
+        ])
+        .write_stdin(contents)
+        .assert()
+        .success()
+        .stdout("Imported 2 snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("Language: sh")
+                .and(predicate::str::contains("Tags: unix, work")),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "2", "--accessible"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("Language: py3").and(predicate::str::contains("Tags: work")),
+        );
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["import", "--map-language", "shell"])
+        .write_stdin("")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Expected old=new language"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn delete() -> color_eyre::Result<()> {
+    let contents_1 = r#"{"description":"test description 1","language":"rust","tags":["tag1","tag2"],"code":"some\ntest\ncode\n"}"#;
+    let contents_2 =
+        r#"{"description":"test description 2","language":"python","code":"some\ntest\ncode\n"}"#;
+    let contents = format!("{}{}", contents_1, contents_2);
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .stdout(predicate::str::starts_with("Imported 2 snippets"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("list")
+        .assert()
+        .stdout(
+            predicate::str::contains("test description 1")
+                .and(predicate::str::contains("test description 2")),
+        );
+
+    // Test bad index
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("del")
+        .arg("-f")
+        .arg("3")
+        .assert()
+        .failure();
+
+    // Test good index
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("del")
+        .arg("-f")
+        .arg("2")
+        .assert()
+        .stdout(predicate::str::starts_with("Snippet #2 deleted"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("list")
+        .assert()
+        .stdout(
+            predicate::str::contains("test description 1")
+                .and(predicate::str::contains("test description 2").not()),
+        );
+
+    // Test already deleted index
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("del")
+        .arg("-f")
+        .arg("2")
+        .assert()
+        .failure();
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn requires() -> color_eyre::Result<()> {
+    let contents = r#"{"description":"needs tools","language":"shell","code":"echo ran\n"}"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["snippet", "requires", "1", "sh", "no-such-tool >= 1.6"])
+        .assert()
+        .success()
+        .stdout("Snippet #1 requires sh, no-such-tool >= 1.6\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["--accessible", "view", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Tags: none\nRequires: sh, no-such-tool >= 1.6\n",
+        ));
+
+    // Only the missing program is warned about
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .env("SHELL", "sh")
+        .args(["run", "1"])
+        .assert()
+        .success()
+        .stdout("ran\n")
+        .stderr("Warning: snippet #1 requires no-such-tool >= 1.6, which isn't in $PATH\n");
+
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["snippet", "requires", "1", "--clear"])
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["--accessible", "view", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Requires").not());
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn git_storage() -> color_eyre::Result<()> {
+    let contents = r#"{"description":"first","language":"rust","code":"fn main() {}\n"}
+{"description":"second","language":"python","code":"print(1)\n"}"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let repository = temp_dir.path().join("snippets_repo");
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\ngit_repository = \"{}\"\n",
+            config_contents,
+            repository.to_str().unwrap()
+        ),
+    )?;
+    let the_way = |args: &[&str]| -> color_eyre::Result<()> {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        cmd.env("THE_WAY_CONFIG", &config_file)
+            .env("GIT_AUTHOR_NAME", "the-way")
+            .env("GIT_AUTHOR_EMAIL", "the-way@example.com")
+            .env("GIT_COMMITTER_NAME", "the-way")
+            .env("GIT_COMMITTER_EMAIL", "the-way@example.com")
+            .args(args)
+            .write_stdin(contents)
+            .assert()
+            .success()
+            .stderr("");
+        Ok(())
+    };
+    the_way(&["import"])?;
+    assert_eq!(
+        fs::read_to_string(repository.join("snippet_1.rs"))?,
+        "fn main() {}\n"
+    );
+    assert!(repository.join("snippet_2.py").exists());
+    the_way(&["del", "-f", "1"])?;
+    assert!(!repository.join("snippet_1.rs").exists());
+    assert!(fs::read_to_string(repository.join("snippets.json"))?.contains("\"second\""));
+
+    let log = std::process::Command::new("git")
+        .arg("-C")
+        .arg(&repository)
+        .args(["log", "--format=%s"])
+        .output()?;
+    assert_eq!(
+        String::from_utf8(log.stdout)?,
+        "del: snippet #1\nimport: snippets #1, #2\n"
+    );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn selftest() -> color_eyre::Result<()> {
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.args(["selftest", "--fixtures", "tests/fixtures/selftest"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "mapped.json (json --map): 1 snippets",
+        ))
+        .stdout(predicate::str::contains("snippets.json (json): 3 snippets"));
+
+    let temp_dir = tempdir()?;
+    fs::write(temp_dir.path().join("broken.json"), "{\"description\":")?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.arg("selftest")
+        .arg("--fixtures")
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("[FAIL] broken.json (json)"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn undo() -> color_eyre::Result<()> {
+    let contents = r#"{"description":"first","language":"rust","code":"fn main() {}\n"}
+{"description":"second","language":"python","tags":["tag1"],"code":"print(1)\n"}"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let tracked = temp_dir.path().join("tracked.py");
+    fs::write(&tracked, "print(1)\n")?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["snippet", "track", "2"])
+        .arg(&tracked)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["snippet", "requires", "2", "python"])
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["del", "-f", "2"])
+        .assert()
+        .success();
+
+    // Undo the delete, along with the snippet's tracked file and requirements
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("undo")
+        .assert()
+        .success()
+        .stdout("Undid `del`\nRestored snippet #2\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--tags", "tag1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("second"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["--accessible", "view", "2"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Requires: python\n"));
+    fs::write(&tracked, "print(2)\n")?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["snippet", "refresh"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Refreshed snippet #2"));
+
+    // Undo the import
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("undo")
+        .assert()
+        .success()
+        .stdout("Undid `import`\nRemoved snippet #1, #2\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("list")
+        .assert()
+        .success()
+        .stdout("");
+
+    // Nothing left
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("undo")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Nothing to undo"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn copy() -> color_eyre::Result<()> {
+    use clipboard::{ClipboardContext, ClipboardProvider};
+
+    let contents = r#"{"description":"test description","language":"rust","tags":["tag1","tag2"],"code":"some\ntest\ncode\n"}"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+
+    // Test bad index
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("cp")
+        .arg("2")
+        .assert()
+        .failure();
+
+    // Test good index
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("cp")
+        .arg("1")
+        .assert()
+        .stdout(predicate::str::starts_with(
+            "Snippet #1 copied to clipboard",
+        ));
+    let ctx: color_eyre::Result<ClipboardContext, _> = ClipboardProvider::new();
+    assert!(ctx.is_ok());
+    let mut ctx = ctx.unwrap();
+    let contents = ctx.get_contents();
+    assert!(contents.is_ok());
+    let contents = contents.unwrap();
+    assert!(contents.contains("some\ntest\ncode"));
+
+    // Test copy history
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("cp")
+        .arg("--last")
+        .assert()
+        .stdout(predicate::str::starts_with(
+            "Snippet #1 copied to clipboard",
+        ));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("--accessible")
+        .arg("history")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Snippet 1: test description").count(2));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn copy_history_empty() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("history")
+        .assert()
+        .success()
+        .stdout("No copied snippets\n");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["cp", "--last"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "You haven't copied any snippets yet",
+        ));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["cp", "1", "--last"])
         .assert()
         .failure();
+    temp_dir.close()?;
+    Ok(())
+}
 
-    // Test good index
+#[ignore]
+#[test]
+/// Tests Gist sync functionality. Needs to have the environment variable $THE_WAY_GITHUB_TOKEN set!
+/// Ignored by default since Travis doesn't allow secret/encrypted environment variables in PRs
+fn gist() -> color_eyre::Result<()> {
+    use the_way::configuration::TheWayConfig;
+    use the_way::gist::{GistClient, GistContent, UpdateGistPayload};
+    use the_way::http::HttpClient;
+
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+
+    let contents_1 = r#"{"description":"test description 1","language":"rust","tags":["tag1","tag2"],"code":"some\ntest\ncode\n"}"#;
+    let contents_2 =
+        r#"{"description":"test description 2","language":"python","code":"some\ntest\ncode\n"}"#;
+    let contents = format!("{}{}", contents_1, contents_2);
+
+    // import
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .stdout(predicate::str::starts_with("Imported 2 snippets"));
+
+    // sync
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("sync")
+        .assert()
+        .success();
+
+    // get gist_id from config
+    std::env::set_var("THE_WAY_CONFIG", &config_file);
+    let config = TheWayConfig::load();
+    assert!(config.is_ok());
+    let config = config?;
+    assert!(config.gist_id.is_some());
+
+    // get Gist
+    let client = GistClient::new(
+        HttpClient::default(),
+        &std::env::var("THE_WAY_GITHUB_TOKEN")?,
+        "the-way",
+    )?;
+    let gist = client.get_gist(&config.gist_id.unwrap());
+    assert!(gist.is_ok());
+    let gist = gist?;
+
+    // check Gist contents
+    assert_eq!(gist.files.len(), 3);
+    for (filename, gistfile) in &gist.files {
+        if filename.starts_with("snippet_") {
+            assert_eq!(gistfile.content, "some\ntest\ncode\n");
+        }
+    }
+
+    // edit Gist
+    let update_payload = UpdateGistPayload {
+        description: &gist.description,
+        files: vec![(
+            "snippet_1.rs".to_owned(),
+            Some(GistContent {
+                content: "some\nmore\ntest\ncode\n",
+            }),
+        )]
+        .into_iter()
+        .collect(),
+    };
+    assert!(client.update_gist(&gist.id, &update_payload).is_ok());
+
+    // delete locally (easier than editing)
     let mut cmd = Command::cargo_bin("the-way")?;
     cmd.env("THE_WAY_CONFIG", &config_file)
         .arg("del")
         .arg("-f")
         .arg("2")
         .assert()
-        .stdout(predicate::str::starts_with("Snippet #2 deleted"));
+        .stdout(predicate::str::starts_with("Snippet #2 deleted"));
+
+    // sync - download + deleted
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("sync")
+        .assert()
+        .success();
+
+    // get Gist
+    let gist = client.get_gist(&gist.id);
+    assert!(gist.is_ok());
+    let gist = gist?;
+    let updated = &gist.updated_at;
+
+    // sync again - nothing changed
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("sync")
+        .assert()
+        .success();
+    let gist = client.get_gist(&gist.id);
+    assert!(gist.is_ok());
+    let gist = gist?;
+    assert_eq!(updated, &gist.updated_at);
+
+    // check Gist contents
+    assert_eq!(gist.files.len(), 2);
+    for (filename, gistfile) in &gist.files {
+        if filename.starts_with("snippet_") {
+            assert_eq!(gistfile.content, "some\nmore\ntest\ncode\n");
+        }
+    }
+
+    // check downloaded
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("view")
+        .arg("1")
+        .assert()
+        .stdout(predicates::str::contains("more"));
+
+    // delete Gist
+    assert!(client.delete_gist(&gist.id).is_ok());
+    assert!(client.get_gist(&gist.id).is_err());
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[ignore]
+#[test]
+/// Shares a snippet as a Gist. Needs $THE_WAY_GITHUB_TOKEN set (and a clipboard)
+fn share_gist() -> color_eyre::Result<()> {
+    use the_way::gist::GistClient;
+    use the_way::http::HttpClient;
+
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let contents = r#"{"description":"shared snippet","language":"rust","code":"fn main() {}\n"}"#;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["snippet", "share", "2"])
+        .assert()
+        .failure();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let output = cmd
+        .env("THE_WAY_CONFIG", &config_file)
+        .args(["snippet", "share", "1"])
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    let url = stdout.lines().next().unwrap_or_default();
+    assert!(url.starts_with("https://gist.github.com/"));
+
+    let client = GistClient::new(
+        HttpClient::default(),
+        &std::env::var("THE_WAY_GITHUB_TOKEN")?,
+        "the-way",
+    )?;
+    let gist_id = url.rsplit('/').next().unwrap_or_default();
+    let gist = client.get_gist(gist_id)?;
+    assert_eq!(gist.description, "shared snippet");
+    assert_eq!(gist.files["snippet_1.rs"].content, "fn main() {}\n");
+    client.delete_gist(gist_id)?;
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[ignore]
+#[test]
+/// Imports octocat's public Gists, needs network access (and GitHub's unauthenticated rate limit)
+fn import_gists() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .env_remove("THE_WAY_GITHUB_TOKEN")
+        .args(["import", "--gist", "octocat"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Imported "))
+        .stdout(predicate::str::contains("Imported 0 snippets").not());
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["--accessible", "view", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tags: "));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn serve_raw() -> color_eyre::Result<()> {
+    use std::io::BufRead;
+    let contents = r#"{"description":"greet","language":"shell","code":"echo hi\n"}"#;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(contents)
+        .assert()
+        .success();
+
+    let mut server = std::process::Command::new(assert_cmd::cargo::cargo_bin("the-way"))
+        .env("THE_WAY_CONFIG", &config_file)
+        .args(["serve", "--port", "0"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    let mut line = String::new();
+    std::io::BufReader::new(server.stdout.take().unwrap()).read_line(&mut line)?;
+    let url = line
+        .trim()
+        .trim_start_matches("Serving snippets at ")
+        .trim_end_matches("<index>")
+        .to_owned();
+
+    let response = ureq::get(&format!("{}1", url)).call();
+    let missing = ureq::get(&format!("{}2", url)).call();
+    server.kill()?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.header("content-type"),
+        Some("text/x-shellscript; charset=utf-8")
+    );
+    assert_eq!(
+        response.header("content-disposition"),
+        Some("inline; filename=\"snippet_1.sh\"")
+    );
+    assert_eq!(response.into_string()?, "echo hi\n");
+    assert_eq!(missing.status(), 404);
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn serve_sync() -> color_eyre::Result<()> {
+    use std::io::{BufRead, Read, Write};
+    let server_dir = tempdir()?;
+    let server_config = make_config_file(&server_dir)?;
+    fs::write(
+        &server_config,
+        format!(
+            "{}\n[server]\ntoken = \"secret\"\n",
+            fs::read_to_string(&server_config)?
+        ),
+    )?;
+    let mut server = std::process::Command::new(assert_cmd::cargo::cargo_bin("the-way"))
+        .env("THE_WAY_CONFIG", &server_config)
+        .args(["serve", "--sync", "--port", "0"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    // Kept open so the server can keep printing
+    let mut output = std::io::BufReader::new(server.stdout.take().unwrap());
+    let mut line = String::new();
+    output.read_line(&mut line)?;
+    let url = line
+        .trim()
+        .trim_start_matches("Serving snippets at ")
+        .trim_end_matches("/raw/<index>")
+        .to_owned();
+
+    let (temp_dir_a, temp_dir_b, temp_dir_c) = (tempdir()?, tempdir()?, tempdir()?);
+    let mut config_files = Vec::new();
+    for (temp_dir, token) in &[
+        (&temp_dir_a, "secret"),
+        (&temp_dir_b, "secret"),
+        (&temp_dir_c, "wrong"),
+    ] {
+        let config_file = make_config_file(temp_dir)?;
+        let config_contents = fs::read_to_string(&config_file)?;
+        fs::write(
+            &config_file,
+            format!(
+                "{}\nsync_provider = \"server\"\n[http]\nno_cache = true\n[server]\nurl = \"{}\"\ntoken = \"{}\"\n",
+                config_contents, url, token
+            ),
+        )?;
+        config_files.push(config_file);
+    }
+    let the_way =
+        |machine: usize, args: &[&str]| -> color_eyre::Result<assert_cmd::assert::Assert> {
+            let mut cmd = Command::cargo_bin("the-way")?;
+            Ok(cmd
+                .env("THE_WAY_CONFIG", &config_files[machine])
+                .args(args)
+                .assert())
+        };
+
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_files[0])
+        .arg("import")
+        .write_stdin(
+            r#"{"description":"first","language":"rust","code":"fn main() {}"}
+            {"description":"second","language":"python","code":"print(2)"}"#,
+        )
+        .assert()
+        .success();
+    let pushed = the_way(0, &["sync"]).map(|assert| assert.success());
+    let pulled = the_way(1, &["sync"]).map(|assert| {
+        assert
+            .success()
+            .stdout(predicate::str::contains("Downloaded 2 snippet(s)"))
+    });
+    let viewed = the_way(1, &["view", "1", "--accessible"]).map(|assert| {
+        assert
+            .success()
+            .stdout(predicate::str::contains("Description: first"))
+    });
+    let refused = the_way(2, &["sync"]).map(|assert| assert.failure());
+    let unauthorized = ureq::get(&format!("{}/sync/", url)).call();
+    // Too large to read, not cut short
+    let mut too_large = String::new();
+    let mut stream = std::net::TcpStream::connect(url.trim_start_matches("http://"))?;
+    stream.write_all(
+        b"PUT /sync/snippet_1.rs HTTP/1.1\r\nAuthorization: Bearer secret\r\nContent-Length: 100000000\r\n\r\n",
+    )?;
+    stream.read_to_string(&mut too_large)?;
+    server.kill()?;
+    drop(output);
+    pushed?;
+    pulled?;
+    viewed?;
+    refused?;
+    assert_eq!(unauthorized.status(), 401);
+    assert!(too_large.starts_with("HTTP/1.1 413 Payload Too Large"));
+    for temp_dir in [server_dir, temp_dir_a, temp_dir_b, temp_dir_c] {
+        temp_dir.close()?;
+    }
+    Ok(())
+}
+
+#[test]
+fn error_format_and_exit_codes() -> color_eyre::Result<()> {
+    use std::io::BufRead;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let output = cmd
+        .env("THE_WAY_CONFIG", &config_file)
+        .args(["view", "12", "--error-format", "json"])
+        .output()?;
+    assert_eq!(output.status.code(), Some(3));
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr)?;
+    assert_eq!(error["kind"], "SnippetNotFound");
+    assert_eq!(error["class"], "not_found");
+    assert_eq!(error["code"], 3);
+    assert_eq!(error["details"]["index"], 12);
+
+    // Bad filters are usage errors
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let output = cmd
+        .env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--from", "notadate", "--error-format=json"])
+        .output()?;
+    assert_eq!(output.status.code(), Some(2));
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr)?;
+    assert_eq!(error["kind"], "UsageError");
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--from", "notadate"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "Invalid value for '--from <from>'",
+        ));
+
+    // Another the-way has the database open
+    let mut server = std::process::Command::new(assert_cmd::cargo::cargo_bin("the-way"))
+        .env("THE_WAY_CONFIG", &config_file)
+        .args(["serve", "--port", "0"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    let mut line = String::new();
+    std::io::BufReader::new(server.stdout.take().unwrap()).read_line(&mut line)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    let output = cmd
+        .env("THE_WAY_CONFIG", &config_file)
+        .args(["list", "--error-format", "json"])
+        .output();
+    server.kill()?;
+    let output = output?;
+    assert_eq!(output.status.code(), Some(4));
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr)?;
+    assert_eq!(error["class"], "locked");
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn backup_restore() -> color_eyre::Result<()> {
+    let temp_dir = tempdir()?;
+    let backup_file = temp_dir.path().join("backup.tar.gz");
+    let config_file = make_config_file(&temp_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(r#"{"description":"kept","language":"rust","code":"1"}"#)
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["backup", backup_file.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Backed up to"));
+
+    // On a new machine
+    let new_dir = tempdir()?;
+    let new_config_file = make_config_file(&new_dir)?;
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &new_config_file)
+        .args(["restore", "--force", config_file.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("isn't a backup"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &new_config_file)
+        .args(["restore", "--force", backup_file.to_str().unwrap()])
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &new_config_file)
+        .args(["--accessible", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Description: kept"));
+    // The new machine's data stays where it was
+    assert!(
+        fs::read_to_string(&new_config_file)?.contains(new_dir.path().join("db").to_str().unwrap())
+    );
+    assert!(!new_dir.path().join("db.restore").exists());
+    temp_dir.close()?;
+    new_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn backups() -> color_eyre::Result<()> {
+    let contents = r#"{"description":"one","language":"rust","code":"1"}
+{"description":"two","language":"rust","code":"2"}"#;
+    let temp_dir = tempdir()?;
+    let backup_dir = temp_dir.path().join("backups");
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\nbackup_dir = \"{}\"\nbackup_keep = 1\n",
+            config_contents,
+            backup_dir.display()
+        ),
+    )?;
     let mut cmd = Command::cargo_bin("the-way")?;
     cmd.env("THE_WAY_CONFIG", &config_file)
-        .arg("list")
+        .arg("import")
+        .write_stdin(contents)
         .assert()
-        .stdout(
-            predicate::str::contains("test description 1")
-                .and(predicate::str::contains("test description 2").not()),
-        );
+        .success();
+    assert!(!backup_dir.exists());
+    for index in &["1", "2"] {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        cmd.env("THE_WAY_CONFIG", &config_file)
+            .args(["del", "--force", index])
+            .assert()
+            .success();
+    }
+    // Only the newest backup is kept
+    let backups = fs::read_dir(&backup_dir)?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<color_eyre::Result<Vec<_>>>()?;
+    assert_eq!(backups.len(), 1);
+    assert!(backups[0].starts_with("the-way_") && backups[0].ends_with("_del.tar.gz"));
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(fs::File::open(
+        backup_dir.join(&backups[0]),
+    )?));
+    let names = archive
+        .entries()?
+        .map(|entry| Ok(entry?.path()?.to_string_lossy().into_owned()))
+        .collect::<color_eyre::Result<Vec<_>>>()?;
+    assert!(names.iter().any(|name| name == "the-way.toml"));
+    assert!(names.iter().any(|name| name.starts_with("db/")));
 
-    // Test already deleted index
+    // With an interval, any command backs up once the newest backup is old enough
+    fs::write(
+        &config_file,
+        format!(
+            "{}\nbackup_dir = \"{}\"\nbackup_interval_hours = 0\n",
+            config_contents,
+            backup_dir.display()
+        ),
+    )?;
     let mut cmd = Command::cargo_bin("the-way")?;
     cmd.env("THE_WAY_CONFIG", &config_file)
-        .arg("del")
-        .arg("-f")
-        .arg("2")
+        .arg("list")
         .assert()
-        .failure();
+        .success();
+    assert!(fs::read_dir(&backup_dir)?.any(|entry| entry
+        .map(|entry| entry
+            .file_name()
+            .to_string_lossy()
+            .ends_with("_periodic.tar.gz"))
+        .unwrap_or(false)));
     temp_dir.close()?;
     Ok(())
 }
 
-#[cfg(target_os = "macos")]
 #[test]
-fn copy() -> color_eyre::Result<()> {
-    use clipboard::{ClipboardContext, ClipboardProvider};
+fn http_retry_and_cache() -> color_eyre::Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+    let yml = "Rust:\n  color: \"#dea584\"\n  extensions:\n  - \".rs\"\n";
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}/languages.yml", listener.local_addr()?);
+    // Busy, then the file, then "not modified" for anyone who already has it
+    let server = std::thread::spawn(move || -> std::io::Result<Vec<Option<String>>> {
+        let mut etags = Vec::new();
+        for response in &[
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n".to_owned(),
+            format!(
+                "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\n\r\n{}",
+                yml.len(),
+                yml
+            ),
+            "HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\n\r\n".to_owned(),
+        ] {
+            let (mut stream, _) = listener.accept()?;
+            let mut etag = None;
+            let mut reader = BufReader::new(stream.try_clone()?);
+            let mut line = String::new();
+            while reader.read_line(&mut line)? > 2 {
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("if-none-match:") {
+                    etag = Some(value.trim().to_owned());
+                }
+                line.clear();
+            }
+            etags.push(etag);
+            stream.write_all(response.as_bytes())?;
+        }
+        Ok(etags)
+    });
 
-    let contents = r#"{"description":"test description","language":"rust","tags":["tag1","tag2"],"code":"some\ntest\ncode\n"}"#;
     let temp_dir = tempdir()?;
     let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\n[http]\ncache_dir = \"{}\"\n",
+            config_contents,
+            temp_dir.path().join("cache").display()
+        ),
+    )?;
+    for _ in 0..2 {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        cmd.env("THE_WAY_CONFIG", &config_file)
+            .env("XDG_DATA_HOME", temp_dir.path())
+            .args(["languages", "update", "--url", &url])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Saved 1 languages"));
+    }
+    assert_eq!(
+        server.join().unwrap()?,
+        vec![None, None, Some("\"v1\"".to_owned())]
+    );
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn daemon() -> color_eyre::Result<()> {
+    use std::io::{BufRead, Write};
+    use std::os::unix::fs::PermissionsExt;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let mut daemon = std::process::Command::new(assert_cmd::cargo::cargo_bin("the-way"))
+        .env("THE_WAY_CONFIG", &config_file)
+        .arg("daemon")
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    let mut line = String::new();
+    std::io::BufReader::new(daemon.stdout.take().unwrap()).read_line(&mut line)?;
+    assert!(line.starts_with("Listening on "));
+    let socket = temp_dir.path().join("db.sock");
+    assert_eq!(fs::metadata(&socket)?.permissions().mode() & 0o777, 0o600);
+
+    // Bad clients don't stop it
+    std::os::unix::net::UnixStream::connect(&socket)?;
+    std::os::unix::net::UnixStream::connect(&socket)?.write_all(b"not a request\n")?;
+
+    // These only work while the daemon holds the database's lock if they're sent to it
     let mut cmd = Command::cargo_bin("the-way")?;
     cmd.env("THE_WAY_CONFIG", &config_file)
         .arg("import")
-        .write_stdin(contents)
+        .write_stdin(r#"{"description":"sent","language":"rust","code":"1"}"#)
         .assert()
-        .success();
-
-    // Test bad index
+        .success()
+        .stdout("Imported 1 snippets\n");
     let mut cmd = Command::cargo_bin("the-way")?;
     cmd.env("THE_WAY_CONFIG", &config_file)
-        .arg("cp")
-        .arg("2")
+        .args(["view", "2"])
         .assert()
-        .failure();
+        .code(3)
+        .stderr(predicate::str::contains("You haven't written that snippet"));
+
+    // Borrows the database and gives it back when it's stopped
+    let mut server = std::process::Command::new(assert_cmd::cargo::cargo_bin("the-way"))
+        .env("THE_WAY_CONFIG", &config_file)
+        .args(["serve", "--port", "0"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    let mut line = String::new();
+    std::io::BufReader::new(server.stdout.take().unwrap()).read_line(&mut line)?;
+    let url = line.trim().trim_start_matches("Serving snippets at ");
+    let code = ureq::get(&url.replace("<index>", "1"))
+        .call()
+        .into_string()?;
+    server.kill()?;
+    server.wait()?;
+    assert_eq!(code, "1");
 
-    // Test good index
     let mut cmd = Command::cargo_bin("the-way")?;
     cmd.env("THE_WAY_CONFIG", &config_file)
-        .arg("cp")
-        .arg("1")
+        .args(["--accessible", "list"])
         .assert()
-        .stdout(predicate::str::starts_with(
-            "Snippet #1 copied to clipboard",
-        ));
-    let ctx: color_eyre::Result<ClipboardContext, _> = ClipboardProvider::new();
-    assert!(ctx.is_ok());
-    let mut ctx = ctx.unwrap();
-    let contents = ctx.get_contents();
-    assert!(contents.is_ok());
-    let contents = contents.unwrap();
-    assert!(contents.contains("some\ntest\ncode"));
+        .success()
+        .stdout(predicate::str::contains("Description: sent"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .args(["daemon", "--stop"])
+        .assert()
+        .success()
+        .stdout("Daemon stopped\n");
+    assert!(daemon.wait()?.success());
+    assert!(!temp_dir.path().join("db.sock").exists());
     temp_dir.close()?;
     Ok(())
 }
 
-#[ignore]
-#[test]
-/// Tests Gist sync functionality. Needs to have the environment variable $THE_WAY_GITHUB_TOKEN set!
-/// Ignored by default since Travis doesn't allow secret/encrypted environment variables in PRs
-fn gist() -> color_eyre::Result<()> {
-    use the_way::configuration::TheWayConfig;
-    use the_way::gist::{GistClient, GistContent, UpdateGistPayload};
+/// Files kept by `webdav_server`: name => (content, last modified)
+type DavFiles =
+    std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, (String, String)>>>;
+
+/// Serves a WebDAV folder at the returned URL from memory, for as long as the test runs
+fn webdav_server() -> color_eyre::Result<(String, DavFiles)> {
+    use std::io::{BufRead, BufReader, Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}/the-way", listener.local_addr()?);
+    let files = DavFiles::default();
+    let served = files.clone();
+    std::thread::spawn(move || -> std::io::Result<()> {
+        for stream in listener.incoming() {
+            let mut stream = stream?;
+            let mut reader = BufReader::new(stream.try_clone()?);
+            let (mut request, mut line, mut length) = (String::new(), String::new(), 0);
+            reader.read_line(&mut request)?;
+            while reader.read_line(&mut line)? > 2 {
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap_or(0);
+                }
+                line.clear();
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body)?;
+            let mut parts = request.split_whitespace();
+            let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+            let name = path.rsplit('/').next().unwrap_or("").to_owned();
+            let mut files = served.lock().unwrap();
+            let (status, body) = match method {
+                "PROPFIND" => {
+                    let responses = files
+                        .iter()
+                        .map(|(name, (_, modified))| {
+                            format!(
+                                "<d:response><d:href>/the-way/{}</d:href><d:propstat><d:prop>\
+                                <d:getlastmodified>{}</d:getlastmodified></d:prop></d:propstat></d:response>",
+                                name, modified
+                            )
+                        })
+                        .collect::<String>();
+                    (
+                        "207 Multi-Status",
+                        format!(
+                            "<d:multistatus xmlns:d=\"DAV:\">{}</d:multistatus>",
+                            responses
+                        ),
+                    )
+                }
+                "GET" => match files.get(&name) {
+                    Some((content, _)) => ("200 OK", content.to_owned()),
+                    None => ("404 Not Found", String::new()),
+                },
+                "PUT" => {
+                    let modified = chrono::Utc::now().to_rfc2822();
+                    files.insert(
+                        name,
+                        (String::from_utf8_lossy(&body).into_owned(), modified),
+                    );
+                    ("201 Created", String::new())
+                }
+                "DELETE" => {
+                    files.remove(&name);
+                    ("204 No Content", String::new())
+                }
+                _ => ("201 Created", String::new()),
+            };
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )?;
+        }
+        Ok(())
+    });
+    Ok((url, files))
+}
 
+#[test]
+fn sync_conflicts() -> color_eyre::Result<()> {
+    let (url, remote) = webdav_server()?;
     let temp_dir = tempdir()?;
     let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\nsync_provider = \"webdav\"\n[http]\nno_cache = true\n[webdav]\nurl = \"{}\"\n",
+            config_contents, url
+        ),
+    )?;
+    let tracked_file = temp_dir.path().join("tracked.rs");
+    let the_way = |args: &[&str]| -> color_eyre::Result<assert_cmd::assert::Assert> {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        Ok(cmd
+            .env("THE_WAY_CONFIG", &config_file)
+            .args(args)
+            .assert()
+            .success())
+    };
+    // Changes the local snippet #1
+    let edit_here = |code: &str| -> color_eyre::Result<()> {
+        fs::write(&tracked_file, code)?;
+        the_way(&["snippet", "refresh"])?;
+        Ok(())
+    };
+    let edit_remote = |name: &str, code: &str, modified: &str| {
+        remote
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), (code.to_owned(), modified.to_owned()));
+    };
+    let remote_code = |name: &str| remote.lock().unwrap().get(name).map(|file| file.0.clone());
+    let exported = || -> color_eyre::Result<String> {
+        Ok(String::from_utf8(
+            the_way(&["export"])?.get_output().stdout.clone(),
+        )?)
+    };
 
-    let contents_1 = r#"{"description":"test description 1","language":"rust","tags":["tag1","tag2"],"code":"some\ntest\ncode\n"}"#;
-    let contents_2 =
-        r#"{"description":"test description 2","language":"python","code":"some\ntest\ncode\n"}"#;
-    let contents = format!("{}{}", contents_1, contents_2);
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_file)
+        .arg("import")
+        .write_stdin(r#"{"description":"one","language":"rust","code":"base"}"#)
+        .assert()
+        .success();
+    fs::write(&tracked_file, "base")?;
+    the_way(&["snippet", "track", "1", tracked_file.to_str().unwrap()])?;
+    the_way(&["sync"])?;
+    assert_eq!(remote_code("snippet_1.rs").as_deref(), Some("base"));
 
-    // import
+    // Only changed remotely: downloaded, even though the local snippet is newer
+    edit_remote("snippet_1.rs", "remote", "Mon, 01 Jan 2001 00:00:00 GMT");
+    the_way(&["sync"])?.stdout(predicate::str::contains("Downloaded 1 snippet(s)"));
+    assert!(exported()?.contains(r#""code":"remote""#));
+
+    // Changed on both sides
+    edit_here("here")?;
+    edit_remote("snippet_1.rs", "there", "Mon, 01 Jan 2001 00:00:00 GMT");
+    the_way(&["sync", "--conflicts", "keep-both"])?
+        .stdout(predicate::str::contains("Resolved 1 conflict(s)"));
+    let snippets = exported()?;
+    assert!(snippets.contains(r#""code":"here""#));
+    assert!(snippets.contains(r#""description":"one (from the WebDAV folder)""#));
+    assert_eq!(remote_code("snippet_1.rs").as_deref(), Some("here"));
+    assert_eq!(remote_code("snippet_2.rs").as_deref(), Some("there"));
+
+    edit_here("older")?;
+    edit_remote("snippet_1.rs", "newer", "Fri, 01 Jan 2100 00:00:00 GMT");
+    the_way(&["sync", "--conflicts", "keep-newest"])?;
+    assert!(exported()?.contains(r#""code":"newer""#));
+    assert_eq!(remote_code("snippet_1.rs").as_deref(), Some("newer"));
+    temp_dir.close()?;
+    Ok(())
+}
+
+fn merge_view_rexpect(config_file: &std::path::Path) -> rexpect::errors::Result<()> {
+    let mut p = spawn_bash(Some(5000))?;
+    p.send_line(&format!(
+        "export THE_WAY_CONFIG={}",
+        config_file.to_string_lossy()
+    ))?;
+    p.wait_for_prompt()?;
+    // The merge view needs a terminal size
+    p.send_line("stty rows 40 cols 160")?;
+    p.wait_for_prompt()?;
+    p.send_line(&format!(
+        "{} sync --conflicts ask",
+        env!("CARGO_BIN_EXE_the-way")
+    ))?;
+    p.exp_string("conflict 1 of 2")?;
+    // Keep this side of the first conflict, take the remote side of the second
+    p.send("j")?;
+    p.send("l")?;
+    p.send("\r")?;
+    p.flush()?;
+    p.exp_string("Resolved 1 conflict(s)")?;
+    p.wait_for_prompt()?;
+    Ok(())
+}
+
+#[test]
+fn sync_merge_view() -> color_eyre::Result<()> {
+    let (url, remote) = webdav_server()?;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\nsync_provider = \"webdav\"\n[http]\nno_cache = true\n[webdav]\nurl = \"{}\"\n",
+            config_contents, url
+        ),
+    )?;
+    let tracked_file = temp_dir.path().join("tracked.rs");
+    let the_way = |args: &[&str]| -> color_eyre::Result<assert_cmd::assert::Assert> {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        Ok(cmd
+            .env("THE_WAY_CONFIG", &config_file)
+            .args(args)
+            .assert()
+            .success())
+    };
     let mut cmd = Command::cargo_bin("the-way")?;
     cmd.env("THE_WAY_CONFIG", &config_file)
         .arg("import")
-        .write_stdin(contents)
+        .write_stdin(r#"{"description":"one","language":"rust","code":"a\nb\nc\nd\ne\n"}"#)
         .assert()
-        .stdout(predicate::str::starts_with("Imported 2 snippets"));
+        .success();
+    fs::write(&tracked_file, "a\nb\nc\nd\ne\n")?;
+    the_way(&["snippet", "track", "1", tracked_file.to_str().unwrap()])?;
+    the_way(&["sync"])?;
 
-    // sync
+    fs::write(&tracked_file, "a\nb here\nc\nd here\ne\n")?;
+    the_way(&["snippet", "refresh"])?;
+    remote.lock().unwrap().insert(
+        String::from("snippet_1.rs"),
+        (
+            String::from("a\nb there\nc\nd there\ne\n"),
+            String::from("Mon, 01 Jan 2001 00:00:00 GMT"),
+        ),
+    );
+    assert!(merge_view_rexpect(&config_file).is_ok());
+    let merged = "a\nb here\nc\nd there\ne\n";
+    assert_eq!(
+        remote
+            .lock()
+            .unwrap()
+            .get("snippet_1.rs")
+            .map(|file| file.0.as_str()),
+        Some(merged)
+    );
+    the_way(&["export"])?.stdout(predicate::str::contains(
+        r#""code":"a\nb here\nc\nd there\ne\n""#,
+    ));
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn sync_status() -> color_eyre::Result<()> {
+    let (url, remote) = webdav_server()?;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\nsync_provider = \"webdav\"\n[http]\nno_cache = true\n[webdav]\nurl = \"{}\"\n",
+            config_contents, url
+        ),
+    )?;
+    let the_way = |args: &[&str]| -> color_eyre::Result<assert_cmd::assert::Assert> {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        Ok(cmd.env("THE_WAY_CONFIG", &config_file).args(args).assert())
+    };
+
+    // Nothing to sync with yet
+    the_way(&["sync", "status"])?
+        .success()
+        .stdout(predicate::str::contains(
+            "No WebDAV folder yet, `sync` would make one with 0 snippet(s)",
+        ));
     let mut cmd = Command::cargo_bin("the-way")?;
     cmd.env("THE_WAY_CONFIG", &config_file)
-        .arg("sync")
+        .arg("import")
+        .write_stdin(
+            r#"{"description":"first","language":"rust","code":"one"}
+            {"description":"second","language":"python","code":"two"}
+            {"description":"third","language":"rust","code":"three"}"#,
+        )
         .assert()
         .success();
+    the_way(&["sync"])?.success();
 
-    // get gist_id from config
-    std::env::set_var("THE_WAY_CONFIG", &config_file);
-    let config = TheWayConfig::load();
-    assert!(config.is_ok());
-    let config = config?;
-    assert!(config.gist_id.is_some());
+    // Changed here, changed remotely, deleted here, and new remotely
+    let tracked_file = temp_dir.path().join("tracked.rs");
+    fs::write(&tracked_file, "one")?;
+    the_way(&["snippet", "track", "1", tracked_file.to_str().unwrap()])?.success();
+    fs::write(&tracked_file, "one, changed here")?;
+    the_way(&["snippet", "refresh"])?.success();
+    the_way(&["del", "-f", "3"])?.success();
+    {
+        let mut files = remote.lock().unwrap();
+        let modified = files["snippet_2.py"].1.clone();
+        files.insert(
+            String::from("snippet_2.py"),
+            (String::from("two, changed remotely"), modified.clone()),
+        );
+        files.insert(
+            String::from("snippet_7.sh"),
+            (String::from("seven"), modified),
+        );
+    }
+    let before = remote.lock().unwrap().clone();
+    for args in &[&["sync", "status"][..], &["sync", "--dry-run"]] {
+        the_way(args)?.success().stdout(
+            predicate::str::contains("Would upload to the WebDAV folder:\n  #1 first\n")
+                .and(predicate::str::contains(
+                    "Would download from the WebDAV folder:\n  snippet_7.sh (new)\n  #2 second\n",
+                ))
+                .and(predicate::str::contains(
+                    "Would delete from the WebDAV folder (deleted here):\n  snippet_3.rs (third)\n",
+                )),
+        );
+    }
+    // Nothing changed on either side
+    assert_eq!(*remote.lock().unwrap(), before);
+    the_way(&["view", "7"])?.failure();
 
-    // get Gist
-    let client = GistClient::new(&std::env::var("THE_WAY_GITHUB_TOKEN")?, "the-way")?;
-    let gist = client.get_gist(&config.gist_id.unwrap());
-    assert!(gist.is_ok());
-    let gist = gist?;
+    // Changed on both sides
+    fs::write(&tracked_file, "one, changed here again")?;
+    the_way(&["snippet", "refresh"])?.success();
+    remote.lock().unwrap().insert(
+        String::from("snippet_1.rs"),
+        (
+            String::from("one, changed remotely"),
+            String::from("Mon, 01 Jan 2001 00:00:00 GMT"),
+        ),
+    );
+    the_way(&["sync", "--dry-run"])?
+        .success()
+        .stdout(predicate::str::contains(
+            "Changed here and in the WebDAV folder:\n  #1 first\n",
+        ));
+    the_way(&["sync", "--conflicts", "keep-newest"])?.success();
+    the_way(&["sync", "status"])?
+        .success()
+        .stdout(predicate::str::contains("Everything up to date"));
+    temp_dir.close()?;
+    Ok(())
+}
 
-    // check Gist contents
-    assert_eq!(gist.files.len(), 3);
-    for (filename, gistfile) in &gist.files {
-        if filename.starts_with("snippet_") {
-            assert_eq!(gistfile.content, "some\ntest\ncode\n");
-        }
+#[test]
+fn sync_deletions() -> color_eyre::Result<()> {
+    let (url, remote) = webdav_server()?;
+    let (temp_dir_a, temp_dir_b) = (tempdir()?, tempdir()?);
+    let mut config_files = Vec::new();
+    for temp_dir in &[&temp_dir_a, &temp_dir_b] {
+        let config_file = make_config_file(temp_dir)?;
+        let config_contents = fs::read_to_string(&config_file)?;
+        fs::write(
+            &config_file,
+            format!(
+                "{}\nsync_provider = \"webdav\"\n[http]\nno_cache = true\n[webdav]\nurl = \"{}\"\n",
+                config_contents, url
+            ),
+        )?;
+        config_files.push(config_file);
     }
+    let the_way =
+        |machine: usize, args: &[&str]| -> color_eyre::Result<assert_cmd::assert::Assert> {
+            let mut cmd = Command::cargo_bin("the-way")?;
+            Ok(cmd
+                .env("THE_WAY_CONFIG", &config_files[machine])
+                .args(args)
+                .assert())
+        };
+    let remote_has = |name: &str| remote.lock().unwrap().contains_key(name);
 
-    // edit Gist
-    let update_payload = UpdateGistPayload {
-        description: &gist.description,
-        files: vec![(
-            "snippet_1.rs".to_owned(),
-            Some(GistContent {
-                content: "some\nmore\ntest\ncode\n",
-            }),
-        )]
-        .into_iter()
-        .collect(),
-    };
-    assert!(client.update_gist(&gist.id, &update_payload).is_ok());
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_files[0])
+        .arg("import")
+        .write_stdin(
+            r#"{"description":"first","language":"rust","code":"fn main() { let x = 1; }"}
+            {"description":"second","language":"python","code":"print(2)"}"#,
+        )
+        .assert()
+        .success();
+    the_way(0, &["sync"])?.success();
 
-    // delete locally (easier than editing)
+    // Snippets only in the remote are downloaded, not deleted
+    the_way(1, &["sync"])?
+        .success()
+        .stdout(predicate::str::contains("Downloaded 2 snippet(s)"));
+    the_way(1, &["view", "1", "--accessible"])?
+        .success()
+        .stdout(
+            predicate::str::contains("Description: first")
+                .and(predicate::str::contains("Language: rust")),
+        );
+    the_way(1, &["view", "2", "--accessible"])?
+        .success()
+        .stdout(predicate::str::contains("Language: python"));
+
+    // Deleting on one machine deletes remotely, then on the other machine
+    the_way(1, &["del", "-f", "1"])?.success();
+    the_way(1, &["sync"])?
+        .success()
+        .stdout(predicate::str::contains("Deleted 1 snippet(s)"));
+    assert!(!remote_has("snippet_1.rs"));
+    the_way(0, &["sync"])?
+        .success()
+        .stdout(predicate::str::contains(
+            "Removed 1 snippet(s) deleted from the WebDAV folder",
+        ));
+    the_way(0, &["view", "1"])?.failure();
+    for machine in 0..2 {
+        the_way(machine, &["sync"])?
+            .success()
+            .stdout(predicate::str::contains("Everything up to date"));
+    }
+    assert!(!remote_has("snippet_1.rs"));
+    assert!(remote_has("snippet_2.py"));
+
+    // New snippets on the downloading machine come after the downloaded ones
     let mut cmd = Command::cargo_bin("the-way")?;
-    cmd.env("THE_WAY_CONFIG", &config_file)
-        .arg("del")
-        .arg("-f")
-        .arg("2")
+    cmd.env("THE_WAY_CONFIG", &config_files[1])
+        .arg("import")
+        .write_stdin(r#"{"description":"third","language":"rust","code":"third"}"#)
         .assert()
-        .stdout(predicate::str::starts_with("Snippet #2 deleted"));
+        .success();
+    the_way(1, &["view", "3", "--accessible"])?
+        .success()
+        .stdout(predicate::str::contains("Description: third"));
+    temp_dir_a.close()?;
+    temp_dir_b.close()?;
+    Ok(())
+}
 
-    // sync - download + deleted
+#[test]
+fn sync_tags() -> color_eyre::Result<()> {
+    let (url, remote) = webdav_server()?;
+    let (temp_dir_a, temp_dir_b) = (tempdir()?, tempdir()?);
+    let mut config_files = Vec::new();
+    for temp_dir in &[&temp_dir_a, &temp_dir_b] {
+        let config_file = make_config_file(temp_dir)?;
+        let config_contents = fs::read_to_string(&config_file)?;
+        fs::write(
+            &config_file,
+            format!(
+                "{}\nsync_provider = \"webdav\"\nsync_tags = [\"shared\"]\nsync_exclude_tags = [\"private\"]\n\
+                [http]\nno_cache = true\n[webdav]\nurl = \"{}\"\n",
+                config_contents, url
+            ),
+        )?;
+        config_files.push(config_file);
+    }
     let mut cmd = Command::cargo_bin("the-way")?;
-    cmd.env("THE_WAY_CONFIG", &config_file)
+    cmd.env("THE_WAY_CONFIG", &config_files[0])
+        .arg("import")
+        .write_stdin(
+            r#"{"description":"shared","language":"rust","code":"fn main() {}","tags":["shared"]}
+            {"description":"secret","language":"sh","code":"echo secret","tags":["shared","private"]}
+            {"description":"untagged","language":"python","code":"print(3)"}"#,
+        )
+        .assert()
+        .success();
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_files[0])
         .arg("sync")
         .assert()
         .success();
+    {
+        let remote = remote.lock().unwrap();
+        assert!(remote.contains_key("snippet_1.rs"));
+        assert!(!remote.contains_key("snippet_2.sh"));
+        assert!(!remote.contains_key("snippet_3.py"));
+    }
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_files[0])
+        .args(["sync", "status"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Everything up to date"));
 
-    // get Gist
-    let gist = client.get_gist(&gist.id);
-    assert!(gist.is_ok());
-    let gist = gist?;
-    let updated = &gist.updated_at;
+    // Downloaded snippets get the sync tags, so they're synced from then on
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_files[1])
+        .arg("sync")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Downloaded 1 snippet(s)"));
+    let mut cmd = Command::cargo_bin("the-way")?;
+    cmd.env("THE_WAY_CONFIG", &config_files[1])
+        .args(["view", "1", "--accessible"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Tags: shared"));
+    temp_dir_a.close()?;
+    temp_dir_b.close()?;
+    Ok(())
+}
 
-    // sync again - nothing changed
+#[test]
+fn sync_log() -> color_eyre::Result<()> {
+    let (url, _remote) = webdav_server()?;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = fs::read_to_string(&config_file)?;
+    fs::write(
+        &config_file,
+        format!(
+            "{}\nsync_provider = \"webdav\"\n[http]\nno_cache = true\n[webdav]\nurl = \"{}\"\n",
+            config_contents, url
+        ),
+    )?;
+    let log = |number: &str| -> color_eyre::Result<String> {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        let output = cmd
+            .env("THE_WAY_CONFIG", &config_file)
+            .args(["--accessible", "sync", "log", "-n", number])
+            .output()?;
+        assert!(output.status.success());
+        Ok(String::from_utf8(output.stdout)?)
+    };
+    assert_eq!(log("10")?, "No syncs yet\n");
+    let import = |code: &str| -> color_eyre::Result<()> {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        cmd.env("THE_WAY_CONFIG", &config_file)
+            .arg("import")
+            .write_stdin(format!(
+                r#"{{"description":"log","language":"rust","code":"{}"}}"#,
+                code
+            ))
+            .assert()
+            .success();
+        Ok(())
+    };
+    let sync = |args: &[&str]| -> color_eyre::Result<()> {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        cmd.env("THE_WAY_CONFIG", &config_file)
+            .arg("sync")
+            .args(args)
+            .assert()
+            .success();
+        Ok(())
+    };
+    import("1")?;
+    sync(&[])?;
+    import("2")?;
+    // Dry runs aren't logged
+    sync(&["--dry-run"])?;
+    sync(&[])?;
+    sync(&[])?;
+
+    let lines: Vec<_> = log("10")?.lines().map(String::from).collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].ends_with(": nothing changed"));
+    assert!(lines[1].ends_with(": uploaded 1"));
+    assert!(lines[2].ends_with(": created with 1 snippet(s)"));
+    assert!(lines[2].contains(&url));
+    assert_eq!(log("1")?.lines().count(), 1);
+    temp_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn sync_encrypted() -> color_eyre::Result<()> {
+    let (url, remote) = webdav_server()?;
+    let temp_dir = tempdir()?;
+    let config_file = make_config_file(&temp_dir)?;
+    let config_contents = format!(
+        "{}\nsync_provider = \"webdav\"\n[http]\nno_cache = true\n[webdav]\nurl = \"{}\"\n",
+        fs::read_to_string(&config_file)?,
+        url
+    );
+    let encrypted_config = format!("{}[encryption]\nenabled = true\n", config_contents);
+    let sync = |passphrase: &str| -> color_eyre::Result<assert_cmd::assert::Assert> {
+        let mut cmd = Command::cargo_bin("the-way")?;
+        Ok(cmd
+            .env("THE_WAY_CONFIG", &config_file)
+            .env("THE_WAY_SYNC_PASSPHRASE", passphrase)
+            .arg("sync")
+            .assert())
+    };
+    let remote_code = |name: &str| remote.lock().unwrap().get(name).map(|file| file.0.clone());
+
+    fs::write(&config_file, &config_contents)?;
     let mut cmd = Command::cargo_bin("the-way")?;
     cmd.env("THE_WAY_CONFIG", &config_file)
-        .arg("sync")
+        .arg("import")
+        .write_stdin(r#"{"description":"secret","language":"rust","code":"let password = 1;"}"#)
         .assert()
         .success();
-    let gist = client.get_gist(&gist.id);
-    assert!(gist.is_ok());
-    let gist = gist?;
-    assert_eq!(updated, &gist.updated_at);
+    sync("")?.success();
+    assert_eq!(
+        remote_code("snippet_1.rs").as_deref(),
+        Some("let password = 1;")
+    );
 
-    // check Gist contents
-    assert_eq!(gist.files.len(), 2);
-    for (filename, gistfile) in &gist.files {
-        if filename.starts_with("snippet_") {
-            assert_eq!(gistfile.content, "some\nmore\ntest\ncode\n");
-        }
+    // Files uploaded before encryption was turned on are encrypted too
+    fs::write(&config_file, &encrypted_config)?;
+    sync("correct horse")?
+        .success()
+        .stdout(predicate::str::contains("Everything up to date"));
+    for name in &["snippet_1.rs", "index.md"] {
+        let code = remote_code(name).unwrap();
+        assert!(code.starts_with("the-way-encrypted:v1\n"));
+        assert!(!code.contains("password") && !code.contains("secret"));
     }
+    sync("correct horse")?
+        .success()
+        .stdout(predicate::str::contains("Everything up to date"));
 
-    // check downloaded
+    sync("battery staple")?
+        .failure()
+        .stderr(predicate::str::contains("Couldn't decrypt"));
+    fs::write(&config_file, &config_contents)?;
+    sync("")?
+        .failure()
+        .stderr(predicate::str::contains("is encrypted"));
     let mut cmd = Command::cargo_bin("the-way")?;
     cmd.env("THE_WAY_CONFIG", &config_file)
-        .arg("view")
-        .arg("1")
+        .args(["view", "1", "--accessible"])
         .assert()
-        .stdout(predicates::str::contains("more"));
-
-    // delete Gist
-    assert!(client.delete_gist(&gist.id).is_ok());
-    assert!(client.get_gist(&gist.id).is_err());
+        .stdout(predicate::str::contains("let password = 1;"));
     temp_dir.close()?;
     Ok(())
 }